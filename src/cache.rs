@@ -0,0 +1,301 @@
+use crate::error::Error;
+use crate::Client;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The amount of content that is cached when `CacheConfig::max_bytes` is not set.
+const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Keeps the content of downloaded objects, so that reading the same object again does not
+/// download it again. This cuts cost and latency for objects that are read often but rarely
+/// change, such as configuration files.
+///
+/// Content is cached per generation of an object. When `ttl` has passed since the content was
+/// last validated, the metadata of the object is read first, which is much cheaper than a
+/// download, and the cached content is only used if its generation is still the latest one.
+/// ### Example
+/// ```rust
+/// use cloud_storage::{CacheConfig, Client, Config};
+/// use std::time::Duration;
+///
+/// let client = Client::with_config(Config {
+///     cache: Some(CacheConfig {
+///         ttl: Some(Duration::from_secs(60)),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheConfig {
+    /// How long cached content is served without checking whether the object has changed. Within
+    /// this time, changes to the object, including those made through the same client, are not
+    /// noticed. When not set, every download checks the generation of the object first.
+    pub ttl: Option<Duration>,
+    /// The maximum total size of the cached content. When exceeded, the content that was used
+    /// least recently is removed. Objects larger than this are never cached. Defaults to 64 MiB.
+    pub max_bytes: Option<u64>,
+    /// The directory to store the cached content in. Content is kept in memory when not set.
+    /// Files that an earlier process left in this directory are reused.
+    pub directory: Option<PathBuf>,
+}
+
+/// The cache of a `Client`, as configured by `Config::cache`.
+#[derive(Debug)]
+pub(crate) struct Cache {
+    config: CacheConfig,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The cached content of each object, by bucket and name.
+    entries: HashMap<(String, String), Entry>,
+    /// The total size of the cached content.
+    size: u64,
+    /// Increases with every use of an entry, to find the least recently used one.
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    generation: i64,
+    size: u64,
+    validated: Instant,
+    last_used: u64,
+    content: Content,
+}
+
+#[derive(Debug)]
+enum Content {
+    Memory(bytes::Bytes),
+    File(PathBuf),
+}
+
+impl Cache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn max_bytes(&self) -> u64 {
+        self.config.max_bytes.unwrap_or(DEFAULT_MAX_BYTES)
+    }
+
+    /// Downloads an object through `client`, unless its latest generation is cached.
+    pub fn download(
+        &self,
+        client: &Client,
+        bucket: &str,
+        name: &str,
+    ) -> Result<bytes::Bytes, Error> {
+        let key = (bucket.to_string(), name.to_string());
+        if let Some(ttl) = self.config.ttl {
+            if let Some(content) = self.fresh(&key, ttl) {
+                return Ok(content);
+            }
+        }
+        let object = client.object().read(bucket, name)?;
+        if let Some(content) = self.get(&key, object.generation, object.size) {
+            return Ok(content);
+        }
+        let content = client
+            .object()
+            .download_generation(bucket, name, Some(object.generation))?;
+        self.insert(key, object.generation, &content);
+        Ok(content)
+    }
+
+    /// Removes all cached content.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        for (_, entry) in state.entries.drain() {
+            remove(entry);
+        }
+        state.size = 0;
+    }
+
+    /// Returns the cached content of an object if it was validated less than `ttl` ago.
+    fn fresh(&self, key: &(String, String), ttl: Duration) -> Option<bytes::Bytes> {
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entries.get(key)?;
+        if entry.validated.elapsed() >= ttl {
+            return None;
+        }
+        self.load(&mut state, key)
+    }
+
+    /// Returns the cached content of `generation` of an object, and marks it as validated.
+    fn get(&self, key: &(String, String), generation: i64, size: u64) -> Option<bytes::Bytes> {
+        let mut state = self.state.lock().unwrap();
+        let cached = state.entries.get(key).map(|entry| entry.generation);
+        if cached != Some(generation) {
+            // an earlier process may have stored this generation already
+            let path = self.path(key, generation)?;
+            if std::fs::metadata(&path).ok()?.len() != size {
+                return None;
+            }
+            self.store(
+                &mut state,
+                key.clone(),
+                generation,
+                size,
+                Content::File(path),
+            );
+        }
+        let content = self.load(&mut state, key)?;
+        if let Some(entry) = state.entries.get_mut(key) {
+            entry.validated = Instant::now();
+        }
+        Some(content)
+    }
+
+    /// Reads the content of an entry, or removes the entry if that is not possible.
+    fn load(&self, state: &mut State, key: &(String, String)) -> Option<bytes::Bytes> {
+        state.clock += 1;
+        let clock = state.clock;
+        let entry = state.entries.get_mut(key)?;
+        entry.last_used = clock;
+        let content = match &entry.content {
+            Content::Memory(content) => Some(content.clone()),
+            Content::File(path) => std::fs::read(path).ok().map(bytes::Bytes::from),
+        };
+        if content.is_none() {
+            if let Some(entry) = state.entries.remove(key) {
+                state.size -= entry.size;
+            }
+        }
+        content
+    }
+
+    /// Caches `content` as `generation` of an object. Failures to write it to disk are ignored,
+    /// the content is then simply not cached.
+    fn insert(&self, key: (String, String), generation: i64, content: &bytes::Bytes) {
+        let size = content.len() as u64;
+        if size > self.max_bytes() {
+            return;
+        }
+        let content = match self.path(&key, generation) {
+            Some(path) => {
+                let partial = path.with_extension("partial");
+                let written = std::fs::create_dir_all(path.parent().unwrap())
+                    .and_then(|_| std::fs::write(&partial, content))
+                    .and_then(|_| std::fs::rename(&partial, &path));
+                if written.is_err() {
+                    return;
+                }
+                Content::File(path)
+            }
+            None => Content::Memory(content.clone()),
+        };
+        let mut state = self.state.lock().unwrap();
+        self.store(&mut state, key, generation, size, content);
+    }
+
+    fn store(
+        &self,
+        state: &mut State,
+        key: (String, String),
+        generation: i64,
+        size: u64,
+        content: Content,
+    ) {
+        if let Some(old) = state.entries.remove(&key) {
+            state.size -= old.size;
+            if old.generation != generation {
+                remove(old);
+            }
+        }
+        while state.size + size > self.max_bytes() {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest.and_then(|oldest| state.entries.remove(&oldest)) {
+                Some(entry) => {
+                    state.size -= entry.size;
+                    remove(entry);
+                }
+                None => break,
+            }
+        }
+        state.clock += 1;
+        state.size += size;
+        let entry = Entry {
+            generation,
+            size,
+            validated: Instant::now(),
+            last_used: state.clock,
+            content,
+        };
+        state.entries.insert(key, entry);
+    }
+
+    /// The file that `generation` of an object is stored in, if content is stored on disk.
+    fn path(&self, (bucket, name): &(String, String), generation: i64) -> Option<PathBuf> {
+        let directory = self.config.directory.as_ref()?;
+        let key = format!("{}/{}#{}", bucket, name, generation);
+        Some(directory.join(hex::encode(crate::crypto::sha256(key.as_bytes()))))
+    }
+}
+
+fn remove(entry: Entry) {
+    if let Content::File(path) = entry.content {
+        std::fs::remove_file(path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> (String, String) {
+        ("bucket".to_string(), name.to_string())
+    }
+
+    #[test]
+    fn generations_and_eviction() {
+        let cache = Cache::new(CacheConfig {
+            max_bytes: Some(5),
+            ..Default::default()
+        });
+        cache.insert(key("a"), 1, &bytes::Bytes::from_static(b"aaa"));
+        assert_eq!(cache.get(&key("a"), 1, 3).as_deref(), Some(&b"aaa"[..]));
+        assert_eq!(cache.get(&key("a"), 2, 3), None);
+        assert!(cache.fresh(&key("a"), Duration::from_secs(60)).is_some());
+        assert!(cache.fresh(&key("a"), Duration::from_secs(0)).is_none());
+
+        // too large to ever be cached
+        cache.insert(key("b"), 1, &bytes::Bytes::from_static(b"bbbbbb"));
+        assert_eq!(cache.get(&key("b"), 1, 6), None);
+        // evicts "a", which was used least recently
+        cache.insert(key("c"), 1, &bytes::Bytes::from_static(b"cc"));
+        cache.insert(key("d"), 1, &bytes::Bytes::from_static(b"ddd"));
+        assert_eq!(cache.get(&key("a"), 1, 3), None);
+        assert!(cache.get(&key("c"), 1, 2).is_some());
+        assert!(cache.get(&key("d"), 1, 3).is_some());
+    }
+
+    #[test]
+    fn files_are_reused() {
+        let directory = std::env::temp_dir().join(format!("cache-test-{}", std::process::id()));
+        let config = CacheConfig {
+            directory: Some(directory.clone()),
+            ..Default::default()
+        };
+        let cache = Cache::new(config.clone());
+        cache.insert(key("a"), 7, &bytes::Bytes::from_static(b"content"));
+        // a new cache, as created by another process, finds the file
+        let other = Cache::new(config);
+        assert_eq!(other.get(&key("a"), 7, 7).as_deref(), Some(&b"content"[..]));
+        assert_eq!(other.get(&key("a"), 8, 7), None);
+        other.clear();
+        assert_eq!(cache.get(&key("a"), 7, 7), None);
+        std::fs::remove_dir_all(directory).ok();
+    }
+}
@@ -0,0 +1,209 @@
+use crate::error::Error;
+use std::cell::RefCell;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The size of the chunks in which downloads are read while a `CancellationToken` is active.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+/// Allows aborting requests that are made within `with_cancellation`, for example from another
+/// thread that handles a shutdown signal. Cloning a token is cheap, and all clones are cancelled
+/// together.
+///
+/// Cancellation is cooperative: it is noticed before every attempt of a request, and while the
+/// body of an upload or download is being transferred, so a transfer of several gigabytes stops
+/// shortly after `cancel` is called. An aborted upload never leaves a partial object behind: an
+/// upload in a single request is simply not completed, and the session of an aborted resumable
+/// upload, such as the one of `Object::create_streamed_unsized`, is deleted, so that Google
+/// discards the chunks that were already uploaded.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is only cancelled by calling `cancel`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token that is cancelled automatically once `deadline` has passed.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                deadline: Some(deadline),
+            }),
+        }
+    }
+
+    /// Cancels the requests that use this token, or any of its clones.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether `cancel` was called or the deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+            || self.inner.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+/// Runs `f`, aborting the requests it makes on the current thread with `Error::Cancelled` as soon
+/// as `token` is cancelled.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::{CancellationToken, Object};
+///
+/// let token = CancellationToken::new();
+/// let handle = token.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_secs(10));
+///     handle.cancel();
+/// });
+/// let bytes = cloud_storage::with_cancellation(&token, || {
+///     Object::download("my_bucket", "path/to/a/huge/file.bin")
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn with_cancellation<T>(token: &CancellationToken, f: impl FnOnce() -> T) -> T {
+    with_token(Some(token.clone()), f)
+}
+
+/// Runs `f` without the token of the current thread, for requests that clean up after a
+/// cancelled transfer and therefore must not be cancelled themselves.
+pub(crate) fn uncancellable<T>(f: impl FnOnce() -> T) -> T {
+    with_token(None, f)
+}
+
+fn with_token<T>(token: Option<CancellationToken>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<CancellationToken>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT.with(|c| *c.borrow_mut() = self.0.take());
+        }
+    }
+
+    let previous = CURRENT.with(|c| c.replace(token));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// The token set by `with_cancellation` on the current thread, if any.
+pub(crate) fn current() -> Option<CancellationToken> {
+    CURRENT.with(|c| c.borrow().clone())
+}
+
+/// Whether the token of the current thread has been cancelled.
+pub(crate) fn cancelled() -> bool {
+    current().is_some_and(|token| token.is_cancelled())
+}
+
+/// Fails with `Error::Cancelled` if the token of the current thread has been cancelled.
+pub(crate) fn check() -> Result<(), Error> {
+    if cancelled() {
+        Err(Error::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads the body of `response`, stopping as soon as the token of the current thread is
+/// cancelled.
+pub(crate) fn read_body(mut response: reqwest::blocking::Response) -> Result<bytes::Bytes, Error> {
+    let token = match current() {
+        Some(token) => token,
+        None => return Ok(response.bytes()?),
+    };
+    let mut body = Vec::with_capacity(response.content_length().unwrap_or(0) as usize);
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        if token.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        match response.read(&mut chunk) {
+            Ok(0) => return Ok(body.into()),
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(Error::Other(e.to_string())),
+        }
+    }
+}
+
+/// Wraps the body of an upload, so that the upload fails once the token of the current thread is
+/// cancelled.
+pub(crate) fn reader<R: Read>(inner: R) -> Cancellable<R> {
+    Cancellable {
+        inner,
+        token: current(),
+    }
+}
+
+pub(crate) struct Cancellable<R> {
+    inner: R,
+    token: Option<CancellationToken>,
+}
+
+impl<R: Read> Read for Cancellable<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &self.token {
+            Some(token) if token.is_cancelled() => {
+                Err(std::io::Error::other("the upload was cancelled"))
+            }
+            _ => self.inner.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_scoped_to_closure() {
+        let token = CancellationToken::new();
+        token.cancel();
+        assert!(check().is_ok());
+        let result = with_cancellation(&token, check);
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(check().is_ok());
+        let result = with_cancellation(&token, || uncancellable(check));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn deadline() {
+        let token = CancellationToken::with_deadline(Instant::now());
+        assert!(token.is_cancelled());
+        let token =
+            CancellationToken::with_deadline(Instant::now() + std::time::Duration::from_secs(60));
+        assert!(!token.is_cancelled());
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn reader_stops_when_cancelled() {
+        let token = CancellationToken::new();
+        let mut reader = with_cancellation(&token, || reader(&[1u8, 2, 3][..]));
+        let mut buf = [0; 1];
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        token.cancel();
+        assert!(reader.read(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Protects Google, and the application, from a flood of requests while Cloud Storage is failing.
+/// When a `Client` has a circuit breaker, two limits apply on top of `Config::retry`:
+///
+/// - Retries come out of a budget that is shared by all requests of the client. Every failed
+///   attempt takes a token from it, and every successful one puts back `retry_token_ratio` tokens.
+///   Retries are only made while more than half of `retry_tokens` are left, so a widespread outage
+///   does not make every request be sent `max_attempts` times.
+/// - After `failure_threshold` calls in a row have failed with a server error, `429 Too Many
+///   Requests` or a connection failure, the circuit opens: calls fail right away with
+///   `Error::CircuitOpen`, without being sent. After `open_duration` a single call is let through
+///   to find out whether Google has recovered; if it succeeds the circuit closes again, otherwise
+///   it stays open for another `open_duration`.
+///
+/// Changes of the state of the circuit are reported to `MetricsSink::circuit_state`.
+/// ### Example
+/// ```rust
+/// use cloud_storage::{CircuitBreaker, Client, Config};
+/// use std::time::Duration;
+///
+/// let client = Client::with_config(Config {
+///     circuit_breaker: Some(CircuitBreaker {
+///         open_duration: Duration::from_secs(10),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreaker {
+    /// The number of consecutive failed calls after which the circuit opens.
+    pub failure_threshold: u32,
+    /// How long calls fail right away once the circuit is open, before a call is let through to
+    /// probe whether Google has recovered.
+    pub open_duration: Duration,
+    /// The size of the retry budget.
+    pub retry_tokens: u32,
+    /// The number of tokens that every successful attempt adds to the retry budget.
+    pub retry_token_ratio: f64,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+            retry_tokens: 10,
+            retry_token_ratio: 0.1,
+        }
+    }
+}
+
+/// The state of the circuit of a `CircuitBreaker`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CircuitState {
+    /// Requests are sent as usual.
+    Closed,
+    /// Requests fail right away with `Error::CircuitOpen`.
+    Open,
+    /// A single request is sent to probe whether Google has recovered, and all others fail right
+    /// away.
+    HalfOpen,
+}
+
+/// How a call, or a single attempt of it, ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Success,
+    /// Google, or the connection to it, failed.
+    Failure,
+    /// The call was aborted through a `CancellationToken`, which says nothing about Google.
+    Cancelled,
+}
+
+/// Enforces a `CircuitBreaker` for all requests of a `Client`.
+#[derive(Debug)]
+pub(crate) struct Breaker {
+    config: CircuitBreaker,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    circuit: Circuit,
+    tokens: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Circuit {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A probe is in flight. If it never reports back, another one is let through at `until`.
+    HalfOpen {
+        until: Instant,
+    },
+}
+
+impl Circuit {
+    fn state(self) -> CircuitState {
+        match self {
+            Self::Closed { .. } => CircuitState::Closed,
+            Self::Open { .. } => CircuitState::Open,
+            Self::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
+
+impl Breaker {
+    pub fn new(config: &CircuitBreaker) -> Self {
+        Self {
+            config: config.clone(),
+            state: Mutex::new(State {
+                circuit: Circuit::Closed { failures: 0 },
+                tokens: f64::from(config.retry_tokens),
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state.lock().unwrap().circuit.state()
+    }
+
+    /// Decides whether a call may be made at `now`. Fails with the time until the next probe is
+    /// allowed when the circuit is open. Also returns the new state of the circuit if it changed.
+    pub fn admit(&self, now: Instant) -> (Result<(), Duration>, Option<CircuitState>) {
+        let mut state = self.state.lock().unwrap();
+        match state.circuit {
+            Circuit::Closed { .. } => (Ok(()), None),
+            Circuit::Open { until } | Circuit::HalfOpen { until } if now < until => {
+                (Err(until - now), None)
+            }
+            circuit => {
+                state.circuit = Circuit::HalfOpen {
+                    until: now + self.config.open_duration,
+                };
+                let changed = matches!(circuit, Circuit::Open { .. });
+                (Ok(()), Some(CircuitState::HalfOpen).filter(|_| changed))
+            }
+        }
+    }
+
+    /// Records the outcome of a single attempt in the retry budget.
+    pub fn record_attempt(&self, outcome: Outcome) {
+        let mut state = self.state.lock().unwrap();
+        state.tokens = match outcome {
+            Outcome::Success => (state.tokens + self.config.retry_token_ratio)
+                .min(f64::from(self.config.retry_tokens)),
+            Outcome::Failure => (state.tokens - 1.0).max(0.0),
+            Outcome::Cancelled => state.tokens,
+        };
+    }
+
+    /// Whether the retry budget allows another retry.
+    pub fn allow_retry(&self) -> bool {
+        self.state.lock().unwrap().tokens > f64::from(self.config.retry_tokens) / 2.0
+    }
+
+    /// Records the outcome of a call that was admitted, after all of its retries. Returns the new
+    /// state of the circuit if it changed.
+    pub fn record_call(&self, outcome: Outcome, now: Instant) -> Option<CircuitState> {
+        let mut state = self.state.lock().unwrap();
+        let before = state.circuit.state();
+        state.circuit = match (state.circuit, outcome) {
+            // a cancelled probe did not find out anything, so the next call may probe right away
+            (Circuit::HalfOpen { .. }, Outcome::Cancelled) => Circuit::HalfOpen { until: now },
+            (circuit, Outcome::Cancelled) => circuit,
+            (Circuit::Closed { .. }, Outcome::Success) => Circuit::Closed { failures: 0 },
+            (Circuit::Closed { failures }, Outcome::Failure)
+                if failures + 1 < self.config.failure_threshold =>
+            {
+                Circuit::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (Circuit::Closed { .. }, Outcome::Failure)
+            | (Circuit::HalfOpen { .. }, Outcome::Failure) => Circuit::Open {
+                until: now + self.config.open_duration,
+            },
+            (Circuit::HalfOpen { .. }, Outcome::Success) => Circuit::Closed { failures: 0 },
+            // calls that were admitted before the circuit opened do not change it
+            (open @ Circuit::Open { .. }, _) => open,
+        };
+        let after = state.circuit.state();
+        if after == before {
+            None
+        } else {
+            Some(after)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_and_recovers() {
+        let breaker = Breaker::new(&CircuitBreaker {
+            failure_threshold: 2,
+            open_duration: Duration::from_secs(10),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert_eq!(breaker.record_call(Outcome::Failure, now), None);
+        assert_eq!(
+            breaker.record_call(Outcome::Failure, now),
+            Some(CircuitState::Open)
+        );
+        assert_eq!(breaker.admit(now).0, Err(Duration::from_secs(10)));
+
+        // a single probe is let through once the circuit has been open long enough
+        let later = now + Duration::from_secs(10);
+        assert_eq!(breaker.admit(later), (Ok(()), Some(CircuitState::HalfOpen)));
+        assert!(breaker.admit(later).0.is_err());
+        assert_eq!(
+            breaker.record_call(Outcome::Failure, later),
+            Some(CircuitState::Open)
+        );
+        assert!(breaker.admit(later).0.is_err());
+
+        let even_later = later + Duration::from_secs(10);
+        assert!(breaker.admit(even_later).0.is_ok());
+        assert_eq!(
+            breaker.record_call(Outcome::Success, even_later),
+            Some(CircuitState::Closed)
+        );
+        assert_eq!(breaker.admit(even_later), (Ok(()), None));
+    }
+
+    #[test]
+    fn stalled_probe() {
+        let breaker = Breaker::new(&CircuitBreaker {
+            failure_threshold: 1,
+            open_duration: Duration::from_secs(10),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        breaker.record_call(Outcome::Failure, now);
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.admit(later).0.is_ok());
+        // the probe never reports back, so another one is let through after a while
+        assert!(breaker.admit(later + Duration::from_secs(5)).0.is_err());
+        let (admitted, state) = breaker.admit(later + Duration::from_secs(10));
+        assert_eq!((admitted, state), (Ok(()), None));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn cancelled_probe() {
+        let breaker = Breaker::new(&CircuitBreaker {
+            failure_threshold: 1,
+            open_duration: Duration::from_secs(10),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        breaker.record_call(Outcome::Failure, now);
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.admit(later).0.is_ok());
+        assert_eq!(breaker.record_call(Outcome::Cancelled, later), None);
+        // the next call probes right away, and the circuit stays half open until it reports
+        assert_eq!(breaker.admit(later), (Ok(()), None));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.record_call(Outcome::Success, later);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn successes_reset_the_failure_count() {
+        let breaker = Breaker::new(&CircuitBreaker {
+            failure_threshold: 2,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        for _ in 0..3 {
+            breaker.record_call(Outcome::Failure, now);
+            breaker.record_call(Outcome::Success, now);
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn retry_budget() {
+        let breaker = Breaker::new(&CircuitBreaker {
+            retry_tokens: 4,
+            retry_token_ratio: 0.5,
+            ..Default::default()
+        });
+        assert!(breaker.allow_retry());
+        breaker.record_attempt(Outcome::Failure);
+        assert!(breaker.allow_retry());
+        breaker.record_attempt(Outcome::Failure);
+        assert!(!breaker.allow_retry());
+        breaker.record_attempt(Outcome::Success);
+        assert!(breaker.allow_retry());
+        // the budget never grows beyond its size
+        for _ in 0..10 {
+            breaker.record_attempt(Outcome::Success);
+        }
+        for _ in 0..2 {
+            breaker.record_attempt(Outcome::Failure);
+        }
+        assert!(!breaker.allow_retry());
+    }
+}
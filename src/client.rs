@@ -0,0 +1,562 @@
+use crate::cache::Cache;
+use crate::circuit_breaker::{Breaker, CircuitState};
+use crate::config::{self, Config};
+use crate::credentials;
+use crate::degraded::Degraded;
+use crate::error::Error;
+use crate::rate_limit::RateLimiter;
+use crate::resources::service_account::ServiceAccount;
+use crate::token::{ServiceAccountTokenProvider, TokenCache, TokenProvider};
+use crate::url_cache::SignedUrls;
+use std::sync::{Arc, Mutex, RwLock};
+
+mod auth;
+mod bucket;
+mod bucket_access_control;
+mod channel;
+mod default_object_access_control;
+#[cfg(feature = "encryption")]
+mod encrypted;
+mod hmac_key;
+mod notification;
+mod object;
+mod object_access_control;
+mod project;
+mod storage;
+#[cfg(feature = "xml-api")]
+mod xml;
+
+pub use auth::AuthClient;
+pub use bucket::BucketClient;
+pub use bucket_access_control::BucketAccessControlClient;
+pub use channel::ChannelClient;
+pub use default_object_access_control::DefaultObjectAccessControlClient;
+#[cfg(feature = "encryption")]
+pub use encrypted::{EncryptedClient, EnvelopeKey};
+pub use hmac_key::HmacKeyClient;
+pub use notification::NotificationClient;
+pub use object::ObjectClient;
+pub use object_access_control::ObjectAccessControlClient;
+pub use project::ProjectServiceAccountClient;
+pub use storage::StorageClient;
+#[cfg(feature = "xml-api")]
+pub use xml::{XmlClient, XmlObject};
+
+/// The scope of the access tokens used for all requests, unless `Config::scopes` is set.
+const ACCESS_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.full_control";
+/// The scope of the access tokens used to wrap and unwrap data keys with Cloud KMS.
+#[cfg(feature = "encryption")]
+const KMS_SCOPE: &str = "https://www.googleapis.com/auth/cloudkms";
+
+lazy_static::lazy_static! {
+    /// The client used by the functions on the resource types, such as `Object::read`.
+    static ref GLOBAL: RwLock<Client> = RwLock::new(Client::default());
+}
+
+/// Talks to Google Cloud Storage with its own credentials and settings. A `Client` keeps its own
+/// connection pool and access token cache, so that several clients with different service accounts
+/// can be used side by side. Cloning a `Client` is cheap, and the clones share their connections
+/// and tokens.
+///
+/// The operations are grouped by the kind of resource they act on, for example
+/// `client.object().read(..)` or `client.bucket().create(..)`. The functions on the resource types
+/// themselves, such as `Object::read`, use a client that is configured through `set_config`.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::{Client, ServiceAccount};
+///
+/// let production = Client::new(ServiceAccount::from_file("production.json")?);
+/// let staging = Client::new(ServiceAccount::from_file("staging.json")?);
+/// let object = production.object().read("my_bucket", "path/to/my/file.png")?;
+/// let bytes = production.object().download("my_bucket", "path/to/my/file.png")?;
+/// staging.object().create("my_staging_bucket", &bytes, &object.name, "image/png")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: Config,
+    /// The HTTP client built from `config`, created on first use.
+    http: RwLock<Option<reqwest::blocking::Client>>,
+    token_cache: TokenCache,
+    #[cfg(feature = "encryption")]
+    kms_token_cache: TokenCache,
+    rate_limiter: Option<RateLimiter>,
+    breaker: Option<Breaker>,
+    cache: Option<Cache>,
+    signed_urls: Option<SignedUrls>,
+    degraded: Degraded,
+    /// The service account found in the environment, if `config` does not contain one.
+    env_service_account: Mutex<Option<ServiceAccount>>,
+    /// The connection to the gRPC API, created on first use.
+    #[cfg(feature = "grpc")]
+    grpc: Mutex<Option<Arc<crate::grpc::Connection>>>,
+}
+
+impl Default for Client {
+    /// Creates a client that finds its credentials in the environment, as described in
+    /// `application_default`. Nothing is read until the first request is made.
+    fn default() -> Self {
+        Self::with_config(Config::default())
+    }
+}
+
+impl Client {
+    /// Creates a client that authenticates as `service_account`, using the default settings.
+    pub fn new(service_account: ServiceAccount) -> Self {
+        Self::with_config(Config {
+            service_account: Some(service_account),
+            ..Default::default()
+        })
+    }
+
+    /// Creates a client with the given settings.
+    pub fn with_config(config: Config) -> Self {
+        let scopes = if config.scopes.is_empty() {
+            vec![ACCESS_SCOPE.to_string()]
+        } else {
+            config
+                .scopes
+                .iter()
+                .map(|s| s.as_str().to_string())
+                .collect()
+        };
+        Self {
+            inner: Arc::new(Inner {
+                rate_limiter: config.rate_limit.as_ref().map(RateLimiter::new),
+                breaker: config.circuit_breaker.as_ref().map(Breaker::new),
+                cache: config.cache.clone().map(Cache::new),
+                signed_urls: config.signed_url_cache.as_ref().map(SignedUrls::new),
+                degraded: Degraded::default(),
+                config,
+                http: RwLock::new(None),
+                token_cache: TokenCache::new(scopes),
+                #[cfg(feature = "encryption")]
+                kms_token_cache: TokenCache::new(vec![KMS_SCOPE.to_string()]),
+                env_service_account: Mutex::new(None),
+                #[cfg(feature = "grpc")]
+                grpc: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Inspection and refreshing of the access token of this client.
+    pub fn auth(&self) -> AuthClient<'_> {
+        AuthClient(self)
+    }
+
+    /// Operations on buckets.
+    pub fn bucket(&self) -> BucketClient<'_> {
+        BucketClient(self)
+    }
+
+    /// Operations on the access control lists of buckets.
+    pub fn bucket_access_control(&self) -> BucketAccessControlClient<'_> {
+        BucketAccessControlClient(self)
+    }
+
+    /// Operations on notification channels.
+    pub fn channel(&self) -> ChannelClient<'_> {
+        ChannelClient(self)
+    }
+
+    /// Operations on the default access control lists of objects.
+    pub fn default_object_access_control(&self) -> DefaultObjectAccessControlClient<'_> {
+        DefaultObjectAccessControlClient(self)
+    }
+
+    /// Uploads and downloads of objects that are encrypted on the client, with data keys that are
+    /// protected by `key`.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::{Client, EnvelopeKey};
+    ///
+    /// let client = Client::default();
+    /// let key = EnvelopeKey::Kms(
+    ///     "projects/my-project/locations/global/keyRings/my-ring/cryptoKeys/my-key".to_string(),
+    /// );
+    /// client.encrypted(&key).create("my_bucket", b"secret", "secret.txt", "text/plain")?;
+    /// let content = client.encrypted(&key).download("my_bucket", "secret.txt")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn encrypted<'a>(&'a self, key: &'a EnvelopeKey) -> EncryptedClient<'a> {
+        EncryptedClient { client: self, key }
+    }
+
+    /// Operations on HMAC keys.
+    pub fn hmac_key(&self) -> HmacKeyClient<'_> {
+        HmacKeyClient(self)
+    }
+
+    /// Operations on Pub/Sub notification configurations.
+    pub fn notification(&self) -> NotificationClient<'_> {
+        NotificationClient(self)
+    }
+
+    /// Operations on objects.
+    pub fn object(&self) -> ObjectClient<'_> {
+        ObjectClient(self)
+    }
+
+    /// Operations on the access control lists of objects.
+    pub fn object_access_control(&self) -> ObjectAccessControlClient<'_> {
+        ObjectAccessControlClient(self)
+    }
+
+    /// Operations on the Cloud Storage service agents of projects.
+    pub fn project_service_account(&self) -> ProjectServiceAccountClient<'_> {
+        ProjectServiceAccountClient(self)
+    }
+
+    /// Uploads, downloads and listings through the S3-compatible XML API.
+    #[cfg(feature = "xml-api")]
+    pub fn xml(&self) -> XmlClient<'_> {
+        XmlClient(self)
+    }
+
+    /// Returns the moment the access token that is currently in use expires, or `None` if no
+    /// token has been obtained yet. Tokens are refreshed automatically a few minutes before they
+    /// expire, so this is mostly useful to schedule long-running transfers that cannot switch
+    /// tokens halfway.
+    pub fn token_expiry(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.inner.token_cache.expires_at()
+    }
+
+    /// Removes all content from the cache configured by `Config::cache`, including the files it
+    /// stored on disk.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.inner.cache {
+            cache.clear();
+        }
+    }
+
+    /// The client used by the functions on the resource types.
+    pub(crate) fn global() -> Self {
+        GLOBAL.read().unwrap().clone()
+    }
+
+    pub(crate) fn config(&self) -> &Config {
+        &self.inner.config
+    }
+
+    pub(crate) fn cache(&self) -> Option<&Cache> {
+        self.inner.cache.as_ref()
+    }
+
+    pub(crate) fn signed_urls(&self) -> Option<&SignedUrls> {
+        self.inner.signed_urls.as_ref()
+    }
+
+    pub(crate) fn breaker(&self) -> Option<&Breaker> {
+        self.inner.breaker.as_ref()
+    }
+
+    pub(crate) fn degraded(&self) -> &Degraded {
+        &self.inner.degraded
+    }
+
+    /// Returns whether this client is currently operating in degraded mode, which is the case when
+    /// `Config::degraded_mode` is enabled and its most recent attempt to obtain an access token
+    /// failed. The flag is cleared as soon as a token is obtained again.
+    pub fn is_degraded(&self) -> bool {
+        self.inner.degraded.is_degraded()
+    }
+
+    /// The state of the circuit of `Config::circuit_breaker`, or `None` if it is not configured.
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.breaker().map(Breaker::state)
+    }
+
+    /// Returns the HTTP client that requests should be sent with.
+    pub(crate) fn http(&self) -> Result<reqwest::blocking::Client, Error> {
+        if let Some(http) = self.inner.http.read().unwrap().as_ref() {
+            return Ok(http.clone());
+        }
+        let http = config::build_client(&self.inner.config)?;
+        *self.inner.http.write().unwrap() = Some(http.clone());
+        Ok(http)
+    }
+
+    /// Returns the connection that gRPC calls should be made on.
+    #[cfg(feature = "grpc")]
+    pub(crate) fn grpc(&self) -> Result<Arc<crate::grpc::Connection>, Error> {
+        let mut grpc = self.inner.grpc.lock().unwrap();
+        if let Some(connection) = grpc.as_ref() {
+            return Ok(connection.clone());
+        }
+        let connection = Arc::new(crate::grpc::Connection::new()?);
+        *grpc = Some(connection.clone());
+        Ok(connection)
+    }
+
+    /// Blocks until `Config::rate_limit` allows sending a request with `method`.
+    pub(crate) fn throttle(&self, method: &reqwest::Method) {
+        if let Some(limiter) = &self.inner.rate_limiter {
+            limiter.acquire(method);
+        }
+    }
+
+    /// The headers that authenticate a request.
+    pub(crate) fn headers(&self) -> Result<reqwest::header::HeaderMap, Error> {
+        self.headers_for(&self.inner.token_cache)
+    }
+
+    /// The headers that authenticate a request to Cloud KMS.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn kms_headers(&self) -> Result<reqwest::header::HeaderMap, Error> {
+        self.headers_for(&self.inner.kms_token_cache)
+    }
+
+    fn headers_for(&self, token_cache: &TokenCache) -> Result<reqwest::header::HeaderMap, Error> {
+        let config = &self.inner.config;
+        if config.anonymous || config::emulator_host().is_some() {
+            return Ok(reqwest::header::HeaderMap::new());
+        }
+        let token = match self
+            .token_provider()
+            .and_then(|provider| token_cache.get(self, &provider))
+        {
+            Ok(token) => token,
+            Err(e) => {
+                self.inner.degraded.set_degraded(config.degraded_mode);
+                return Err(e);
+            }
+        };
+        self.inner.degraded.set_degraded(false);
+        authenticated_headers(config, &token)
+    }
+
+    fn token_provider(&self) -> Result<Arc<dyn TokenProvider>, Error> {
+        let config = &self.inner.config;
+        if let Some(provider) = &config.token_provider {
+            return Ok(provider.clone());
+        }
+        match &config.service_account {
+            Some(service_account) => Ok(Arc::new(ServiceAccountTokenProvider::new(
+                service_account.clone(),
+            ))),
+            None => credentials::default_provider(),
+        }
+    }
+
+    /// The service account of this client, needed to sign urls and to find the default project.
+    pub(crate) fn service_account(&self) -> Result<ServiceAccount, Error> {
+        if let Some(service_account) = &self.inner.config.service_account {
+            return Ok(service_account.clone());
+        }
+        let mut guard = self.inner.env_service_account.lock().unwrap();
+        if let Some(service_account) = guard.as_ref() {
+            return Ok(service_account.clone());
+        }
+        let service_account = ServiceAccount::from_env()?;
+        *guard = Some(service_account.clone());
+        Ok(service_account)
+    }
+
+    /// The project in which buckets and HMAC keys are created and listed: `Config::project_id`,
+    /// or else the project of the service account.
+    pub fn project_id(&self) -> Result<String, Error> {
+        match &self.inner.config.project_id {
+            Some(project_id) => Ok(project_id.clone()),
+            None => Ok(self.service_account()?.project_id),
+        }
+    }
+
+    /// The query parameters that should be added to every request to bill the right project.
+    pub(crate) fn user_project_query(&self) -> Vec<(&'static str, String)> {
+        config::user_project_query(&self.inner.config)
+    }
+
+    /// The base url of the JSON API.
+    pub(crate) fn api_url(&self) -> String {
+        config::api_url(&self.inner.config)
+    }
+
+    /// The base url for media uploads.
+    pub(crate) fn upload_url(&self) -> String {
+        config::upload_url(&self.inner.config)
+    }
+
+    /// The base url for plain, unauthenticated downloads.
+    pub(crate) fn download_url(&self) -> String {
+        config::download_url(&self.inner.config)
+    }
+}
+
+/// Replaces the client used by the functions on the resource types.
+pub(crate) fn set_global(client: Client) {
+    *GLOBAL.write().unwrap() = client;
+}
+
+fn authenticated_headers(
+    config: &Config,
+    token: &str,
+) -> Result<reqwest::header::HeaderMap, Error> {
+    let mut result = reqwest::header::HeaderMap::new();
+    result.insert(
+        reqwest::header::AUTHORIZATION,
+        format!("Bearer {}", token).parse().unwrap(),
+    );
+    if let Some(project) = &config.quota_project {
+        result.insert("x-goog-user-project", project.parse()?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AccessScope;
+
+    #[test]
+    fn anonymous_requests_have_no_credentials() {
+        let client = Client::with_config(Config {
+            anonymous: true,
+            ..Default::default()
+        });
+        assert!(client.headers().unwrap().is_empty());
+    }
+
+    fn credentials(kind: &str, project_id: &str) -> String {
+        format!(
+            r#"{{
+                "type": "{}",
+                "project_id": "{}",
+                "private_key_id": "id",
+                "private_key": "key",
+                "client_email": "storage@{1}.iam.gserviceaccount.com",
+                "client_id": "1",
+                "auth_uri": "https://accounts.google.com/o/oauth2/auth",
+                "token_uri": "https://oauth2.googleapis.com/token",
+                "auth_provider_x509_cert_url": "https://www.googleapis.com/oauth2/v1/certs",
+                "client_x509_cert_url": "https://www.googleapis.com/robot/v1/metadata/x509/storage"
+            }}"#,
+            kind, project_id
+        )
+    }
+
+    fn service_account(project_id: &str) -> ServiceAccount {
+        ServiceAccount::from_json(&credentials("service_account", project_id)).unwrap()
+    }
+
+    #[test]
+    fn clients_use_their_own_service_account() {
+        let first = Client::new(service_account("first-project"));
+        let second = Client::new(service_account("second-project"));
+        assert_eq!(first.service_account().unwrap().project_id, "first-project");
+        assert_eq!(
+            second.service_account().unwrap().project_id,
+            "second-project"
+        );
+        assert_eq!(
+            first.clone().service_account().unwrap().client_email,
+            "storage@first-project.iam.gserviceaccount.com"
+        );
+    }
+
+    #[derive(Debug)]
+    struct FixedToken(&'static str);
+
+    impl TokenProvider for FixedToken {
+        fn token(&self, _client: &Client, _scopes: &[&str]) -> Result<crate::Token, Error> {
+            Ok(crate::Token {
+                access_token: self.0.to_string(),
+                expires_at: None,
+            })
+        }
+    }
+
+    #[test]
+    fn clients_have_their_own_tokens_and_project() {
+        let first = Client::with_config(Config {
+            token_provider: Some(Arc::new(FixedToken("first-token"))),
+            project_id: Some("first-project".to_string()),
+            ..Default::default()
+        });
+        let second = Client::with_config(Config {
+            token_provider: Some(Arc::new(FixedToken("second-token"))),
+            service_account: Some(service_account("second-project")),
+            ..Default::default()
+        });
+        assert_eq!(
+            first.headers().unwrap()["authorization"],
+            "Bearer first-token"
+        );
+        assert_eq!(
+            second.headers().unwrap()["authorization"],
+            "Bearer second-token"
+        );
+        assert_eq!(first.project_id().unwrap(), "first-project");
+        assert_eq!(second.project_id().unwrap(), "second-project");
+    }
+
+    #[test]
+    fn current_token() {
+        let client = Client::with_config(Config {
+            token_provider: Some(Arc::new(FixedToken("a-token"))),
+            ..Default::default()
+        });
+        let token = client.auth().current_token().unwrap();
+        assert_eq!(token.access_token, "a-token");
+        assert_eq!(token.expires_at, None);
+        assert_eq!(token.scopes, [ACCESS_SCOPE]);
+        assert_eq!(client.auth().force_refresh().unwrap(), token);
+
+        let anonymous = Client::with_config(Config {
+            anonymous: true,
+            ..Default::default()
+        });
+        assert!(anonymous.auth().current_token().is_err());
+    }
+
+    #[test]
+    fn scopes() {
+        let client = Client::with_config(Config {
+            token_provider: Some(Arc::new(FixedToken("a-token"))),
+            scopes: vec![
+                AccessScope::ReadOnly,
+                AccessScope::from("https://www.googleapis.com/auth/pubsub"),
+            ],
+            ..Default::default()
+        });
+        let token = client.auth().current_token().unwrap();
+        assert_eq!(
+            token.scopes,
+            [
+                "https://www.googleapis.com/auth/devstorage.read_only",
+                "https://www.googleapis.com/auth/pubsub",
+            ]
+        );
+        assert_eq!(
+            AccessScope::from("https://www.googleapis.com/auth/devstorage.full_control"),
+            AccessScope::FullControl
+        );
+        assert_eq!(AccessScope::FullControl.as_str(), ACCESS_SCOPE);
+    }
+
+    #[test]
+    fn rejects_other_credential_types() {
+        let json = credentials("external_account", "project");
+        assert!(ServiceAccount::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn quota_project_header() {
+        let config = Config {
+            quota_project: Some("billing-project".to_string()),
+            ..Default::default()
+        };
+        let headers = authenticated_headers(&config, "token").unwrap();
+        assert_eq!(headers["authorization"], "Bearer token");
+        assert_eq!(headers["x-goog-user-project"], "billing-project");
+    }
+}
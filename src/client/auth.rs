@@ -0,0 +1,60 @@
+use crate::config;
+use crate::token::{Token, TokenInfo};
+use crate::Client;
+
+/// Access to the credentials of a `Client`, for applications that hand its access token to other
+/// processes or need to diagnose authentication problems. Obtained through `Client::auth`.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthClient<'a>(pub(super) &'a Client);
+
+impl<'a> AuthClient<'a> {
+    /// Returns the access token that requests are currently sent with. A token is obtained first
+    /// if there is none yet or if it has expired, just like before a request. Fails for clients
+    /// that do not authenticate, because of `Config::anonymous` or an emulator.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Client;
+    ///
+    /// let token = Client::default().auth().current_token()?;
+    /// std::process::Command::new("gsutil")
+    ///     .env("CLOUDSDK_AUTH_ACCESS_TOKEN", &token.access_token)
+    ///     .args(&["ls", "gs://my_bucket"])
+    ///     .status()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn current_token(&self) -> Result<TokenInfo, crate::Error> {
+        self.check_authenticated()?;
+        let provider = self.0.token_provider()?;
+        let token = self.0.inner.token_cache.get_token(self.0, &provider)?;
+        Ok(self.info(token))
+    }
+
+    /// Obtains a new access token, even if the current one is still valid, and uses it for all
+    /// following requests. This is useful after the permissions of the account changed, or when a
+    /// token was revoked. If no new token can be obtained, the current one is kept.
+    pub fn force_refresh(&self) -> Result<TokenInfo, crate::Error> {
+        self.check_authenticated()?;
+        let provider = self.0.token_provider()?;
+        let token = self.0.inner.token_cache.refresh(self.0, &provider)?;
+        Ok(self.info(token))
+    }
+
+    fn check_authenticated(&self) -> Result<(), crate::Error> {
+        if self.0.config().anonymous || config::emulator_host().is_some() {
+            return Err(crate::Error::Other(
+                "the client does not authenticate, so it has no access token".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn info(&self, token: Token) -> TokenInfo {
+        TokenInfo {
+            access_token: token.access_token,
+            expires_at: token.expires_at,
+            scopes: self.0.inner.token_cache.scopes().to_vec(),
+        }
+    }
+}
@@ -0,0 +1,478 @@
+use crate::error::{Error, GoogleResponse};
+use crate::http::RequestExt;
+use crate::resources::bucket::*;
+use crate::resources::bucket_access_control::{BucketAccessControl, NewBucketAccessControl};
+use crate::resources::common::Entity;
+use crate::resources::common::ListIter;
+use crate::resources::default_object_access_control::{
+    DefaultObjectAccessControl, NewDefaultObjectAccessControl,
+};
+use crate::Client;
+
+/// Operations on buckets, authenticated with the credentials of a `Client`. Obtained through
+/// `Client::bucket`.
+#[derive(Debug, Clone, Copy)]
+pub struct BucketClient<'a>(pub(super) &'a Client);
+
+/// The first version of IAM policies that supports bindings with a condition.
+const CONDITIONAL_POLICY_VERSION: i32 = 3;
+
+/// How often `modify_iam_policy` reads and writes the policy before giving up on a policy that
+/// keeps being changed concurrently.
+const IAM_POLICY_ATTEMPTS: u32 = 5;
+
+impl<'a> BucketClient<'a> {
+    /// Creates a new `Bucket`. There are many options that you can provide for creating a new
+    /// bucket, so the `NewBucket` resource contains all of them. Note that `NewBucket` implements
+    /// `Default`, so you don't have to specify the fields you're not using. And error is returned
+    /// if that bucket name is already taken.
+    pub fn create(&self, new_bucket: &NewBucket) -> Result<Bucket, Error> {
+        new_bucket.location.validate()?;
+        let url = format!("{}/b/", self.0.api_url());
+        let project = self.0.project_id()?;
+        let query = [("project", project)];
+        let client = self.0.http()?;
+        let result: GoogleResponse<Bucket> = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .query(&query)
+            .json(new_bucket)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns all `Bucket`s within this project.
+    pub fn list(&self) -> Result<Vec<Bucket>, Error> {
+        self.list_with(&self.0.project_id()?, None, None).collect()
+    }
+
+    /// Returns an iterator over the `Bucket`s within `project`, optionally limited to the buckets
+    /// whose name starts with `prefix`. Buckets are requested from Google in pages of at most
+    /// `page_size` buckets as the iterator is advanced, so this also works for projects with a
+    /// large amount of buckets.
+    pub fn list_with(
+        &self,
+        project: &str,
+        prefix: Option<&str>,
+        page_size: Option<u32>,
+    ) -> impl Iterator<Item = Result<Bucket, Error>> {
+        let url = format!("{}/b/", self.0.api_url());
+        let mut query = vec![("project", project.to_string())];
+        if let Some(prefix) = prefix {
+            query.push(("prefix", prefix.to_string()));
+        }
+        if let Some(page_size) = page_size {
+            query.push(("maxResults", page_size.to_string()));
+        }
+        ListIter::new(self.0, url, query)
+    }
+
+    /// Returns a single `Bucket` by its name. If the Bucket does not exist, an error is returned.
+    pub fn read(&self, name: &str) -> Result<Bucket, Error> {
+        self.read_with(name, &BucketPreconditions::default())
+    }
+
+    /// Returns a single `Bucket` by its name, if its metageneration satisfies `preconditions`.
+    pub fn read_with(
+        &self,
+        name: &str,
+        preconditions: &BucketPreconditions,
+    ) -> Result<Bucket, Error> {
+        let url = format!("{}/b/{}", self.0.api_url(), name);
+        let conditional = *preconditions != BucketPreconditions::default();
+        let headers = match self.0.headers() {
+            Ok(headers) => headers,
+            Err(e) if conditional => return Err(e),
+            Err(e) => return self.0.degraded().recall(&url).ok_or(e),
+        };
+        let client = self.0.http()?;
+        let response = client
+            .get(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .query(&preconditions.query())
+            .execute(self.0)?;
+        if !response.status().is_success() {
+            return Err(Error::from_response(response));
+        }
+        let body = response.text()?;
+        let result: GoogleResponse<Bucket> = serde_json::from_str(&body)?;
+        match result {
+            GoogleResponse::Success(s) => {
+                if !conditional {
+                    self.0.degraded().remember(self.0.config(), &url, body);
+                }
+                Ok(s)
+            }
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Checks whether a bucket with the given name exists. Returns `Ok(false)` only if Google
+    /// responds with `404 Not Found`. If the bucket exists but you are not allowed to view it, an
+    /// `Error::PermissionDenied` is returned instead, so that you can tell a missing bucket
+    /// apart from one you lack permission for.
+    pub fn exists(&self, name: &str) -> Result<bool, Error> {
+        let url = format!("{}/b/{}", self.0.api_url(), name);
+        let client = self.0.http()?;
+        let response = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&[("fields", "name")])
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(true)
+        } else if response.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(false)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Update an existing `Bucket`. If you declare you bucket as mutable, you can edit its fields.
+    /// You can then flush your changes to Google Cloud Storage using this method.
+    pub fn update(&self, bucket: &Bucket) -> Result<Bucket, Error> {
+        self.update_with(bucket, &BucketPreconditions::default())
+    }
+
+    /// Updates an existing `Bucket` like `update`, if its metageneration satisfies
+    /// `preconditions`.
+    pub fn update_with(
+        &self,
+        bucket: &Bucket,
+        preconditions: &BucketPreconditions,
+    ) -> Result<Bucket, Error> {
+        let url = format!("{}/b/{}", self.0.api_url(), bucket.name);
+        let client = self.0.http()?;
+        let result: GoogleResponse<Bucket> = client
+            .put(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .query(&preconditions.query())
+            .json(bucket)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Applies the changes in `patch` to `bucket`, leaving all properties that are not set in
+    /// `patch` untouched. The request only succeeds if the bucket was not modified since `bucket`
+    /// was retrieved, that is, if its metageneration still matches. Otherwise, Google responds with
+    /// `412 Precondition Failed` and nothing is changed, so that concurrent updates are never
+    /// silently overwritten. In that case, read the bucket again and retry.
+    pub fn patch(&self, bucket: &Bucket, patch: &BucketPatch) -> Result<Bucket, Error> {
+        let preconditions = BucketPreconditions {
+            if_metageneration_match: Some(bucket.metageneration),
+            ..Default::default()
+        };
+        self.patch_with(bucket, patch, &preconditions)
+    }
+
+    /// Applies the changes in `patch` to `bucket` like `patch`, but under `preconditions` instead
+    /// of the metageneration of `bucket`. Without preconditions, the changes are always applied.
+    pub fn patch_with(
+        &self,
+        bucket: &Bucket,
+        patch: &BucketPatch,
+        preconditions: &BucketPreconditions,
+    ) -> Result<Bucket, Error> {
+        let url = format!("{}/b/{}", self.0.api_url(), bucket.name);
+        let client = self.0.http()?;
+        let result: GoogleResponse<Bucket> = client
+            .patch(&url)
+            .headers(self.0.headers()?)
+            .query(&preconditions.query())
+            .query(&self.0.user_project_query())
+            .json(patch)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Delete an existing `Bucket`. This permanently removes a bucket from Google Cloud Storage.
+    /// An error is returned when you don't have sufficient permissions, or when the
+    /// `retention_policy` prevents you from deleting your Bucket.
+    pub fn delete(&self, bucket: Bucket) -> Result<(), Error> {
+        self.delete_with(bucket, &BucketPreconditions::default())
+    }
+
+    /// Deletes an existing `Bucket` like `delete`, if its metageneration satisfies
+    /// `preconditions`.
+    pub fn delete_with(
+        &self,
+        bucket: Bucket,
+        preconditions: &BucketPreconditions,
+    ) -> Result<(), Error> {
+        let url = format!("{}/b/{}", self.0.api_url(), bucket.name);
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .query(&preconditions.query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Restores a soft-deleted bucket. Since a new bucket with the same name may have been created
+    /// in the meantime, the `generation` of the deleted bucket must be provided as well.
+    pub fn restore(&self, name: &str, generation: i64) -> Result<Bucket, Error> {
+        let url = format!("{}/b/{}/restore", self.0.api_url(), name);
+        let client = self.0.http()?;
+        let result: GoogleResponse<Bucket> = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&[("generation", generation)])
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the [IAM Policy](https://cloud.google.com/iam/docs/) for this bucket. Version 3 of
+    /// the policy is requested, so that bindings with a condition are returned intact and survive
+    /// being passed back to `set_iam_policy`.
+    pub fn get_iam_policy(&self, bucket: &Bucket) -> Result<IamPolicy, Error> {
+        let url = format!("{}/b/{}/iam", self.0.api_url(), bucket.name);
+        let client = self.0.http()?;
+        let result: GoogleResponse<IamPolicy> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&[("optionsRequestedPolicyVersion", CONDITIONAL_POLICY_VERSION)])
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Updates the [IAM Policy](https://cloud.google.com/iam/docs/) for this bucket. Google does
+    /// not accept metageneration preconditions here; instead, a policy with the `etag` of the
+    /// policy it was based on is only applied if the policy was not changed in the meantime.
+    ///
+    /// Google only accepts bindings with a condition in version 3 policies, so a policy with such
+    /// bindings is sent as version 3, whatever its `version` is.
+    pub fn set_iam_policy(&self, bucket: &Bucket, iam: &IamPolicy) -> Result<IamPolicy, Error> {
+        let url = format!("{}/b/{}/iam", self.0.api_url(), bucket.name);
+        let client = self.0.http()?;
+        let conditional = iam.bindings.iter().any(|b| b.condition.is_some());
+        let upgraded;
+        let iam = if conditional && iam.version < CONDITIONAL_POLICY_VERSION {
+            upgraded = IamPolicy {
+                version: CONDITIONAL_POLICY_VERSION,
+                ..iam.clone()
+            };
+            &upgraded
+        } else {
+            iam
+        };
+        let result: GoogleResponse<IamPolicy> = client
+            .put(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(iam)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Reads the [IAM Policy](https://cloud.google.com/iam/docs/) of this bucket, lets `modify`
+    /// change it and saves the result. The policy is only saved if its `etag` still matches, so
+    /// that concurrent changes are not overwritten; if it does not, the cycle is repeated with
+    /// the new policy. Nothing is saved if `modify` leaves the policy unchanged.
+    pub fn modify_iam_policy<F>(&self, bucket: &Bucket, mut modify: F) -> Result<IamPolicy, Error>
+    where
+        F: FnMut(&mut IamPolicy),
+    {
+        let mut attempt = 1;
+        loop {
+            let current = self.get_iam_policy(bucket)?;
+            let mut policy = current.clone();
+            modify(&mut policy);
+            if policy == current {
+                return Ok(current);
+            }
+            match self.set_iam_policy(bucket, &policy) {
+                Err(e)
+                    if (e.is_precondition_failed() || e.is_conflict())
+                        && attempt < IAM_POLICY_ATTEMPTS =>
+                {
+                    attempt += 1
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Checks whether the user provided in the service account has this permission.
+    pub fn test_iam_permission(
+        &self,
+        bucket: &Bucket,
+        permission: &str,
+    ) -> Result<TestIamPermission, Error> {
+        if permission == "storage.buckets.list" || permission == "storage.buckets.create" {
+            return Err(Error::new(
+                "tested permission must not be `storage.buckets.list` or `storage.buckets.create`",
+            ));
+        }
+        let url = format!("{}/b/{}/iam/testPermissions", self.0.api_url(), bucket.name);
+        let client = self.0.http()?;
+        let result: GoogleResponse<TestIamPermission> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .query(&[("permissions", permission)])
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the ACL entries of this bucket.
+    pub fn acl_list(&self, bucket: &Bucket) -> Result<Vec<BucketAccessControl>, Error> {
+        self.0.bucket_access_control().list(&bucket.name)
+    }
+
+    /// Adds an ACL entry to this bucket.
+    pub fn acl_create(
+        &self,
+        bucket: &Bucket,
+        new_acl: &NewBucketAccessControl,
+    ) -> Result<BucketAccessControl, Error> {
+        self.0.bucket_access_control().create(&bucket.name, new_acl)
+    }
+
+    /// Changes the role of the entity in `acl` in the ACL of this bucket. Only the role is sent to
+    /// Google, as a patch, so the other properties of the entry are left untouched.
+    pub fn acl_update(
+        &self,
+        bucket: &Bucket,
+        acl: &NewBucketAccessControl,
+    ) -> Result<BucketAccessControl, Error> {
+        let url = format!("{}/b/{}/acl/{}", self.0.api_url(), bucket.name, acl.entity);
+        let client = self.0.http()?;
+        let result: GoogleResponse<BucketAccessControl> = client
+            .patch(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(&serde_json::json!({ "role": acl.role }))
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Removes the entry for `entity` from the ACL of this bucket.
+    pub fn acl_delete(&self, bucket: &Bucket, entity: &Entity) -> Result<(), Error> {
+        let url = format!("{}/b/{}/acl/{}", self.0.api_url(), bucket.name, entity);
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Returns the default object ACL entries of this bucket, which are applied to new objects
+    /// that are created without an ACL of their own.
+    pub fn default_object_acl_list(
+        &self,
+        bucket: &Bucket,
+    ) -> Result<Vec<DefaultObjectAccessControl>, Error> {
+        self.0.default_object_access_control().list(&bucket.name)
+    }
+
+    /// Adds a default object ACL entry to this bucket.
+    pub fn default_object_acl_create(
+        &self,
+        bucket: &Bucket,
+        new_acl: &NewDefaultObjectAccessControl,
+    ) -> Result<DefaultObjectAccessControl, Error> {
+        self.0
+            .default_object_access_control()
+            .create(&bucket.name, new_acl)
+    }
+
+    /// Changes the role of the entity in `acl` in the default object ACL of this bucket.
+    pub fn default_object_acl_update(
+        &self,
+        bucket: &Bucket,
+        acl: &NewDefaultObjectAccessControl,
+    ) -> Result<DefaultObjectAccessControl, Error> {
+        let url = format!(
+            "{}/b/{}/defaultObjectAcl/{}",
+            self.0.api_url(),
+            bucket.name,
+            acl.entity
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<DefaultObjectAccessControl> = client
+            .put(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(acl)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(DefaultObjectAccessControl {
+                bucket: bucket.name.clone(),
+                ..s
+            }),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Removes the entry for `entity` from the default object ACL of this bucket.
+    pub fn default_object_acl_delete(&self, bucket: &Bucket, entity: &Entity) -> Result<(), Error> {
+        let url = format!(
+            "{}/b/{}/defaultObjectAcl/{}",
+            self.0.api_url(),
+            bucket.name,
+            entity
+        );
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+}
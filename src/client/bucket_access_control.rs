@@ -0,0 +1,100 @@
+use crate::error::GoogleResponse;
+use crate::http::RequestExt;
+use crate::resources::bucket_access_control::*;
+use crate::resources::common::Entity;
+use crate::resources::common::ListResponse;
+use crate::Client;
+
+/// Operations on the access control lists of buckets, authenticated with the credentials of a
+/// `Client`. Obtained through `Client::bucket_access_control`.
+#[derive(Debug, Clone, Copy)]
+pub struct BucketAccessControlClient<'a>(pub(super) &'a Client);
+
+impl<'a> BucketAccessControlClient<'a> {
+    /// Create a new `BucketAccessControl` using the provided `NewBucketAccessControl`, related to
+    /// the `Bucket` provided by the `bucket_name` argument.
+    pub fn create(
+        &self,
+        bucket: &str,
+        new_bucket_access_control: &NewBucketAccessControl,
+    ) -> Result<BucketAccessControl, crate::Error> {
+        let url = format!("{}/b/{}/acl", self.0.api_url(), bucket);
+        let client = self.0.http()?;
+        let result: GoogleResponse<BucketAccessControl> = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(new_bucket_access_control)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns all `BucketAccessControl`s related to this bucket.
+    pub fn list(&self, bucket: &str) -> Result<Vec<BucketAccessControl>, crate::Error> {
+        let url = format!("{}/b/{}/acl", self.0.api_url(), bucket);
+        let client = self.0.http()?;
+        let result: GoogleResponse<ListResponse<BucketAccessControl>> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s.items),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the ACL entry for the specified entity on the specified bucket.
+    pub fn read(&self, bucket: &str, entity: &Entity) -> Result<BucketAccessControl, crate::Error> {
+        let url = format!("{}/b/{}/acl/{}", self.0.api_url(), bucket, entity);
+        let client = self.0.http()?;
+        let result: GoogleResponse<BucketAccessControl> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Update the given `BucketAccessControl`.
+    pub fn update(&self, acl: &BucketAccessControl) -> Result<BucketAccessControl, crate::Error> {
+        let url = format!("{}/b/{}/acl/{}", self.0.api_url(), acl.bucket, acl.entity);
+        let client = self.0.http()?;
+        let result: GoogleResponse<BucketAccessControl> = client
+            .put(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(acl)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Permanently deletes the ACL entry for the specified entity on the specified bucket.
+    pub fn delete(&self, acl: BucketAccessControl) -> Result<(), crate::Error> {
+        let url = format!("{}/b/{}/acl/{}", self.0.api_url(), acl.bucket, acl.entity);
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(crate::Error::from_response(response))
+        }
+    }
+}
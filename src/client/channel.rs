@@ -0,0 +1,58 @@
+use crate::error::GoogleResponse;
+use crate::http::RequestExt;
+use crate::resources::channel::*;
+use crate::Client;
+
+/// Operations on notification channels, authenticated with the credentials of a `Client`. Obtained
+/// through `Client::channel`.
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelClient<'a>(pub(super) &'a Client);
+
+impl<'a> ChannelClient<'a> {
+    /// Starts watching all objects in `bucket` for changes. Every change is delivered to the
+    /// webhook at `new_channel.address` until the channel expires or is stopped.
+    pub fn watch_all(
+        &self,
+        bucket: &str,
+        new_channel: &NewChannel,
+    ) -> Result<Channel, crate::Error> {
+        let url = format!("{}/b/{}/o/watch", self.0.api_url(), bucket);
+        let request = WatchRequest {
+            channel: new_channel,
+            r#type: "web_hook",
+        };
+        let client = self.0.http()?;
+        let result: GoogleResponse<Channel> = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(&request)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Stop receiving object change notifications through this channel.
+    pub fn stop(&self, channel: &Channel) -> Result<(), crate::Error> {
+        let url = format!("{}/channels/stop", self.0.api_url());
+        let body = serde_json::json!({
+            "id": channel.id,
+            "resourceId": channel.resource_id,
+        });
+        let client = self.0.http()?;
+        let response = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(&body)
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(crate::Error::from_response(response))
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::error::GoogleResponse;
+use crate::http::RequestExt;
+use crate::resources::common::Entity;
+use crate::resources::common::ListResponse;
+use crate::resources::default_object_access_control::*;
+use crate::Client;
+
+/// Operations on the default access control lists of objects, authenticated with the credentials of
+/// a `Client`. Obtained through `Client::default_object_access_control`.
+#[derive(Debug, Clone, Copy)]
+pub struct DefaultObjectAccessControlClient<'a>(pub(super) &'a Client);
+
+impl<'a> DefaultObjectAccessControlClient<'a> {
+    /// Create a new `DefaultObjectAccessControl` entry on the specified bucket.
+    pub fn create(
+        &self,
+        bucket: &str,
+        new_acl: &NewDefaultObjectAccessControl,
+    ) -> Result<DefaultObjectAccessControl, crate::Error> {
+        let url = format!("{}/b/{}/defaultObjectAcl", self.0.api_url(), bucket);
+        let client = self.0.http()?;
+        let result: GoogleResponse<DefaultObjectAccessControl> = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(new_acl)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(mut s) => {
+                s.bucket = bucket.to_string();
+                Ok(s)
+            }
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Retrieves default object ACL entries on the specified bucket.
+    pub fn list(&self, bucket: &str) -> Result<Vec<DefaultObjectAccessControl>, crate::Error> {
+        let url = format!("{}/b/{}/defaultObjectAcl", self.0.api_url(), bucket);
+        let client = self.0.http()?;
+        let result: GoogleResponse<ListResponse<DefaultObjectAccessControl>> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s
+                .items
+                .into_iter()
+                .map(|item| DefaultObjectAccessControl {
+                    bucket: bucket.to_string(),
+                    ..item
+                })
+                .collect()),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Read a single `DefaultObjectAccessControl`.
+    /// The `bucket` argument is the name of the bucket whose `DefaultObjectAccessControl` is to be
+    /// read, and the `entity` argument is the entity holding the permission. Options are
+    /// Can be "user-`userId`", "user-`email_address`", "group-`group_id`", "group-`email_address`",
+    /// "allUsers", or "allAuthenticatedUsers".
+    pub fn read(
+        &self,
+        bucket: &str,
+        entity: &Entity,
+    ) -> Result<DefaultObjectAccessControl, crate::Error> {
+        let url = format!(
+            "{}/b/{}/defaultObjectAcl/{}",
+            self.0.api_url(),
+            bucket,
+            entity
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<DefaultObjectAccessControl> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(mut s) => {
+                s.bucket = bucket.to_string();
+                Ok(s)
+            }
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Update the current `DefaultObjectAccessControl`.
+    pub fn update(
+        &self,
+        acl: &DefaultObjectAccessControl,
+    ) -> Result<DefaultObjectAccessControl, crate::Error> {
+        let url = format!(
+            "{}/b/{}/defaultObjectAcl/{}",
+            self.0.api_url(),
+            acl.bucket,
+            acl.entity
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<DefaultObjectAccessControl> = client
+            .put(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(acl)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(mut s) => {
+                s.bucket = acl.bucket.to_string();
+                Ok(s)
+            }
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Delete this 'DefaultObjectAccessControl`.
+    pub fn delete(&self, acl: DefaultObjectAccessControl) -> Result<(), crate::Error> {
+        let url = format!(
+            "{}/b/{}/defaultObjectAcl/{}",
+            self.0.api_url(),
+            acl.bucket,
+            acl.entity
+        );
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(crate::Error::from_response(response))
+        }
+    }
+}
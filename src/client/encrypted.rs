@@ -0,0 +1,272 @@
+use crate::error::Error;
+use crate::http::RequestExt;
+use crate::resources::object::{percent_encode, Object};
+use crate::Client;
+use std::collections::HashMap;
+
+/// The metadata key that holds the wrapped data key of an encrypted object.
+const WRAPPED_KEY: &str = "x-encryption-wrapped-key";
+/// The metadata key that holds the nonce the content of an encrypted object was encrypted with.
+const NONCE: &str = "x-encryption-nonce";
+/// The metadata key that holds the algorithm the content of an encrypted object was encrypted with.
+const ALGORITHM: &str = "x-encryption-algorithm";
+const AES_256_GCM: &str = "AES256-GCM";
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// The key that protects the data keys of objects encrypted through `Client::encrypted`.
+///
+/// Every object is encrypted with its own random data key. That data key is wrapped, that is
+/// encrypted, with this key, and stored in the metadata of the object. Google therefore never sees
+/// the content in plain text, nor a key that can decrypt it.
+#[derive(Clone, PartialEq)]
+pub enum EnvelopeKey {
+    /// A 256-bit AES key held by the application, which wraps data keys with AES-256-GCM. Losing
+    /// this key makes all objects encrypted with it unreadable.
+    Aes256([u8; 32]),
+    /// The resource name of a [Cloud KMS](https://cloud.google.com/kms/docs) key, for example
+    /// `projects/my-project/locations/global/keyRings/my-ring/cryptoKeys/my-key`, which wraps data
+    /// keys through the KMS API. The credentials of the client need the
+    /// `roles/cloudkms.cryptoKeyEncrypterDecrypter` role on this key.
+    Kms(String),
+}
+
+impl std::fmt::Debug for EnvelopeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvelopeKey::Aes256(_) => f.write_str("Aes256(..)"),
+            EnvelopeKey::Kms(name) => f.debug_tuple("Kms").field(name).finish(),
+        }
+    }
+}
+
+/// Uploads and downloads of objects whose content is encrypted on the client, with a data key that
+/// is protected by an `EnvelopeKey`. Obtained through `Client::encrypted`.
+///
+/// Content is encrypted with AES-256-GCM, which also detects any change made to the encrypted
+/// content. The wrapped data key, the nonce and the algorithm are stored in the metadata of the
+/// object, so an object can be copied or renamed and still be decrypted with the same
+/// `EnvelopeKey`. Note that the size, checksums and hashes that Google reports for such objects
+/// are those of the encrypted content.
+#[derive(Debug, Clone, Copy)]
+pub struct EncryptedClient<'a> {
+    pub(super) client: &'a Client,
+    pub(super) key: &'a EnvelopeKey,
+}
+
+#[derive(serde::Serialize)]
+struct KmsEncryptRequest {
+    plaintext: String,
+}
+
+#[derive(serde::Deserialize)]
+struct KmsEncryptResponse {
+    ciphertext: String,
+}
+
+#[derive(serde::Serialize)]
+struct KmsDecryptRequest {
+    ciphertext: String,
+}
+
+#[derive(serde::Deserialize)]
+struct KmsDecryptResponse {
+    plaintext: String,
+}
+
+impl<'a> EncryptedClient<'a> {
+    /// Encrypts `file` and uploads it as the object `filename` in `bucket`. The encryption
+    /// parameters are stored in the metadata of the object, next to the content in a single
+    /// request, so the object never exists without them.
+    pub fn create(
+        &self,
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error> {
+        let mime_type = crate::mime::resolve(mime_type, filename, file);
+        let data_key = crate::crypto::random_bytes(KEY_LEN)?;
+        let nonce = crate::crypto::random_bytes(NONCE_LEN)?;
+        let content = crate::crypto::aes256_gcm_seal(&data_key, &nonce, file)?;
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            WRAPPED_KEY.to_string(),
+            base64::encode(&self.wrap_key(&data_key)?),
+        );
+        metadata.insert(NONCE.to_string(), base64::encode(&nonce));
+        metadata.insert(ALGORITHM.to_string(), AES_256_GCM.to_string());
+        let resource = serde_json::json!({
+            "name": filename,
+            "contentType": mime_type,
+            "metadata": metadata,
+        });
+
+        let (content_type, body) =
+            super::object::multipart_related(&resource, mime_type, &content)?;
+
+        let url = format!(
+            "{}/b/{}/o?uploadType=multipart",
+            self.client.upload_url(),
+            percent_encode(bucket),
+        );
+        let mut headers = self.client.headers()?;
+        headers.insert(reqwest::header::CONTENT_TYPE, content_type.parse()?);
+        let response = self
+            .client
+            .http()?
+            .post(&url)
+            .headers(headers)
+            .query(&self.client.user_project_query())
+            .body(body)
+            .execute(self.client)?;
+        if response.status().is_success() {
+            Ok(serde_json::from_str(&response.text()?)?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Downloads the object `file_name` in `bucket`, and returns its decrypted content. Fails if
+    /// the object was not encrypted through `Client::encrypted`, if it was encrypted with another
+    /// `EnvelopeKey`, or if its content was changed.
+    pub fn download(&self, bucket: &str, file_name: &str) -> Result<Vec<u8>, Error> {
+        let object = self.client.object().read(bucket, file_name)?;
+        let metadata = object.metadata.unwrap_or_default();
+        let field = |key: &str| {
+            metadata.get(key).ok_or_else(|| {
+                Error::Other(format!(
+                    "{} is not encrypted: {} is missing",
+                    file_name, key
+                ))
+            })
+        };
+        let algorithm = field(ALGORITHM)?;
+        if algorithm != AES_256_GCM {
+            return Err(Error::Other(format!(
+                "{} is encrypted with unsupported algorithm {}",
+                file_name, algorithm
+            )));
+        }
+        let invalid = |e| Error::Other(format!("invalid encryption metadata: {}", e));
+        let wrapped_key = base64::decode(field(WRAPPED_KEY)?).map_err(invalid)?;
+        let nonce = base64::decode(field(NONCE)?).map_err(invalid)?;
+        let data_key = self.unwrap_key(&wrapped_key)?;
+        // the content of exactly the generation whose metadata was read
+        let content =
+            self.client
+                .object()
+                .download_generation(bucket, file_name, Some(object.generation))?;
+        crate::crypto::aes256_gcm_open(&data_key, &nonce, &content)
+    }
+
+    /// Encrypts a data key with the `EnvelopeKey`.
+    fn wrap_key(&self, data_key: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.key {
+            EnvelopeKey::Aes256(key) => {
+                let nonce = crate::crypto::random_bytes(NONCE_LEN)?;
+                let mut wrapped = crate::crypto::aes256_gcm_seal(key, &nonce, data_key)?;
+                wrapped.splice(0..0, nonce);
+                Ok(wrapped)
+            }
+            EnvelopeKey::Kms(name) => {
+                let request = KmsEncryptRequest {
+                    plaintext: base64::encode(data_key),
+                };
+                let response: KmsEncryptResponse = self.kms(name, "encrypt", &request)?;
+                base64::decode(&response.ciphertext)
+                    .map_err(|e| Error::Other(format!("invalid response from Cloud KMS: {}", e)))
+            }
+        }
+    }
+
+    /// Decrypts a data key that was encrypted by `wrap_key`.
+    fn unwrap_key(&self, wrapped_key: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.key {
+            EnvelopeKey::Aes256(key) => {
+                if wrapped_key.len() < NONCE_LEN {
+                    return Err(Error::new("invalid encryption metadata: key is too short"));
+                }
+                let (nonce, wrapped) = wrapped_key.split_at(NONCE_LEN);
+                crate::crypto::aes256_gcm_open(key, nonce, wrapped)
+            }
+            EnvelopeKey::Kms(name) => {
+                let request = KmsDecryptRequest {
+                    ciphertext: base64::encode(wrapped_key),
+                };
+                let response: KmsDecryptResponse = self.kms(name, "decrypt", &request)?;
+                base64::decode(&response.plaintext)
+                    .map_err(|e| Error::Other(format!("invalid response from Cloud KMS: {}", e)))
+            }
+        }
+    }
+
+    /// Calls `method` on the Cloud KMS key called `name`.
+    fn kms<T: serde::de::DeserializeOwned>(
+        &self,
+        name: &str,
+        method: &str,
+        request: &impl serde::Serialize,
+    ) -> Result<T, Error> {
+        let url = format!("https://cloudkms.googleapis.com/v1/{}:{}", name, method);
+        let response = self
+            .client
+            .http()?
+            .post(&url)
+            .headers(self.client.kms_headers()?)
+            .json(request)
+            .execute(self.client)?;
+        if response.status().is_success() {
+            Ok(response.json()?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_keys_wrap_data_keys() {
+        let client = Client::default();
+        let key = EnvelopeKey::Aes256([7; 32]);
+        let encrypted = client.encrypted(&key);
+        let wrapped = encrypted.wrap_key(&[1; 32]).unwrap();
+        assert_eq!(wrapped.len(), NONCE_LEN + KEY_LEN + 16);
+        assert_eq!(encrypted.unwrap_key(&wrapped).unwrap(), vec![1; 32]);
+
+        let other = EnvelopeKey::Aes256([8; 32]);
+        assert!(client.encrypted(&other).unwrap_key(&wrapped).is_err());
+        assert_eq!(format!("{:?}", key), "Aes256(..)");
+    }
+
+    #[test]
+    fn create_download() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let key = EnvelopeKey::Aes256([42; 32]);
+        let object = client.encrypted(&key).create(
+            &bucket.name,
+            b"secret",
+            "test-encrypted",
+            "text/plain",
+        )?;
+        assert_eq!(object.size, 6 + 16);
+        let stored = client.object().download(&bucket.name, "test-encrypted")?;
+        assert_ne!(&stored[..], b"secret");
+        let content = client
+            .encrypted(&key)
+            .download(&bucket.name, "test-encrypted")?;
+        assert_eq!(content, b"secret");
+
+        let other = EnvelopeKey::Aes256([43; 32]);
+        assert!(client
+            .encrypted(&other)
+            .download(&bucket.name, "test-encrypted")
+            .is_err());
+        client.object().delete(&bucket.name, "test-encrypted")?;
+        Ok(())
+    }
+}
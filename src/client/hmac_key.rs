@@ -0,0 +1,177 @@
+use crate::error::GoogleResponse;
+use crate::http::RequestExt;
+use crate::resources::common::ListIter;
+use crate::resources::hmac_key::*;
+use crate::Client;
+
+/// Operations on HMAC keys, authenticated with the credentials of a `Client`. Obtained through
+/// `Client::hmac_key`.
+#[derive(Debug, Clone, Copy)]
+pub struct HmacKeyClient<'a>(pub(super) &'a Client);
+
+impl<'a> HmacKeyClient<'a> {
+    /// Creates a new HMAC key for the specified service account.
+    pub fn create(&self) -> Result<HmacKey, crate::Error> {
+        self.create_for(&self.0.service_account()?.client_email)
+    }
+
+    /// Creates a new HMAC key for the service account identified by `service_account_email`, in
+    /// the project of the service account that is used to authenticate. This allows you to hand
+    /// out S3-interoperable credentials for a dedicated service account with limited permissions.
+    pub fn create_for(&self, service_account_email: &str) -> Result<HmacKey, crate::Error> {
+        use reqwest::header::CONTENT_LENGTH;
+
+        let url = format!(
+            "{}/projects/{}/hmacKeys",
+            self.0.api_url(),
+            self.0.project_id()?
+        );
+        let query = [("serviceAccountEmail", service_account_email)];
+        let mut headers = self.0.headers()?;
+        headers.insert(CONTENT_LENGTH, 0.into());
+        let client = self.0.http()?;
+        let result: GoogleResponse<HmacKey> = client
+            .post(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .query(&query)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Replaces the active HMAC keys of the service account identified by
+    /// `service_account_email` with a new key, and deactivates, or with `delete_old` deletes, the
+    /// keys that were active before. Keys that could not be retired are listed in
+    /// `HmacRotation::failed` rather than returned as an error, so that the new secret is not lost.
+    pub fn rotate(
+        &self,
+        service_account_email: &str,
+        delete_old: bool,
+    ) -> Result<HmacRotation, crate::Error> {
+        let old = self
+            .list_with(Some(service_account_email), false)
+            .filter(|key| !matches!(key, Ok(meta) if meta.state != HmacState::Active))
+            .collect::<Result<Vec<_>, _>>()?;
+        let key = self.create_for(service_account_email)?;
+        let mut retired = Vec::new();
+        let mut failed = Vec::new();
+        for meta in old {
+            let result = self
+                .update(&meta.access_id, HmacState::Inactive)
+                .and_then(|meta| {
+                    if delete_old {
+                        self.delete(&meta.access_id)?;
+                    }
+                    Ok(meta)
+                });
+            match result {
+                Ok(meta) => retired.push(meta),
+                Err(e) => failed.push((meta.access_id, e)),
+            }
+        }
+        Ok(HmacRotation {
+            key,
+            retired,
+            failed,
+        })
+    }
+
+    /// Retrieves a list of HMAC keys matching the criteria. Since the HmacKey is secret, this does
+    /// not return a `HmacKey`, but a `HmacMeta`. This is a redacted version of a `HmacKey`, but
+    /// with the secret data omitted.
+    pub fn list(&self) -> Result<Vec<HmacMeta>, crate::Error> {
+        self.list_with(None, false).collect()
+    }
+
+    /// Lazily lists the HMAC keys of the project, fetching additional pages as needed. If
+    /// `service_account_email` is provided, only the keys of that service account are returned.
+    /// Keys in the `Deleted` state are only included if `show_deleted_keys` is set.
+    pub fn list_with(
+        &self,
+        service_account_email: Option<&str>,
+        show_deleted_keys: bool,
+    ) -> impl Iterator<Item = Result<HmacMeta, crate::Error>> {
+        let project_id = match self.0.project_id() {
+            Ok(project_id) => project_id,
+            Err(e) => return ListIter::failed(self.0, e),
+        };
+        let url = format!("{}/projects/{}/hmacKeys", self.0.api_url(), project_id);
+        let mut query = vec![("showDeletedKeys", show_deleted_keys.to_string())];
+        if let Some(email) = service_account_email {
+            query.push(("serviceAccountEmail", email.to_string()));
+        }
+        ListIter::new(self.0, url, query)
+    }
+
+    /// Retrieves an HMAC key's metadata. Since the HmacKey is secret, this does not return a
+    /// `HmacKey`, but a `HmacMeta`. This is a redacted version of a `HmacKey`, but with the secret
+    /// data omitted.
+    pub fn read(&self, access_id: &str) -> Result<HmacMeta, crate::Error> {
+        let url = format!(
+            "{}/projects/{}/hmacKeys/{}",
+            self.0.api_url(),
+            self.0.project_id()?,
+            access_id
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<HmacMeta> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Updates the state of an HMAC key. See the HMAC Key resource descriptor for valid states.
+    /// Since the HmacKey is secret, this does not return a `HmacKey`, but a `HmacMeta`. This is a
+    /// redacted version of a `HmacKey`, but with the secret data omitted.
+    pub fn update(&self, access_id: &str, state: HmacState) -> Result<HmacMeta, crate::Error> {
+        let url = format!(
+            "{}/projects/{}/hmacKeys/{}",
+            self.0.api_url(),
+            self.0.project_id()?,
+            access_id
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<HmacMeta> = client
+            .put(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(&UpdateMeta { state })
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Deletes an HMAC key. Note that a key must be set to `Inactive` first.
+    pub fn delete(&self, access_id: &str) -> Result<(), crate::Error> {
+        let url = format!(
+            "{}/projects/{}/hmacKeys/{}",
+            self.0.api_url(),
+            self.0.project_id()?,
+            access_id
+        );
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(crate::Error::from_response(response))
+        }
+    }
+}
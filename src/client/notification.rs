@@ -0,0 +1,93 @@
+use crate::error::GoogleResponse;
+use crate::http::RequestExt;
+use crate::resources::common::ListResponse;
+use crate::resources::notification::*;
+use crate::Client;
+
+/// Operations on Pub/Sub notification configurations, authenticated with the credentials of a
+/// `Client`. Obtained through `Client::notification`.
+#[derive(Debug, Clone, Copy)]
+pub struct NotificationClient<'a>(pub(super) &'a Client);
+
+impl<'a> NotificationClient<'a> {
+    /// Creates a notification subscription for a given bucket. Fails without making a request if
+    /// `NewNotification::validate` does.
+    pub fn create(
+        &self,
+        bucket: &str,
+        new_notification: &NewNotification,
+    ) -> Result<Notification, crate::Error> {
+        new_notification.validate()?;
+        let url = format!("{}/b/{}/notificationConfigs", self.0.api_url(), bucket);
+        let client = self.0.http()?;
+        let result: GoogleResponse<Notification> = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(new_notification)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// View a notification configuration.
+    pub fn read(&self, bucket: &str, notification: &str) -> Result<Notification, crate::Error> {
+        let url = format!(
+            "{}/b/{}/notificationConfigs/{}",
+            self.0.api_url(),
+            bucket,
+            notification
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<Notification> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Retrieves a list of notification subscriptions for a given bucket.
+    pub fn list(&self, bucket: &str) -> Result<Vec<Notification>, crate::Error> {
+        let url = format!("{}/b/{}/notificationConfigs", self.0.api_url(), bucket);
+        let client = self.0.http()?;
+        let result: GoogleResponse<ListResponse<Notification>> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s.items),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Permanently deletes a notification subscription.
+    pub fn delete(&self, bucket: &str, notification: &str) -> Result<(), crate::Error> {
+        let url = format!(
+            "{}/b/{}/notificationConfigs/{}",
+            self.0.api_url(),
+            bucket,
+            notification
+        );
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(crate::Error::from_response(response))
+        }
+    }
+}
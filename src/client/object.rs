@@ -0,0 +1,1720 @@
+use crate::error::{Error, GoogleResponse};
+use crate::http::RequestExt;
+use crate::resources::common::ListIter;
+use crate::resources::object::*;
+use crate::resources::object::{percent_encode, RewriteResponse};
+use crate::signer::UrlSigner;
+use crate::sync::{
+    CopyOptions, DownloadOptions, FileDownload, FileUpload, ObjectCopy, SyncOptions, SyncSummary,
+    UploadOptions,
+};
+use crate::Client;
+
+/// Operations on objects, authenticated with the credentials of a `Client`. Obtained through
+/// `Client::object`.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectClient<'a>(pub(super) &'a Client);
+
+impl<'a> ObjectClient<'a> {
+    /// Create a new object.
+    /// Upload a file as that is loaded in memory to google cloud storage, where it will be
+    /// interpreted according to the mime type you specified.
+    pub fn create(
+        &self,
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error> {
+        self.insert(bucket, file, filename, mime_type, None)
+    }
+
+    /// Uploads a file like `create`, and applies the predefined ACL `acl` to the new object.
+    pub fn create_with_acl(
+        &self,
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+        acl: PredefinedAcl,
+    ) -> Result<Object, Error> {
+        self.insert(bucket, file, filename, mime_type, Some(acl))
+    }
+
+    fn insert(
+        &self,
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+        acl: Option<PredefinedAcl>,
+    ) -> Result<Object, Error> {
+        use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+
+        let mime_type = crate::mime::resolve(mime_type, filename, file);
+        #[cfg(feature = "grpc")]
+        if self.0.config().grpc && acl.is_none() {
+            return crate::grpc::create(self.0, bucket, file, filename, mime_type);
+        }
+        // has its own url for some reason
+        let client = self.0.http()?;
+        let url = &format!(
+            "{}/b/{}/o?uploadType=media&name={}",
+            self.0.upload_url(),
+            percent_encode(bucket),
+            percent_encode(filename),
+        );
+        let mut headers = self.0.headers()?;
+        headers.insert(CONTENT_TYPE, mime_type.to_string().parse()?);
+        headers.insert(CONTENT_LENGTH, file.len().to_string().parse()?);
+        let mut request = client
+            .post(url)
+            .headers(headers)
+            .query(&self.0.user_project_query());
+        if let Some(acl) = acl {
+            request = request.query(&[("predefinedAcl", acl)]);
+        }
+        let response = request.body(file.to_owned()).execute(self.0)?;
+        if response.status() == 200 {
+            Ok(serde_json::from_str(&response.text()?)?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Compresses `file` with gzip and uploads it as the object `filename` in `bucket`, with
+    /// `Content-Encoding: gzip`. The object keeps `mime_type` as its content type, and records
+    /// the size of `file` in its metadata under `UNCOMPRESSED_SIZE`.
+    ///
+    /// Through [decompressive transcoding](https://cloud.google.com/storage/docs/transcoding),
+    /// downloads with `download` return the uncompressed content, while `Object::size` and the
+    /// storage costs are those of the compressed content.
+    #[cfg(feature = "gzip")]
+    pub fn create_gzipped(
+        &self,
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error> {
+        use std::io::Write;
+
+        let mime_type = crate::mime::resolve(mime_type, filename, file);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let compressed = encoder
+            .write_all(file)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| Error::Other(format!("failed to compress {}: {}", filename, e)))?;
+        let resource = serde_json::json!({
+            "name": filename,
+            "contentType": mime_type,
+            "contentEncoding": "gzip",
+            "metadata": { (UNCOMPRESSED_SIZE): file.len().to_string() },
+        });
+
+        let (content_type, body) = multipart_related(&resource, mime_type, &compressed)?;
+
+        let url = format!(
+            "{}/b/{}/o?uploadType=multipart",
+            self.0.upload_url(),
+            percent_encode(bucket),
+        );
+        let mut headers = self.0.headers()?;
+        headers.insert(reqwest::header::CONTENT_TYPE, content_type.parse()?);
+        let response = self
+            .0
+            .http()?
+            .post(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .body(body)
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(serde_json::from_str(&response.text()?)?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Create a new object. This works in the same way as `Object::create`, except it does not need
+    /// to load the entire file in ram.
+    pub fn create_streamed<R: std::io::Read + Send + 'static>(
+        &self,
+        bucket: &str,
+        mut file: R,
+        length: u64,
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error> {
+        use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+        use std::io::Read;
+
+        let (file, mime_type): (Box<dyn Read + Send>, _) = if mime_type == crate::mime::AUTO {
+            // the start of the file is read ahead to look at its content
+            let mut start = Vec::new();
+            (&mut file)
+                .take(crate::mime::SNIFF_LEN as u64)
+                .read_to_end(&mut start)
+                .map_err(|e| Error::Other(format!("failed to read {}: {}", filename, e)))?;
+            let mime_type = crate::mime::guess(filename, &start);
+            (Box::new(std::io::Cursor::new(start).chain(file)), mime_type)
+        } else {
+            (Box::new(file), mime_type)
+        };
+
+        // has its own url for some reason
+        let client = self.0.http()?;
+        let url = &format!(
+            "{}/b/{}/o?uploadType=media&name={}",
+            self.0.upload_url(),
+            percent_encode(bucket),
+            percent_encode(filename),
+        );
+        let mut headers = self.0.headers()?;
+        headers.insert(CONTENT_TYPE, mime_type.to_string().parse()?);
+        headers.insert(CONTENT_LENGTH, length.to_string().parse()?);
+        let body = reqwest::blocking::Body::sized(crate::cancel::reader(file), length);
+        let response = client
+            .post(url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .body(body)
+            .execute(self.0)?;
+        if response.status() == 200 {
+            Ok(serde_json::from_str(&response.text()?)?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Create a new object from `file` without knowing its length up front, for example for
+    /// generated or piped data. The content is sent in a
+    /// [resumable upload](https://cloud.google.com/storage/docs/performing-resumable-uploads),
+    /// in chunks of 8 MiB that are each retried on their own, so at most two chunks are held in
+    /// memory at a time. When the upload fails or is cancelled, its session is deleted, so that
+    /// the chunks that were already uploaded are discarded.
+    pub fn create_streamed_unsized<R: std::io::Read>(
+        &self,
+        bucket: &str,
+        file: R,
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error> {
+        use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, LOCATION};
+        use std::io::Read;
+
+        let mut file = crate::cancel::reader(file);
+        let mut read_chunk = || {
+            let mut chunk = Vec::with_capacity(RESUMABLE_CHUNK_SIZE);
+            (&mut file)
+                .take(RESUMABLE_CHUNK_SIZE as u64)
+                .read_to_end(&mut chunk)
+                .map_err(|e| match crate::cancel::check() {
+                    Err(cancelled) => cancelled,
+                    Ok(()) => Error::Other(format!("failed to read {}: {}", filename, e)),
+                })?;
+            Ok::<_, Error>(chunk)
+        };
+        let mut chunk = read_chunk()?;
+        let mime_type = crate::mime::resolve(mime_type, filename, &chunk);
+
+        // start the session, which is where the chunks are uploaded to
+        let client = self.0.http()?;
+        let url = &format!(
+            "{}/b/{}/o?uploadType=resumable&name={}",
+            self.0.upload_url(),
+            percent_encode(bucket),
+            percent_encode(filename),
+        );
+        let mut headers = self.0.headers()?;
+        headers.insert("X-Upload-Content-Type", mime_type.to_string().parse()?);
+        headers.insert(CONTENT_LENGTH, 0.into());
+        let response = client
+            .post(url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if !response.status().is_success() {
+            return Err(Error::from_response(response));
+        }
+        let session = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or_else(|| Error::Other("resumable upload has no session url".to_string()))?
+            .to_string();
+
+        let upload = || {
+            let mut offset = 0;
+            loop {
+                // the next chunk is read ahead, since only the last chunk may state the total size
+                let next = if chunk.len() == RESUMABLE_CHUNK_SIZE {
+                    read_chunk()?
+                } else {
+                    Vec::new()
+                };
+                let end = offset + chunk.len();
+                let range = match (chunk.is_empty(), next.is_empty()) {
+                    (true, _) => format!("bytes */{}", end),
+                    (false, true) => format!("bytes {}-{}/{}", offset, end - 1, end),
+                    (false, false) => format!("bytes {}-{}/*", offset, end - 1),
+                };
+                let mut headers = self.0.headers()?;
+                headers.insert(CONTENT_RANGE, range.parse()?);
+                headers.insert(CONTENT_LENGTH, chunk.len().into());
+                let response = client
+                    .put(&session)
+                    .headers(headers)
+                    .body(chunk)
+                    .execute(self.0)?;
+                match response.status().as_u16() {
+                    200 | 201 => return Ok(serde_json::from_str(&response.text()?)?),
+                    // Google has received the chunk, and waits for the next one
+                    308 if !next.is_empty() => {
+                        let expected = format!("bytes=0-{}", end - 1);
+                        if response.headers().get("range").map(|r| r == &*expected) != Some(true) {
+                            let msg = format!(
+                                "resumable upload of {} lost bytes before {}",
+                                filename, end
+                            );
+                            return Err(Error::Other(msg));
+                        }
+                    }
+                    _ => return Err(Error::from_response(response)),
+                }
+                offset = end;
+                chunk = next;
+            }
+        };
+        let result = upload();
+        if result.is_err() {
+            // discard the chunks that were uploaded, also when the upload was cancelled
+            let _ = crate::cancel::uncancellable(|| self.cancel_session(&session));
+        }
+        result
+    }
+
+    /// Cancels the resumable upload session at `url`, so that Google discards the chunks that
+    /// were uploaded to it.
+    pub(crate) fn cancel_session(&self, url: &str) -> Result<(), Error> {
+        let mut headers = self.0.headers()?;
+        headers.insert(reqwest::header::CONTENT_LENGTH, 0.into());
+        let response = self
+            .0
+            .http()?
+            .delete(url)
+            .headers(headers)
+            .execute(self.0)?;
+        match response.status().as_u16() {
+            // the status with which Google confirms that the session was cancelled
+            499 => Ok(()),
+            _ if response.status().is_success() => Ok(()),
+            _ => Err(Error::from_response(response)),
+        }
+    }
+
+    /// Create a new object from the chunks of `stream`, which are uploaded as they arrive. When
+    /// `length` is known the content is sent in a single request like `create_streamed`,
+    /// otherwise in chunks like `create_streamed_unsized`. The current thread blocks while it
+    /// waits for the stream.
+    #[cfg(feature = "stream")]
+    pub fn create_from_stream<S, B, E>(
+        &self,
+        bucket: &str,
+        stream: S,
+        length: Option<u64>,
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error>
+    where
+        S: futures::Stream<Item = Result<B, E>> + Send + Unpin + 'static,
+        B: AsRef<[u8]> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let file = StreamReader {
+            stream: futures::executor::block_on_stream(stream),
+            chunk: None,
+            position: 0,
+        };
+        match length {
+            Some(length) => self.create_streamed(bucket, file, length, filename, mime_type),
+            None => self.create_streamed_unsized(bucket, file, filename, mime_type),
+        }
+    }
+
+    /// Obtain a list of objects within this Bucket.
+    pub fn list(&self, bucket: &str) -> Result<Vec<Object>, Error> {
+        self.list_with(bucket, None, None).collect()
+    }
+
+    /// Obtain a list of objects by prefix within this Bucket .
+    pub fn list_prefix(&self, bucket: &str, prefix: &str) -> Result<Vec<Object>, Error> {
+        self.list_with(bucket, Some(prefix), None).collect()
+    }
+
+    /// Returns an iterator over the objects in `bucket`, optionally limited to the objects whose
+    /// name starts with `prefix`. Objects are requested from Google in pages of at most
+    /// `page_size` objects as the iterator is advanced, so this also works for buckets that
+    /// contain millions of objects.
+    pub fn list_with(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        page_size: Option<u32>,
+    ) -> Box<dyn Iterator<Item = Result<Object, Error>> + Send> {
+        let options = ListOptions {
+            prefix: prefix.map(str::to_string),
+            page_size,
+            ..Default::default()
+        };
+        self.list_with_options(bucket, &options)
+    }
+
+    /// Returns an iterator over the objects in `bucket` whose name matches the glob `pattern`,
+    /// such as `**/*.parquet`. The pattern is evaluated by Google, so only matching objects are
+    /// transferred.
+    pub fn list_glob(
+        &self,
+        bucket: &str,
+        pattern: &str,
+    ) -> Box<dyn Iterator<Item = Result<Object, Error>> + Send> {
+        let options = ListOptions {
+            match_glob: Some(pattern.to_string()),
+            ..Default::default()
+        };
+        self.list_with_options(bucket, &options)
+    }
+
+    /// Returns an iterator over the objects in `bucket` that match `options`. Objects are
+    /// requested from Google page by page as the iterator is advanced.
+    pub fn list_with_options(
+        &self,
+        bucket: &str,
+        options: &ListOptions,
+    ) -> Box<dyn Iterator<Item = Result<Object, Error>> + Send> {
+        #[cfg(feature = "grpc")]
+        if self.0.config().grpc {
+            // gRPC listings are only used for the options that they support
+            let json_only = ListOptions {
+                prefix: None,
+                match_glob: None,
+                page_size: None,
+                ..options.clone()
+            };
+            if json_only == ListOptions::default() {
+                let iter = crate::grpc::GrpcListIter::new(
+                    self.0,
+                    bucket,
+                    options.prefix.as_deref(),
+                    options.match_glob.as_deref(),
+                    options.page_size,
+                );
+                return Box::new(iter);
+            }
+        }
+        let url = format!("{}/b/{}/o", self.0.api_url(), percent_encode(bucket));
+        Box::new(ListIter::new(self.0, url, list_query(options)))
+    }
+
+    /// Returns an iterator over the objects in `bucket` that match `options`, with only the
+    /// properties in `fields`, such as `name,size,updated`. Requesting only the properties that
+    /// are needed makes listings much smaller. The syntax of `fields` is described in
+    /// [the documentation](https://cloud.google.com/storage/docs/json_api#partial-response).
+    pub fn list_partial(
+        &self,
+        bucket: &str,
+        options: &ListOptions,
+        fields: &str,
+    ) -> Box<dyn Iterator<Item = Result<PartialObject, Error>> + Send> {
+        let url = format!("{}/b/{}/o", self.0.api_url(), percent_encode(bucket));
+        let mut query = list_query(options);
+        // the page token is needed to request the next page
+        query.push(("fields", format!("items({}),nextPageToken", fields)));
+        Box::new(ListIter::new(self.0, url, query))
+    }
+
+    /// Lists the objects whose name starts with `prefix` and contains no further `/`, and the
+    /// common prefixes of the objects that do, each of which ends with a `/`.
+    #[cfg(any(feature = "object-store", feature = "fs"))]
+    pub(crate) fn list_delimited(
+        &self,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<(Vec<Object>, Vec<String>), Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DelimitedList {
+            #[serde(default)]
+            items: Vec<Object>,
+            #[serde(default)]
+            prefixes: Vec<String>,
+            next_page_token: Option<String>,
+        }
+
+        let url = format!("{}/b/{}/o", self.0.api_url(), percent_encode(bucket));
+        let mut objects = Vec::new();
+        let mut prefixes = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![("delimiter", "/"), ("prefix", prefix)];
+            if let Some(page_token) = page_token.as_deref() {
+                query.push(("pageToken", page_token));
+            }
+            let response = self
+                .0
+                .http()?
+                .get(&url)
+                .headers(self.0.headers()?)
+                .query(&self.0.user_project_query())
+                .query(&query)
+                .execute(self.0)?;
+            if !response.status().is_success() {
+                return Err(Error::from_response(response));
+            }
+            let page: DelimitedList = response.json()?;
+            objects.extend(page.items);
+            prefixes.extend(page.prefixes);
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                return Ok((objects, prefixes));
+            }
+        }
+    }
+
+    /// Obtains a single object with the specified name in the specified bucket.
+    pub fn read(&self, bucket: &str, file_name: &str) -> Result<Object, Error> {
+        #[cfg(feature = "grpc")]
+        if self.0.config().grpc {
+            return crate::grpc::read(self.0, bucket, file_name);
+        }
+        self.get(bucket, file_name, None)
+    }
+
+    /// Obtains a single object like `read`, with the properties selected by `projection`.
+    pub fn read_with_projection(
+        &self,
+        bucket: &str,
+        file_name: &str,
+        projection: Projection,
+    ) -> Result<Object, Error> {
+        self.get(bucket, file_name, Some(projection))
+    }
+
+    fn get(
+        &self,
+        bucket: &str,
+        file_name: &str,
+        projection: Option<Projection>,
+    ) -> Result<Object, Error> {
+        let mut url = format!(
+            "{}/b/{}/o/{}",
+            self.0.api_url(),
+            percent_encode(bucket),
+            percent_encode(file_name),
+        );
+        // part of the url, so that the degraded mode cache keeps projections apart
+        if let Some(projection) = projection {
+            url.push_str(&format!("?projection={}", projection.as_str()));
+        }
+        let headers = match self.0.headers() {
+            Ok(headers) => headers,
+            Err(e) => return self.0.degraded().recall(&url).ok_or(e),
+        };
+        let client = self.0.http()?;
+        let response = client
+            .get(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if !response.status().is_success() {
+            return Err(Error::from_response(response));
+        }
+        let body = response.text()?;
+        let result: GoogleResponse<Object> = serde_json::from_str(&body)?;
+        match result {
+            GoogleResponse::Success(s) => {
+                self.0.degraded().remember(self.0.config(), &url, body);
+                Ok(s)
+            }
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Obtains only the properties in `fields`, such as `name,size,updated`, of a single object.
+    /// The syntax of `fields` is described in
+    /// [the documentation](https://cloud.google.com/storage/docs/json_api#partial-response).
+    pub fn read_partial(
+        &self,
+        bucket: &str,
+        file_name: &str,
+        fields: &str,
+    ) -> Result<PartialObject, Error> {
+        let url = format!(
+            "{}/b/{}/o/{}",
+            self.0.api_url(),
+            percent_encode(bucket),
+            percent_encode(file_name),
+        );
+        let response = self
+            .0
+            .http()?
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .query(&[("fields", fields)])
+            .execute(self.0)?;
+        // every property of a partial object is optional, so an error would parse as one too
+        if response.status().is_success() {
+            Ok(response.json()?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Download the content of the object with the specified name in the specified bucket.
+    /// When `Config::cache` is set, the content may be served from the cache instead.
+    pub fn download(&self, bucket: &str, file_name: &str) -> Result<bytes::Bytes, Error> {
+        match self.0.cache() {
+            Some(cache) => cache.download(self.0, bucket, file_name),
+            None => self.download_generation(bucket, file_name, None),
+        }
+    }
+
+    /// Downloads the content of `generation` of an object, or of its latest generation if not set.
+    pub(crate) fn download_generation(
+        &self,
+        bucket: &str,
+        file_name: &str,
+        generation: Option<i64>,
+    ) -> Result<bytes::Bytes, Error> {
+        #[cfg(feature = "grpc")]
+        if self.0.config().grpc {
+            return crate::grpc::download(self.0, bucket, file_name, generation);
+        }
+        let mut url = format!(
+            "{}/b/{}/o/{}?alt=media",
+            self.0.api_url(),
+            percent_encode(bucket),
+            percent_encode(file_name),
+        );
+        if let Some(generation) = generation {
+            url.push_str(&format!("&generation={}", generation));
+        }
+        let client = self.0.http()?;
+        let headers = match self.0.headers() {
+            Ok(headers) => headers,
+            Err(e) if self.0.is_degraded() => {
+                // publicly readable objects can still be downloaded without a token, in the
+                // same generation, since the metadata it was chosen from may be stale
+                let url = format!(
+                    "{}/{}/{}",
+                    self.0.download_url(),
+                    percent_encode(bucket),
+                    percent_encode_noslash(file_name),
+                );
+                let mut request = client.get(&url);
+                if let Some(generation) = generation {
+                    request = request.query(&[("generation", generation)]);
+                }
+                let response = request.execute(self.0)?;
+                return if response.status().is_success() {
+                    crate::cancel::read_body(response.into_inner())
+                } else {
+                    Err(e)
+                };
+            }
+            Err(e) => return Err(e),
+        };
+        let response = client
+            .get(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        crate::cancel::read_body(response.into_inner())
+    }
+
+    /// Reads the size, content type, generation and hashes of the object with the specified name
+    /// in the specified bucket from the headers of a `HEAD` request for its content, which is
+    /// cheaper than reading its metadata. Fails with `Error::NotFound` if the object does not
+    /// exist.
+    pub fn head(&self, bucket: &str, file_name: &str) -> Result<ObjectHead, Error> {
+        let url = format!(
+            "{}/b/{}/o/{}",
+            self.0.api_url(),
+            percent_encode(bucket),
+            percent_encode(file_name),
+        );
+        let response = self
+            .0
+            .http()?
+            .head(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .query(&[("alt", "media")])
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(ObjectHead::from_headers(response.headers()))
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Download the content of the object with the specified name in the specified bucket,
+    /// together with the hashes, generation and stored size that Google reports for it in the
+    /// `x-goog-*` headers of the response. This always downloads from Google through the JSON API,
+    /// so `Config::cache` and `Config::grpc` do not apply.
+    pub fn download_with_info(
+        &self,
+        bucket: &str,
+        file_name: &str,
+    ) -> Result<(bytes::Bytes, MediaInfo), Error> {
+        let url = format!(
+            "{}/b/{}/o/{}",
+            self.0.api_url(),
+            percent_encode(bucket),
+            percent_encode(file_name),
+        );
+        let response = self
+            .0
+            .http()?
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .query(&[("alt", "media")])
+            .execute(self.0)?;
+        if !response.status().is_success() {
+            return Err(Error::from_response(response));
+        }
+        let info = MediaInfo::from_headers(response.headers());
+        Ok((crate::cancel::read_body(response.into_inner())?, info))
+    }
+
+    /// Replaces the metadata of `object` with the values in it. Changes that were made since
+    /// `object` was retrieved are overwritten; use `update_if_unchanged` to prevent that.
+    pub fn update(&self, object: &Object) -> Result<Object, Error> {
+        self.replace(object, None, false)
+    }
+
+    /// Replaces the metadata of `object` like `update`, but only if it was not changed since
+    /// `object` was retrieved, that is, if its metageneration still matches. Otherwise Google
+    /// responds with `412 Precondition Failed`, so that concurrent changes are never silently
+    /// overwritten. Unlike `update`, the request is retried when it fails with a transient error.
+    pub fn update_if_unchanged(&self, object: &Object) -> Result<Object, Error> {
+        self.replace(object, None, true)
+    }
+
+    /// Replaces the metadata of `object` like `update`, and replaces its ACL with the predefined
+    /// ACL `acl`.
+    pub fn update_with_acl(&self, object: &Object, acl: PredefinedAcl) -> Result<Object, Error> {
+        self.replace(object, Some(acl), false)
+    }
+
+    fn replace(
+        &self,
+        object: &Object,
+        acl: Option<PredefinedAcl>,
+        if_unchanged: bool,
+    ) -> Result<Object, Error> {
+        let url = format!(
+            "{}/b/{}/o/{}",
+            self.0.api_url(),
+            percent_encode(&object.bucket),
+            percent_encode(&object.name),
+        );
+        let client = self.0.http()?;
+        let mut request = client
+            .put(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query());
+        if if_unchanged {
+            request = request.query(&[("ifMetagenerationMatch", object.metageneration)]);
+        }
+        if let Some(acl) = acl {
+            request = request.query(&[("predefinedAcl", acl)]);
+        }
+        let result: GoogleResponse<Object> = request.json(object).execute(self.0)?.json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Deletes a single object with the specified name in the specified bucket.
+    pub fn delete(&self, bucket: &str, file_name: &str) -> Result<(), Error> {
+        let url = format!(
+            "{}/b/{}/o/{}",
+            self.0.api_url(),
+            percent_encode(bucket),
+            percent_encode(file_name),
+        );
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Obtains a single object with the specified name in the specified bucket.
+    pub fn compose(
+        &self,
+        bucket: &str,
+        req: &ComposeRequest,
+        destination_object: &str,
+    ) -> Result<Object, Error> {
+        self.concatenate(bucket, req, destination_object, None)
+    }
+
+    /// Concatenates objects like `compose`, and applies the predefined ACL `acl` to the resulting
+    /// object.
+    pub fn compose_with_acl(
+        &self,
+        bucket: &str,
+        req: &ComposeRequest,
+        destination_object: &str,
+        acl: PredefinedAcl,
+    ) -> Result<Object, Error> {
+        self.concatenate(bucket, req, destination_object, Some(acl))
+    }
+
+    fn concatenate(
+        &self,
+        bucket: &str,
+        req: &ComposeRequest,
+        destination_object: &str,
+        acl: Option<PredefinedAcl>,
+    ) -> Result<Object, Error> {
+        let url = format!(
+            "{}/b/{}/o/{}/compose",
+            self.0.api_url(),
+            percent_encode(bucket),
+            percent_encode(destination_object)
+        );
+        let client = self.0.http()?;
+        let mut request = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query());
+        if let Some(acl) = acl {
+            request = request.query(&[("destinationPredefinedAcl", acl)]);
+        }
+        let result: GoogleResponse<Object> = request.json(req).execute(self.0)?.json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Copy this object to the target bucket and path
+    pub fn copy(
+        &self,
+        object: &Object,
+        destination_bucket: &str,
+        path: &str,
+    ) -> Result<Object, Error> {
+        self.copy_to(object, destination_bucket, path, None)
+    }
+
+    /// Copies an object like `copy`, and applies the predefined ACL `acl` to the copy.
+    pub fn copy_with_acl(
+        &self,
+        object: &Object,
+        destination_bucket: &str,
+        path: &str,
+        acl: PredefinedAcl,
+    ) -> Result<Object, Error> {
+        self.copy_to(object, destination_bucket, path, Some(acl))
+    }
+
+    fn copy_to(
+        &self,
+        object: &Object,
+        destination_bucket: &str,
+        path: &str,
+        acl: Option<PredefinedAcl>,
+    ) -> Result<Object, Error> {
+        use reqwest::header::CONTENT_LENGTH;
+
+        let url = format!(
+            "{base}/b/{sBucket}/o/{sObject}/copyTo/b/{dBucket}/o/{dObject}",
+            base = self.0.api_url(),
+            sBucket = percent_encode(&object.bucket),
+            sObject = percent_encode(&object.name),
+            dBucket = percent_encode(destination_bucket),
+            dObject = percent_encode(path),
+        );
+        let client = self.0.http()?;
+        let mut headers = self.0.headers()?;
+        headers.insert(CONTENT_LENGTH, "0".parse()?);
+        let mut request = client
+            .post(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query());
+        if let Some(acl) = acl {
+            request = request.query(&[("destinationPredefinedAcl", acl)]);
+        }
+        let result: GoogleResponse<Object> = request.execute(self.0)?.json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Restores the noncurrent `generation` of the object `name` in `bucket`, by copying it over
+    /// the live object as a new generation. The copy is only made if the live object did not
+    /// change since it was looked up, so a concurrent write is never overwritten: that fails
+    /// with `Error::is_precondition_failed` instead. When `generation` is already live, it is
+    /// returned as is.
+    pub fn promote_generation(
+        &self,
+        bucket: &str,
+        name: &str,
+        generation: i64,
+    ) -> Result<Object, Error> {
+        use reqwest::header::CONTENT_LENGTH;
+
+        // 0 means that the live object must not exist, when it was deleted
+        let live_generation = match self.read(bucket, name) {
+            Ok(live) if live.generation == generation => return Ok(live),
+            Ok(live) => live.generation,
+            Err(e) if e.is_not_found() => 0,
+            Err(e) => return Err(e),
+        };
+        let url = format!(
+            "{base}/b/{bucket}/o/{name}/copyTo/b/{bucket}/o/{name}",
+            base = self.0.api_url(),
+            bucket = percent_encode(bucket),
+            name = percent_encode(name),
+        );
+        let mut headers = self.0.headers()?;
+        headers.insert(CONTENT_LENGTH, "0".parse()?);
+        let response = self
+            .0
+            .http()?
+            .post(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .query(&[
+                ("sourceGeneration", generation),
+                ("ifGenerationMatch", live_generation),
+            ])
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(response.json()?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Renames `object` to `new_name` within its bucket, and returns the renamed object. This
+    /// fails with `Error::is_precondition_failed` when an object called `new_name` already
+    /// exists, or when `object` is no longer the live generation.
+    ///
+    /// In buckets with a hierarchical namespace the object is moved atomically. In other buckets
+    /// it is copied to `new_name` first, and then deleted; if that deletion fails the copy is
+    /// kept, and the error is returned.
+    pub fn rename(&self, object: &Object, new_name: &str) -> Result<Object, Error> {
+        use reqwest::header::CONTENT_LENGTH;
+
+        // without permission to read the bucket, it is treated as a flat bucket
+        let hierarchical = match self.0.bucket().read(&object.bucket) {
+            Ok(bucket) => bucket.hierarchical_namespace.is_some_and(|hns| hns.enabled),
+            Err(e) if e.status() == Some(403) => false,
+            Err(e) => return Err(e),
+        };
+        let bucket = percent_encode(&object.bucket);
+        let destination = if hierarchical {
+            format!("moveTo/o/{}", percent_encode(new_name))
+        } else {
+            format!("copyTo/b/{}/o/{}", bucket, percent_encode(new_name))
+        };
+        let url = format!(
+            "{}/b/{}/o/{}/{}",
+            self.0.api_url(),
+            bucket,
+            percent_encode(&object.name),
+            destination,
+        );
+        let mut headers = self.0.headers()?;
+        headers.insert(CONTENT_LENGTH, "0".parse()?);
+        let response = self
+            .0
+            .http()?
+            .post(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            // the new name must not exist yet
+            .query(&[
+                ("ifSourceGenerationMatch", object.generation),
+                ("ifGenerationMatch", 0),
+            ])
+            .execute(self.0)?;
+        if !response.status().is_success() {
+            return Err(Error::from_response(response));
+        }
+        let renamed: Object = response.json()?;
+        if !hierarchical {
+            let url = format!(
+                "{}/b/{}/o/{}",
+                self.0.api_url(),
+                bucket,
+                percent_encode(&object.name),
+            );
+            let response = self
+                .0
+                .http()?
+                .delete(&url)
+                .headers(self.0.headers()?)
+                .query(&self.0.user_project_query())
+                .query(&[("ifGenerationMatch", object.generation)])
+                .execute(self.0)?;
+            if !response.status().is_success() {
+                return Err(Error::from_response(response));
+            }
+        }
+        Ok(renamed)
+    }
+
+    /// Moves a file from the current location to the target bucket and path.
+    pub fn rewrite(
+        &self,
+        object: &Object,
+        destination_bucket: &str,
+        path: &str,
+    ) -> Result<Object, Error> {
+        use reqwest::header::CONTENT_LENGTH;
+
+        let url = format!(
+            "{base}/b/{sBucket}/o/{sObject}/rewriteTo/b/{dBucket}/o/{dObject}",
+            base = self.0.api_url(),
+            sBucket = percent_encode(&object.bucket),
+            sObject = percent_encode(&object.name),
+            dBucket = percent_encode(destination_bucket),
+            dObject = percent_encode(path),
+        );
+        let client = self.0.http()?;
+        let mut headers = self.0.headers()?;
+        headers.insert(CONTENT_LENGTH, "0".parse()?);
+        let result: GoogleResponse<RewriteResponse> = client
+            .post(&url)
+            .headers(headers)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s.resource),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Uploads all files in `local_dir` and its subdirectories under `prefix` in `bucket`, and
+    /// reports the outcome for each file.
+    pub fn upload_dir(
+        &self,
+        local_dir: impl AsRef<std::path::Path>,
+        bucket: &str,
+        prefix: &str,
+        options: &UploadOptions,
+    ) -> Result<Vec<FileUpload>, Error> {
+        crate::sync::upload_dir(self.0, local_dir.as_ref(), bucket, prefix, options)
+    }
+
+    /// Downloads all objects under `prefix` in `bucket` into `local_dir`, and reports the outcome
+    /// for each object.
+    pub fn download_prefix(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        local_dir: impl AsRef<std::path::Path>,
+        options: &DownloadOptions,
+    ) -> Result<Vec<FileDownload>, Error> {
+        crate::sync::download_prefix(self.0, bucket, prefix, local_dir.as_ref(), options)
+    }
+
+    /// Copies all objects under `source_prefix` in `source_bucket` to `destination_prefix` in
+    /// `destination_bucket`, and reports the outcome for each object.
+    pub fn copy_prefix(
+        &self,
+        source_bucket: &str,
+        source_prefix: &str,
+        destination_bucket: &str,
+        destination_prefix: &str,
+        options: &CopyOptions,
+    ) -> Result<Vec<ObjectCopy>, Error> {
+        crate::sync::copy_prefix(
+            self.0,
+            source_bucket,
+            source_prefix,
+            destination_bucket,
+            destination_prefix,
+            options,
+            false,
+        )
+    }
+
+    /// Moves all objects under `source_prefix` in `source_bucket` to `destination_prefix` in
+    /// `destination_bucket`, and reports the outcome for each object. A source object is only
+    /// deleted once its copy is complete.
+    pub fn move_prefix(
+        &self,
+        source_bucket: &str,
+        source_prefix: &str,
+        destination_bucket: &str,
+        destination_prefix: &str,
+        options: &CopyOptions,
+    ) -> Result<Vec<ObjectCopy>, Error> {
+        crate::sync::copy_prefix(
+            self.0,
+            source_bucket,
+            source_prefix,
+            destination_bucket,
+            destination_prefix,
+            options,
+            true,
+        )
+    }
+
+    /// Uploads the files in `local_dir` and its subdirectories that are missing or different under
+    /// `prefix` in `bucket`, optionally deleting the objects that have no local counterpart.
+    pub fn sync_up(
+        &self,
+        local_dir: impl AsRef<std::path::Path>,
+        bucket: &str,
+        prefix: &str,
+        options: &SyncOptions,
+    ) -> Result<SyncSummary, Error> {
+        crate::sync::sync_up(self.0, local_dir.as_ref(), bucket, prefix, options)
+    }
+
+    /// Downloads the objects under `prefix` in `bucket` that are missing or different in
+    /// `local_dir`, optionally deleting the local files that have no remote counterpart.
+    pub fn sync_down(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        local_dir: impl AsRef<std::path::Path>,
+        options: &SyncOptions,
+    ) -> Result<SyncSummary, Error> {
+        crate::sync::sync_down(self.0, bucket, prefix, local_dir.as_ref(), options)
+    }
+
+    /// Creates a [Signed Url](https://cloud.google.com/storage/docs/access-control/signed-urls)
+    /// which is valid until `expiry`, at most 7 days from now, and lets the posessor download the
+    /// file contents without any authentication.
+    pub fn download_url(
+        &self,
+        object: &Object,
+        expiry: impl Into<Expiry>,
+    ) -> Result<String, Error> {
+        self.download_url_with(object, expiry, &SignedUrlOptions::default())
+    }
+
+    /// Creates a signed download url like `download_url`, with the query parameters in `options`.
+    pub fn download_url_with(
+        &self,
+        object: &Object,
+        expiry: impl Into<Expiry>,
+        options: &SignedUrlOptions,
+    ) -> Result<String, Error> {
+        self.signed_url(
+            &object.bucket,
+            &object.name,
+            SignedUrlMethod::Get,
+            expiry,
+            options,
+        )
+    }
+
+    /// Creates a signed url that lets the posessor make a `method` request to the object `name` in
+    /// `bucket` without any authentication, such as a `PUT` to upload it or a `DELETE` to delete
+    /// it. The object does not need to exist yet. Requests made with the url must carry the
+    /// headers in `options`.
+    pub fn signed_url(
+        &self,
+        bucket: &str,
+        name: &str,
+        method: SignedUrlMethod,
+        expiry: impl Into<Expiry>,
+        options: &SignedUrlOptions,
+    ) -> Result<String, Error> {
+        let verb = method.as_str();
+        self.sign(bucket, name, expiry.into(), verb, options, &options.headers)
+    }
+
+    /// Creates a signed url that lets the posessor start a
+    /// [resumable upload](https://cloud.google.com/storage/docs/performing-resumable-uploads) of
+    /// the object `filename` in `bucket` without any authentication, for example from a browser.
+    ///
+    /// The upload is started by sending a `POST` request without a body to the url, with the
+    /// `x-goog-resumable: start` header and any headers in `options`. The `Location` header of
+    /// the response is the url of the upload session, to which the content is then uploaded with
+    /// `PUT` requests. The session itself is valid for a week, regardless of `expiry`.
+    pub fn resumable_upload_url(
+        &self,
+        bucket: &str,
+        filename: &str,
+        expiry: impl Into<Expiry>,
+        options: &SignedUrlOptions,
+    ) -> Result<String, Error> {
+        let mut headers = options.headers.clone();
+        headers.push(("x-goog-resumable".to_string(), "start".to_string()));
+        self.sign(bucket, filename, expiry.into(), "POST", options, &headers)
+    }
+
+    /// Creates a V4 signed url for a `http_verb` request to the object `name` in `bucket`, which
+    /// must carry `headers`, or reuses one from `Config::signed_url_cache`.
+    fn sign(
+        &self,
+        bucket: &str,
+        name: &str,
+        expiry: Expiry,
+        http_verb: &'static str,
+        options: &SignedUrlOptions,
+        headers: &[(String, String)],
+    ) -> Result<String, Error> {
+        let sign = || self.sign_uncached(bucket, name, expiry, http_verb, options, headers);
+        match self.0.signed_urls() {
+            Some(signed_urls) => {
+                let key = (
+                    bucket.to_string(),
+                    name.to_string(),
+                    http_verb,
+                    expiry,
+                    options.query_parameters.clone(),
+                    headers.to_vec(),
+                );
+                signed_urls.get_or_sign(key, sign)
+            }
+            None => sign().map(|(url, _)| url),
+        }
+    }
+
+    /// Creates a V4 signed url like `sign`, and returns it with the number of seconds it is valid
+    /// for.
+    fn sign_uncached(
+        &self,
+        bucket: &str,
+        name: &str,
+        expiry: Expiry,
+        http_verb: &str,
+        options: &SignedUrlOptions,
+        headers: &[(String, String)],
+    ) -> Result<(String, u64), Error> {
+        let config = self.0.config();
+        let signer = match &config.hmac_credentials {
+            Some(credentials) => UrlSigner::hmac(credentials.clone()),
+            None => UrlSigner::new(&self.0.service_account()?),
+        };
+        signer
+            .region(crate::config::signing_region(config))
+            .sign(bucket, name, expiry, http_verb, options, headers)
+    }
+}
+
+/// The query parameters of a listing with `options`.
+fn list_query(options: &ListOptions) -> Vec<(&'static str, String)> {
+    let mut query = Vec::new();
+    if let Some(prefix) = &options.prefix {
+        query.push(("prefix", prefix.clone()));
+    }
+    if let Some(match_glob) = &options.match_glob {
+        query.push(("matchGlob", match_glob.clone()));
+    }
+    if let Some(page_size) = options.page_size {
+        query.push(("maxResults", page_size.to_string()));
+    }
+    if let Some(projection) = options.projection {
+        query.push(("projection", projection.as_str().to_string()));
+    }
+    if let Some(delimiter) = &options.delimiter {
+        query.push(("delimiter", delimiter.clone()));
+    }
+    if options.include_trailing_delimiter {
+        query.push(("includeTrailingDelimiter", "true".to_string()));
+    }
+    if let Some(start_offset) = &options.start_offset {
+        query.push(("startOffset", start_offset.clone()));
+    }
+    if let Some(end_offset) = &options.end_offset {
+        query.push(("endOffset", end_offset.clone()));
+    }
+    query
+}
+
+/// Reads the chunks of a stream, waiting for each one in turn.
+#[cfg(feature = "stream")]
+struct StreamReader<S: futures::Stream + Unpin, B> {
+    stream: futures::executor::BlockingStream<S>,
+    chunk: Option<B>,
+    position: usize,
+}
+
+#[cfg(feature = "stream")]
+impl<S, B, E> std::io::Read for StreamReader<S, B>
+where
+    S: futures::Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if let Some(chunk) = &self.chunk {
+                let rest = &chunk.as_ref()[self.position..];
+                if !rest.is_empty() {
+                    let len = rest.len().min(buf.len());
+                    buf[..len].copy_from_slice(&rest[..len]);
+                    self.position += len;
+                    return Ok(len);
+                }
+            }
+            match self.stream.next() {
+                Some(Ok(chunk)) => {
+                    self.chunk = Some(chunk);
+                    self.position = 0;
+                }
+                Some(Err(e)) => return Err(std::io::Error::other(e)),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Builds the body of a [multipart upload](https://cloud.google.com/storage/docs/uploading-objects#uploading-an-object),
+/// which carries the metadata `resource` of the object together with its `content`. Returns the
+/// content type of the body, which names the random boundary between the parts, and the body.
+#[cfg(any(feature = "gzip", feature = "encryption"))]
+pub(super) fn multipart_related(
+    resource: &serde_json::Value,
+    mime_type: &str,
+    content: &[u8],
+) -> Result<(String, Vec<u8>), Error> {
+    let boundary = hex::encode(crate::crypto::random_bytes(16)?);
+    let mut body = format!(
+        "--{0}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{1}\r\n--{0}\r\nContent-Type: {2}\r\n\r\n",
+        boundary, resource, mime_type,
+    )
+    .into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    let content_type = format!("multipart/related; boundary={}", boundary);
+    Ok((content_type, body))
+}
+
+/// The size of the chunks of `ObjectClient::create_streamed_unsized`, which Google requires to be
+/// a multiple of 256 KiB.
+const RESUMABLE_CHUNK_SIZE: usize = 32 * 256 * 1024;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_urls_are_accepted() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let object =
+            client
+                .object()
+                .create(&bucket.name, b"signed", "test-signed url ~*", "text/plain")?;
+        let options = SignedUrlOptions::default()
+            .query_parameter("generation", object.generation.to_string())
+            .query_parameter("response-content-type", "application/x-test; a=b");
+        let url = client.object().download_url_with(
+            &object,
+            std::time::Duration::from_secs(60),
+            &options,
+        )?;
+        let response = reqwest::blocking::get(&url)?;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/x-test; a=b"
+        );
+        assert_eq!(response.text()?, "signed");
+        client.object().delete(&bucket.name, "test-signed url ~*")?;
+        Ok(())
+    }
+
+    #[test]
+    fn hmac_signed_urls_are_accepted() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let key = Client::global().hmac_key().create()?;
+        let access_id = key.metadata.access_id.clone();
+        let client = Client::with_config(crate::Config {
+            hmac_credentials: Some(key.into()),
+            ..Default::default()
+        });
+        let object =
+            client
+                .object()
+                .create(&bucket.name, b"hmac", "test-hmac-signed", "text/plain")?;
+        let url = client
+            .object()
+            .download_url(&object, std::time::Duration::from_secs(60))?;
+        assert!(url.contains("X-Goog-Algorithm=GOOG4-HMAC-SHA256"));
+        let response = reqwest::blocking::get(&url)?;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.text()?, "hmac");
+        client.object().delete(&bucket.name, "test-hmac-signed")?;
+        let global = Client::global();
+        let state = crate::resources::hmac_key::HmacState::Inactive;
+        global.hmac_key().update(&access_id, state)?;
+        global.hmac_key().delete(&access_id)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn create_gzipped() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let content = "a line that repeats\n".repeat(100);
+        let object = client.object().create_gzipped(
+            &bucket.name,
+            content.as_bytes(),
+            "test-gzipped.log",
+            "text/plain",
+        )?;
+        assert_eq!(object.content_encoding.as_deref(), Some("gzip"));
+        assert_eq!(object.content_type.as_deref(), Some("text/plain"));
+        assert!(object.size < content.len() as u64);
+        let metadata = object.metadata.unwrap_or_default();
+        assert_eq!(metadata[UNCOMPRESSED_SIZE], content.len().to_string());
+        let downloaded = client.object().download(&bucket.name, "test-gzipped.log")?;
+        assert_eq!(&downloaded[..], content.as_bytes());
+        client.object().delete(&bucket.name, "test-gzipped.log")?;
+        Ok(())
+    }
+
+    #[test]
+    fn create_streamed_unsized() -> Result<(), Box<dyn std::error::Error>> {
+        use std::io::Read;
+
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let objects = client.object();
+        let length = RESUMABLE_CHUNK_SIZE as u64 + 10;
+        let file = std::io::repeat(b'x').take(length);
+        let object =
+            objects.create_streamed_unsized(&bucket.name, file, "test-unsized", "text/plain")?;
+        assert_eq!(object.size, length);
+        let empty = objects.create_streamed_unsized(
+            &bucket.name,
+            std::io::empty(),
+            "test-unsized-empty",
+            "text/plain",
+        )?;
+        assert_eq!(empty.size, 0);
+        objects.delete(&bucket.name, "test-unsized")?;
+        objects.delete(&bucket.name, "test-unsized-empty")?;
+        Ok(())
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream_reader() {
+        use std::io::Read;
+
+        let chunks = vec![Ok::<_, &str>(vec![1, 2, 3]), Ok(vec![]), Ok(vec![4])];
+        let mut reader = StreamReader {
+            stream: futures::executor::block_on_stream(futures::stream::iter(chunks)),
+            chunk: None,
+            position: 0,
+        };
+        let mut buf = [0; 2];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, vec![3, 4]);
+
+        let chunks = vec![Ok(vec![1]), Err("broken pipe")];
+        let mut reader = StreamReader {
+            stream: futures::executor::block_on_stream(futures::stream::iter(chunks)),
+            chunk: None,
+            position: 0,
+        };
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "broken pipe");
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn create_from_stream() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        for length in [Some(12), None] {
+            let chunks = vec![Ok::<_, std::io::Error>(&b"hello "[..]), Ok(&b"stream"[..])];
+            let stream = futures::stream::iter(chunks);
+            let object = client.object().create_from_stream(
+                &bucket.name,
+                stream,
+                length,
+                "test-stream",
+                "text/plain",
+            )?;
+            assert_eq!(object.size, 12);
+        }
+        client.object().delete(&bucket.name, "test-stream")?;
+        Ok(())
+    }
+
+    #[cfg(any(feature = "gzip", feature = "encryption"))]
+    #[test]
+    fn multipart_related_body() -> Result<(), Box<dyn std::error::Error>> {
+        let resource = serde_json::json!({ "name": "a.txt" });
+        let (content_type, body) = multipart_related(&resource, "text/plain", b"hello")?;
+        let boundary = content_type
+            .strip_prefix("multipart/related; boundary=")
+            .unwrap();
+        let expected = format!(
+            "--{0}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{{\"name\":\"a.txt\"}}\r\n--{0}\r\nContent-Type: text/plain\r\n\r\nhello\r\n--{0}--\r\n",
+            boundary,
+        );
+        assert_eq!(String::from_utf8(body)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn degraded_download_keeps_generation() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug)]
+        struct NoToken;
+
+        impl crate::TokenProvider for NoToken {
+            fn token(&self, _client: &Client, _scopes: &[&str]) -> Result<crate::Token, Error> {
+                Err(Error::new("the token service is down"))
+            }
+        }
+
+        /// Serves every request as a public object, recording the urls.
+        #[derive(Debug, Default)]
+        struct Public(Mutex<Vec<String>>);
+
+        impl crate::Transport for Public {
+            fn send(
+                &self,
+                request: reqwest::blocking::Request,
+            ) -> Result<reqwest::blocking::Response, Error> {
+                self.0.lock().unwrap().push(request.url().to_string());
+                Ok(http::Response::builder()
+                    .status(200)
+                    .body("content")
+                    .unwrap()
+                    .into())
+            }
+        }
+
+        let transport = Arc::new(Public::default());
+        let client = Client::with_config(crate::Config {
+            token_provider: Some(Arc::new(NoToken)),
+            transport: Some(transport.clone()),
+            degraded_mode: true,
+            ..Default::default()
+        });
+        client.degraded().set_degraded(true);
+        let content = client
+            .object()
+            .download_generation("bucket", "a b", Some(7))?;
+        assert_eq!(&content[..], b"content");
+        let urls = transport.0.lock().unwrap();
+        assert!(urls.last().unwrap().ends_with("/bucket/a%20b?generation=7"));
+        Ok(())
+    }
+
+    #[test]
+    fn failed_resumable_upload_deletes_session() {
+        use std::sync::{Arc, Mutex};
+
+        /// Starts a session, rejects its first chunk, and records the requests.
+        #[derive(Debug, Default)]
+        struct Sessions(Mutex<Vec<String>>);
+
+        impl crate::Transport for Sessions {
+            fn send(
+                &self,
+                request: reqwest::blocking::Request,
+            ) -> Result<reqwest::blocking::Response, Error> {
+                let method = request.method().to_string();
+                self.0.lock().unwrap().push(method.clone());
+                let response = match method.as_str() {
+                    "POST" => http::Response::builder()
+                        .status(200)
+                        .header("location", "https://example.com/session"),
+                    "PUT" => http::Response::builder().status(400),
+                    _ => http::Response::builder().status(499),
+                };
+                Ok(response.body("{}").unwrap().into())
+            }
+        }
+
+        let transport = Arc::new(Sessions::default());
+        let client = Client::with_config(crate::Config {
+            anonymous: true,
+            transport: Some(transport.clone()),
+            ..Default::default()
+        });
+        let file = std::io::Read::take(std::io::repeat(b'x'), 10);
+        let result = client
+            .object()
+            .create_streamed_unsized("bucket", file, "name", "text/plain");
+        assert!(result.is_err());
+        assert_eq!(*transport.0.lock().unwrap(), ["POST", "PUT", "DELETE"]);
+    }
+
+    #[test]
+    fn list_options_query() {
+        let options = ListOptions {
+            prefix: Some("logs/".to_string()),
+            delimiter: Some("/".to_string()),
+            include_trailing_delimiter: true,
+            start_offset: Some("logs/2020".to_string()),
+            end_offset: Some("logs/2021".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            list_query(&options),
+            vec![
+                ("prefix", "logs/".to_string()),
+                ("delimiter", "/".to_string()),
+                ("includeTrailingDelimiter", "true".to_string()),
+                ("startOffset", "logs/2020".to_string()),
+                ("endOffset", "logs/2021".to_string()),
+            ]
+        );
+        assert!(list_query(&ListOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn signed_url_methods() {
+        let client = Client::with_config(crate::Config {
+            hmac_credentials: Some(crate::hmac_key::HmacCredentials {
+                access_id: "GOOG1EXAMPLE".to_string(),
+                secret: "secret".to_string(),
+            }),
+            ..Default::default()
+        });
+        let options = SignedUrlOptions::default();
+        let sign = |method| {
+            let expiry = std::time::Duration::from_secs(60);
+            let url = client
+                .object()
+                .signed_url("my_bucket", "a b.txt", method, expiry, &options)
+                .unwrap();
+            // the signature is the last query parameter
+            let (unsigned, signature) = url.rsplit_once("&X-Goog-Signature=").unwrap();
+            (unsigned.to_string(), signature.to_string())
+        };
+        let (get, get_signature) = sign(SignedUrlMethod::Get);
+        assert!(get.starts_with("https://storage.googleapis.com/my_bucket/a%20b.txt?"));
+        assert!(get.contains("X-Goog-Algorithm=GOOG4-HMAC-SHA256"));
+        assert!(get.contains("X-Goog-SignedHeaders=host"));
+        for method in &[
+            SignedUrlMethod::Head,
+            SignedUrlMethod::Put,
+            SignedUrlMethod::Post,
+            SignedUrlMethod::Delete,
+        ] {
+            let (url, signature) = sign(*method);
+            assert_eq!(url, get);
+            assert_ne!(signature, get_signature, "{} is signed as GET", method);
+        }
+    }
+
+    #[test]
+    fn signed_url_cache() {
+        use chrono::Timelike;
+
+        let config = crate::Config {
+            hmac_credentials: Some(crate::hmac_key::HmacCredentials {
+                access_id: "GOOG1EXAMPLE".to_string(),
+                secret: "secret".to_string(),
+            }),
+            signed_url_cache: Some(Default::default()),
+            ..Default::default()
+        };
+        let client = Client::with_config(config);
+        let expiry = chrono::Utc::now() + chrono::Duration::hours(1);
+        let expiry = Expiry::At(expiry.with_nanosecond(0).unwrap());
+        let sign = |name, method, options: &SignedUrlOptions| {
+            client
+                .object()
+                .signed_url("my_bucket", name, method, expiry, options)
+                .unwrap()
+        };
+        let options = SignedUrlOptions::default();
+        let url = sign("a", SignedUrlMethod::Get, &options);
+        // urls contain the moment they were signed, so a new url would differ a second later
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        assert_eq!(sign("a", SignedUrlMethod::Get, &options), url);
+        assert_ne!(sign("a", SignedUrlMethod::Head, &options), url);
+        assert_ne!(sign("b", SignedUrlMethod::Get, &options), url);
+        let options = options.query_parameter("generation", "1");
+        assert_ne!(sign("a", SignedUrlMethod::Get, &options), url);
+    }
+
+    #[test]
+    fn signed_delete() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let object = client.object().create(
+            &bucket.name,
+            b"delete me",
+            "test-signed-delete",
+            "text/plain",
+        )?;
+        let expiry = std::time::Duration::from_secs(60);
+        let options = SignedUrlOptions::default();
+        let head = object.signed_url(SignedUrlMethod::Head, expiry, &options)?;
+        let delete = object.signed_url(SignedUrlMethod::Delete, expiry, &options)?;
+        let http = reqwest::blocking::Client::new();
+        let response = http.head(&head).send()?;
+        assert!(response.status().is_success());
+        assert_eq!(response.headers()["content-length"], "9");
+        // the urls allow nothing but the signed method
+        assert_eq!(http.delete(&head).send()?.status().as_u16(), 403);
+        assert!(http.delete(&delete).send()?.status().is_success());
+        assert!(client
+            .object()
+            .read(&bucket.name, "test-signed-delete")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn signed_resumable_upload() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let options = SignedUrlOptions::default().header("content-type", "text/plain");
+        let url = client.object().resumable_upload_url(
+            &bucket.name,
+            "test-signed-resumable",
+            std::time::Duration::from_secs(60),
+            &options,
+        )?;
+        let http = reqwest::blocking::Client::new();
+        let response = http
+            .post(&url)
+            .header("x-goog-resumable", "start")
+            .header("content-type", "text/plain")
+            .header("content-length", 0)
+            .send()?;
+        assert_eq!(response.status().as_u16(), 201);
+        let session = response.headers()["location"].to_str()?.to_string();
+        let response = http.put(&session).body("resumable").send()?;
+        assert!(response.status().is_success());
+        let content = client
+            .object()
+            .download(&bucket.name, "test-signed-resumable")?;
+        assert_eq!(&content[..], b"resumable");
+        client
+            .object()
+            .delete(&bucket.name, "test-signed-resumable")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use crate::error::GoogleResponse;
+use crate::http::RequestExt;
+use crate::resources::common::Entity;
+use crate::resources::common::ListResponse;
+use crate::resources::object_access_control::*;
+use crate::Client;
+
+/// Operations on the access control lists of objects, authenticated with the credentials of a
+/// `Client`. Obtained through `Client::object_access_control`.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectAccessControlClient<'a>(pub(super) &'a Client);
+
+impl<'a> ObjectAccessControlClient<'a> {
+    /// Creates a new ACL entry on the specified `object`.
+    pub fn create(
+        &self,
+        bucket: &str,
+        object: &str,
+        new_object_access_control: &NewObjectAccessControl,
+    ) -> Result<ObjectAccessControl, crate::Error> {
+        let url = format!("{}/b/{}/o/{}/acl", self.0.api_url(), bucket, object);
+        let client = self.0.http()?;
+        let result: GoogleResponse<ObjectAccessControl> = client
+            .post(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(new_object_access_control)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Retrieves `ACL` entries on the specified object.
+    pub fn list(
+        &self,
+        bucket: &str,
+        object: &str,
+    ) -> Result<Vec<ObjectAccessControl>, crate::Error> {
+        let url = format!("{}/b/{}/o/{}/acl", self.0.api_url(), bucket, object);
+        let client = self.0.http()?;
+        let result: GoogleResponse<ListResponse<ObjectAccessControl>> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s.items),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the `ACL` entry for the specified entity on the specified bucket.
+    pub fn read(
+        &self,
+        bucket: &str,
+        object: &str,
+        entity: &Entity,
+    ) -> Result<ObjectAccessControl, crate::Error> {
+        let url = format!(
+            "{}/b/{}/o/{}/acl/{}",
+            self.0.api_url(),
+            bucket,
+            object,
+            entity
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<ObjectAccessControl> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Updates an ACL entry on the specified object.
+    pub fn update(&self, acl: &ObjectAccessControl) -> Result<ObjectAccessControl, crate::Error> {
+        let url = format!(
+            "{}/b/{}/o/{}/acl/{}",
+            self.0.api_url(),
+            acl.bucket,
+            acl.object,
+            acl.entity,
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<ObjectAccessControl> = client
+            .put(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .json(acl)
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+
+    /// Permanently deletes the ACL entry for the specified entity on the specified object.
+    pub fn delete(&self, acl: ObjectAccessControl) -> Result<(), crate::Error> {
+        let url = format!(
+            "{}/b/{}/o/{}/acl/{}",
+            self.0.api_url(),
+            acl.bucket,
+            acl.object,
+            acl.entity,
+        );
+        let client = self.0.http()?;
+        let response = client
+            .delete(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(crate::Error::from_response(response))
+        }
+    }
+}
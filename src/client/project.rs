@@ -0,0 +1,31 @@
+use crate::error::GoogleResponse;
+use crate::http::RequestExt;
+use crate::resources::project::*;
+use crate::Client;
+
+/// Operations on the Cloud Storage service agents of projects, authenticated with the credentials
+/// of a `Client`. Obtained through `Client::project_service_account`.
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectServiceAccountClient<'a>(pub(super) &'a Client);
+
+impl<'a> ProjectServiceAccountClient<'a> {
+    /// Retrieves the email address of the Cloud Storage service agent of the given project.
+    pub fn get(&self, project_id: &str) -> Result<ProjectServiceAccount, crate::Error> {
+        let url = format!(
+            "{}/projects/{}/serviceAccount",
+            self.0.api_url(),
+            project_id
+        );
+        let client = self.0.http()?;
+        let result: GoogleResponse<ProjectServiceAccount> = client
+            .get(&url)
+            .headers(self.0.headers()?)
+            .query(&self.0.user_project_query())
+            .execute(self.0)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => Ok(s),
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::error::Error;
+use crate::resources::bucket::{Bucket, NewBucket};
+use crate::resources::object::{ComposeRequest, Object};
+use crate::Client;
+
+/// The operations on objects and buckets that applications use most, as a trait. Code that takes
+/// a `&dyn StorageClient` instead of a `Client` can be unit tested with a stub implementation,
+/// without talking to Google or to an emulator.
+///
+/// `Client` implements this trait by calling the matching methods of `Client::object` and
+/// `Client::bucket`.
+/// ### Example
+/// ```no_run
+/// use cloud_storage::{Client, Error, StorageClient};
+///
+/// fn publish(storage: &dyn StorageClient, report: &str) -> Result<(), Error> {
+///     storage.create_object("reports", report.as_bytes(), "latest.txt", "text/plain")?;
+///     Ok(())
+/// }
+///
+/// # fn main() -> Result<(), Error> {
+/// publish(&Client::default(), "all good")?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait StorageClient: std::fmt::Debug + Send + Sync {
+    /// Uploads `file` as the object `filename` in `bucket`. See `ObjectClient::create`.
+    fn create_object(
+        &self,
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error>;
+
+    /// Reads the metadata of an object. See `ObjectClient::read`.
+    fn read_object(&self, bucket: &str, file_name: &str) -> Result<Object, Error>;
+
+    /// Downloads the content of an object. See `ObjectClient::download`.
+    fn download_object(&self, bucket: &str, file_name: &str) -> Result<bytes::Bytes, Error>;
+
+    /// Lists the objects in `bucket`, or only those whose name starts with `prefix`. See
+    /// `ObjectClient::list_prefix`.
+    fn list_objects(&self, bucket: &str, prefix: Option<&str>) -> Result<Vec<Object>, Error>;
+
+    /// Replaces the metadata of an object. See `ObjectClient::update`.
+    fn update_object(&self, object: &Object) -> Result<Object, Error>;
+
+    /// Deletes an object. See `ObjectClient::delete`.
+    fn delete_object(&self, bucket: &str, file_name: &str) -> Result<(), Error>;
+
+    /// Copies an object to `path` in `destination_bucket`. See `ObjectClient::copy`.
+    fn copy_object(
+        &self,
+        object: &Object,
+        destination_bucket: &str,
+        path: &str,
+    ) -> Result<Object, Error>;
+
+    /// Concatenates the objects in `req` into `destination_object`. See `ObjectClient::compose`.
+    fn compose_object(
+        &self,
+        bucket: &str,
+        req: &ComposeRequest,
+        destination_object: &str,
+    ) -> Result<Object, Error>;
+
+    /// Creates a bucket. See `BucketClient::create`.
+    fn create_bucket(&self, new_bucket: &NewBucket) -> Result<Bucket, Error>;
+
+    /// Reads the metadata of a bucket. See `BucketClient::read`.
+    fn read_bucket(&self, name: &str) -> Result<Bucket, Error>;
+
+    /// Lists the buckets of the project of the client. See `BucketClient::list`.
+    fn list_buckets(&self) -> Result<Vec<Bucket>, Error>;
+
+    /// Replaces the metadata of a bucket. See `BucketClient::update`.
+    fn update_bucket(&self, bucket: &Bucket) -> Result<Bucket, Error>;
+
+    /// Deletes a bucket, which must be empty. See `BucketClient::delete`.
+    fn delete_bucket(&self, bucket: Bucket) -> Result<(), Error>;
+}
+
+impl StorageClient for Client {
+    fn create_object(
+        &self,
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error> {
+        self.object().create(bucket, file, filename, mime_type)
+    }
+
+    fn read_object(&self, bucket: &str, file_name: &str) -> Result<Object, Error> {
+        self.object().read(bucket, file_name)
+    }
+
+    fn download_object(&self, bucket: &str, file_name: &str) -> Result<bytes::Bytes, Error> {
+        self.object().download(bucket, file_name)
+    }
+
+    fn list_objects(&self, bucket: &str, prefix: Option<&str>) -> Result<Vec<Object>, Error> {
+        match prefix {
+            Some(prefix) => self.object().list_prefix(bucket, prefix),
+            None => self.object().list(bucket),
+        }
+    }
+
+    fn update_object(&self, object: &Object) -> Result<Object, Error> {
+        self.object().update(object)
+    }
+
+    fn delete_object(&self, bucket: &str, file_name: &str) -> Result<(), Error> {
+        self.object().delete(bucket, file_name)
+    }
+
+    fn copy_object(
+        &self,
+        object: &Object,
+        destination_bucket: &str,
+        path: &str,
+    ) -> Result<Object, Error> {
+        self.object().copy(object, destination_bucket, path)
+    }
+
+    fn compose_object(
+        &self,
+        bucket: &str,
+        req: &ComposeRequest,
+        destination_object: &str,
+    ) -> Result<Object, Error> {
+        self.object().compose(bucket, req, destination_object)
+    }
+
+    fn create_bucket(&self, new_bucket: &NewBucket) -> Result<Bucket, Error> {
+        self.bucket().create(new_bucket)
+    }
+
+    fn read_bucket(&self, name: &str) -> Result<Bucket, Error> {
+        self.bucket().read(name)
+    }
+
+    fn list_buckets(&self) -> Result<Vec<Bucket>, Error> {
+        self.bucket().list()
+    }
+
+    fn update_bucket(&self, bucket: &Bucket) -> Result<Bucket, Error> {
+        self.bucket().update(bucket)
+    }
+
+    fn delete_bucket(&self, bucket: Bucket) -> Result<(), Error> {
+        self.bucket().delete(bucket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_as_trait_object() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let storage: Box<dyn StorageClient> = Box::new(Client::global());
+        storage.create_object(&bucket.name, b"trait", "test-storage-client", "text/plain")?;
+        let content = storage.download_object(&bucket.name, "test-storage-client")?;
+        assert_eq!(&content[..], b"trait");
+        let listed = storage.list_objects(&bucket.name, Some("test-storage-client"))?;
+        assert!(listed.iter().any(|o| o.name == "test-storage-client"));
+        storage.delete_object(&bucket.name, "test-storage-client")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+/// The number of bytes of a response body that are kept in a `RequestContext`.
+const MAX_BODY_LEN: usize = 1024;
+
+/// Query parameters whose values are replaced in the url of a `RequestContext`, because they grant
+/// access to a resource.
+const SECRET_PARAMS: &[&str] = &[
+    "upload_id",
+    "key",
+    "access_token",
+    "X-Goog-Signature",
+    "GoogleAccessId",
+    "Signature",
+];
+
+/// Describes the request that caused an error response, so that failures can be diagnosed from
+/// logs. It is available through `Error::context`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestContext {
+    /// The HTTP method of the request.
+    pub method: reqwest::Method,
+    /// The url the request was sent to, with secrets such as upload ids and signatures redacted.
+    pub url: String,
+    /// The attempt that produced the error response, starting at 1.
+    pub attempt: u32,
+    /// The start of the response body, if it was read. Long bodies are cut off.
+    pub body: Option<String>,
+}
+
+impl RequestContext {
+    pub(crate) fn new(method: reqwest::Method, url: &reqwest::Url, attempt: u32) -> Self {
+        let mut redacted = url.clone();
+        let _ = redacted.set_password(None);
+        if redacted.query().is_some() {
+            let pairs: Vec<(String, String)> = url
+                .query_pairs()
+                .map(|(key, value)| {
+                    let secret = SECRET_PARAMS.iter().any(|p| p.eq_ignore_ascii_case(&key));
+                    let value = if secret { "REDACTED".into() } else { value };
+                    (key.into_owned(), value.into_owned())
+                })
+                .collect();
+            redacted.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        Self {
+            method,
+            url: redacted.to_string(),
+            attempt,
+            body: None,
+        }
+    }
+
+    pub(crate) fn with_body(mut self, body: &str) -> Self {
+        let mut end = body.len().min(MAX_BODY_LEN);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut truncated = body[..end].to_string();
+        if end < body.len() {
+            truncated.push_str("...");
+        }
+        self.body = Some(truncated);
+        self
+    }
+}
+
+impl std::fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} (attempt {})", self.method, self.url, self.attempt)?;
+        if let Some(body) = &self.body {
+            write!(f, ": {}", body)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_context_redacts_secrets() {
+        let url = "https://www.googleapis.com/upload/storage/v1/b/b/o?uploadType=resumable&upload_id=s3cr3t";
+        let context = RequestContext::new(reqwest::Method::PUT, &url.parse().unwrap(), 2);
+        assert!(!context.url.contains("s3cr3t"));
+        assert!(context.url.contains("uploadType=resumable"));
+        let context = context.with_body(&"é".repeat(MAX_BODY_LEN));
+        let body = context.body.as_deref().unwrap();
+        assert!(body.len() <= MAX_BODY_LEN + 3 && body.ends_with("..."));
+        assert!(context
+            .to_string()
+            .starts_with("PUT https://www.googleapis.com/upload/"));
+        assert!(context.to_string().contains("(attempt 2)"));
+    }
+}
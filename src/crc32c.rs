@@ -0,0 +1,102 @@
+//! CRC32C checksums in the format that Google Cloud Storage uses. Google reports the checksum of
+//! an object in `Object::crc32c` as the base64 encoding of its big-endian bytes, so checksums that
+//! are computed with this module can be compared to it directly:
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use cloud_storage::{crc32c, Object};
+//!
+//! let content = std::fs::read("my_file.txt")?;
+//! let object = Object::read("my_bucket", "my_file.txt")?;
+//! if crc32c::encode(crc32c::checksum(&content)) == object.crc32c {
+//!     println!("my_file.txt is up to date");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Error;
+use std::convert::TryInto;
+
+/// Returns the CRC32C checksum of `data`.
+pub fn checksum(data: &[u8]) -> u32 {
+    ::crc32c::crc32c(data)
+}
+
+/// Returns the checksum of the data that `crc` is the checksum of, followed by `data`.
+pub fn append(crc: u32, data: &[u8]) -> u32 {
+    ::crc32c::crc32c_append(crc, data)
+}
+
+/// Returns the checksum of `a` followed by `b`, given the checksum `crc_a` of `a`, the checksum
+/// `crc_b` of `b` and the length of `b`. This is how Google computes the checksum of a composite
+/// object, so it can be used to predict the checksum of `Object::compose`.
+pub fn combine(crc_a: u32, crc_b: u32, len_b: u64) -> u32 {
+    ::crc32c::crc32c_combine(crc_a, crc_b, len_b as usize)
+}
+
+/// Encodes a checksum as it appears in `Object::crc32c` and the `x-goog-hash` header.
+pub fn encode(crc: u32) -> String {
+    base64::encode(&crc.to_be_bytes())
+}
+
+/// Decodes a checksum as it appears in `Object::crc32c` and the `x-goog-hash` header.
+pub fn decode(crc: &str) -> Result<u32, Error> {
+    let bytes = base64::decode(crc).map_err(|e| Error::Other(format!("invalid crc32c: {}", e)))?;
+    let bytes: [u8; 4] = bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| Error::Other(format!("invalid crc32c: {} is not 4 bytes long", crc)))?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+/// Computes a checksum of data that is written to it in parts, for example with `std::io::copy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Crc32c {
+    crc: u32,
+}
+
+impl Crc32c {
+    /// Creates the checksum of no data at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `data` to the checksummed data.
+    pub fn update(&mut self, data: &[u8]) {
+        self.crc = append(self.crc, data);
+    }
+
+    /// The checksum of all data so far.
+    pub fn value(&self) -> u32 {
+        self.crc
+    }
+}
+
+impl std::io::Write for Crc32c {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_google() {
+        assert_eq!(encode(checksum(b"123456789")), "4waSgw==");
+        assert_eq!(decode("4waSgw==").unwrap(), 0xE3069283);
+        assert!(decode("4waS").is_err());
+
+        let mut crc = Crc32c::new();
+        std::io::copy(&mut &b"123456789"[..], &mut crc).unwrap();
+        assert_eq!(crc.value(), checksum(b"123456789"));
+        let combined = combine(checksum(b"1234"), checksum(b"56789"), 5);
+        assert_eq!(combined, checksum(b"123456789"));
+    }
+}
@@ -0,0 +1,579 @@
+use crate::error::Error;
+use crate::http::RequestExt;
+use crate::resources::object::percent_encode;
+use crate::resources::service_account::ServiceAccount;
+use crate::token::{ServiceAccountTokenProvider, Token, TokenProvider};
+use crate::Client;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+mod authorized_user;
+mod external_account;
+
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// How long connecting to the metadata server may take. It answers within milliseconds on Google
+/// Cloud, so a connection that takes longer means that there is no metadata server.
+const METADATA_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long requests to the metadata server may take as a whole.
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a failure to reach the metadata server is remembered. Until then, requests to it fail
+/// right away instead of waiting for the connection to fail again.
+const METADATA_UNREACHABLE_FOR: Duration = Duration::from_secs(60);
+
+pub use authorized_user::AuthorizedUserCredentials;
+pub use external_account::ExternalAccountTokenProvider;
+
+lazy_static::lazy_static! {
+    /// The provider found by `application_default`, resolved on first use.
+    static ref DEFAULT_PROVIDER: Mutex<Option<Arc<dyn TokenProvider>>> = Mutex::new(None);
+}
+
+/// Returns the provider that is used when `Config::token_provider` is not set, resolving it
+/// through the Application Default Credentials chain the first time it is needed.
+pub(crate) fn default_provider() -> Result<Arc<dyn TokenProvider>, Error> {
+    let mut guard = DEFAULT_PROVIDER.lock().unwrap();
+    if let Some(provider) = guard.as_ref() {
+        return Ok(provider.clone());
+    }
+    let provider = application_default()?;
+    *guard = Some(provider.clone());
+    Ok(provider)
+}
+
+/// Finds credentials the same way other Google client libraries do, by trying the following
+/// sources in order:
+///
+/// 1. The file that the `SERVICE_ACCOUNT` environment parameter points to, for backwards
+///    compatibility with earlier versions of this crate.
+/// 2. The file that the `GOOGLE_APPLICATION_CREDENTIALS` environment parameter points to.
+///    `service_account`, `authorized_user`, `external_account` (workload identity federation) and
+///    `impersonated_service_account` files are supported.
+/// 3. The well-known file that `gcloud auth application-default login` writes to, which holds the
+///    `authorized_user` credentials of the developer who logged in.
+/// 4. The metadata server that is available on Compute Engine, Cloud Run and GKE.
+///
+/// The first source that is present is used, and failing to load it is an error.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::Config;
+///
+/// cloud_storage::set_config(Config {
+///     token_provider: Some(cloud_storage::application_default()?),
+///     ..Default::default()
+/// });
+/// # Ok(())
+/// # }
+/// ```
+pub fn application_default() -> Result<Arc<dyn TokenProvider>, Error> {
+    dotenv::dotenv().ok();
+    for var in &["SERVICE_ACCOUNT", "GOOGLE_APPLICATION_CREDENTIALS"] {
+        if let Some(path) = std::env::var_os(var).filter(|p| !p.is_empty()) {
+            return from_file(std::path::Path::new(&path));
+        }
+    }
+    if let Some(path) = well_known_file().filter(|p| p.is_file()) {
+        return from_file(&path);
+    }
+    Ok(Arc::new(MetadataServerTokenProvider::new()))
+}
+
+/// Loads a credentials file, picking the right provider based on its `type`.
+fn from_file(path: &std::path::Path) -> Result<Arc<dyn TokenProvider>, Error> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        Error::Other(format!(
+            "could not read credentials file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    from_value(serde_json::from_str(&contents)?)
+        .map_err(|e| Error::Other(format!("invalid credentials in {}: {}", path.display(), e)))
+}
+
+fn from_value(value: serde_json::Value) -> Result<Arc<dyn TokenProvider>, Error> {
+    #[derive(serde::Deserialize)]
+    struct Impersonated {
+        service_account_impersonation_url: String,
+        source_credentials: serde_json::Value,
+        #[serde(default)]
+        delegates: Vec<String>,
+    }
+
+    let credential_type = value["type"].as_str().unwrap_or_default().to_string();
+    match credential_type.as_str() {
+        "service_account" => {
+            let service_account: ServiceAccount = serde_json::from_value(value)?;
+            Ok(Arc::new(ServiceAccountTokenProvider::new(service_account)))
+        }
+        "authorized_user" => Ok(Arc::new(
+            serde_json::from_value::<AuthorizedUserCredentials>(value)?,
+        )),
+        "external_account" => Ok(Arc::new(ExternalAccountTokenProvider::from_json(
+            &value.to_string(),
+        )?)),
+        "impersonated_service_account" => {
+            let impersonated: Impersonated = serde_json::from_value(value)?;
+            Ok(Arc::new(ImpersonatedCredentials {
+                source: from_value(impersonated.source_credentials)?,
+                url: impersonated.service_account_impersonation_url,
+                delegates: impersonated.delegates,
+                lifetime: std::time::Duration::from_secs(3600),
+            }))
+        }
+        other => Err(Error::Other(format!(
+            "credentials of type `{}` are not supported",
+            other
+        ))),
+    }
+}
+
+/// Obtains short-lived tokens for a target service account by
+/// [impersonating](https://cloud.google.com/iam/docs/service-account-impersonation) it with the
+/// tokens of another `TokenProvider`. The source identity needs the
+/// `Service Account Token Creator` role on the target service account, or on each of the
+/// delegates in the chain.
+///
+/// Credential files of type `impersonated_service_account`, as written by
+/// `gcloud auth application-default login --impersonate-service-account`, are also supported by
+/// `application_default`.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::{Config, ImpersonatedCredentials};
+///
+/// let source = cloud_storage::application_default()?;
+/// let provider = ImpersonatedCredentials::new(source, "bucket-admin@my-project.iam.gserviceaccount.com")
+///     .lifetime(std::time::Duration::from_secs(600));
+/// cloud_storage::set_config(Config {
+///     token_provider: Some(std::sync::Arc::new(provider)),
+///     ..Default::default()
+/// });
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImpersonatedCredentials {
+    source: Arc<dyn TokenProvider>,
+    url: String,
+    delegates: Vec<String>,
+    lifetime: std::time::Duration,
+}
+
+impl ImpersonatedCredentials {
+    /// Creates a provider that uses the tokens of `source` to impersonate the service account
+    /// identified by `target_email`.
+    pub fn new(source: Arc<dyn TokenProvider>, target_email: &str) -> Self {
+        Self {
+            source,
+            url: format!(
+                "https://iamcredentials.googleapis.com/v1/projects/-/serviceAccounts/{}:generateAccessToken",
+                percent_encode(target_email)
+            ),
+            delegates: Vec::new(),
+            lifetime: std::time::Duration::from_secs(3600),
+        }
+    }
+
+    /// Sets the chain of service accounts, identified by their email addresses, through which the
+    /// target service account is impersonated. Each account must be allowed to impersonate the
+    /// next one, and the last one must be allowed to impersonate the target.
+    pub fn delegates(mut self, delegates: &[&str]) -> Self {
+        self.delegates = delegates
+            .iter()
+            .map(|email| format!("projects/-/serviceAccounts/{}", email))
+            .collect();
+        self
+    }
+
+    /// Sets how long the issued tokens are valid. Defaults to one hour, which is also the maximum
+    /// unless the organization policy allows longer lifetimes.
+    pub fn lifetime(mut self, lifetime: std::time::Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+}
+
+impl TokenProvider for ImpersonatedCredentials {
+    fn token(&self, client: &Client, scopes: &[&str]) -> Result<Token, Error> {
+        let source = self.source.token(client, &[CLOUD_PLATFORM_SCOPE])?;
+        impersonate(
+            client,
+            &self.url,
+            &source.access_token,
+            scopes,
+            &self.delegates,
+            self.lifetime,
+        )
+    }
+}
+
+/// The location where `gcloud auth application-default login` stores its credentials.
+fn well_known_file() -> Option<std::path::PathBuf> {
+    let config_dir = match std::env::var_os("CLOUDSDK_CONFIG") {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None if cfg!(windows) => {
+            std::path::PathBuf::from(std::env::var_os("APPDATA")?).join("gcloud")
+        }
+        None => std::path::PathBuf::from(std::env::var_os("HOME")?)
+            .join(".config")
+            .join("gcloud"),
+    };
+    Some(config_dir.join("application_default_credentials.json"))
+}
+
+/// Obtains tokens from the [metadata server](https://cloud.google.com/compute/docs/metadata/overview)
+/// that is available to workloads on Compute Engine, Cloud Run, Cloud Functions and GKE (with
+/// Workload Identity). This requires no key files at all: the tokens belong to the service account
+/// that is attached to the instance.
+///
+/// The metadata server is reached at `metadata.google.internal`, unless the `GCE_METADATA_HOST`
+/// environment parameter specifies a different address. Requests to it are sent directly, without
+/// the proxy, transport, interceptors and retries of the client, and fail after two seconds when
+/// the server cannot be reached, for example outside of Google Cloud. Such a failure is remembered
+/// for a minute, during which requests fail right away. Tokens are cached by this crate until
+/// they expire.
+/// ### Example
+/// ```no_run
+/// use cloud_storage::{Config, MetadataServerTokenProvider};
+/// use std::sync::Arc;
+///
+/// cloud_storage::set_config(Config {
+///     token_provider: Some(Arc::new(MetadataServerTokenProvider::new())),
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct MetadataServerTokenProvider {
+    host: String,
+    service_account: String,
+    /// Shared by all clones, so that they reuse the connection and the outcome of the last probe.
+    state: Arc<Mutex<MetadataState>>,
+}
+
+#[derive(Debug, Default)]
+struct MetadataState {
+    http: Option<reqwest::blocking::Client>,
+    /// When the metadata server last could not be reached.
+    unreachable_since: Option<Instant>,
+}
+
+impl MetadataServerTokenProvider {
+    /// Creates a provider for the default service account of the instance.
+    pub fn new() -> Self {
+        Self::for_service_account("default")
+    }
+
+    /// Creates a provider for another service account that is attached to the instance,
+    /// identified by its email address.
+    pub fn for_service_account(email: &str) -> Self {
+        let host = std::env::var("GCE_METADATA_HOST")
+            .unwrap_or_else(|_| "metadata.google.internal".to_string());
+        Self::with_host(host, email)
+    }
+
+    fn with_host(host: String, email: &str) -> Self {
+        Self {
+            host,
+            service_account: email.to_string(),
+            state: Arc::default(),
+        }
+    }
+
+    /// Sends a `GET` request for `path` with `query` to the metadata server, on behalf of
+    /// `client`. The request is sent on a plain HTTP client of its own and is not retried, since
+    /// the settings of `client` are meant for the APIs of Google.
+    fn get(
+        &self,
+        client: &Client,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<reqwest::blocking::Response, Error> {
+        let http = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(since) = state.unreachable_since {
+                if since.elapsed() < METADATA_UNREACHABLE_FOR {
+                    let msg = format!("the metadata server at {} is unreachable", self.host);
+                    return Err(Error::Other(msg));
+                }
+            }
+            match &state.http {
+                Some(http) => http.clone(),
+                None => {
+                    let http = reqwest::blocking::Client::builder()
+                        .no_proxy()
+                        .connect_timeout(METADATA_CONNECT_TIMEOUT)
+                        .timeout(METADATA_TIMEOUT)
+                        .build()?;
+                    state.http = Some(http.clone());
+                    http
+                }
+            }
+        };
+        let url = format!("http://{}/computeMetadata/v1/{}", self.host, path);
+        let user_agent = crate::config::user_agent(client.config());
+        let result = http
+            .get(&url)
+            .header("Metadata-Flavor", "Google")
+            .header(reqwest::header::USER_AGENT, user_agent)
+            .query(query)
+            .send();
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Ok(response) => {
+                state.unreachable_since = None;
+                Ok(response)
+            }
+            Err(e) => {
+                if e.is_connect() || e.is_timeout() {
+                    state.unreachable_since = Some(Instant::now());
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Returns the id of the project that the instance belongs to, asking the metadata server on
+    /// behalf of `client`.
+    pub fn project_id(&self, client: &Client) -> Result<String, Error> {
+        let response = self.get(client, "project/project-id", &[])?;
+        if response.status().is_success() {
+            Ok(response.text()?)
+        } else {
+            Err(metadata_error(response))
+        }
+    }
+
+    /// Returns the email address of the service account whose tokens are handed out, asking the
+    /// metadata server on behalf of `client`.
+    pub fn email(&self, client: &Client) -> Result<String, Error> {
+        let path = format!("instance/service-accounts/{}/email", self.service_account);
+        let response = self.get(client, &path, &[])?;
+        if response.status().is_success() {
+            Ok(response.text()?)
+        } else {
+            Err(metadata_error(response))
+        }
+    }
+}
+
+impl Default for MetadataServerTokenProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenProvider for MetadataServerTokenProvider {
+    fn token(&self, client: &Client, scopes: &[&str]) -> Result<Token, Error> {
+        #[derive(serde::Deserialize)]
+        struct TokenResponse {
+            access_token: String,
+            expires_in: i64,
+        }
+
+        let path = format!("instance/service-accounts/{}/token", self.service_account);
+        let response = self.get(client, &path, &[("scopes", scopes.join(","))])?;
+        if !response.status().is_success() {
+            return Err(metadata_error(response));
+        }
+        let response: TokenResponse = response.json()?;
+        Ok(Token {
+            access_token: response.access_token,
+            expires_at: Some(chrono::Utc::now() + chrono::Duration::seconds(response.expires_in)),
+        })
+    }
+}
+
+/// Exchanges `access_token` for a token of the service account behind `url`, which is an IAM
+/// Credentials `generateAccessToken` endpoint, with the requests of `client`.
+fn impersonate(
+    client: &Client,
+    url: &str,
+    access_token: &str,
+    scopes: &[&str],
+    delegates: &[String],
+    lifetime: std::time::Duration,
+) -> Result<Token, Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Response {
+        access_token: String,
+        expire_time: chrono::DateTime<chrono::Utc>,
+    }
+
+    let mut body = serde_json::json!({
+        "scope": scopes,
+        "lifetime": format!("{}s", lifetime.as_secs()),
+    });
+    if !delegates.is_empty() {
+        body["delegates"] = serde_json::json!(delegates);
+    }
+    let response = client
+        .http()?
+        .post(url)
+        .bearer_auth(access_token)
+        .json(&body)
+        .execute(client)?;
+    if !response.status().is_success() {
+        return Err(Error::Other(format!(
+            "impersonation failed with {}: {}",
+            response.status(),
+            response.text()?
+        )));
+    }
+    let response: Response = response.json()?;
+    Ok(Token {
+        access_token: response.access_token,
+        expires_at: Some(response.expire_time),
+    })
+}
+
+fn metadata_error(response: reqwest::blocking::Response) -> Error {
+    let status = response.status();
+    let body = response.text().unwrap_or_default();
+    Error::Other(format!(
+        "metadata server responded with {}: {}",
+        status, body
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serves a single request with `body`, and returns the address to connect to together with
+    /// a handle that yields the raw request.
+    pub(super) fn serve_once(body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let mut n = 0;
+            // read until the headers and the body they announce have arrived
+            while !is_complete(&buf[..n]) {
+                match stream.read(&mut buf[n..]).unwrap() {
+                    0 => break,
+                    read => n += read,
+                }
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+        (address, handle)
+    }
+
+    fn is_complete(request: &[u8]) -> bool {
+        let request = String::from_utf8_lossy(request);
+        let (head, body) = match request.split_once("\r\n\r\n") {
+            Some(parts) => parts,
+            None => return false,
+        };
+        let length = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse().ok())
+            .unwrap_or(0);
+        body.len() >= length
+    }
+
+    #[test]
+    fn metadata_server_token() {
+        let (host, request) =
+            serve_once(r#"{"access_token":"ya29.abc","expires_in":3599,"token_type":"Bearer"}"#);
+        let provider = MetadataServerTokenProvider::with_host(host, "default");
+        let token = provider
+            .token(&Client::default(), &["scope-a", "scope-b"])
+            .unwrap();
+        assert_eq!(token.access_token, "ya29.abc");
+        assert!(token.expires_at.unwrap() > chrono::Utc::now());
+        let request = request.join().unwrap().to_lowercase();
+        assert!(request.starts_with(
+            "get /computemetadata/v1/instance/service-accounts/default/token?scopes=scope-a%2cscope-b "
+        ));
+        assert!(request.contains("metadata-flavor: google"));
+    }
+
+    #[test]
+    fn unreachable_metadata_server_fails_fast() {
+        // Nothing listens on a port that was just released, so connecting is refused.
+        let host = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let provider = MetadataServerTokenProvider::with_host(host, "default");
+        let client = Client::default();
+        assert!(matches!(
+            provider.project_id(&client),
+            Err(Error::Reqwest(_))
+        ));
+        match provider.clone().email(&client) {
+            Err(Error::Other(msg)) => assert!(msg.contains("unreachable")),
+            other => panic!("expected the cached failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn impersonated_target_is_encoded() {
+        let source: Arc<dyn TokenProvider> = Arc::new(MetadataServerTokenProvider::new());
+        let provider =
+            ImpersonatedCredentials::new(source, "admin@p.iam.gserviceaccount.com/../x?y");
+        assert_eq!(
+            provider.url,
+            "https://iamcredentials.googleapis.com/v1/projects/-/serviceAccounts/\
+             admin%40p.iam.gserviceaccount.com%2F..%2Fx%3Fy:generateAccessToken"
+        );
+    }
+
+    #[test]
+    fn impersonated_service_account_file() {
+        let value = serde_json::json!({
+            "type": "impersonated_service_account",
+            "service_account_impersonation_url": "https://iamcredentials.googleapis.com/v1/projects/-/serviceAccounts/target@p.iam.gserviceaccount.com:generateAccessToken",
+            "delegates": [],
+            "source_credentials": {
+                "type": "external_account",
+                "audience": "audience",
+                "subject_token_type": "urn:ietf:params:oauth:token-type:jwt",
+                "token_url": "https://sts.googleapis.com/v1/token",
+                "credential_source": { "file": "/dev/null" }
+            }
+        });
+        let provider = from_value(value).unwrap();
+        assert!(format!("{:?}", provider).contains("target@p.iam.gserviceaccount.com"));
+    }
+
+    #[test]
+    fn authorized_user_file() {
+        let value = serde_json::json!({
+            "type": "authorized_user",
+            "client_id": "id.apps.googleusercontent.com",
+            "client_secret": "secret",
+            "refresh_token": "1//refresh"
+        });
+        let provider = from_value(value).unwrap();
+        assert!(format!("{:?}", provider).contains("AuthorizedUserCredentials"));
+    }
+
+    #[test]
+    fn unsupported_credential_type() {
+        let path = std::env::temp_dir().join("cloud-storage-unsupported-credentials.json");
+        std::fs::write(&path, r#"{"type": "something_else"}"#).unwrap();
+        let err = from_file(&path).unwrap_err();
+        assert!(err.to_string().contains("something_else"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
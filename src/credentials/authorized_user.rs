@@ -0,0 +1,148 @@
+use crate::error::Error;
+use crate::http::RequestExt;
+use crate::token::{Token, TokenProvider};
+use crate::Client;
+
+/// The endpoint at which refresh tokens are exchanged for access tokens.
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// Obtains tokens for a Google user account with an OAuth2 refresh token, as found in credentials
+/// files of type `authorized_user`. `gcloud auth application-default login` writes such a file to
+/// a well-known location, where `application_default` finds it, so that developers can run code
+/// locally under their own identity without creating service account keys.
+///
+/// The tokens have the scopes that were granted when logging in, regardless of `Config::scopes`.
+/// Since a user account does not belong to a project, set `Config::project_id` for operations that
+/// need one, such as creating buckets.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::{AuthorizedUserCredentials, Config};
+/// use std::sync::Arc;
+///
+/// let json = std::fs::read_to_string("application_default_credentials.json")?;
+/// cloud_storage::set_config(Config {
+///     token_provider: Some(Arc::new(AuthorizedUserCredentials::from_json(&json)?)),
+///     project_id: Some("my-project".to_string()),
+///     ..Default::default()
+/// });
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, serde::Deserialize)]
+pub struct AuthorizedUserCredentials {
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+    #[serde(default)]
+    quota_project_id: Option<String>,
+    #[serde(skip, default = "token_url")]
+    token_url: String,
+}
+
+fn token_url() -> String {
+    TOKEN_URL.to_string()
+}
+
+impl AuthorizedUserCredentials {
+    /// Creates a provider from the id and secret of an OAuth2 client and a refresh token that was
+    /// issued to it.
+    pub fn new(client_id: &str, client_secret: &str, refresh_token: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            refresh_token: refresh_token.to_string(),
+            quota_project_id: None,
+            token_url: token_url(),
+        }
+    }
+
+    /// Parses the contents of an `authorized_user` credentials file.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// The project that the credentials file names for quota and billing, if any. Pass it to
+    /// `Config::user_project` to bill requests to it.
+    pub fn quota_project_id(&self) -> Option<&str> {
+        self.quota_project_id.as_deref()
+    }
+}
+
+impl std::fmt::Debug for AuthorizedUserCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthorizedUserCredentials")
+            .field("client_id", &self.client_id)
+            .field("quota_project_id", &self.quota_project_id)
+            .finish()
+    }
+}
+
+impl TokenProvider for AuthorizedUserCredentials {
+    fn token(&self, client: &Client, _scopes: &[&str]) -> Result<Token, Error> {
+        #[derive(serde::Deserialize)]
+        struct Response {
+            access_token: String,
+            expires_in: i64,
+        }
+
+        let body = [
+            ("grant_type", "refresh_token"),
+            ("client_id", &self.client_id),
+            ("client_secret", &self.client_secret),
+            ("refresh_token", &self.refresh_token),
+        ];
+        let response = client
+            .http()?
+            .post(&self.token_url)
+            .form(&body)
+            .execute(client)?;
+        if !response.status().is_success() {
+            return Err(Error::Other(format!(
+                "refreshing the user credentials failed with {}: {}",
+                response.status(),
+                response.text()?
+            )));
+        }
+        let response: Response = response.json()?;
+        Ok(Token {
+            access_token: response.access_token,
+            expires_at: Some(chrono::Utc::now() + chrono::Duration::seconds(response.expires_in)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh() {
+        let (address, request) = crate::credentials::tests::serve_once(
+            r#"{"access_token":"ya29.user","expires_in":3599,"scope":"openid","token_type":"Bearer"}"#,
+        );
+        let mut credentials = AuthorizedUserCredentials::from_json(
+            r#"{
+                "client_id": "id.apps.googleusercontent.com",
+                "client_secret": "secret",
+                "refresh_token": "1//refresh",
+                "quota_project_id": "my-project",
+                "type": "authorized_user"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(credentials.quota_project_id(), Some("my-project"));
+        assert!(!format!("{:?}", credentials).contains("secret"));
+        credentials.token_url = format!("http://{}/token", address);
+        let token = credentials
+            .token(&crate::Client::default(), &["scope"])
+            .unwrap();
+        assert_eq!(token.access_token, "ya29.user");
+        assert!(token.expires_at.unwrap() > chrono::Utc::now());
+        let request = request.join().unwrap();
+        assert!(request.starts_with("POST /token "));
+        assert!(request.contains("grant_type=refresh_token"));
+        assert!(request.contains("refresh_token=1%2F%2Frefresh"));
+        assert!(request.contains("client_secret=secret"));
+    }
+}
@@ -0,0 +1,417 @@
+use super::CLOUD_PLATFORM_SCOPE;
+use crate::error::Error;
+use crate::http::RequestExt;
+use crate::token::{Token, TokenProvider};
+use crate::Client;
+use std::collections::HashMap;
+
+/// Obtains tokens through
+/// [Workload Identity Federation](https://cloud.google.com/iam/docs/workload-identity-federation),
+/// using a credentials file of type `external_account`. A subject token, issued by AWS or an OIDC
+/// identity provider, is exchanged for a Google access token at the Security Token Service, and
+/// optionally used to impersonate a service account.
+///
+/// Subject tokens can be read from a file, fetched from a url, or derived from the AWS credentials
+/// of the environment. Executable-sourced credentials are not supported.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::{Config, ExternalAccountTokenProvider};
+/// use std::sync::Arc;
+///
+/// let json = std::fs::read_to_string("wif-config.json")?;
+/// cloud_storage::set_config(Config {
+///     token_provider: Some(Arc::new(ExternalAccountTokenProvider::from_json(&json)?)),
+///     ..Default::default()
+/// });
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ExternalAccountTokenProvider {
+    audience: String,
+    subject_token_type: String,
+    token_url: String,
+    service_account_impersonation_url: Option<String>,
+    credential_source: CredentialSource,
+    workforce_pool_user_project: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct CredentialSource {
+    file: Option<String>,
+    url: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    format: Option<Format>,
+    environment_id: Option<String>,
+    region_url: Option<String>,
+    regional_cred_verification_url: Option<String>,
+    imdsv2_session_token_url: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct Format {
+    r#type: String,
+    subject_token_field_name: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct StsResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+impl ExternalAccountTokenProvider {
+    /// Parses the contents of an `external_account` credentials file.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Obtains the token that is presented to the Security Token Service.
+    fn subject_token(&self, client: &Client) -> Result<String, Error> {
+        let source = &self.credential_source;
+        if let Some(environment_id) = &source.environment_id {
+            if !environment_id.starts_with("aws") {
+                return Err(Error::Other(format!(
+                    "unsupported environment `{}` in external account credentials",
+                    environment_id
+                )));
+            }
+            return self.aws_subject_token(client);
+        }
+        let raw = if let Some(file) = &source.file {
+            std::fs::read_to_string(file).map_err(|e| {
+                Error::Other(format!("could not read subject token from {}: {}", file, e))
+            })?
+        } else if let Some(url) = &source.url {
+            let mut request = client.http()?.get(url);
+            for (key, value) in &source.headers {
+                request = request.header(key.as_str(), value.as_str());
+            }
+            let response = request.execute(client)?;
+            if !response.status().is_success() {
+                return Err(Error::Other(format!(
+                    "subject token url responded with {}",
+                    response.status()
+                )));
+            }
+            response.text()?
+        } else {
+            return Err(Error::new(
+                "external account credentials have no supported credential source",
+            ));
+        };
+        match &source.format {
+            Some(format) if format.r#type == "json" => {
+                let field = format.subject_token_field_name.as_deref().unwrap_or("");
+                let value: serde_json::Value = serde_json::from_str(&raw)?;
+                value[field]
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| Error::Other(format!("subject token field `{}` missing", field)))
+            }
+            _ => Ok(raw.trim().to_string()),
+        }
+    }
+
+    /// Builds a signed `GetCallerIdentity` request from the AWS credentials of the environment,
+    /// which serves as the subject token for AWS workloads.
+    fn aws_subject_token(&self, client: &Client) -> Result<String, Error> {
+        let source = &self.credential_source;
+        let http = client.http()?;
+        let session_token = match &source.imdsv2_session_token_url {
+            Some(url) => Some(
+                http.put(url)
+                    .header("X-aws-ec2-metadata-token-ttl-seconds", "300")
+                    .execute(client)?
+                    .text()?,
+            ),
+            None => None,
+        };
+        let imds_get = |url: &str| -> Result<String, Error> {
+            let mut request = http.get(url);
+            if let Some(token) = &session_token {
+                request = request.header("X-aws-ec2-metadata-token", token.as_str());
+            }
+            let response = request.execute(client)?;
+            if !response.status().is_success() {
+                return Err(Error::Other(format!(
+                    "AWS metadata server responded with {}",
+                    response.status()
+                )));
+            }
+            response.text()
+        };
+
+        let region =
+            match std::env::var("AWS_REGION").or_else(|_| std::env::var("AWS_DEFAULT_REGION")) {
+                Ok(region) => region,
+                Err(_) => {
+                    let url = source
+                        .region_url
+                        .as_deref()
+                        .ok_or_else(|| Error::new("no AWS region available"))?;
+                    // the metadata server returns the availability zone, e.g. `us-east-1b`
+                    let mut zone = imds_get(url)?;
+                    zone.pop();
+                    zone
+                }
+            };
+        let credentials = match (
+            std::env::var("AWS_ACCESS_KEY_ID"),
+            std::env::var("AWS_SECRET_ACCESS_KEY"),
+        ) {
+            (Ok(access_key_id), Ok(secret_access_key)) => AwsCredentials {
+                access_key_id,
+                secret_access_key,
+                token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            },
+            _ => {
+                let url = source
+                    .url
+                    .as_deref()
+                    .ok_or_else(|| Error::new("no AWS credentials available"))?;
+                let role = imds_get(url)?;
+                let body = imds_get(&format!("{}/{}", url, role.trim()))?;
+                serde_json::from_str(&body)?
+            }
+        };
+
+        let url = source
+            .regional_cred_verification_url
+            .as_deref()
+            .ok_or_else(|| Error::new("regional_cred_verification_url missing"))?
+            .replace("{region}", &region);
+        let parsed = reqwest::Url::parse(&url).map_err(|e| Error::Other(e.to_string()))?;
+        let host = parsed.host_str().unwrap_or_default().to_string();
+        let mut headers = vec![
+            ("host".to_string(), host),
+            (
+                "x-amz-date".to_string(),
+                chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+            ),
+            (
+                "x-goog-cloud-target-resource".to_string(),
+                self.audience.clone(),
+            ),
+        ];
+        if let Some(token) = &credentials.token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        let authorization = aws_authorization(
+            "POST",
+            parsed.path(),
+            parsed.query().unwrap_or_default(),
+            &headers,
+            b"",
+            &region,
+            "sts",
+            &credentials,
+        )?;
+        let mut request_headers = vec![serde_json::json!({
+            "key": "Authorization",
+            "value": authorization,
+        })];
+        for (key, value) in headers {
+            request_headers.push(serde_json::json!({ "key": key, "value": value }));
+        }
+        let request = serde_json::json!({
+            "url": url,
+            "method": "POST",
+            "headers": request_headers,
+        });
+        Ok(percent_encoding::utf8_percent_encode(
+            &request.to_string(),
+            percent_encoding::NON_ALPHANUMERIC,
+        )
+        .to_string())
+    }
+
+    /// Exchanges the subject token for a Google access token.
+    fn exchange(&self, client: &Client, scopes: &str) -> Result<Token, Error> {
+        let subject_token = self.subject_token(client)?;
+        let mut form = vec![
+            (
+                "grant_type",
+                "urn:ietf:params:oauth:grant-type:token-exchange",
+            ),
+            ("audience", self.audience.as_str()),
+            ("scope", scopes),
+            (
+                "requested_token_type",
+                "urn:ietf:params:oauth:token-type:access_token",
+            ),
+            ("subject_token", subject_token.as_str()),
+            ("subject_token_type", self.subject_token_type.as_str()),
+        ];
+        let options;
+        if let Some(project) = &self.workforce_pool_user_project {
+            options = serde_json::json!({ "userProject": project }).to_string();
+            form.push(("options", options.as_str()));
+        }
+        let response = client
+            .http()?
+            .post(&self.token_url)
+            .form(&form)
+            .execute(client)?;
+        if !response.status().is_success() {
+            return Err(Error::Other(format!(
+                "security token service responded with {}: {}",
+                response.status(),
+                response.text()?
+            )));
+        }
+        let response: StsResponse = response.json()?;
+        Ok(Token {
+            access_token: response.access_token,
+            expires_at: Some(chrono::Utc::now() + chrono::Duration::seconds(response.expires_in)),
+        })
+    }
+}
+
+impl TokenProvider for ExternalAccountTokenProvider {
+    fn token(&self, client: &Client, scopes: &[&str]) -> Result<Token, Error> {
+        match &self.service_account_impersonation_url {
+            Some(url) => {
+                let federated = self.exchange(client, CLOUD_PLATFORM_SCOPE)?;
+                super::impersonate(
+                    client,
+                    url,
+                    &federated.access_token,
+                    scopes,
+                    &[],
+                    std::time::Duration::from_secs(3600),
+                )
+            }
+            None => self.exchange(client, &scopes.join(" ")),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct AwsCredentials {
+    #[serde(rename = "AccessKeyId")]
+    access_key_id: String,
+    #[serde(rename = "SecretAccessKey")]
+    secret_access_key: String,
+    #[serde(rename = "Token")]
+    token: Option<String>,
+}
+
+/// Computes the `Authorization` header for an AWS Signature Version 4 signed request. `headers`
+/// must have lowercase names and include `host` and `x-amz-date`.
+#[allow(clippy::too_many_arguments)]
+fn aws_authorization(
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(String, String)],
+    payload: &[u8],
+    region: &str,
+    service: &str,
+    credentials: &AwsCredentials,
+) -> Result<String, Error> {
+    let mut headers = headers.to_vec();
+    headers.sort();
+    let amz_date = headers
+        .iter()
+        .find(|(key, _)| key == "x-amz-date")
+        .map(|(_, value)| value.clone())
+        .ok_or_else(|| Error::new("x-amz-date header missing"))?;
+    let date = &amz_date[..8];
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(key, value)| format!("{}:{}\n", key, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let mut query_pairs: Vec<&str> = query.split('&').filter(|q| !q.is_empty()).collect();
+    query_pairs.sort();
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        if path.is_empty() { "/" } else { path },
+        query_pairs.join("&"),
+        canonical_headers,
+        signed_headers,
+        hex::encode(crate::crypto::sha256(payload)),
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(crate::crypto::sha256(canonical_request.as_bytes())),
+    );
+    let mut key = format!("AWS4{}", credentials.secret_access_key).into_bytes();
+    for part in &[date, region, service, "aws4_request"] {
+        key = crate::crypto::hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex::encode(crate::crypto::hmac_sha256(&key, string_to_sign.as_bytes()));
+    Ok(format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aws_signature_v4() {
+        // the `post-vanilla` case of the AWS Signature Version 4 test suite
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            token: None,
+        };
+        let headers = vec![
+            ("host".to_string(), "example.amazonaws.com".to_string()),
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+        ];
+        let authorization = aws_authorization(
+            "POST",
+            "/",
+            "",
+            &headers,
+            b"",
+            "us-east-1",
+            "service",
+            &credentials,
+        )
+        .unwrap();
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+
+    #[test]
+    fn file_sourced_json_subject_token() {
+        let path = std::env::temp_dir().join("cloud-storage-subject-token.json");
+        std::fs::write(&path, r#"{"id_token": "oidc-token"}"#).unwrap();
+        let json = serde_json::json!({
+            "type": "external_account",
+            "audience": "//iam.googleapis.com/projects/1/locations/global/workloadIdentityPools/p/providers/q",
+            "subject_token_type": "urn:ietf:params:oauth:token-type:jwt",
+            "token_url": "https://sts.googleapis.com/v1/token",
+            "credential_source": {
+                "file": path,
+                "format": { "type": "json", "subject_token_field_name": "id_token" }
+            }
+        });
+        let provider = ExternalAccountTokenProvider::from_json(&json.to_string()).unwrap();
+        assert_eq!(
+            provider.subject_token(&Client::default()).unwrap(),
+            "oidc-token"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// The number of metadata responses that a client remembers for degraded mode. When exceeded, the
+/// one that was used least recently is forgotten.
+const MAX_ENTRIES: usize = 1024;
+
+/// Returns whether the client configured through `set_config` is currently operating in degraded
+/// mode. See `Client::is_degraded`.
+pub fn is_degraded() -> bool {
+    crate::Client::global().is_degraded()
+}
+
+/// The degraded mode state of a `Client`, as enabled by `Config::degraded_mode`.
+#[derive(Debug, Default)]
+pub(crate) struct Degraded {
+    degraded: AtomicBool,
+    metadata: Mutex<Metadata>,
+}
+
+#[derive(Debug, Default)]
+struct Metadata {
+    /// The last successful metadata response for each url, and when it was last used.
+    entries: HashMap<String, (String, u64)>,
+    /// Increases with every use of an entry, to find the least recently used one.
+    clock: u64,
+}
+
+impl Degraded {
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::SeqCst)
+    }
+
+    pub fn set_degraded(&self, degraded: bool) {
+        self.degraded.store(degraded, Ordering::SeqCst);
+    }
+
+    pub fn remember(&self, config: &crate::Config, url: &str, body: String) {
+        if !config.degraded_mode {
+            return;
+        }
+        let mut metadata = self.metadata.lock().unwrap();
+        metadata.clock += 1;
+        let clock = metadata.clock;
+        metadata.entries.insert(url.to_string(), (body, clock));
+        if metadata.entries.len() > MAX_ENTRIES {
+            let oldest = metadata
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(url, _)| url.clone());
+            if let Some(oldest) = oldest {
+                metadata.entries.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn recall<T: serde::de::DeserializeOwned>(&self, url: &str) -> Option<T> {
+        if !self.is_degraded() {
+            return None;
+        }
+        let mut metadata = self.metadata.lock().unwrap();
+        metadata.clock += 1;
+        let clock = metadata.clock;
+        let (body, last_used) = metadata.entries.get_mut(url)?;
+        *last_used = clock;
+        serde_json::from_str(body).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recall_only_when_degraded() {
+        let config = crate::Config {
+            degraded_mode: true,
+            ..Default::default()
+        };
+        let degraded = Degraded::default();
+        let url = "https://example.com/degraded-test";
+        degraded.remember(&config, url, "[1, 2]".to_string());
+        assert_eq!(degraded.recall::<Vec<u8>>(url), None);
+        degraded.set_degraded(true);
+        assert_eq!(degraded.recall::<Vec<u8>>(url), Some(vec![1, 2]));
+        // other clients are not affected
+        assert!(!Degraded::default().is_degraded());
+    }
+
+    #[test]
+    fn forgets_least_recently_used() {
+        let config = crate::Config {
+            degraded_mode: true,
+            ..Default::default()
+        };
+        let degraded = Degraded::default();
+        degraded.set_degraded(true);
+        for i in 0..MAX_ENTRIES {
+            degraded.remember(&config, &format!("url-{}", i), i.to_string());
+        }
+        assert_eq!(degraded.recall::<usize>("url-0"), Some(0));
+        degraded.remember(&config, "url-new", "1".to_string());
+        assert_eq!(degraded.recall::<usize>("url-0"), Some(0));
+        assert_eq!(degraded.recall::<usize>("url-1"), None);
+        assert_eq!(degraded.metadata.lock().unwrap().entries.len(), MAX_ENTRIES);
+    }
+}
@@ -1,24 +1,188 @@
 /// Represents any of the ways storing something in Google Cloud Storage can fail.
 #[derive(Debug)]
 pub enum Error {
-    /// If the error is caused by a non 2xx response by Google, this variant is returned.
+    /// If the error is caused by a non 2xx response by Google that is not covered by one of the
+    /// more specific variants below, this variant is returned.
     Google(GoogleErrorResponse),
+    /// The bucket, object or other resource does not exist (`404 Not Found`).
+    NotFound(GoogleErrorResponse),
+    /// The credentials are missing or invalid, or they do not grant the permission that is needed
+    /// (`401 Unauthorized` or `403 Forbidden`).
+    PermissionDenied(GoogleErrorResponse),
+    /// The resource already exists, or it was changed by a concurrent request (`409 Conflict`).
+    Conflict(GoogleErrorResponse),
+    /// A precondition such as `ifGenerationMatch` or `ifMetagenerationMatch` did not hold, so
+    /// nothing was changed (`412 Precondition Failed`).
+    PreconditionFailed(GoogleErrorResponse),
+    /// Too many requests were sent in a short time (`429 Too Many Requests`).
+    RateLimited {
+        /// How long Google asked to wait before sending the next request, taken from the
+        /// `Retry-After` header.
+        retry_after: Option<std::time::Duration>,
+        /// The error response sent by Google.
+        error: GoogleErrorResponse,
+    },
     /// If another network error causes something to fail, this variant is used.
     Reqwest(reqwest::Error),
-    /// If we encouter a SSL error, for example an invalid certificate, this variant is used.
-    Ssl(openssl::error::ErrorStack),
     /// If we have problems creating or parsing a json web token, this variant is used.
     Jwt(jsonwebtoken::errors::Error),
     /// If we cannot deserialize one of the repsonses sent by Google, this variant is used.
     Serialization(serde_json::error::Error),
     /// If another failure causes the error, this variant is populated.
     Other(String),
+    /// The request was aborted because the `CancellationToken` passed to `with_cancellation` was
+    /// cancelled.
+    Cancelled,
+    /// The request was not sent, because `Config::circuit_breaker` stopped sending requests after
+    /// too many of them failed.
+    CircuitOpen {
+        /// How long it takes until a request is let through again.
+        retry_after: std::time::Duration,
+    },
+    /// `Object::sync_up` or `Object::sync_down` stopped because a file could not be transferred
+    /// or deleted.
+    #[cfg(not(target_arch = "wasm32"))]
+    SyncIncomplete {
+        /// What the synchronization changed before it stopped.
+        summary: Box<crate::SyncSummary>,
+        /// The failure that stopped it.
+        error: Box<Error>,
+    },
 }
 
 impl Error {
     pub(crate) fn new(msg: &str) -> Error {
         Error::Other(msg.to_string())
     }
+
+    /// Turns an unsuccessful `response` into the matching variant. Bodies that are not a JSON
+    /// error response, such as the ones sent by proxies, are kept as the error message.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_response(response: crate::http::Response) -> Error {
+        let (response, context) = response.into_parts();
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        match response.text() {
+            Ok(body) => Self::from_body(status, &headers, body, context),
+            Err(e) => e.into(),
+        }
+    }
+
+    /// Turns the `status`, `headers` and `body` of an unsuccessful response to the request
+    /// described by `context` into the matching variant, like `from_response`.
+    pub(crate) fn from_body(
+        status: u16,
+        headers: &reqwest::header::HeaderMap,
+        body: String,
+        context: crate::RequestContext,
+    ) -> Error {
+        let retry_after = headers
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(std::time::Duration::from_secs);
+        let context = Some(Box::new(context.with_body(&body)));
+        let mut error = serde_json::from_str(&body)
+            .unwrap_or_else(|_| GoogleErrorResponse::from_status(status, body));
+        error.context = context;
+        Self::from_google(error, retry_after)
+    }
+
+    /// Creates the variant that matches the HTTP `status`, for failures that did not come from the
+    /// JSON API, such as failed gRPC calls.
+    #[cfg(any(feature = "grpc", feature = "testing", feature = "fs"))]
+    pub(crate) fn from_status(status: u16, message: String) -> Error {
+        Self::from_google(GoogleErrorResponse::from_status(status, message), None)
+    }
+
+    fn from_google(error: GoogleErrorResponse, retry_after: Option<std::time::Duration>) -> Error {
+        match error.code() {
+            401 | 403 => Self::PermissionDenied(error),
+            404 => Self::NotFound(error),
+            409 => Self::Conflict(error),
+            412 => Self::PreconditionFailed(error),
+            429 => Self::RateLimited { retry_after, error },
+            _ => Self::Google(error),
+        }
+    }
+
+    /// The error response sent by Google, if this error was caused by one.
+    pub fn google_error(&self) -> Option<&GoogleErrorResponse> {
+        match self {
+            Self::Google(e)
+            | Self::NotFound(e)
+            | Self::PermissionDenied(e)
+            | Self::Conflict(e)
+            | Self::PreconditionFailed(e)
+            | Self::RateLimited { error: e, .. } => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status code of the response sent by Google, if this error was caused by one.
+    pub fn status(&self) -> Option<u16> {
+        self.google_error().map(GoogleErrorResponse::code)
+    }
+
+    /// The reason Google gave for the error, such as `Reason::RateLimitExceeded`, if this error
+    /// was caused by an error response.
+    pub fn reason(&self) -> Option<&Reason> {
+        self.google_error().and_then(GoogleErrorResponse::reason)
+    }
+
+    /// The request that caused the error response sent by Google, if this error was caused by
+    /// one.
+    pub fn context(&self) -> Option<&crate::RequestContext> {
+        self.google_error().and_then(GoogleErrorResponse::context)
+    }
+
+    /// Whether sending the same request again may succeed: the request was rate limited, Google
+    /// had a temporary failure (`408`, `500`, `502`, `503` or `504`), or the connection failed or
+    /// timed out. Note that non-idempotent requests are only safe to retry when they are guarded
+    /// by a precondition.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } => true,
+            Self::Google(e) => matches!(e.code(), 408 | 500 | 502 | 503 | 504),
+            Self::Reqwest(e) => {
+                is_connect(e)
+                    || e.is_timeout()
+                    || e.status()
+                        .is_some_and(|s| s.is_server_error() || s.as_u16() == 429)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the bucket, object or other resource does not exist, for callers that want to
+    /// treat a missing resource as `None`.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::NotFound(_))
+    }
+
+    /// Whether a precondition such as `ifGenerationMatch` did not hold, meaning that the resource
+    /// was changed by someone else in the meantime.
+    pub fn is_precondition_failed(&self) -> bool {
+        matches!(self, Self::PreconditionFailed(_))
+    }
+
+    /// Whether the request conflicted with the current state of the resource, for example because
+    /// it already exists.
+    pub fn is_conflict(&self) -> bool {
+        matches!(self, Self::Conflict(_))
+    }
+}
+
+/// Whether connecting to the server failed. The browser does not tell why a `fetch` failed, so
+/// on wasm32 this is never known.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_connect(e: &reqwest::Error) -> bool {
+    e.is_connect()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn is_connect(_: &reqwest::Error) -> bool {
+    false
 }
 
 impl std::fmt::Display for Error {
@@ -30,12 +194,18 @@ impl std::fmt::Display for Error {
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::Google(e) => Some(e),
+            Self::Google(e)
+            | Self::NotFound(e)
+            | Self::PermissionDenied(e)
+            | Self::Conflict(e)
+            | Self::PreconditionFailed(e)
+            | Self::RateLimited { error: e, .. } => Some(e),
             Self::Reqwest(e) => Some(e),
-            Self::Ssl(e) => Some(e),
             Self::Jwt(e) => Some(e),
             Self::Serialization(e) => Some(e),
-            Self::Other(_) => None,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::SyncIncomplete { error, .. } => Some(error.as_ref()),
+            Self::Other(_) | Self::Cancelled | Self::CircuitOpen { .. } => None,
         }
     }
 }
@@ -46,12 +216,6 @@ impl From<reqwest::Error> for Error {
     }
 }
 
-impl From<openssl::error::ErrorStack> for Error {
-    fn from(err: openssl::error::ErrorStack) -> Self {
-        Self::Ssl(err)
-    }
-}
-
 impl From<jsonwebtoken::errors::Error> for Error {
     fn from(err: jsonwebtoken::errors::Error) -> Self {
         Self::Jwt(err)
@@ -102,7 +266,43 @@ pub(crate) enum GoogleResponse<T> {
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename = "camelCase")]
 pub struct GoogleErrorResponse {
-    error: ErrorList,
+    /// The details of the error.
+    pub error: ErrorList,
+    #[serde(skip)]
+    context: Option<Box<crate::RequestContext>>,
+}
+
+impl GoogleErrorResponse {
+    fn from_status(code: u16, message: String) -> Self {
+        Self {
+            error: ErrorList {
+                errors: Vec::new(),
+                code,
+                message,
+            },
+            context: None,
+        }
+    }
+
+    /// The HTTP status code of the response, for example `403` if the caller lacks permission.
+    pub fn code(&self) -> u16 {
+        self.error.code
+    }
+
+    /// The message that describes the error as a whole.
+    pub fn message(&self) -> &str {
+        &self.error.message
+    }
+
+    /// The reason of the first of the individual errors, which is usually the only one.
+    pub fn reason(&self) -> Option<&Reason> {
+        self.error.errors.first().map(|e| &e.reason)
+    }
+
+    /// The method, url and attempt of the request that caused this response.
+    pub fn context(&self) -> Option<&crate::RequestContext> {
+        self.context.as_deref()
+    }
 }
 
 impl std::fmt::Display for GoogleErrorResponse {
@@ -117,35 +317,48 @@ impl std::error::Error for GoogleErrorResponse {
     }
 }
 
+/// The body of an error response, containing one or more individual errors.
 #[derive(Debug, serde::Deserialize)]
-#[serde(rename = "camelCase")]
-struct ErrorList {
-    errors: Vec<GoogleError>,
-    code: u16,
-    message: String,
+#[serde(rename_all = "camelCase")]
+pub struct ErrorList {
+    /// The individual errors that caused the request to fail.
+    #[serde(default)]
+    pub errors: Vec<GoogleError>,
+    /// The HTTP status code of the response.
+    pub code: u16,
+    /// A description of the error as a whole, usually the message of the first error.
+    #[serde(default)]
+    pub message: String,
 }
 
+/// A single error within an error response.
 #[derive(Debug, serde::Deserialize)]
-#[serde(rename = "camelCase")]
-struct GoogleError {
-    domain: String,
-    reason: Reason,
-    message: String,
-    location_type: Option<String>,
-    location: Option<String>,
+#[serde(rename_all = "camelCase")]
+pub struct GoogleError {
+    /// The scope of the error, for example `global` or `usageLimits`.
+    #[serde(default)]
+    pub domain: String,
+    /// The kind of error, which can be used to decide how to handle it.
+    #[serde(deserialize_with = "Reason::deserialize_or_other")]
+    pub reason: Reason,
+    /// A description of the error.
+    #[serde(default)]
+    pub message: String,
+    /// How `location` should be interpreted, for example `header` or `parameter`.
+    pub location_type: Option<String>,
+    /// The header or parameter of the request that caused the error, if any.
+    pub location: Option<String>,
 }
 
 impl From<GoogleErrorResponse> for Error {
     fn from(err: GoogleErrorResponse) -> Self {
-        Self::Other(format!(
-            "got error response from Google: {}",
-            err.error.message
-        ))
+        Self::from_google(err, None)
     }
 }
 
 /// Google provides a list of codes, but testing indicates that this list is not exhaustive.
-#[derive(Debug, serde::Deserialize)]
+/// Reasons that are not known to this crate are kept as `Reason::Other`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Reason {
     /// When requesting a download using alt=media URL parameter, the direct URL path to use is
@@ -364,6 +577,24 @@ pub enum Reason {
     /// May be returned by Google, meaning undocumented.
     // NONEXHAUST
     GatewayTimeout,
+    /// A reason that is not listed above, as it was sent by Google.
+    #[serde(skip)]
+    Other(String),
+}
+
+impl Reason {
+    fn deserialize_or_other<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::Deserialize;
+
+        let reason = String::deserialize(deserializer)?;
+        Ok(
+            serde_json::from_value(serde_json::Value::String(reason.clone()))
+                .unwrap_or(Reason::Other(reason)),
+        )
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -385,3 +616,125 @@ enum PreconditionFailed {}
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename = "camelCase")]
 enum InternalServerError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn google_error(code: u16) -> GoogleErrorResponse {
+        serde_json::from_value(serde_json::json!({
+            "error": {
+                "errors": [{
+                    "domain": "global",
+                    "reason": "notFound",
+                    "message": "No such object: bucket/object",
+                }],
+                "code": code,
+                "message": "No such object: bucket/object",
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn variant_depends_on_status() {
+        assert!(matches!(Error::from(google_error(404)), Error::NotFound(_)));
+        assert!(matches!(
+            Error::from(google_error(401)),
+            Error::PermissionDenied(_)
+        ));
+        assert!(matches!(
+            Error::from(google_error(403)),
+            Error::PermissionDenied(_)
+        ));
+        assert!(matches!(Error::from(google_error(409)), Error::Conflict(_)));
+        assert!(matches!(
+            Error::from(google_error(412)),
+            Error::PreconditionFailed(_)
+        ));
+        assert!(matches!(Error::from(google_error(500)), Error::Google(_)));
+        let error = Error::from_google(google_error(429), Some(std::time::Duration::from_secs(3)));
+        match &error {
+            Error::RateLimited { retry_after, .. } => {
+                assert_eq!(*retry_after, Some(std::time::Duration::from_secs(3)))
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert_eq!(error.status(), Some(429));
+        assert_eq!(Error::new("other").status(), None);
+    }
+
+    #[test]
+    fn error_from_body() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::RETRY_AFTER, "7".parse().unwrap());
+        let url = "https://www.googleapis.com/storage/v1/b/bucket/o/object"
+            .parse()
+            .unwrap();
+        let context = crate::RequestContext::new(reqwest::Method::GET, &url, 1);
+        let error = Error::from_body(429, &headers, "slow down".to_string(), context);
+        match &error {
+            Error::RateLimited { retry_after, .. } => {
+                assert_eq!(*retry_after, Some(std::time::Duration::from_secs(7)))
+            }
+            other => panic!("expected RateLimited, got {:?}", other),
+        }
+        let context = error.context().unwrap();
+        assert_eq!(context.body.as_deref(), Some("slow down"));
+        assert!(context.url.ends_with("/b/bucket/o/object"));
+    }
+
+    #[test]
+    fn parse_error_body() {
+        let body = r#"{
+            "error": {
+                "errors": [
+                    {
+                        "domain": "global",
+                        "reason": "userProjectMissing",
+                        "message": "Bucket is a requester pays bucket but no user project provided.",
+                        "locationType": "parameter",
+                        "location": "userProject"
+                    },
+                    { "domain": "global", "reason": "somethingNew", "message": "" }
+                ],
+                "code": 400,
+                "message": "Bucket is a requester pays bucket but no user project provided."
+            }
+        }"#;
+        let response: GoogleErrorResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response.reason(), Some(&Reason::UserProjectMissing));
+        assert_eq!(
+            response.error.errors[0].location_type.as_deref(),
+            Some("parameter")
+        );
+        assert_eq!(
+            response.error.errors[0].location.as_deref(),
+            Some("userProject")
+        );
+        assert_eq!(
+            response.error.errors[1].reason,
+            Reason::Other("somethingNew".into())
+        );
+        assert!(response.message().starts_with("Bucket is a requester pays"));
+        let error = Error::from(response);
+        assert_eq!(error.reason(), Some(&Reason::UserProjectMissing));
+        let response: GoogleErrorResponse = serde_json::from_str(
+            r#"{"error":{"errors":[{"domain":"usageLimits","reason":"rateLimitExceeded","message":"slow down"}],"code":429,"message":"slow down"}}"#,
+        )
+        .unwrap();
+        assert_eq!(response.reason(), Some(&Reason::RateLimitExceeded));
+    }
+
+    #[test]
+    fn predicates() {
+        assert!(Error::from(google_error(404)).is_not_found());
+        assert!(!Error::from(google_error(404)).is_retryable());
+        assert!(Error::from(google_error(412)).is_precondition_failed());
+        assert!(Error::from(google_error(409)).is_conflict());
+        assert!(Error::from(google_error(429)).is_retryable());
+        assert!(Error::from(google_error(503)).is_retryable());
+        assert!(!Error::from(google_error(400)).is_retryable());
+        assert!(!Error::Cancelled.is_retryable());
+    }
+}
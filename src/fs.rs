@@ -0,0 +1,497 @@
+//! A filesystem-like view of the objects in a bucket, for applications that treat Cloud Storage
+//! like a disk. Directories do not exist in Cloud Storage: a directory is simply the common prefix
+//! of the objects in it, up to a `/`.
+
+use crate::error::Error;
+use crate::http::RequestExt;
+use crate::resources::object::{percent_encode, Object};
+use crate::Client;
+use futures::channel::mpsc;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::stream::StreamExt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// The number of writes to a `FileWriter` that are buffered while the upload catches up.
+const WRITE_BUFFER: usize = 16;
+
+/// Exposes the objects in a bucket whose name starts with a prefix as a virtual filesystem, with
+/// operations modelled after `std::fs`. Paths are relative to the prefix and use `/` as separator.
+///
+/// This crate only offers a blocking api, so every request is made on a separate thread pool, in
+/// the same way as `GcsStore` does. This works under any async runtime.
+/// ### Example
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::{Client, GcsFs};
+/// use futures::io::{AsyncReadExt, AsyncWriteExt};
+///
+/// let fs = GcsFs::new(Client::default(), "my_bucket", "home/user");
+/// let mut file = fs.create("notes.txt");
+/// file.write_all(b"remember the milk").await?;
+/// file.close().await?;
+/// for entry in fs.read_dir("").await? {
+///     println!("{} ({} bytes)", entry.path(), entry.metadata().len());
+/// }
+/// let mut content = String::new();
+/// fs.open("notes.txt").await?.read_to_string(&mut content).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GcsFs {
+    client: Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl GcsFs {
+    /// Creates a filesystem for the objects in `bucket` whose name starts with `prefix`, which
+    /// are accessed through `client`. Use an empty prefix to expose the whole bucket.
+    pub fn new(client: Client, bucket: impl Into<String>, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        Self {
+            client,
+            bucket: bucket.into(),
+            prefix: if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", prefix)
+            },
+        }
+    }
+
+    /// The name of the bucket that contains the files.
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// The prefix of the names of the objects in this filesystem, which is empty or ends with a
+    /// `/`.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the entries of the directory at `path`: the files in it and its subdirectories.
+    /// Use an empty path for the root of the filesystem. Fails with `Error::NotFound` if there is
+    /// no such directory, that is, if no object has a name that starts with it.
+    pub async fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, Error> {
+        let mut dir = self.name(path);
+        if !dir.is_empty() && !dir.ends_with('/') {
+            dir.push('/');
+        }
+        self.run(move |fs| {
+            let (objects, prefixes) = fs.client.object().list_delimited(&fs.bucket, &dir)?;
+            if !dir.is_empty() && objects.is_empty() && prefixes.is_empty() {
+                let message = format!("no such directory: {}", fs.path(dir.trim_end_matches('/')));
+                return Err(Error::from_status(404, message));
+            }
+            let dirs = prefixes.iter().map(|prefix| DirEntry {
+                path: fs.path(prefix.trim_end_matches('/')).to_string(),
+                metadata: Metadata::dir(),
+            });
+            let files = objects
+                .iter()
+                // placeholder objects that some tools create for empty directories
+                .filter(|object| object.name != dir)
+                .map(|object| DirEntry {
+                    path: fs.path(&object.name).to_string(),
+                    metadata: Metadata::file(object),
+                });
+            Ok(dirs.chain(files).collect())
+        })
+        .await
+    }
+
+    /// Returns the metadata of the file or directory at `path`. Fails with `Error::NotFound` if
+    /// there is neither.
+    pub async fn metadata(&self, path: &str) -> Result<Metadata, Error> {
+        let name = self.name(path);
+        if name.is_empty() {
+            return Ok(Metadata::dir());
+        }
+        self.run(move |fs| match fs.client.object().read(&fs.bucket, &name) {
+            Ok(object) => Ok(Metadata::file(&object)),
+            Err(e) if e.is_not_found() => {
+                let dir = format!("{}/", name.trim_end_matches('/'));
+                match fs
+                    .client
+                    .object()
+                    .list_with(&fs.bucket, Some(&dir), Some(1))
+                    .next()
+                {
+                    Some(Ok(_)) => Ok(Metadata::dir()),
+                    Some(Err(e)) => Err(e),
+                    None => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        })
+        .await
+    }
+
+    /// Opens the file at `path` for reading. Its content is streamed as it is read.
+    pub async fn open(&self, path: &str) -> Result<File, Error> {
+        let name = self.name(path);
+        let response = self
+            .run(move |fs| {
+                let url = format!(
+                    "{}/b/{}/o/{}",
+                    fs.client.api_url(),
+                    percent_encode(&fs.bucket),
+                    percent_encode(&name),
+                );
+                let response = fs
+                    .client
+                    .http()?
+                    .get(&url)
+                    .headers(fs.client.headers()?)
+                    .query(&fs.client.user_project_query())
+                    .query(&[("alt", "media")])
+                    .execute(&fs.client)?;
+                if response.status().is_success() {
+                    Ok(response.into_inner())
+                } else {
+                    Err(Error::from_response(response))
+                }
+            })
+            .await?;
+        Ok(File {
+            reader: blocking::Unblock::new(response),
+        })
+    }
+
+    /// Creates the file at `path`, replacing it if it exists. The content written to it is
+    /// uploaded in chunks of 8 MiB while it is written, in a
+    /// [resumable upload](https://cloud.google.com/storage/docs/performing-resumable-uploads), so
+    /// that large files are never held in memory. The file only appears once `close` has
+    /// completed; if the writer is dropped before, nothing is stored.
+    pub fn create(&self, path: &str) -> FileWriter {
+        FileWriter {
+            fs: self.clone(),
+            name: self.name(path),
+            sender: None,
+            complete: Arc::new(AtomicBool::new(false)),
+            upload: None,
+            closed: false,
+        }
+    }
+
+    /// Removes the file at `path`.
+    pub async fn remove_file(&self, path: &str) -> Result<(), Error> {
+        let name = self.name(path);
+        self.run(move |fs| fs.client.object().delete(&fs.bucket, &name))
+            .await
+    }
+
+    /// Copies the file at `from` to `to`, replacing `to` if it exists. The content is copied by
+    /// Google, without passing through this machine.
+    pub async fn copy(&self, from: &str, to: &str) -> Result<(), Error> {
+        let (from, to) = (self.name(from), self.name(to));
+        self.run(move |fs| {
+            let object = fs.client.object().read(&fs.bucket, &from)?;
+            fs.client.object().copy(&object, &fs.bucket, &to)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Moves the file at `from` to `to`, like `Object::rename`. Fails with
+    /// `Error::is_precondition_failed` if `to` already exists, or if `from` is replaced while it
+    /// is being moved, so that no content is ever lost. In buckets with a hierarchical namespace
+    /// the file is moved atomically; in others it is copied and then removed.
+    pub async fn rename(&self, from: &str, to: &str) -> Result<(), Error> {
+        let (from, to) = (self.name(from), self.name(to));
+        self.run(move |fs| {
+            let object = fs.client.object().read(&fs.bucket, &from)?;
+            fs.client.object().rename(&object, &to)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// The name of the object at `path`.
+    fn name(&self, path: &str) -> String {
+        format!("{}{}", self.prefix, path.trim_start_matches('/'))
+    }
+
+    /// The path of the object called `name`.
+    fn path<'a>(&self, name: &'a str) -> &'a str {
+        name.strip_prefix(self.prefix.as_str()).unwrap_or(name)
+    }
+
+    /// Runs `f` on a thread on which it may block.
+    async fn run<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self) -> Result<T, Error> + Send + 'static,
+    {
+        let fs = self.clone();
+        crate::runtime::unblock(move || f(&fs)).await
+    }
+}
+
+/// A file or directory in a `GcsFs`, as returned by `GcsFs::read_dir`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirEntry {
+    path: String,
+    metadata: Metadata,
+}
+
+impl DirEntry {
+    /// The path of the entry, relative to the root of the filesystem.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The last component of the path of the entry.
+    pub fn file_name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+
+    /// The metadata of the entry.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+/// The metadata of a file or directory in a `GcsFs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    is_dir: bool,
+    len: u64,
+    modified: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Metadata {
+    fn dir() -> Self {
+        Self {
+            is_dir: true,
+            len: 0,
+            modified: None,
+        }
+    }
+
+    fn file(object: &Object) -> Self {
+        Self {
+            is_dir: false,
+            len: object.size,
+            modified: Some(object.updated),
+        }
+    }
+
+    /// Whether this is the metadata of a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Whether this is the metadata of a file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// The size of the file in bytes, or `0` for directories.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file is empty. Directories are always empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The moment the file was last changed. Not known for directories.
+    pub fn modified(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.modified
+    }
+}
+
+/// A file in a `GcsFs` that is opened for reading, as returned by `GcsFs::open`.
+#[derive(Debug)]
+pub struct File {
+    reader: blocking::Unblock<reqwest::blocking::Response>,
+}
+
+impl AsyncRead for File {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+type Upload = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+/// A file in a `GcsFs` that is opened for writing, as returned by `GcsFs::create`. The content is
+/// uploaded while it is written, and the file appears when it is closed, which must therefore
+/// always be done.
+pub struct FileWriter {
+    fs: GcsFs,
+    name: String,
+    /// Passes the written content to the upload, which runs on another thread.
+    sender: Option<mpsc::Sender<io::Result<Vec<u8>>>>,
+    /// Set when the file is closed, so that the upload can tell the end of the content apart
+    /// from a writer that was dropped.
+    complete: Arc<AtomicBool>,
+    upload: Option<Upload>,
+    closed: bool,
+}
+
+impl FileWriter {
+    /// Starts the upload, unless it is already running.
+    fn start(&mut self) -> &mut Upload {
+        let (fs, name) = (&self.fs, &self.name);
+        let (sender, complete) = (&mut self.sender, &self.complete);
+        self.upload.get_or_insert_with(|| {
+            let (tx, receiver) = mpsc::channel(WRITE_BUFFER);
+            *sender = Some(tx);
+            let complete = complete.clone();
+            // the content is cut short when the channel closes before the file was closed
+            let end = futures::stream::poll_fn(move |_| {
+                Poll::Ready(if complete.load(Ordering::SeqCst) {
+                    None
+                } else {
+                    Some(Err(io::Error::other(
+                        "the file was dropped before it was closed",
+                    )))
+                })
+            });
+            let content = receiver.chain(end);
+            let (fs, name) = (fs.clone(), name.clone());
+            Box::pin(async move {
+                fs.run(move |fs| {
+                    fs.client.object().create_from_stream(
+                        &fs.bucket,
+                        content,
+                        None,
+                        &name,
+                        crate::mime::AUTO,
+                    )?;
+                    Ok(())
+                })
+                .await
+            })
+        })
+    }
+
+    /// Ends the upload after it has failed.
+    fn fail(&mut self, result: Result<(), Error>) -> io::Error {
+        self.upload = None;
+        self.sender = None;
+        self.closed = true;
+        match result {
+            Err(e) => io::Error::other(e),
+            Ok(()) => io::Error::other("the upload ended before the file was closed"),
+        }
+    }
+}
+
+impl std::fmt::Debug for FileWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWriter")
+            .field("bucket", &self.fs.bucket)
+            .field("name", &self.name)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+impl AsyncWrite for FileWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.closed {
+            return Poll::Ready(Err(io::Error::other("the file is closed")));
+        }
+        // the upload only ends while content is still being written when it fails
+        if let Poll::Ready(result) = this.start().as_mut().poll(cx) {
+            return Poll::Ready(Err(this.fail(result)));
+        }
+        let sender = this.sender.as_mut().unwrap();
+        if futures::ready!(sender.poll_ready(cx)).is_err() {
+            return Poll::Ready(Err(this.fail(Ok(()))));
+        }
+        match sender.start_send(Ok(buf.to_vec())) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(_) => Poll::Ready(Err(this.fail(Ok(())))),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.closed {
+            return Poll::Ready(Ok(()));
+        }
+        this.start();
+        // closing the channel ends the content
+        this.complete.store(true, Ordering::SeqCst);
+        this.sender = None;
+        let upload = this.upload.as_mut().unwrap();
+        let result = futures::ready!(upload.as_mut().poll(cx));
+        this.upload = None;
+        this.closed = true;
+        Poll::Ready(result.map_err(io::Error::other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn paths() {
+        let fs = GcsFs::new(Client::default(), "bucket", "/home/user/");
+        assert_eq!(fs.prefix(), "home/user/");
+        assert_eq!(fs.name("/notes.txt"), "home/user/notes.txt");
+        assert_eq!(fs.path("home/user/dir/notes.txt"), "dir/notes.txt");
+        let root = GcsFs::new(Client::default(), "bucket", "");
+        assert_eq!(root.name("notes.txt"), "notes.txt");
+        let entry = DirEntry {
+            path: "dir/notes.txt".to_string(),
+            metadata: Metadata::dir(),
+        };
+        assert_eq!(entry.file_name(), "notes.txt");
+    }
+
+    #[test]
+    fn write_read_rename_remove() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let fs = GcsFs::new(Client::global(), bucket.name, "test-fs");
+        futures::executor::block_on(async {
+            let mut file = fs.create("dir/a.txt");
+            file.write_all(b"hello").await?;
+            file.close().await?;
+            assert!(fs.metadata("dir").await?.is_dir());
+            assert_eq!(fs.metadata("dir/a.txt").await?.len(), 5);
+            assert!(fs.read_dir("missing").await.unwrap_err().is_not_found());
+            let entries = fs.read_dir("").await?;
+            assert!(entries
+                .iter()
+                .any(|e| e.path() == "dir" && e.metadata().is_dir()));
+            fs.rename("dir/a.txt", "dir/b.txt").await?;
+            let mut content = String::new();
+            fs.open("dir/b.txt")
+                .await?
+                .read_to_string(&mut content)
+                .await?;
+            assert_eq!(content, "hello");
+            assert!(fs.metadata("dir/a.txt").await.unwrap_err().is_not_found());
+            fs.remove_file("dir/b.txt").await?;
+            Ok(())
+        })
+    }
+}
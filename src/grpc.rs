@@ -0,0 +1,609 @@
+//! Reads, writes and listings of objects through the
+//! [gRPC API](https://cloud.google.com/storage/docs/grpc) of Cloud Storage, which offers a higher
+//! throughput for large transfers than the JSON API. Used by `Client`s that have `Config::grpc`
+//! enabled.
+
+use crate::error::Error;
+use crate::resources::object::{percent_encode, Object};
+use crate::Client;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::MetadataValue;
+
+const ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// The largest amount of data that may be sent in a single message of an upload.
+const MAX_WRITE_CHUNK_BYTES: usize = 2 * 1024 * 1024;
+
+/// The messages of `google.storage.v2`, limited to the fields this crate uses. Fields that are
+/// `oneof` members in the protocol are declared as optional fields, which are encoded the same way.
+mod proto {
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Timestamp {
+        #[prost(int64, tag = "1")]
+        pub seconds: i64,
+        #[prost(int32, tag = "2")]
+        pub nanos: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ObjectChecksums {
+        #[prost(fixed32, optional, tag = "1")]
+        pub crc32c: Option<u32>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub md5_hash: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Object {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub bucket: String,
+        #[prost(int64, tag = "3")]
+        pub generation: i64,
+        #[prost(int64, tag = "4")]
+        pub metageneration: i64,
+        #[prost(string, tag = "5")]
+        pub storage_class: String,
+        #[prost(int64, tag = "6")]
+        pub size: i64,
+        #[prost(string, tag = "7")]
+        pub content_encoding: String,
+        #[prost(string, tag = "8")]
+        pub content_disposition: String,
+        #[prost(string, tag = "9")]
+        pub cache_control: String,
+        #[prost(string, tag = "11")]
+        pub content_language: String,
+        #[prost(message, optional, tag = "12")]
+        pub delete_time: Option<Timestamp>,
+        #[prost(string, tag = "13")]
+        pub content_type: String,
+        #[prost(message, optional, tag = "14")]
+        pub create_time: Option<Timestamp>,
+        #[prost(int32, tag = "15")]
+        pub component_count: i32,
+        #[prost(message, optional, tag = "16")]
+        pub checksums: Option<ObjectChecksums>,
+        #[prost(message, optional, tag = "17")]
+        pub update_time: Option<Timestamp>,
+        #[prost(string, tag = "18")]
+        pub kms_key: String,
+        #[prost(message, optional, tag = "19")]
+        pub update_storage_class_time: Option<Timestamp>,
+        #[prost(bool, tag = "20")]
+        pub temporary_hold: bool,
+        #[prost(message, optional, tag = "21")]
+        pub retention_expire_time: Option<Timestamp>,
+        #[prost(map = "string, string", tag = "22")]
+        pub metadata: HashMap<String, String>,
+        #[prost(bool, optional, tag = "23")]
+        pub event_based_hold: Option<bool>,
+        #[prost(string, tag = "27")]
+        pub etag: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ChecksummedData {
+        #[prost(bytes = "vec", tag = "1")]
+        pub content: Vec<u8>,
+        #[prost(fixed32, optional, tag = "2")]
+        pub crc32c: Option<u32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GetObjectRequest {
+        #[prost(string, tag = "1")]
+        pub bucket: String,
+        #[prost(string, tag = "2")]
+        pub object: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ReadObjectRequest {
+        #[prost(string, tag = "1")]
+        pub bucket: String,
+        #[prost(string, tag = "2")]
+        pub object: String,
+        #[prost(int64, tag = "3")]
+        pub generation: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ReadObjectResponse {
+        #[prost(message, optional, tag = "1")]
+        pub checksummed_data: Option<ChecksummedData>,
+        #[prost(message, optional, tag = "4")]
+        pub metadata: Option<Object>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct WriteObjectSpec {
+        #[prost(message, optional, tag = "1")]
+        pub resource: Option<Object>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct WriteObjectRequest {
+        #[prost(message, optional, tag = "2")]
+        pub write_object_spec: Option<WriteObjectSpec>,
+        #[prost(int64, tag = "3")]
+        pub write_offset: i64,
+        #[prost(message, optional, tag = "4")]
+        pub checksummed_data: Option<ChecksummedData>,
+        #[prost(bool, tag = "7")]
+        pub finish_write: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct WriteObjectResponse {
+        #[prost(int64, optional, tag = "1")]
+        pub persisted_size: Option<i64>,
+        #[prost(message, optional, tag = "2")]
+        pub resource: Option<Object>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ListObjectsRequest {
+        #[prost(string, tag = "1")]
+        pub parent: String,
+        #[prost(int32, tag = "2")]
+        pub page_size: i32,
+        #[prost(string, tag = "3")]
+        pub page_token: String,
+        #[prost(string, tag = "6")]
+        pub prefix: String,
+        #[prost(string, tag = "14")]
+        pub match_glob: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ListObjectsResponse {
+        #[prost(message, repeated, tag = "1")]
+        pub objects: Vec<Object>,
+        #[prost(string, repeated, tag = "2")]
+        pub prefixes: Vec<String>,
+        #[prost(string, tag = "3")]
+        pub next_page_token: String,
+    }
+}
+
+/// The runtime and connection that gRPC calls of a `Client` are made on. Calls block the current
+/// thread until they are done, like all other calls of this crate.
+#[derive(Debug)]
+pub(crate) struct Connection {
+    runtime: tokio::runtime::Runtime,
+    channel: tonic::transport::Channel,
+}
+
+impl Connection {
+    pub fn new() -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("cloud-storage-grpc")
+            .enable_all()
+            .build()
+            .map_err(|e| Error::Other(format!("could not start the gRPC runtime: {}", e)))?;
+        let tls = tonic::transport::ClientTlsConfig::new().with_webpki_roots();
+        let channel = {
+            let _guard = runtime.enter();
+            tonic::transport::Endpoint::from_static(ENDPOINT)
+                .tls_config(tls)
+                .map_err(transport_error)?
+                .connect_lazy()
+        };
+        Ok(Self { runtime, channel })
+    }
+}
+
+/// The name of `bucket` as used by the gRPC API.
+fn bucket_name(bucket: &str) -> String {
+    format!("projects/_/buckets/{}", bucket)
+}
+
+/// Wraps `message` in a request that is authenticated as `client`, and routed to `bucket`.
+fn request<T>(client: &Client, bucket: &str, message: T) -> Result<tonic::Request<T>, Error> {
+    let mut request = tonic::Request::new(message);
+    let metadata = request.metadata_mut();
+    for (name, value) in client.headers()?.iter() {
+        if let Ok(value) = MetadataValue::try_from(value.as_bytes()) {
+            let name = tonic::metadata::MetadataKey::from_bytes(name.as_str().as_bytes())
+                .map_err(|e| Error::Other(e.to_string()))?;
+            metadata.insert(name, value);
+        }
+    }
+    let params = format!("bucket={}", percent_encode(&bucket_name(bucket)));
+    metadata.insert(
+        "x-goog-request-params",
+        params
+            .parse()
+            .map_err(|_| Error::new("invalid bucket name"))?,
+    );
+    for (_, project) in client.user_project_query() {
+        if let Ok(project) = project.parse() {
+            metadata.insert("x-goog-user-project", project);
+        }
+    }
+    Ok(request)
+}
+
+async fn ready(
+    channel: &tonic::transport::Channel,
+) -> Result<tonic::client::Grpc<tonic::transport::Channel>, Error> {
+    let mut grpc = tonic::client::Grpc::new(channel.clone());
+    grpc.ready().await.map_err(transport_error)?;
+    Ok(grpc)
+}
+
+/// Retrieves the metadata of an object.
+pub(crate) fn read(client: &Client, bucket: &str, name: &str) -> Result<Object, Error> {
+    let connection = client.grpc()?;
+    client.throttle(&reqwest::Method::GET);
+    crate::cancel::check()?;
+    let request = request(
+        client,
+        bucket,
+        proto::GetObjectRequest {
+            bucket: bucket_name(bucket),
+            object: name.to_string(),
+        },
+    )?;
+    let object = connection.runtime.block_on(async {
+        let mut grpc = ready(&connection.channel).await?;
+        let path = PathAndQuery::from_static("/google.storage.v2.Storage/GetObject");
+        let codec = tonic::codec::ProstCodec::<_, proto::Object>::default();
+        let response = grpc.unary(request, path, codec).await;
+        response
+            .map(tonic::Response::into_inner)
+            .map_err(status_error)
+    })?;
+    Ok(convert(client, object))
+}
+
+/// Downloads the content of an object, in the chunks in which it is streamed by Google. Downloads
+/// the latest generation when `generation` is not set.
+pub(crate) fn download(
+    client: &Client,
+    bucket: &str,
+    name: &str,
+    generation: Option<i64>,
+) -> Result<bytes::Bytes, Error> {
+    let connection = client.grpc()?;
+    client.throttle(&reqwest::Method::GET);
+    crate::cancel::check()?;
+    let request = request(
+        client,
+        bucket,
+        proto::ReadObjectRequest {
+            bucket: bucket_name(bucket),
+            object: name.to_string(),
+            generation: generation.unwrap_or_default(),
+        },
+    )?;
+    let content = connection.runtime.block_on(async {
+        let mut grpc = ready(&connection.channel).await?;
+        let path = PathAndQuery::from_static("/google.storage.v2.Storage/ReadObject");
+        let codec = tonic::codec::ProstCodec::<_, proto::ReadObjectResponse>::default();
+        let mut stream = grpc
+            .server_streaming(request, path, codec)
+            .await
+            .map_err(status_error)?
+            .into_inner();
+        let mut content = Vec::new();
+        while let Some(message) = stream.message().await.map_err(status_error)? {
+            if let Some(metadata) = &message.metadata {
+                content.reserve(metadata.size.max(0) as usize);
+            }
+            if let Some(data) = message.checksummed_data {
+                content.extend_from_slice(&data.content);
+            }
+        }
+        Ok::<_, Error>(content)
+    })?;
+    Ok(content.into())
+}
+
+/// Uploads `file` as a new object in a single streaming call.
+pub(crate) fn create(
+    client: &Client,
+    bucket: &str,
+    file: &[u8],
+    filename: &str,
+    mime_type: &str,
+) -> Result<Object, Error> {
+    let connection = client.grpc()?;
+    client.throttle(&reqwest::Method::POST);
+    crate::cancel::check()?;
+    let spec = proto::WriteObjectSpec {
+        resource: Some(proto::Object {
+            name: filename.to_string(),
+            bucket: bucket_name(bucket),
+            content_type: mime_type.to_string(),
+            ..Default::default()
+        }),
+    };
+    let mut messages: Vec<_> = file
+        .chunks(MAX_WRITE_CHUNK_BYTES)
+        .enumerate()
+        .map(|(i, chunk)| proto::WriteObjectRequest {
+            write_object_spec: None,
+            write_offset: (i * MAX_WRITE_CHUNK_BYTES) as i64,
+            checksummed_data: Some(proto::ChecksummedData {
+                content: chunk.to_vec(),
+                crc32c: None,
+            }),
+            finish_write: false,
+        })
+        .collect();
+    if messages.is_empty() {
+        messages.push(proto::WriteObjectRequest::default());
+    }
+    messages[0].write_object_spec = Some(spec);
+    if let Some(last) = messages.last_mut() {
+        last.finish_write = true;
+    }
+    let request = request(client, bucket, futures::stream::iter(messages))?;
+    let response = connection.runtime.block_on(async {
+        let mut grpc = ready(&connection.channel).await?;
+        let path = PathAndQuery::from_static("/google.storage.v2.Storage/WriteObject");
+        let codec = tonic::codec::ProstCodec::<_, proto::WriteObjectResponse>::default();
+        let response = grpc.client_streaming(request, path, codec).await;
+        response
+            .map(tonic::Response::into_inner)
+            .map_err(status_error)
+    })?;
+    match response.resource {
+        Some(object) => Ok(convert(client, object)),
+        None => Err(Error::new("the upload did not finish")),
+    }
+}
+
+/// Iterates over the objects in a bucket, requesting the next page whenever the current one is
+/// exhausted, like `ListIter` does for the JSON API.
+pub(crate) struct GrpcListIter {
+    client: Client,
+    bucket: String,
+    prefix: String,
+    match_glob: String,
+    page_size: i32,
+    items: std::vec::IntoIter<Object>,
+    next_page_token: Option<String>,
+    finished: bool,
+}
+
+impl GrpcListIter {
+    pub fn new(
+        client: &Client,
+        bucket: &str,
+        prefix: Option<&str>,
+        match_glob: Option<&str>,
+        page_size: Option<u32>,
+    ) -> Self {
+        Self {
+            client: client.clone(),
+            bucket: bucket.to_string(),
+            prefix: prefix.unwrap_or_default().to_string(),
+            match_glob: match_glob.unwrap_or_default().to_string(),
+            page_size: page_size.unwrap_or(0) as i32,
+            items: Vec::new().into_iter(),
+            next_page_token: None,
+            finished: false,
+        }
+    }
+
+    fn fetch(&mut self) -> Result<(), Error> {
+        let client = &self.client;
+        let connection = client.grpc()?;
+        client.throttle(&reqwest::Method::GET);
+        crate::cancel::check()?;
+        let request = request(
+            client,
+            &self.bucket,
+            proto::ListObjectsRequest {
+                parent: bucket_name(&self.bucket),
+                page_size: self.page_size,
+                page_token: self.next_page_token.take().unwrap_or_default(),
+                prefix: self.prefix.clone(),
+                match_glob: self.match_glob.clone(),
+            },
+        )?;
+        let response = connection.runtime.block_on(async {
+            let mut grpc = ready(&connection.channel).await?;
+            let path = PathAndQuery::from_static("/google.storage.v2.Storage/ListObjects");
+            let codec = tonic::codec::ProstCodec::<_, proto::ListObjectsResponse>::default();
+            let response = grpc.unary(request, path, codec).await;
+            response
+                .map(tonic::Response::into_inner)
+                .map_err(status_error)
+        })?;
+        self.items = response
+            .objects
+            .into_iter()
+            .map(|object| convert(client, object))
+            .collect::<Vec<_>>()
+            .into_iter();
+        self.finished = response.next_page_token.is_empty();
+        self.next_page_token = Some(response.next_page_token);
+        Ok(())
+    }
+}
+
+impl Iterator for GrpcListIter {
+    type Item = Result<Object, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(e) = self.fetch() {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+fn transport_error(error: tonic::transport::Error) -> Error {
+    Error::Other(format!("gRPC connection failed: {}", error))
+}
+
+/// Turns a failed call into the variant that the JSON API would have returned.
+fn status_error(status: tonic::Status) -> Error {
+    use tonic::Code;
+
+    let http_status = match status.code() {
+        Code::InvalidArgument => 400,
+        Code::Unauthenticated => 401,
+        Code::PermissionDenied => 403,
+        Code::NotFound => 404,
+        Code::AlreadyExists | Code::Aborted => 409,
+        Code::FailedPrecondition => 412,
+        Code::OutOfRange => 416,
+        Code::ResourceExhausted => 429,
+        Code::Cancelled => 499,
+        Code::Unimplemented => 501,
+        Code::Unavailable => 503,
+        Code::DeadlineExceeded => 504,
+        _ => 500,
+    };
+    Error::from_status(http_status, status.message().to_string())
+}
+
+fn timestamp(timestamp: Option<proto::Timestamp>) -> Option<chrono::DateTime<chrono::Utc>> {
+    let timestamp = timestamp?;
+    chrono::DateTime::from_timestamp(timestamp.seconds, timestamp.nanos as u32)
+}
+
+/// Converts an object of the gRPC API into the representation of the JSON API.
+fn convert(client: &Client, object: proto::Object) -> Object {
+    let bucket = object
+        .bucket
+        .strip_prefix("projects/_/buckets/")
+        .unwrap_or(&object.bucket)
+        .to_string();
+    let non_empty = |value: String| Some(value).filter(|v| !v.is_empty());
+    let updated = timestamp(object.update_time).unwrap_or_default();
+    let checksums = object.checksums.unwrap_or_default();
+    let self_link = format!(
+        "{}/b/{}/o/{}",
+        client.api_url(),
+        percent_encode(&bucket),
+        percent_encode(&object.name)
+    );
+    Object {
+        kind: "storage#object".to_string(),
+        id: format!("{}/{}/{}", bucket, object.name, object.generation),
+        media_link: format!("{}?generation={}&alt=media", self_link, object.generation),
+        self_link,
+        generation: object.generation,
+        metageneration: object.metageneration,
+        content_type: non_empty(object.content_type),
+        time_created: timestamp(object.create_time).unwrap_or(updated),
+        updated,
+        time_deleted: timestamp(object.delete_time),
+        temporary_hold: Some(object.temporary_hold),
+        event_based_hold: object.event_based_hold,
+        retention_expiration_time: timestamp(object.retention_expire_time),
+        storage_class: object.storage_class.into(),
+        time_storage_class_updated: timestamp(object.update_storage_class_time).unwrap_or(updated),
+        size: object.size.max(0) as u64,
+        md5_hash: Some(checksums.md5_hash)
+            .filter(|hash| !hash.is_empty())
+            .map(|hash| base64::encode(&hash)),
+        content_encoding: non_empty(object.content_encoding),
+        content_disposition: non_empty(object.content_disposition),
+        content_language: non_empty(object.content_language),
+        cache_control: non_empty(object.cache_control),
+        metadata: Some(object.metadata).filter(|m: &HashMap<_, _>| !m.is_empty()),
+        acl: None,
+        owner: None,
+        crc32c: checksums
+            .crc32c
+            .map(crate::crc32c::encode)
+            .unwrap_or_default(),
+        component_count: Some(object.component_count).filter(|count| *count > 0),
+        etag: object.etag,
+        customer_encryption: None,
+        kms_key_name: non_empty(object.kms_key),
+        name: object.name,
+        bucket,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    #[test]
+    fn convert_object() {
+        let object = proto::Object {
+            name: "dir/file.txt".to_string(),
+            bucket: "projects/_/buckets/my-bucket".to_string(),
+            generation: 7,
+            size: 12,
+            content_type: "text/plain".to_string(),
+            update_time: Some(proto::Timestamp {
+                seconds: 1_600_000_000,
+                nanos: 0,
+            }),
+            checksums: Some(proto::ObjectChecksums {
+                crc32c: Some(0xE3069283),
+                md5_hash: Vec::new(),
+            }),
+            ..Default::default()
+        };
+        // survives encoding, as the gRPC codec would do
+        let object = proto::Object::decode(object.encode_to_vec().as_slice()).unwrap();
+        let object = convert(&Client::default(), object);
+        assert_eq!(object.bucket, "my-bucket");
+        assert_eq!(object.id, "my-bucket/dir/file.txt/7");
+        assert_eq!(object.size, 12);
+        assert_eq!(object.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(object.crc32c, "4waSgw==");
+        assert_eq!(object.md5_hash, None);
+        assert_eq!(object.updated.timestamp(), 1_600_000_000);
+        assert!(object.self_link.ends_with("/b/my-bucket/o/dir%2Ffile.txt"));
+    }
+
+    #[test]
+    fn status_codes() {
+        let error = status_error(tonic::Status::not_found("no such object"));
+        assert!(error.is_not_found());
+        let error = status_error(tonic::Status::failed_precondition("generation mismatch"));
+        assert!(error.is_precondition_failed());
+        let error = status_error(tonic::Status::unavailable("try again"));
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn create_download_list() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::with_config(crate::Config {
+            grpc: true,
+            ..Default::default()
+        });
+        let object = client
+            .object()
+            .create(&bucket.name, &[1, 2, 3], "test-grpc", "text/plain")?;
+        assert_eq!(object.size, 3);
+        let content = client.object().download(&bucket.name, "test-grpc")?;
+        assert_eq!(content.as_ref(), [1, 2, 3]);
+        assert_eq!(client.object().read(&bucket.name, "test-grpc")?.size, 3);
+        let listed = client
+            .object()
+            .list_with(&bucket.name, Some("test-grpc"), None)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(listed.iter().any(|o| o.name == "test-grpc"));
+        client.object().delete(&bucket.name, "test-grpc")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1059 @@
+use crate::circuit_breaker::{CircuitState, Outcome};
+use crate::context::RequestContext;
+use crate::error::Error;
+use std::time::Duration;
+
+/// Determines if and how failed requests are retried. Requests are retried with exponential
+/// backoff: the first retry happens after `initial_backoff`, and every next one waits
+/// `multiplier` times longer, up to `max_backoff`. A random jitter of up to half the delay is
+/// subtracted, so that many clients that fail at the same time do not retry in lockstep.
+///
+/// Requests with a streamed body, such as `Object::create_streamed`, can not be replayed and are
+/// never retried. Requests that are not
+/// [idempotent](https://cloud.google.com/storage/docs/retry-strategy#idempotency), such as
+/// uploads without an `ifGenerationMatch` precondition or changes to access control lists, are
+/// only retried when the connection could not be established, because repeating them could
+/// duplicate or overwrite the effect of a request that did reach Google.
+/// ### Example
+/// ```
+/// use cloud_storage::{Config, RetryConfig};
+/// use std::time::Duration;
+///
+/// cloud_storage::set_config(Config {
+///     retry: RetryConfig {
+///         max_attempts: 5,
+///         initial_backoff: Duration::from_millis(200),
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// The maximum number of times a request is sent, including the first attempt. A value of 1
+    /// disables retries.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+    /// The factor by which the delay grows after every attempt.
+    pub multiplier: f64,
+    /// The HTTP status codes that are considered transient.
+    pub retryable_status_codes: Vec<u16>,
+    /// Whether requests that failed to connect, or were interrupted, are retried.
+    pub retry_connection_errors: bool,
+    /// Whether requests that timed out are retried.
+    pub retry_timeouts: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(32),
+            multiplier: 2.0,
+            retryable_status_codes: vec![408, 429, 500, 502, 503, 504],
+            retry_connection_errors: true,
+            retry_timeouts: true,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// A configuration that never retries.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    fn should_retry(
+        &self,
+        result: &Result<reqwest::blocking::Response, Error>,
+        idempotent: bool,
+    ) -> bool {
+        match result {
+            // the request never left this machine, so repeating it is always safe
+            Err(Error::Reqwest(e)) if e.is_connect() => self.retry_connection_errors,
+            _ if !idempotent => false,
+            Ok(response) => self
+                .retryable_status_codes
+                .contains(&response.status().as_u16()),
+            Err(Error::Reqwest(e)) if e.is_timeout() => self.retry_timeouts,
+            Err(Error::Reqwest(e)) => self.retry_connection_errors && e.is_request(),
+            Err(_) => false,
+        }
+    }
+
+    /// The delay before retry number `retry`, starting at 0, without jitter.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.powi(retry as i32);
+        let delay = self.initial_backoff.as_secs_f64() * factor;
+        Duration::from_secs_f64(delay.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+/// Hooks into every HTTP request made by this crate. Interceptors are configured through
+/// `Config::interceptors`, and can be used to add custom headers, log requests for auditing, or
+/// tamper with requests to test how an application copes with failures. Both methods do nothing by
+/// default, so only the ones that are needed have to be implemented.
+/// ### Example
+/// ```no_run
+/// use cloud_storage::{Config, Interceptor, RequestParts, ResponseParts};
+/// use std::sync::Arc;
+///
+/// #[derive(Debug)]
+/// struct AuditLog;
+///
+/// impl Interceptor for AuditLog {
+///     fn on_request(&self, request: &mut RequestParts) {
+///         request.headers.insert("x-request-origin", "billing-service".parse().unwrap());
+///     }
+///
+///     fn on_response(&self, response: &ResponseParts) {
+///         println!("{} {} -> {}", response.method, response.url, response.status);
+///     }
+/// }
+///
+/// cloud_storage::set_config(Config {
+///     interceptors: vec![Arc::new(AuditLog)],
+///     ..Default::default()
+/// });
+/// ```
+pub trait Interceptor: std::fmt::Debug + Send + Sync {
+    /// Called right before a request is sent, including every time it is retried. Changes made to
+    /// `request` are applied to the request that is sent.
+    fn on_request(&self, _request: &mut RequestParts) {}
+
+    /// Called for every response that is received, including the ones that cause a retry.
+    fn on_response(&self, _response: &ResponseParts) {}
+}
+
+/// Sends the HTTP requests made by this crate. By default requests are sent by the reqwest client
+/// that is built from the `Config`, but through `Config::transport` they can be sent in another
+/// way: wrapped in middleware, sent by a different HTTP library, or answered by a stub in unit
+/// tests. Requests are passed to the transport after interceptors, rate limiting and
+/// authentication have been applied, and it is called again for every retry.
+/// ### Example
+/// ```no_run
+/// use cloud_storage::{Config, Error, Transport};
+/// use std::sync::Arc;
+///
+/// /// Answers every request with `404 Not Found`.
+/// #[derive(Debug)]
+/// struct NotFound;
+///
+/// impl Transport for NotFound {
+///     fn send(
+///         &self,
+///         _request: reqwest::blocking::Request,
+///     ) -> Result<reqwest::blocking::Response, Error> {
+///         let body = r#"{"error":{"errors":[],"code":404,"message":"Not Found"}}"#;
+///         Ok(http::Response::builder().status(404).body(body).unwrap().into())
+///     }
+/// }
+///
+/// cloud_storage::set_config(Config {
+///     transport: Some(Arc::new(NotFound)),
+///     ..Default::default()
+/// });
+/// ```
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    /// Sends `request` and returns the response, without following up on its status. Failures to
+    /// send the request should be returned as `Error::Reqwest` when possible, so that they can be
+    /// retried according to `Config::retry`.
+    fn send(
+        &self,
+        request: reqwest::blocking::Request,
+    ) -> Result<reqwest::blocking::Response, Error>;
+}
+
+impl Transport for reqwest::blocking::Client {
+    fn send(
+        &self,
+        request: reqwest::blocking::Request,
+    ) -> Result<reqwest::blocking::Response, Error> {
+        Ok(self.execute(request)?)
+    }
+}
+
+/// The parts of an outgoing request that an `Interceptor` can inspect and change.
+#[derive(Debug)]
+pub struct RequestParts {
+    /// The HTTP method of the request.
+    pub method: reqwest::Method,
+    /// The url the request is sent to, including the query string.
+    pub url: reqwest::Url,
+    /// The headers of the request, including the `Authorization` header.
+    pub headers: reqwest::header::HeaderMap,
+    /// The attempt that is about to be made, starting at 1.
+    pub attempt: u32,
+}
+
+/// A description of a response, as passed to `Interceptor::on_response`.
+#[derive(Debug)]
+pub struct ResponseParts {
+    /// The HTTP method of the request.
+    pub method: reqwest::Method,
+    /// The url the request was sent to.
+    pub url: reqwest::Url,
+    /// The status code of the response.
+    pub status: reqwest::StatusCode,
+    /// The headers of the response.
+    pub headers: reqwest::header::HeaderMap,
+    /// The attempt that produced this response, starting at 1.
+    pub attempt: u32,
+    /// The time it took to receive the response headers.
+    pub elapsed: Duration,
+}
+
+/// A response returned by `RequestExt::execute`, together with the context of the request that
+/// produced it, which is attached to the error built from it by `Error::from_response`.
+#[derive(Debug)]
+pub(crate) struct Response {
+    inner: reqwest::blocking::Response,
+    context: RequestContext,
+}
+
+impl Response {
+    pub(crate) fn into_inner(self) -> reqwest::blocking::Response {
+        self.inner
+    }
+
+    pub(crate) fn into_parts(self) -> (reqwest::blocking::Response, RequestContext) {
+        (self.inner, self.context)
+    }
+
+    /// Parses the body as JSON. Unsuccessful responses are turned into an error, which carries the
+    /// context of the request.
+    pub(crate) fn json<T: serde::de::DeserializeOwned>(self) -> Result<T, Error> {
+        let status = self.status();
+        if status.is_client_error() || status.is_server_error() {
+            return Err(Error::from_response(self));
+        }
+        Ok(self.inner.json()?)
+    }
+
+    pub(crate) fn text(self) -> Result<String, Error> {
+        Ok(self.inner.text()?)
+    }
+}
+
+impl std::ops::Deref for Response {
+    type Target = reqwest::blocking::Response;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl std::io::Read for Response {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+/// Receives measurements of every API call made by this crate, so that they can be exported to a
+/// monitoring system such as Prometheus or OpenTelemetry. Configure it through `Config::metrics`.
+///
+/// `record` is called on the thread that made the request, once the response headers have been
+/// received or the request has failed for good, so it should return quickly.
+/// ### Example
+/// ```no_run
+/// use cloud_storage::{Config, MetricsSink, RequestMetrics};
+/// use std::sync::Arc;
+///
+/// #[derive(Debug)]
+/// struct PrintMetrics;
+///
+/// impl MetricsSink for PrintMetrics {
+///     fn record(&self, metrics: &RequestMetrics) {
+///         println!("{} {:?} took {:?}", metrics.operation, metrics.status_class, metrics.latency);
+///     }
+/// }
+///
+/// cloud_storage::set_config(Config {
+///     metrics: Some(Arc::new(PrintMetrics)),
+///     ..Default::default()
+/// });
+/// ```
+pub trait MetricsSink: std::fmt::Debug + Send + Sync {
+    /// Records the measurements of a single API call.
+    fn record(&self, metrics: &RequestMetrics);
+
+    /// Called when the circuit of `Config::circuit_breaker` changes to `state`. Does nothing by
+    /// default.
+    fn circuit_state(&self, _state: CircuitState) {}
+}
+
+/// The measurements of a single API call, including all of its retries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestMetrics {
+    /// The kind of call that was made, in the form `GET /b/{bucket}/o/{object}`. Bucket and object
+    /// names are replaced by placeholders, so this can safely be used as a metric label.
+    pub operation: String,
+    /// The class of the final response status.
+    pub status_class: StatusClass,
+    /// The final status code, if a response was received.
+    pub status: Option<u16>,
+    /// The time from sending the first attempt until receiving the headers of the final response.
+    pub latency: std::time::Duration,
+    /// The number of attempts that were made, including the first one.
+    pub attempts: u32,
+    /// The size of the request body in bytes, if known.
+    pub request_bytes: Option<u64>,
+    /// The size of the response body in bytes, as announced by the `Content-Length` header.
+    pub response_bytes: Option<u64>,
+}
+
+/// The class of a response status, for coarse-grained error counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusClass {
+    /// A 1xx or 2xx response.
+    Success,
+    /// A 3xx response.
+    Redirection,
+    /// A 4xx response.
+    ClientError,
+    /// A 5xx response.
+    ServerError,
+    /// No response was received, for example because of a timeout or connection failure.
+    TransportError,
+}
+
+impl StatusClass {
+    fn of(result: &Result<reqwest::blocking::Response, Error>) -> Self {
+        match result {
+            Ok(response) if response.status().is_server_error() => Self::ServerError,
+            Ok(response) if response.status().is_client_error() => Self::ClientError,
+            Ok(response) if response.status().is_redirection() => Self::Redirection,
+            Ok(_) => Self::Success,
+            Err(_) => Self::TransportError,
+        }
+    }
+}
+
+fn request_bytes(request: &reqwest::blocking::Request) -> Option<u64> {
+    match request.body().and_then(|body| body.as_bytes()) {
+        Some(bytes) => Some(bytes.len() as u64),
+        None => request
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok(),
+    }
+}
+
+/// Sends requests the way all requests of this crate should be sent.
+pub(crate) trait RequestExt {
+    /// Sends the request with `client`, passing it through `Config::interceptors` and retrying it
+    /// according to `Config::retry`.
+    fn execute(self, client: &crate::Client) -> Result<Response, Error>;
+}
+
+impl RequestExt for reqwest::blocking::RequestBuilder {
+    fn execute(self, client: &crate::Client) -> Result<Response, Error> {
+        let config = client.config();
+        let policy = &config.retry;
+        let http = client.http()?;
+        let mut request = self.build()?;
+        let timeout = crate::config::timeout_override();
+        let api = Api::of(client, request.url());
+        let operation = Operation::from_request(api, request.method(), request.url());
+        let request_bytes = request_bytes(&request);
+        let idempotent = is_idempotent(&request, &operation);
+        #[cfg(feature = "tracing")]
+        let span = {
+            tracing::info_span!(
+                "cloud_storage",
+                operation = %operation.name,
+                bucket = operation.bucket.as_deref().unwrap_or_default(),
+                object = operation.object.as_deref().unwrap_or_default(),
+                status = tracing::field::Empty,
+                retries = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+            )
+        };
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+        let started = std::time::Instant::now();
+        if let Some(breaker) = client.breaker() {
+            let (admitted, state) = breaker.admit(started);
+            report_circuit(client, state);
+            if let Err(retry_after) = admitted {
+                return Err(Error::CircuitOpen { retry_after });
+            }
+        }
+        let mut attempt = 1;
+        loop {
+            let next = if attempt < policy.max_attempts {
+                request.try_clone()
+            } else {
+                None
+            };
+            intercept_request(&config.interceptors, &mut request, attempt);
+            // takes precedence over the timeout of the shared client, and is lost by `try_clone`
+            if timeout.is_some() {
+                *request.timeout_mut() = timeout;
+            }
+            client.throttle(request.method());
+            let method = request.method().clone();
+            let url = request.url().clone();
+            let start = std::time::Instant::now();
+            // a cancelled call ends like any other, so that the circuit breaker and metrics see it
+            let result = match (crate::cancel::check(), &config.transport) {
+                (Err(e), _) => Err(e),
+                (Ok(()), Some(transport)) => transport.send(request),
+                (Ok(()), None) => http.execute(request).map_err(Error::from),
+            };
+            if let Ok(response) = &result {
+                let parts = ResponseParts {
+                    method: method.clone(),
+                    url: url.clone(),
+                    status: response.status(),
+                    headers: response.headers().clone(),
+                    attempt,
+                    elapsed: start.elapsed(),
+                };
+                for interceptor in &config.interceptors {
+                    interceptor.on_response(&parts);
+                }
+            }
+            if let Some(breaker) = client.breaker() {
+                breaker.record_attempt(outcome(&result));
+            }
+            let budget = || match client.breaker() {
+                Some(breaker) => breaker.allow_retry(),
+                None => true,
+            };
+            match next {
+                Some(next) if policy.should_retry(&result, idempotent) && budget() => {
+                    #[cfg(feature = "tracing")]
+                    match &result {
+                        Ok(response) => tracing::debug!(
+                            status = response.status().as_u16(),
+                            attempt,
+                            "retrying request"
+                        ),
+                        Err(e) => tracing::debug!(error = %e, attempt, "retrying request"),
+                    }
+                    std::thread::sleep(jitter(policy.backoff(attempt - 1)));
+                    request = next;
+                    attempt += 1;
+                }
+                _ => {
+                    if let Some(breaker) = client.breaker() {
+                        let state =
+                            breaker.record_call(outcome(&result), std::time::Instant::now());
+                        report_circuit(client, state);
+                    }
+                    if let Some(metrics) = &config.metrics {
+                        let response = result.as_ref().ok();
+                        metrics.record(&RequestMetrics {
+                            operation: operation.name,
+                            status_class: StatusClass::of(&result),
+                            status: response.map(|r| r.status().as_u16()),
+                            latency: started.elapsed(),
+                            attempts: attempt,
+                            request_bytes,
+                            response_bytes: response.and_then(|r| r.content_length()),
+                        });
+                    }
+                    #[cfg(feature = "tracing")]
+                    {
+                        span.record("retries", attempt - 1);
+                        span.record("duration_ms", started.elapsed().as_millis() as u64);
+                        match &result {
+                            Ok(response) => {
+                                span.record("status", response.status().as_u16());
+                            }
+                            Err(e) => tracing::warn!(error = %e, "request failed"),
+                        }
+                    }
+                    return match result {
+                        Err(_) if crate::cancel::cancelled() => Err(Error::Cancelled),
+                        Err(e) => Err(e),
+                        Ok(inner) => Ok(Response {
+                            inner,
+                            context: RequestContext::new(method, &url, attempt),
+                        }),
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Whether `result` indicates that Google, or the connection to it, is failing, as opposed to a
+/// problem with the request itself.
+fn outcome(result: &Result<reqwest::blocking::Response, Error>) -> Outcome {
+    match result {
+        Ok(response)
+            if response.status().is_server_error() || response.status().as_u16() == 429 =>
+        {
+            Outcome::Failure
+        }
+        Ok(_) => Outcome::Success,
+        Err(Error::Cancelled) => Outcome::Cancelled,
+        Err(_) if crate::cancel::cancelled() => Outcome::Cancelled,
+        Err(_) => Outcome::Failure,
+    }
+}
+
+fn report_circuit(client: &crate::Client, state: Option<CircuitState>) {
+    if let (Some(state), Some(metrics)) = (state, &client.config().metrics) {
+        metrics.circuit_state(state);
+    }
+}
+
+/// Whether sending `request` twice has the same effect as sending it once, following
+/// https://cloud.google.com/storage/docs/retry-strategy#idempotency. Requests to other services,
+/// such as the ones that obtain access tokens, are always safe to repeat.
+fn is_idempotent(request: &reqwest::blocking::Request, operation: &Operation) -> bool {
+    use reqwest::Method;
+
+    let method = request.method();
+    if [Method::GET, Method::HEAD, Method::OPTIONS].contains(method) {
+        return true;
+    }
+    let path = operation
+        .name
+        .split_once(' ')
+        .map(|(_, path)| path)
+        .unwrap_or_default();
+    let query: Vec<_> = request.url().query_pairs().map(|(key, _)| key).collect();
+    let has = |key: &str| query.iter().any(|k| k == key);
+    match operation.api {
+        Api::Json => {}
+        Api::Xml => {
+            let headers = request.headers();
+            if headers.contains_key("x-goog-if-generation-match")
+                || headers.contains_key("x-goog-if-metageneration-match")
+            {
+                return true;
+            }
+            // deleting a bucket fails with not found the second time
+            return method == Method::DELETE && (operation.object.is_none() || has("generation"));
+        }
+        Api::Other => return true,
+    }
+    if path.contains("/hmacKeys") {
+        return method == Method::DELETE || (method == Method::PUT && has_etag(request));
+    }
+    if path != "/b" && !path.starts_with("/b/") {
+        return true;
+    }
+    // changing an access control list depends on its current state
+    if path.contains("/acl") || path.contains("/defaultObjectAcl") {
+        return false;
+    }
+    if path.contains("/notificationConfigs") {
+        return method == Method::DELETE;
+    }
+    if has("ifGenerationMatch") || has("ifMetagenerationMatch") || has("upload_id") {
+        return true;
+    }
+    match *method {
+        // creating a bucket fails with a conflict the second time
+        Method::POST if path == "/b" => true,
+        // starting a resumable upload does not write anything yet
+        Method::POST => request
+            .url()
+            .query_pairs()
+            .any(|(key, value)| key == "uploadType" && value == "resumable"),
+        Method::DELETE => path == "/b/{bucket}" || has("generation"),
+        Method::PUT if path.ends_with("/iam") => has_etag(request),
+        _ => false,
+    }
+}
+
+/// Whether the json body of `request` contains an etag, which makes updates conditional.
+fn has_etag(request: &reqwest::blocking::Request) -> bool {
+    request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(bytes).ok())
+        .and_then(|body| body.get("etag")?.as_str().map(|etag| !etag.is_empty()))
+        .unwrap_or(false)
+}
+
+/// The API, or other service, that a request is sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Api {
+    /// The JSON API, including media uploads, with urls such as `/storage/v1/b/{bucket}`.
+    Json,
+    /// The XML API, with urls of the form `/{bucket}/{object}`.
+    Xml,
+    /// Other services, such as the ones that issue access tokens.
+    Other,
+}
+
+impl Api {
+    /// Tells the APIs apart by the base urls that `client` sends their requests to.
+    pub fn of(client: &crate::Client, url: &reqwest::Url) -> Self {
+        let url = url.as_str();
+        let under = |base: String| {
+            url.strip_prefix(base.trim_end_matches('/'))
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+        };
+        if under(client.api_url()) || under(client.upload_url()) {
+            Self::Json
+        } else if under(client.download_url()) {
+            Self::Xml
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// A low-cardinality description of an API call, derived from its method and url, such as
+/// `GET /b/{bucket}/o/{object}`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Operation {
+    pub api: Api,
+    pub name: String,
+    pub bucket: Option<String>,
+    pub object: Option<String>,
+}
+
+impl Operation {
+    pub fn from_request(api: Api, method: &reqwest::Method, url: &reqwest::Url) -> Self {
+        let decode = |s: &str| {
+            percent_encoding::percent_decode_str(s)
+                .decode_utf8_lossy()
+                .into_owned()
+        };
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(Iterator::collect)
+            .unwrap_or_default();
+        if api == Api::Xml {
+            let bucket = segments
+                .first()
+                .filter(|s| !s.is_empty())
+                .map(|s| decode(s));
+            let object = Some(decode(&segments.get(1..).unwrap_or_default().join("/")))
+                .filter(|object| !object.is_empty());
+            let name = match (&bucket, &object) {
+                (Some(_), Some(_)) => "/{bucket}/{object}",
+                (Some(_), None) => "/{bucket}",
+                _ => "/",
+            };
+            return Self {
+                api,
+                name: format!("{} {}", method, name),
+                bucket,
+                object,
+            };
+        }
+        let mut bucket = None;
+        let mut object = None;
+        let mut template = Vec::new();
+        let mut i = 0;
+        while i < segments.len() {
+            let segment = segments[i];
+            template.push(segment.to_string());
+            if let (Some(placeholder), Some(name)) = (placeholder(segment), segments.get(i + 1)) {
+                // the first bucket and object are the source of a copy, rewrite or compose
+                match segment {
+                    "b" if bucket.is_none() => bucket = Some(decode(name)),
+                    "o" if object.is_none() => object = Some(decode(name)),
+                    _ => {}
+                }
+                template.push(placeholder.to_string());
+                i += 1;
+            }
+            i += 1;
+        }
+        // only keep the part that identifies the resource, not the api prefix
+        let start = template
+            .iter()
+            .position(|s| s == "b" || s == "projects")
+            .unwrap_or(0);
+        Self {
+            api,
+            name: format!("{} /{}", method, template[start..].join("/")),
+            bucket,
+            object,
+        }
+    }
+}
+
+/// The placeholder for the name that follows `segment` in a path, so that the names of buckets,
+/// objects and other resources never end up in an operation name.
+fn placeholder(segment: &str) -> Option<&'static str> {
+    match segment {
+        "b" => Some("{bucket}"),
+        "o" => Some("{object}"),
+        "projects" => Some("{project}"),
+        "hmacKeys" => Some("{accessId}"),
+        "acl" | "defaultObjectAcl" => Some("{entity}"),
+        "notificationConfigs" => Some("{notification}"),
+        _ => None,
+    }
+}
+
+fn intercept_request(
+    interceptors: &[std::sync::Arc<dyn Interceptor>],
+    request: &mut reqwest::blocking::Request,
+    attempt: u32,
+) {
+    if interceptors.is_empty() {
+        return;
+    }
+    let mut parts = RequestParts {
+        method: request.method().clone(),
+        url: request.url().clone(),
+        headers: std::mem::take(request.headers_mut()),
+        attempt,
+    };
+    for interceptor in interceptors {
+        interceptor.on_request(&mut parts);
+    }
+    *request.method_mut() = parts.method;
+    *request.url_mut() = parts.url;
+    *request.headers_mut() = parts.headers;
+}
+
+/// Subtracts a pseudo-random amount of up to half of `delay`.
+pub(crate) fn jitter(delay: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let fraction = f64::from(nanos % 1000) / 2000.0;
+    delay.mul_f64(1.0 - fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct AddHeader;
+
+    impl Interceptor for AddHeader {
+        fn on_request(&self, request: &mut RequestParts) {
+            request.headers.insert("x-test", "yes".parse().unwrap());
+            request.url.set_path("/intercepted");
+        }
+    }
+
+    #[test]
+    fn operation_from_request() {
+        let url = "https://www.googleapis.com/storage/v1/b/my-bucket/o/dir%2Ffile.txt?alt=media";
+        let operation =
+            Operation::from_request(Api::Json, &reqwest::Method::GET, &url.parse().unwrap());
+        assert_eq!(operation.name, "GET /b/{bucket}/o/{object}");
+        assert_eq!(operation.bucket.as_deref(), Some("my-bucket"));
+        assert_eq!(operation.object.as_deref(), Some("dir/file.txt"));
+        let url = "https://www.googleapis.com/upload/storage/v1/b/my-bucket/o?name=x";
+        let operation =
+            Operation::from_request(Api::Json, &reqwest::Method::POST, &url.parse().unwrap());
+        assert_eq!(operation.name, "POST /b/{bucket}/o");
+        assert_eq!(operation.object, None);
+
+        let base = "https://www.googleapis.com/storage/v1/b/src/o/a.txt";
+        for (suffix, name) in [
+            (
+                "/copyTo/b/dst/o/b.txt",
+                "POST /b/{bucket}/o/{object}/copyTo/b/{bucket}/o/{object}",
+            ),
+            (
+                "/rewriteTo/b/dst/o/b.txt?rewriteToken=x",
+                "POST /b/{bucket}/o/{object}/rewriteTo/b/{bucket}/o/{object}",
+            ),
+            ("/compose", "POST /b/{bucket}/o/{object}/compose"),
+            (
+                "/acl/user-a@example.com",
+                "POST /b/{bucket}/o/{object}/acl/{entity}",
+            ),
+        ]
+        .iter()
+        {
+            let url = format!("{}{}", base, suffix).parse().unwrap();
+            let operation = Operation::from_request(Api::Json, &reqwest::Method::POST, &url);
+            assert_eq!(&operation.name, name);
+            assert_eq!(operation.bucket.as_deref(), Some("src"));
+            assert_eq!(operation.object.as_deref(), Some("a.txt"));
+        }
+        let url = "https://www.googleapis.com/storage/v1/projects/p/hmacKeys/GOOG1ABC";
+        let operation =
+            Operation::from_request(Api::Json, &reqwest::Method::GET, &url.parse().unwrap());
+        assert_eq!(
+            operation.name,
+            "GET /projects/{project}/hmacKeys/{accessId}"
+        );
+    }
+
+    #[test]
+    fn is_idempotent() {
+        let client = reqwest::blocking::Client::new();
+        let base = "https://www.googleapis.com/storage/v1";
+        let global = crate::Client::default();
+        let check = |request: reqwest::blocking::RequestBuilder| {
+            let request = request.build().unwrap();
+            let api = Api::of(&global, request.url());
+            let operation = Operation::from_request(api, request.method(), request.url());
+            super::is_idempotent(&request, &operation)
+        };
+        let object = format!("{}/b/bucket/o/file", base);
+        assert!(check(client.get(&object)));
+        assert!(!check(client.put(&object)));
+        assert!(check(
+            client.put(&object).query(&[("ifMetagenerationMatch", "2")])
+        ));
+        assert!(!check(client.delete(&object)));
+        assert!(check(client.delete(&object).query(&[("generation", "1")])));
+        assert!(!check(client.delete(&format!("{}/acl/allUsers", object))));
+        assert!(check(client.delete(&format!("{}/b/bucket", base))));
+        assert!(check(client.post(&format!("{}/b", base))));
+        let upload = "https://www.googleapis.com/upload/storage/v1/b/bucket/o";
+        assert!(!check(
+            client.post(upload).query(&[("uploadType", "media")])
+        ));
+        assert!(check(
+            client.post(upload).query(&[("uploadType", "resumable")])
+        ));
+        let iam = format!("{}/b/bucket/iam", base);
+        assert!(!check(client.put(&iam).body(r#"{"bindings":[]}"#)));
+        assert!(check(
+            client.put(&iam).body(r#"{"bindings":[],"etag":"CAE="}"#)
+        ));
+        assert!(!check(
+            client.post(&format!("{}/projects/p/hmacKeys", base))
+        ));
+        assert!(check(client.post("https://oauth2.googleapis.com/token")));
+
+        // the XML API, where the path starts with the name of the bucket
+        let xml = "https://storage.googleapis.com/archive/dir/file";
+        assert!(!check(client.put(xml)));
+        assert!(check(
+            client.put(xml).header("x-goog-if-generation-match", "0")
+        ));
+        assert!(!check(client.delete(xml)));
+        assert!(check(client.delete(xml).query(&[("generation", "1")])));
+        assert!(check(
+            client.delete("https://storage.googleapis.com/archive")
+        ));
+        assert!(!check(client.post("https://storage.googleapis.com/b/o")));
+    }
+
+    #[test]
+    fn intercept_request() {
+        let client = reqwest::blocking::Client::new();
+        let mut request = client
+            .get("https://example.com/original")
+            .header("x-existing", "1")
+            .build()
+            .unwrap();
+        let interceptors: Vec<std::sync::Arc<dyn Interceptor>> =
+            vec![std::sync::Arc::new(AddHeader)];
+        super::intercept_request(&interceptors, &mut request, 1);
+        assert_eq!(request.url().path(), "/intercepted");
+        assert_eq!(request.headers()["x-test"], "yes");
+        assert_eq!(request.headers()["x-existing"], "1");
+    }
+
+    #[test]
+    fn request_bytes() {
+        let client = reqwest::blocking::Client::new();
+        let request = client
+            .post("https://example.com")
+            .body(vec![0; 5])
+            .build()
+            .unwrap();
+        assert_eq!(super::request_bytes(&request), Some(5));
+        let request = client.get("https://example.com").build().unwrap();
+        assert_eq!(super::request_bytes(&request), None);
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = RetryConfig {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(500));
+        let jittered = jitter(Duration::from_millis(100));
+        assert!(jittered > Duration::from_millis(49) && jittered <= Duration::from_millis(100));
+    }
+
+    /// Answers requests with the given statuses in order, recording the urls and timeouts of the
+    /// requests it was sent.
+    #[derive(Debug, Default)]
+    struct Stub {
+        statuses: std::sync::Mutex<Vec<u16>>,
+        urls: std::sync::Mutex<Vec<String>>,
+        timeouts: std::sync::Mutex<Vec<Option<Duration>>>,
+    }
+
+    impl Transport for Stub {
+        fn send(
+            &self,
+            request: reqwest::blocking::Request,
+        ) -> Result<reqwest::blocking::Response, Error> {
+            self.urls.lock().unwrap().push(request.url().to_string());
+            self.timeouts
+                .lock()
+                .unwrap()
+                .push(request.timeout().copied());
+            let status = self.statuses.lock().unwrap().remove(0);
+            let body = format!(
+                r#"{{"error":{{"errors":[],"code":{0},"message":"status {0}"}}}}"#,
+                status
+            );
+            Ok(http::Response::builder()
+                .status(status)
+                .body(body)
+                .unwrap()
+                .into())
+        }
+    }
+
+    #[test]
+    fn transport() {
+        let stub = std::sync::Arc::new(Stub {
+            statuses: std::sync::Mutex::new(vec![503, 404]),
+            ..Default::default()
+        });
+        let client = crate::Client::with_config(crate::Config {
+            anonymous: true,
+            transport: Some(stub.clone()),
+            retry: RetryConfig {
+                initial_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let error = client.object().read("bucket", "object").unwrap_err();
+        assert!(error.is_not_found());
+        let context = error.context().unwrap();
+        assert_eq!(context.method, reqwest::Method::GET);
+        assert!(context.url.ends_with("/b/bucket/o/object"));
+        assert_eq!(context.attempt, 2);
+        let urls = stub.urls.lock().unwrap();
+        assert_eq!(urls.len(), 2);
+        assert!(urls[1].ends_with("/b/bucket/o/object"));
+        // errors that were not built from a response do not pick up the request sent before them
+        let body = r#"{"error":{"errors":[],"code":404,"message":"not found"}}"#;
+        let other: crate::error::GoogleErrorResponse = serde_json::from_str(body).unwrap();
+        assert!(Error::from(other).context().is_none());
+    }
+
+    #[test]
+    fn timeout_override() {
+        let stub = std::sync::Arc::new(Stub {
+            statuses: std::sync::Mutex::new(vec![503, 404, 404]),
+            ..Default::default()
+        });
+        let client = crate::Client::with_config(crate::Config {
+            anonymous: true,
+            transport: Some(stub.clone()),
+            retry: RetryConfig {
+                initial_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let timeout = Duration::from_secs(2);
+        // every attempt gets the timeout, including retries
+        crate::with_timeout(timeout, || {
+            client.object().read("bucket", "object").unwrap_err();
+        });
+        client.object().read("bucket", "object").unwrap_err();
+        let timeouts = stub.timeouts.lock().unwrap();
+        assert_eq!(*timeouts, [Some(timeout), Some(timeout), None]);
+    }
+
+    #[derive(Debug, Default)]
+    struct CircuitStates(std::sync::Mutex<Vec<CircuitState>>, std::sync::Mutex<u32>);
+
+    impl MetricsSink for CircuitStates {
+        fn record(&self, _metrics: &RequestMetrics) {
+            *self.1.lock().unwrap() += 1;
+        }
+
+        fn circuit_state(&self, state: CircuitState) {
+            self.0.lock().unwrap().push(state);
+        }
+    }
+
+    #[test]
+    fn circuit_breaker() {
+        let stub = std::sync::Arc::new(Stub {
+            statuses: std::sync::Mutex::new(vec![503, 503, 503]),
+            ..Default::default()
+        });
+        let states = std::sync::Arc::new(CircuitStates::default());
+        let client = crate::Client::with_config(crate::Config {
+            anonymous: true,
+            transport: Some(stub.clone()),
+            retry: RetryConfig {
+                max_attempts: 5,
+                initial_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+            circuit_breaker: Some(crate::CircuitBreaker {
+                failure_threshold: 1,
+                retry_tokens: 4,
+                ..Default::default()
+            }),
+            metrics: Some(states.clone()),
+            ..Default::default()
+        });
+        // the retry budget stops retrying before `max_attempts` is reached
+        let error = client.object().read("bucket", "object").unwrap_err();
+        assert!(matches!(error, Error::Google(_)));
+        assert_eq!(stub.urls.lock().unwrap().len(), 2);
+        assert_eq!(client.circuit_state(), Some(CircuitState::Open));
+        assert_eq!(*states.0.lock().unwrap(), vec![CircuitState::Open]);
+        // once open, requests are not sent at all
+        let error = client.object().read("bucket", "object").unwrap_err();
+        assert!(matches!(error, Error::CircuitOpen { .. }));
+        assert_eq!(stub.urls.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn cancelled_probe() {
+        let stub = std::sync::Arc::new(Stub {
+            statuses: std::sync::Mutex::new(vec![503, 404]),
+            ..Default::default()
+        });
+        let states = std::sync::Arc::new(CircuitStates::default());
+        let client = crate::Client::with_config(crate::Config {
+            anonymous: true,
+            transport: Some(stub.clone()),
+            retry: RetryConfig::disabled(),
+            circuit_breaker: Some(crate::CircuitBreaker {
+                failure_threshold: 1,
+                open_duration: Duration::from_millis(50),
+                ..Default::default()
+            }),
+            metrics: Some(states.clone()),
+            ..Default::default()
+        });
+        assert!(client.object().read("bucket", "object").is_err());
+        assert_eq!(client.circuit_state(), Some(CircuitState::Open));
+        std::thread::sleep(Duration::from_millis(60));
+
+        let token = crate::CancellationToken::new();
+        token.cancel();
+        let result = crate::with_cancellation(&token, || client.object().read("bucket", "object"));
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(*states.1.lock().unwrap(), 2);
+        // the cancelled probe does not hold up the next one
+        let error = client.object().read("bucket", "object").unwrap_err();
+        assert!(error.is_not_found());
+        assert_eq!(stub.urls.lock().unwrap().len(), 2);
+        assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+    }
+}
@@ -14,6 +14,86 @@
 //! then be granted `Roles` in the cloud storage console. The roles required for this project to
 //! function are `Service Account Token Creator` and `Storage Object Admin`.
 //!
+//! Access tokens are obtained through
+//! [Application Default Credentials](https://cloud.google.com/docs/authentication/application-default-credentials),
+//! just like other Google client libraries do. Besides the `SERVICE_ACCOUNT` file, this also picks
+//! up the `GOOGLE_APPLICATION_CREDENTIALS` environment parameter, the credentials stored by
+//! `gcloud auth application-default login`, and the metadata server when running on Google Cloud.
+//! See `application_default` for details. Creating buckets and signing urls still require a
+//! service account file.
+//!
+//! Public buckets can be read without any credentials by enabling `Config::anonymous`.
+//!
+//! The functions on the resource types, such as `Object::read`, use a client that finds its
+//! credentials in the environment as described above. To use several service accounts side by
+//! side, or credentials that are not stored in a file, create a `Client` instead:
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use cloud_storage::{Client, ServiceAccount};
+//!
+//! let client = Client::new(ServiceAccount::from_json(&std::env::var("CREDENTIALS")?)?);
+//! let object = client.object().read("my_bucket", "path/to/my/file.png")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! All functions block the current thread until Google has responded, like those of reqwest's
+//! `blocking` module, so command line tools and build scripts can use this crate without setting up
+//! an async runtime. Async applications should call them from a thread on which blocking is
+//! allowed, for example with `tokio::task::spawn_blocking`, or use `GcsStore` or `GcsFs`, which do
+//! this for them.
+//!
+//! # Features
+//! - `native-tls` (enabled by default): connects to Google using the platform's TLS implementation,
+//!   which is OpenSSL on Linux.
+//! - `rustls-tls`: connects to Google using [rustls](https://docs.rs/rustls) instead, so no system
+//!   TLS library is needed at runtime. Combine it with `default-features = false`:
+//!   ```toml
+//!   cloud-storage = { version = "0.3", default-features = false, features = ["rustls-tls"] }
+//!   ```
+//! - `tracing`: emits a [tracing](https://docs.rs/tracing) span for every request made to Google,
+//!   recording the operation, bucket, object, response status, number of retries and duration.
+//! - `xml-api`: adds `Client::xml`, which uploads, downloads and lists objects through the
+//!   S3-compatible [XML API](https://cloud.google.com/storage/docs/xml-api/overview), optionally
+//!   authenticated with an HMAC key.
+//! - `object-store`: adds `GcsStore`, which implements the
+//!   [`ObjectStore`](https://docs.rs/object_store) trait, so that a bucket can be used as storage by
+//!   DataFusion, Arrow and Parquet. It works under any async runtime.
+//! - `fs`: adds `GcsFs`, which exposes the objects under a prefix of a bucket as a virtual
+//!   filesystem with `read_dir`, `metadata`, `open`, `create`, `remove_file`, `rename` and `copy`.
+//! - `runtime-tokio`, `runtime-async-std`: make `GcsStore`, `GcsFs` and `web` run their blocking
+//!   requests on the thread pool of tokio or async-std, instead of the runtime-independent pool
+//!   that smol also uses.
+//! - `encryption`: adds `Client::encrypted`, which encrypts the content of objects before they are
+//!   uploaded and decrypts it after downloading, with a data key per object that is protected by
+//!   a key of your own or a Cloud KMS key.
+//! - `gzip`: adds `Object::create_gzipped`, which compresses the content of an object with gzip
+//!   before uploading it. Google decompresses such objects again when they are downloaded.
+//! - `stream`: adds `Object::create_from_stream`, which uploads the chunks of a
+//!   [`Stream`](https://docs.rs/futures/0.3/futures/stream/trait.Stream.html) as they arrive, for
+//!   example to forward the body of an incoming request without buffering it.
+//! - `web`: adds the `web` module, which uploads the body stream of an incoming request of a web
+//!   framework such as axum or actix-web, and turns a download into a response body with matching
+//!   headers, so that proxy services pass content through without buffering it.
+//! - `testing`: adds `testing::FakeClient`, an in-memory implementation of `StorageClient` for
+//!   unit tests of code that uses this crate, and transports that record requests to Google in
+//!   fixture files and replay them without network access.
+//! - `grpc`: adds `Config::grpc`, which makes a client read, upload, download and list objects
+//!   through the [gRPC API](https://cloud.google.com/storage/docs/grpc) of Cloud Storage.
+//!
+//! On `wasm32-unknown-unknown`, such as in browsers and edge runtimes like Cloudflare Workers,
+//! threads cannot block, so the blocking `Client` and everything built on it is left out. What
+//! remains are the resource types, `UrlSigner`, which signs urls with a service account or HMAC key
+//! without sending requests, and `wasm::WasmClient`, an asynchronous client for reading, writing,
+//! listing and deleting objects with an access token provided by the application. The features
+//! `object-store`, `fs`, `web`, `grpc` and the runtimes need the blocking client and are not
+//! available there.
+//!
+//! To run against an emulator such as [fake-gcs-server](https://github.com/fsouza/fake-gcs-server),
+//! set the `STORAGE_EMULATOR_HOST` environment parameter to its address, for example
+//! `localhost:4443`. Requests are then sent to the emulator without authentication. Other endpoints
+//! can be configured through `Config`.
+//!
 //! # Quickstart
 //! Add the following line to your `Cargo.toml`
 //! ```toml
@@ -63,49 +143,117 @@
 //! Object::delete("mybucket", "myfile");
 //! ```
 #![forbid(unsafe_code, missing_docs)]
+// the helpers of the blocking client in `resources` are unused on wasm32, where it is left out
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
 
+#[cfg(not(target_arch = "wasm32"))]
+mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+mod cancel;
+#[cfg(not(target_arch = "wasm32"))]
+mod circuit_breaker;
+/// A client with its own credentials and settings, and the operations it offers.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod client;
 /// Contains objects as represented by Google, to be used for serialization and deserialization.
+#[cfg(not(target_arch = "wasm32"))]
+mod config;
+mod context;
+pub mod crc32c;
+#[cfg(not(target_arch = "wasm32"))]
+mod credentials;
+mod crypto;
+#[cfg(not(target_arch = "wasm32"))]
+mod degraded;
 mod error;
+#[cfg(feature = "fs")]
+pub mod fs;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(not(target_arch = "wasm32"))]
+mod http;
+pub mod md5;
+pub mod mime;
+#[cfg(not(target_arch = "wasm32"))]
+mod rate_limit;
 mod resources;
+#[cfg(any(feature = "object-store", feature = "fs", feature = "web"))]
+mod runtime;
+mod signer;
+#[cfg(feature = "object-store")]
+mod store;
+#[cfg(not(target_arch = "wasm32"))]
+mod sync;
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub mod testing;
+#[cfg(not(target_arch = "wasm32"))]
 mod token;
+mod uri;
+#[cfg(not(target_arch = "wasm32"))]
+mod url_cache;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+#[cfg(feature = "web")]
+pub mod web;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::cache::CacheConfig;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::cancel::{with_cancellation, CancellationToken};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+#[cfg(all(feature = "encryption", not(target_arch = "wasm32")))]
+pub use crate::client::EnvelopeKey;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::client::{Client, StorageClient};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::config::{set_config, with_timeout, with_user_project, Config, ProxyConfig};
+pub use crate::context::RequestContext;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::credentials::{
+    application_default, AuthorizedUserCredentials, ExternalAccountTokenProvider,
+    ImpersonatedCredentials, MetadataServerTokenProvider,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::degraded::is_degraded;
 pub use crate::error::*;
-use crate::resources::service_account::ServiceAccount;
+#[cfg(feature = "fs")]
+pub use crate::fs::GcsFs;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::http::{
+    Interceptor, MetricsSink, RequestMetrics, RequestParts, ResponseParts, RetryConfig,
+    StatusClass, Transport,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::rate_limit::RateLimit;
+pub use crate::resources::service_account::ServiceAccount;
 pub use crate::resources::{
     bucket::{Bucket, NewBucket},
     object::Object,
     *,
 };
-use crate::token::Token;
-use std::sync::Mutex;
-
-lazy_static::lazy_static! {
-    /// Static `Token` struct that caches
-    static ref TOKEN_CACHE: Mutex<Token> = Mutex::new(Token::new(
-        "https://www.googleapis.com/auth/devstorage.full_control",
-    ));
-
-    static ref IAM_TOKEN_CACHE: Mutex<Token> = Mutex::new(Token::new(
-        "https://www.googleapis.com/auth/iam"
-    ));
-
-    /// The struct is the parsed service account json file. It is publicly exported to enable easier
-    /// debugging of which service account is currently used. It is of the type
-    /// [ServiceAccount](service_account/struct.ServiceAccount.html).
-    pub static ref SERVICE_ACCOUNT: ServiceAccount = ServiceAccount::get();
-}
+pub use crate::signer::UrlSigner;
+#[cfg(feature = "object-store")]
+pub use crate::store::GcsStore;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::sync::{
+    CopyOptions, DownloadOptions, FileDownload, FileUpload, ObjectCopy, SyncOptions, SyncSummary,
+    UploadOptions,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::token::{AccessScope, ServiceAccountTokenProvider, Token, TokenInfo, TokenProvider};
+pub use crate::uri::GsUri;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::url_cache::SignedUrlCache;
 
 const BASE_URL: &'static str = "https://www.googleapis.com/storage/v1";
 
-fn get_headers() -> Result<reqwest::header::HeaderMap, Error> {
-    let mut result = reqwest::header::HeaderMap::new();
-    let mut guard = TOKEN_CACHE.lock().unwrap();
-    let token = guard.get()?;
-    result.insert(
-        reqwest::header::AUTHORIZATION,
-        format!("Bearer {}", token).parse().unwrap(),
-    );
-    Ok(result)
+/// Returns the moment the access token that is currently in use expires, or `None` if no token
+/// has been obtained yet. Tokens are refreshed automatically a few minutes before they expire, so
+/// this is mostly useful to schedule long-running transfers that cannot switch tokens halfway.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn token_expiry() -> Option<chrono::DateTime<chrono::Utc>> {
+    Client::global().token_expiry()
 }
 
 fn from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
@@ -126,7 +274,6 @@ where
     D: serde::Deserializer<'de>,
 {
     let s: Result<serde_json::Value, _> = serde::Deserialize::deserialize(deserializer);
-    println!("{:?}", s);
     match s {
         Ok(serde_json::Value::String(s)) => T::from_str(&s)
             .map_err(serde::de::Error::custom)
@@ -153,6 +300,30 @@ fn read_test_bucket() -> Bucket {
     }
 }
 
+/// The project and bucket that the fixtures in `tests/fixtures` were recorded against. To record
+/// them again, with `CLOUD_STORAGE_RECORD=1`, point these at a project and bucket of your own.
+#[cfg(all(test, feature = "testing"))]
+const FIXTURE_PROJECT: &str = "cloud-storage-rs";
+#[cfg(all(test, feature = "testing"))]
+const FIXTURE_BUCKET: &str = "cloud-storage-rs-fixtures";
+
+/// Returns a client whose requests are answered from the fixture `tests/fixtures/{name}.json`, so
+/// that the test runs without a live bucket, see `testing::record_or_replay`.
+#[cfg(all(test, feature = "testing"))]
+fn replay_client(name: &str) -> Client {
+    let path = format!(
+        "{}/tests/fixtures/{}.json",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    Client::with_config(Config {
+        transport: Some(testing::record_or_replay(path).unwrap()),
+        anonymous: !testing::is_recording(),
+        project_id: Some(FIXTURE_PROJECT.to_string()),
+        ..Default::default()
+    })
+}
+
 // since all tests run in parallel, we need to make sure we do not create multiple buckets with
 // the same name in each test.
 #[cfg(test)]
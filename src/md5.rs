@@ -0,0 +1,89 @@
+//! MD5 hashes in the format that Google Cloud Storage uses. Google reports the hash of an object in
+//! `Object::md5_hash` as the base64 encoding of its bytes. Composite objects do not have an MD5
+//! hash, use `crc32c` to compare those.
+
+use crate::error::Error;
+use std::convert::TryInto;
+
+/// Returns the MD5 hash of `data`.
+pub fn compute(data: &[u8]) -> [u8; 16] {
+    ::md5::compute(data).0
+}
+
+/// Encodes a hash as it appears in `Object::md5_hash` and the `x-goog-hash` header.
+pub fn encode(hash: [u8; 16]) -> String {
+    base64::encode(&hash)
+}
+
+/// Decodes a hash as it appears in `Object::md5_hash` and the `x-goog-hash` header.
+pub fn decode(hash: &str) -> Result<[u8; 16], Error> {
+    let bytes = base64::decode(hash).map_err(|e| Error::Other(format!("invalid md5: {}", e)))?;
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| Error::Other(format!("invalid md5: {} is not 16 bytes long", hash)))
+}
+
+/// Computes the hash of data that is written to it in parts, for example with `std::io::copy`.
+#[derive(Clone)]
+pub struct Md5 {
+    context: ::md5::Context,
+}
+
+impl std::fmt::Debug for Md5 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Md5").finish()
+    }
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Md5 {
+    /// Starts hashing.
+    pub fn new() -> Self {
+        Self {
+            context: ::md5::Context::new(),
+        }
+    }
+
+    /// Adds `data` to the hashed data.
+    pub fn update(&mut self, data: &[u8]) {
+        self.context.consume(data);
+    }
+
+    /// The hash of all data that was added.
+    pub fn finish(self) -> [u8; 16] {
+        self.context.compute().0
+    }
+}
+
+impl std::io::Write for Md5 {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_google() {
+        assert_eq!(encode(compute(b"")), "1B2M2Y8AsgTpgAmY7PhCfg==");
+        assert_eq!(decode("1B2M2Y8AsgTpgAmY7PhCfg==").unwrap(), compute(b""));
+        assert!(decode("4waSgw==").is_err());
+
+        let mut md5 = Md5::new();
+        std::io::copy(&mut &b"123456789"[..], &mut md5).unwrap();
+        assert_eq!(md5.finish(), compute(b"123456789"));
+    }
+}
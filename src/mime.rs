@@ -0,0 +1,210 @@
+//! Guessing the content type of files, for uploads that do not specify one. Objects that are
+//! uploaded as `application/octet-stream` are downloaded by browsers instead of being displayed, so
+//! web assets need their actual type.
+//!
+//! Passing `AUTO` as the mime type to `Object::create` or `Object::create_streamed` makes them
+//! guess the type with `guess`:
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use cloud_storage::{mime, Object};
+//!
+//! let content = std::fs::read("site/index.html")?;
+//! Object::create("my_bucket", &content, "index.html", mime::AUTO)?;
+//! # Ok(())
+//! # }
+//! ```
+
+/// The mime type that makes uploads guess the content type of a file from its name and content.
+pub const AUTO: &str = "auto";
+
+/// The content type of files whose type is not known.
+pub const DEFAULT: &str = "application/octet-stream";
+
+/// The number of bytes at the start of a file that `from_content` looks at.
+pub(crate) const SNIFF_LEN: usize = 512;
+
+/// Content types by lowercase file extension.
+const TYPES: &[(&str, &str)] = &[
+    ("avif", "image/avif"),
+    ("bmp", "image/bmp"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("md", "text/markdown"),
+    ("mjs", "text/javascript"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("parquet", "application/vnd.apache.parquet"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("tsv", "text/tab-separated-values"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("xml", "application/xml"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("zip", "application/zip"),
+];
+
+/// Content types by the bytes that files of that type start with. Text formats are recognized
+/// separately, since they may start with whitespace.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xFF\xD8\xFF", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"\x00\x00\x01\x00", "image/vnd.microsoft.icon"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1F\x8B", "application/gzip"),
+    (b"\x00asm", "application/wasm"),
+    (b"PAR1", "application/vnd.apache.parquet"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+    (b"ID3", "audio/mpeg"),
+    (b"\x1A\x45\xDF\xA3", "video/webm"),
+];
+
+/// Returns the content type of a file called `name`, based on its extension, or `DEFAULT` if the
+/// extension is not known.
+pub fn from_name(name: &str) -> &'static str {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    let extension = match file_name.rsplit_once('.') {
+        Some((_, extension)) => extension.to_ascii_lowercase(),
+        None => return DEFAULT,
+    };
+    TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map_or(DEFAULT, |(_, mime_type)| mime_type)
+}
+
+/// Returns the content type of a file that starts with `content`, based on the signatures of
+/// common binary formats, or `None` if it is not recognized. Files that are valid UTF-8 are
+/// recognized as HTML, SVG or XML by their first tag, and as plain text otherwise. Only the first
+/// 512 bytes of `content` are looked at.
+pub fn from_content(content: &[u8]) -> Option<&'static str> {
+    let content = &content[..content.len().min(SNIFF_LEN)];
+    if content.is_empty() {
+        return None;
+    }
+    if let Some((_, mime_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| content.starts_with(signature))
+    {
+        return Some(mime_type);
+    }
+    if content.len() >= 12 && &content[..4] == b"RIFF" && &content[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if content.len() >= 12 && &content[4..8] == b"ftyp" {
+        return Some(match &content[8..12] {
+            b"avif" => "image/avif",
+            _ => "video/mp4",
+        });
+    }
+    let text = match std::str::from_utf8(content) {
+        Ok(text) => text,
+        // the last character may have been cut off
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&content[..e.valid_up_to()]).ok()?
+        }
+        Err(_) => return None,
+    };
+    if text.contains('\0') {
+        return None;
+    }
+    let start = text.trim_start().to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        Some("text/html")
+    } else if start.starts_with("<svg") {
+        Some("image/svg+xml")
+    } else if start.starts_with("<?xml") {
+        Some(if start.contains("<svg") {
+            "image/svg+xml"
+        } else {
+            "application/xml"
+        })
+    } else {
+        Some("text/plain")
+    }
+}
+
+/// Returns the content type of a file called `name` that starts with `content`. The extension of
+/// the name is used when it is known, since text formats such as CSS and JavaScript cannot be told
+/// apart by their content. Otherwise the content is inspected with `from_content`, and `DEFAULT`
+/// is returned if that does not recognize it either.
+pub fn guess(name: &str, content: &[u8]) -> &'static str {
+    match from_name(name) {
+        DEFAULT => from_content(content).unwrap_or(DEFAULT),
+        mime_type => mime_type,
+    }
+}
+
+/// Returns `mime_type`, or the guessed content type if it is `AUTO`.
+pub(crate) fn resolve<'a>(mime_type: &'a str, name: &str, content: &[u8]) -> &'a str {
+    if mime_type == AUTO {
+        guess(name, content)
+    } else {
+        mime_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guess_from_extension() {
+        assert_eq!(from_name("site/index.HTML"), "text/html");
+        assert_eq!(
+            from_name("data/part-0.parquet"),
+            "application/vnd.apache.parquet"
+        );
+        assert_eq!(from_name("v1.2/README"), DEFAULT);
+        assert_eq!(from_name("archive.unknown"), DEFAULT);
+    }
+
+    #[test]
+    fn guess_from_content() {
+        assert_eq!(from_content(b"\x89PNG\r\n\x1a\n\0\0"), Some("image/png"));
+        assert_eq!(from_content(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(
+            from_content(b"\n  <!DOCTYPE HTML><html>"),
+            Some("text/html")
+        );
+        assert_eq!(
+            from_content(b"<?xml version=\"1.0\"?><svg>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(from_content("caf\u{e9}".as_bytes()), Some("text/plain"));
+        // a multi-byte character cut off at the end is still text
+        assert_eq!(
+            from_content(&"caf\u{e9}".as_bytes()[..4]),
+            Some("text/plain")
+        );
+        assert_eq!(from_content(b"\0\x01\x02"), None);
+        assert_eq!(from_content(b""), None);
+
+        assert_eq!(guess("style.css", b"body {}"), "text/css");
+        assert_eq!(guess("logo", b"GIF89a"), "image/gif");
+        assert_eq!(guess("blob", b"\0\x01\x02"), DEFAULT);
+        assert_eq!(resolve(AUTO, "notes.txt", b""), "text/plain");
+        assert_eq!(resolve("image/png", "notes.txt", b""), "image/png");
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Limits the rate at which a `Client` sends requests, so that bulk jobs stay below the limits
+/// that Google enforces, such as roughly one write per second to the same object and a few
+/// thousand writes per second to a bucket, instead of running into a storm of
+/// `429 Too Many Requests` responses. Requests that would exceed the limit wait until they are
+/// allowed to be sent. Reads (`GET` and `HEAD` requests) and writes (all other requests) are
+/// limited separately.
+/// ### Example
+/// ```rust
+/// use cloud_storage::{Client, Config, RateLimit};
+///
+/// let client = Client::with_config(Config {
+///     rate_limit: Some(RateLimit {
+///         writes_per_second: Some(100.0),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimit {
+    /// The maximum number of reads per second. Unlimited when not set.
+    pub reads_per_second: Option<f64>,
+    /// The maximum number of writes per second. Unlimited when not set.
+    pub writes_per_second: Option<f64>,
+    /// The number of requests that may be sent at once after a quiet period, before the limit
+    /// applies. Defaults to the number of requests allowed per second.
+    pub burst: Option<u32>,
+}
+
+/// Enforces a `RateLimit` for all requests of a `Client`.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    reads: Option<Mutex<TokenBucket>>,
+    writes: Option<Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: &RateLimit) -> Self {
+        let bucket = |rate: Option<f64>| {
+            rate.filter(|rate| *rate > 0.0)
+                .map(|rate| Mutex::new(TokenBucket::new(rate, limit.burst)))
+        };
+        Self {
+            reads: bucket(limit.reads_per_second),
+            writes: bucket(limit.writes_per_second),
+        }
+    }
+
+    /// Blocks until a request with `method` may be sent.
+    pub fn acquire(&self, method: &reqwest::Method) {
+        let is_read = method == reqwest::Method::GET || method == reqwest::Method::HEAD;
+        let bucket = if is_read { &self.reads } else { &self.writes };
+        if let Some(bucket) = bucket {
+            let wait = bucket.lock().unwrap().reserve(Instant::now());
+            if wait > Duration::from_secs(0) {
+                std::thread::sleep(wait);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: Option<u32>) -> Self {
+        let capacity = burst.map_or(rate, f64::from).max(1.0);
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Takes a token, returning how long the caller has to wait before it becomes available. The
+    /// token is reserved right away, so that callers waiting concurrently are served in order.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket() {
+        let mut bucket = TokenBucket::new(2.0, None);
+        let now = bucket.updated;
+        assert_eq!(bucket.reserve(now), Duration::from_secs(0));
+        assert_eq!(bucket.reserve(now), Duration::from_secs(0));
+        assert_eq!(bucket.reserve(now), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(now), Duration::from_millis(1000));
+        // after two seconds, the two reserved tokens have been paid off
+        let later = now + Duration::from_secs(2);
+        assert_eq!(bucket.reserve(later), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn burst() {
+        let mut bucket = TokenBucket::new(1.0, Some(3));
+        let now = bucket.updated;
+        for _ in 0..3 {
+            assert_eq!(bucket.reserve(now), Duration::from_secs(0));
+        }
+        assert_eq!(bucket.reserve(now), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn reads_and_writes_are_limited_separately() {
+        let limiter = RateLimiter::new(&RateLimit {
+            writes_per_second: Some(1.0),
+            ..Default::default()
+        });
+        assert!(limiter.reads.is_none());
+        assert!(limiter.writes.is_some());
+        let started = Instant::now();
+        for _ in 0..10 {
+            limiter.acquire(&reqwest::Method::GET);
+        }
+        limiter.acquire(&reqwest::Method::POST);
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
@@ -1,7 +1,7 @@
-use crate::error::{Error, GoogleResponse};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::error::Error;
 use crate::resources::bucket_access_control::{BucketAccessControl, NewBucketAccessControl};
 pub use crate::resources::common::Entity;
-use crate::resources::common::ListResponse;
 use crate::resources::default_object_access_control::{
     DefaultObjectAccessControl, NewDefaultObjectAccessControl,
 };
@@ -18,7 +18,7 @@ pub use crate::resources::location::*;
 /// controls.
 ///
 /// A bucket is always owned by the project team owners group.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Bucket {
     /// The kind of item this is. For buckets, this is always `storage#bucket`.
@@ -37,27 +37,51 @@ pub struct Bucket {
     /// The modification time of the bucket in RFC 3339 format.
     pub updated: chrono::DateTime<chrono::Utc>,
     /// Whether or not to automatically apply an eventBasedHold to new objects added to the bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub default_event_based_hold: Option<bool>,
     /// The bucket's retention policy, which defines the minimum age an object in the bucket must
     /// reach before it can be deleted or overwritten.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub retention_policy: Option<RetentionPolicy>,
+    /// The generation of this bucket. Together with the name, it identifies a soft-deleted bucket
+    /// that can be restored using `Bucket::restore`.
+    #[serde(
+        default,
+        deserialize_with = "crate::from_str_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub generation: Option<i64>,
+    /// The bucket's soft delete policy, which defines how long deleted objects are kept before
+    /// they are permanently removed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_delete_policy: Option<SoftDeletePolicy>,
+    /// If this bucket is soft-deleted, the time at which it was deleted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_delete_time: Option<chrono::DateTime<chrono::Utc>>,
+    /// If this bucket is soft-deleted, the time at which it will be permanently deleted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hard_delete_time: Option<chrono::DateTime<chrono::Utc>>,
     /// The metadata generation of this bucket.
     #[serde(deserialize_with = "crate::from_str")]
     pub metageneration: i64,
     /// Access controls on the bucket, containing one or more bucketAccessControls Resources. If
     /// iamConfiguration.uniformBucketLevelAccess.enabled is set to true, this field is omitted in
     /// responses, and requests that specify this field fail with a 400 Bad Request response.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub acl: Option<Vec<BucketAccessControl>>,
     /// Default access controls to apply to new objects when no ACL is provided. This list contains
     /// one or more defaultObjectAccessControls Resources. If
     /// iamConfiguration.uniformBucketLevelAccess.enabled is set to true, this field is omitted in
     /// responses, and requests that specify this field fail.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub default_object_acl: Option<Vec<DefaultObjectAccessControl>>,
     /// The bucket's IAM configuration.
     pub iam_configuration: IamConfiguration,
     /// Encryption configuration for a bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub encryption: Option<Encryption>,
     /// The owner of the bucket. This is always the project team's owner group.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub owner: Option<Owner>,
     /// The location of the bucket. Object data for objects in the bucket resides in physical
     /// storage within this region. Defaults to US. See Cloud Storage bucket locations for the
@@ -65,83 +89,296 @@ pub struct Bucket {
     pub location: Location,
     /// The type of location that the bucket resides in, as determined by the location property.
     pub location_type: String,
+    /// The regions that make up a configurable dual-region bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_placement_config: Option<CustomPlacementConfig>,
+    /// The recovery point objective for cross-region replication of the bucket. Only applies to
+    /// dual-region and multi-region buckets.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpo: Option<Rpo>,
     /// The bucket's website configuration, controlling how the service behaves when accessing
     /// bucket contents as a web site. See the Static Website Examples for more information.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub website: Option<Website>,
     /// The bucket's logging configuration, which defines the destination bucket and optional name
     /// prefix for the current bucket's logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub logging: Option<Logging>,
     /// The bucket's versioning configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub versioning: Option<Versioning>,
     /// The bucket's Cross-Origin Resource Sharing (CORS) configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cors: Option<Vec<Cors>>,
     /// The bucket's lifecycle configuration. See
     /// [lifecycle management](https://cloud.google.com/storage/docs/lifecycle) for more
     /// information.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub lifecycle: Option<Lifecycle>,
     /// User-provided bucket labels, in key/value pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub labels: Option<std::collections::HashMap<String, String>>,
     /// The bucket's default storage class, used whenever no storageClass is specified for a
     /// newly-created object. If storageClass is not specified when the bucket
     /// is created, it defaults to STANDARD. For more information, see storage classes.
     pub storage_class: StorageClass,
     /// The bucket's billing configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub billing: Option<Billing>,
+    /// The bucket's Autoclass configuration, which automatically transitions objects to
+    /// appropriate storage classes based on their access pattern.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoclass: Option<Autoclass>,
+    /// The bucket's hierarchical namespace configuration, which gives it real folders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hierarchical_namespace: Option<HierarchicalNamespace>,
     /// HTTP 1.1 [Entity tag](https://tools.ietf.org/html/rfc7232#section-2.3) for the bucket.
     pub etag: String,
 }
 
 /// A model that can be used to insert new buckets into Google Cloud Storage.
-#[derive(Debug, PartialEq, Default, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NewBucket {
     /// The name of the bucket. See the bucket naming guidelines for more information.
     pub name: String,
     /// Whether or not to automatically apply an eventBasedHold to new objects added to the bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub default_event_based_hold: Option<bool>,
+    /// The bucket's retention policy, which defines the minimum age an object in the bucket must
+    /// reach before it can be deleted or overwritten.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_policy: Option<NewRetentionPolicy>,
     /// Access controls on the bucket, containing one or more `BucketAccessControls` resources. If
     /// `iamConfiguration.uniformBucketLevelAccess.enabled` is set to true, this field is omitted in
     /// responses, and requests that specify this field fail with a `400 Bad Request` response.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub acl: Option<Vec<NewBucketAccessControl>>,
     /// Default access controls to apply to new objects when no ACL is provided. This list defines
     /// an entity and role for one or more `DefaultObjectAccessControls` resources. If
     /// `iamConfiguration.uniformBucketLevelAccess.enabled` is set to true, this field is omitted in
     /// responses, and requests that specify this field fail with a `400 Bad Request` response.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub default_object_acl: Option<Vec<NewDefaultObjectAccessControl>>,
     /// The bucket's IAM configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub iam_configuration: Option<IamConfiguration>,
     /// Encryption configuration for a bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub encryption: Option<Encryption>,
     /// The location of the bucket. Object data for objects in the bucket resides in physical
     /// storage within this region. Defaults to US. See Cloud Storage bucket locations for the
     /// authoritative list.
     pub location: Location,
+    /// The regions that make up a configurable dual-region bucket. When this is set, `location`
+    /// must be the multi-region that contains both regions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_placement_config: Option<CustomPlacementConfig>,
+    /// The recovery point objective for cross-region replication of the bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpo: Option<Rpo>,
     /// The bucket's website configuration, controlling how the service behaves when accessing
     /// bucket contents as a web site. See the Static Website Examples for more information.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub website: Option<Website>,
     /// The bucket's logging configuration, which defines the destination bucket and optional name
     /// prefix for the current bucket's logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub logging: Option<Logging>,
     /// The bucket's versioning configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub versioning: Option<Versioning>,
     /// The bucket's Cross-Origin Resource Sharing (CORS) configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cors: Option<Vec<Cors>>,
     /// The bucket's lifecycle configuration. See
     /// [lifecycle management](https://cloud.google.com/storage/docs/lifecycle) for more
     /// information.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub lifecycle: Option<Lifecycle>,
     /// User-provided bucket labels, in key/value pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub labels: Option<std::collections::HashMap<String, String>>,
     /// The bucket's default storage class, used whenever no storageClass is specified for a
     /// newly-created object. If storageClass is not specified when the bucket
     /// is created, it defaults to STANDARD. For more information, see storage classes.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub storage_class: Option<StorageClass>,
     /// The bucket's billing configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub billing: Option<Billing>,
+    /// The bucket's Autoclass configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoclass: Option<Autoclass>,
+    /// The bucket's soft delete policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_delete_policy: Option<SoftDeletePolicy>,
+    /// The bucket's hierarchical namespace configuration, which can only be set when the bucket
+    /// is created, and requires uniform bucket-level access.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hierarchical_namespace: Option<HierarchicalNamespace>,
+}
+
+impl NewBucket {
+    /// Creates a `NewBucket` with the provided name, where all other options have their default
+    /// values. The methods on `NewBucket` can be chained to configure the bucket further.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::bucket::{Bucket, NewBucket, Location, MultiRegion, StorageClass};
+    ///
+    /// let new_bucket = NewBucket::new("my-bucket")
+    ///     .location(Location::Multi(MultiRegion::Eu))
+    ///     .storage_class(StorageClass::Nearline)
+    ///     .versioning(true)
+    ///     .label("team", "storage")
+    ///     .uniform_bucket_level_access(true);
+    /// let bucket = Bucket::create(&new_bucket)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the location where the objects in the bucket are stored.
+    pub fn location(mut self, location: Location) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// Stores the data of the bucket in the two given regions, making it a configurable
+    /// dual-region bucket. The regions must both be part of `multi_region`.
+    pub fn dual_region(mut self, multi_region: MultiRegion, regions: [&str; 2]) -> Self {
+        self.location = Location::Multi(multi_region);
+        self.custom_placement_config = Some(CustomPlacementConfig {
+            data_locations: regions.iter().map(|r| r.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Sets the recovery point objective of the bucket. Use `Rpo::AsyncTurbo` to enable turbo
+    /// replication on a dual-region bucket.
+    pub fn rpo(mut self, rpo: Rpo) -> Self {
+        self.rpo = Some(rpo);
+        self
+    }
+
+    /// Sets the default storage class of the bucket.
+    pub fn storage_class(mut self, storage_class: StorageClass) -> Self {
+        self.storage_class = Some(storage_class);
+        self
+    }
+
+    /// Enables or disables object versioning.
+    pub fn versioning(mut self, enabled: bool) -> Self {
+        self.versioning = Some(Versioning { enabled });
+        self
+    }
+
+    /// Adds a label to the bucket.
+    pub fn label(mut self, key: &str, value: &str) -> Self {
+        self.labels
+            .get_or_insert_with(Default::default)
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Adds a rule to the lifecycle configuration of the bucket.
+    pub fn lifecycle_rule(mut self, rule: Rule) -> Self {
+        self.lifecycle
+            .get_or_insert_with(|| Lifecycle { rule: Vec::new() })
+            .rule
+            .push(rule);
+        self
+    }
+
+    /// Enables or disables uniform bucket-level access.
+    pub fn uniform_bucket_level_access(mut self, enabled: bool) -> Self {
+        self.iam_configuration
+            .get_or_insert_with(Default::default)
+            .uniform_bucket_level_access = UniformBucketLevelAccess {
+            enabled,
+            locked_time: None,
+        };
+        self
+    }
+
+    /// Sets whether public access to the bucket and its objects is prevented.
+    pub fn public_access_prevention(mut self, setting: PublicAccessPrevention) -> Self {
+        self.iam_configuration
+            .get_or_insert_with(Default::default)
+            .public_access_prevention = Some(setting);
+        self
+    }
+
+    /// Enables Autoclass for the bucket. Objects that are not accessed eventually end up in
+    /// `terminal_storage_class`, or in `Nearline` if it is `None`.
+    pub fn autoclass(mut self, terminal_storage_class: Option<StorageClass>) -> Self {
+        self.autoclass = Some(Autoclass {
+            enabled: true,
+            terminal_storage_class,
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Sets the period of time, in seconds, that deleted objects remain restorable. A duration of 0
+    /// disables soft delete for the bucket.
+    pub fn soft_delete_retention(mut self, retention_duration_seconds: u64) -> Self {
+        self.soft_delete_policy = Some(SoftDeletePolicy {
+            retention_duration_seconds,
+            effective_time: None,
+        });
+        self
+    }
+
+    /// Sets the period of time, in seconds, that objects in the bucket must be retained.
+    pub fn retention_period(mut self, retention_period: u64) -> Self {
+        self.retention_policy = Some(NewRetentionPolicy { retention_period });
+        self
+    }
+
+    /// Adds a CORS configuration to the bucket.
+    pub fn cors(mut self, cors: Cors) -> Self {
+        self.cors.get_or_insert_with(Vec::new).push(cors);
+        self
+    }
+
+    /// Writes the access logs of the bucket to `log_bucket`, using `log_object_prefix` as a prefix
+    /// for the names of the log objects.
+    pub fn logging(mut self, log_bucket: &str, log_object_prefix: &str) -> Self {
+        self.logging = Some(Logging {
+            log_bucket: log_bucket.to_string(),
+            log_object_prefix: log_object_prefix.to_string(),
+        });
+        self
+    }
+
+    /// Configures how the bucket behaves when it is accessed as a website.
+    pub fn website(mut self, main_page_suffix: &str, not_found_page: &str) -> Self {
+        self.website = Some(Website {
+            main_page_suffix: main_page_suffix.to_string(),
+            not_found_page: not_found_page.to_string(),
+        });
+        self
+    }
+
+    /// Sets the Cloud KMS key that is used to encrypt objects that are inserted without specifying
+    /// an encryption method.
+    pub fn default_kms_key(mut self, default_kms_key_name: &str) -> Self {
+        self.encryption = Some(Encryption {
+            default_kms_key_name: default_kms_key_name.to_string(),
+        });
+        self
+    }
 }
 
 /// Contains information about how files are kept after deletion.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RetentionPolicy {
     /// The period of time, in seconds, that objects in the bucket must be retained and cannot be
@@ -153,11 +390,81 @@ pub struct RetentionPolicy {
     pub effective_time: chrono::DateTime<chrono::Utc>,
     /// Whether or not the retentionPolicy is locked. If true, the retentionPolicy cannot be removed
     /// and the retention period cannot be reduced.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub is_locked: Option<bool>,
 }
 
+/// A set of changes to apply to an existing bucket using `Bucket::patch`. Only the fields that are
+/// set are sent to Google Cloud Storage, all other properties of the bucket are left untouched.
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketPatch {
+    /// Whether or not to automatically apply an eventBasedHold to new objects added to the bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_event_based_hold: Option<bool>,
+    /// The bucket's retention policy, which defines the minimum age an object in the bucket must
+    /// reach before it can be deleted or overwritten.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_policy: Option<NewRetentionPolicy>,
+    /// The bucket's IAM configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iam_configuration: Option<IamConfiguration>,
+    /// Encryption configuration for a bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<Encryption>,
+    /// The bucket's website configuration, controlling how the service behaves when accessing
+    /// bucket contents as a web site.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website: Option<Website>,
+    /// The bucket's logging configuration, which defines the destination bucket and optional name
+    /// prefix for the current bucket's logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Logging>,
+    /// The bucket's versioning configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versioning: Option<Versioning>,
+    /// The bucket's Cross-Origin Resource Sharing (CORS) configuration. This replaces the complete
+    /// existing configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cors: Option<Vec<Cors>>,
+    /// The bucket's lifecycle configuration. This replaces the complete existing configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lifecycle: Option<Lifecycle>,
+    /// Labels to add or change. Labels that are not mentioned here are kept, and a label can be
+    /// removed by setting its value to `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<std::collections::HashMap<String, Option<String>>>,
+    /// The bucket's default storage class, used whenever no storageClass is specified for a
+    /// newly-created object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_class: Option<StorageClass>,
+    /// The bucket's billing configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing: Option<Billing>,
+    /// The bucket's Autoclass configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoclass: Option<Autoclass>,
+    /// The bucket's soft delete policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_delete_policy: Option<SoftDeletePolicy>,
+    /// The recovery point objective for cross-region replication of the bucket. Note that the
+    /// regions of a dual-region bucket cannot be changed after it has been created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpo: Option<Rpo>,
+}
+
+/// The retention policy of a bucket that is yet to be created.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewRetentionPolicy {
+    /// The period of time, in seconds, that objects in the bucket must be retained and cannot be
+    /// deleted, overwritten, or made noncurrent. The value must be greater than 0 seconds and less
+    /// than 3,155,760,000 seconds.
+    pub retention_period: u64,
+}
+
 /// Contains information about the Buckets IAM configuration.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IamConfiguration {
     /// The bucket's uniform bucket-level access configuration.
@@ -165,11 +472,69 @@ pub struct IamConfiguration {
     /// Note: iamConfiguration also includes the bucketPolicyOnly field, which uses a legacy name
     /// but has the same functionality as the uniformBucketLevelAccess field. We recommend only
     /// using uniformBucketLevelAccess, as specifying both fields may result in unreliable behavior.
+    #[serde(default)]
     pub uniform_bucket_level_access: UniformBucketLevelAccess,
+    /// Whether the bucket and its objects may be made public. Buckets that were created before
+    /// public access prevention existed may not report it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_access_prevention: Option<PublicAccessPrevention>,
+}
+
+/// Whether public access to a bucket and its objects is prevented.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PublicAccessPrevention {
+    /// Public access is prevented: ACLs and IAM policies that grant access to `allUsers` or
+    /// `allAuthenticatedUsers` are rejected, and existing grants to them no longer apply.
+    Enforced,
+    /// The bucket follows the public access prevention organization policy constraint of its
+    /// project, if any.
+    Inherited,
+    /// A value that this version of the crate does not know about, by the name Google uses for it.
+    /// Older buckets may report `unspecified`, which behaves like `Inherited`.
+    Other(String),
+}
+
+impl PublicAccessPrevention {
+    /// The name Google uses for this setting, for example `enforced`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            PublicAccessPrevention::Enforced => "enforced",
+            PublicAccessPrevention::Inherited => "inherited",
+            PublicAccessPrevention::Other(name) => name,
+        }
+    }
+}
+
+impl From<&str> for PublicAccessPrevention {
+    fn from(name: &str) -> Self {
+        match name {
+            "enforced" => PublicAccessPrevention::Enforced,
+            "inherited" => PublicAccessPrevention::Inherited,
+            other => PublicAccessPrevention::Other(other.to_string()),
+        }
+    }
+}
+
+impl serde::Serialize for PublicAccessPrevention {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PublicAccessPrevention {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(|name| PublicAccessPrevention::from(name.as_str()))
+    }
 }
 
 /// Access that is configured for all objects in one go.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UniformBucketLevelAccess {
     /// Whether or not the bucket uses uniform bucket-level access. If set, access checks only use
@@ -180,11 +545,109 @@ pub struct UniformBucketLevelAccess {
     ///
     /// iamConfiguration.uniformBucketLevelAccess.enabled may be changed from true to false until
     /// the locked time, after which the field is immutable.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub locked_time: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// Conditions on the metageneration of a bucket, which is incremented whenever its configuration
+/// changes. An operation with preconditions is only carried out while they hold. Otherwise Google
+/// responds with `412 Precondition Failed`, or with `304 Not Modified` when a read fails
+/// `if_metageneration_not_match`, so that concurrent tools never act on a stale configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BucketPreconditions {
+    /// Only proceed if the bucket has this metageneration.
+    pub if_metageneration_match: Option<i64>,
+    /// Only proceed if the bucket does not have this metageneration.
+    pub if_metageneration_not_match: Option<i64>,
+}
+
+impl BucketPreconditions {
+    /// The preconditions as query parameters.
+    pub(crate) fn query(&self) -> Vec<(&'static str, i64)> {
+        let mut query = Vec::new();
+        if let Some(metageneration) = self.if_metageneration_match {
+            query.push(("ifMetagenerationMatch", metageneration));
+        }
+        if let Some(metageneration) = self.if_metageneration_not_match {
+            query.push(("ifMetagenerationNotMatch", metageneration));
+        }
+        query
+    }
+}
+
+/// With a [hierarchical namespace](https://cloud.google.com/storage/docs/hns-overview), the
+/// folders of a bucket are resources of their own, and objects and folders can be renamed
+/// atomically.
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HierarchicalNamespace {
+    /// Whether or not the bucket has a hierarchical namespace.
+    pub enabled: bool,
+}
+
+/// With [Autoclass](https://cloud.google.com/storage/docs/autoclass) enabled, objects in the bucket
+/// are automatically transitioned between storage classes based on how often they are accessed.
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Autoclass {
+    /// Whether or not Autoclass is enabled for the bucket.
+    pub enabled: bool,
+    /// The time at which Autoclass was last enabled or disabled. This is set by Google Cloud
+    /// Storage and is ignored when sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toggle_time: Option<chrono::DateTime<chrono::Utc>>,
+    /// The storage class that objects in the bucket eventually transition to if they are not read
+    /// for a certain length of time. Valid values are `Nearline` and `Archive`, and Google uses
+    /// `Nearline` if this is not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terminal_storage_class: Option<StorageClass>,
+    /// The time at which the terminal storage class was last changed. This is set by Google Cloud
+    /// Storage and is ignored when sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terminal_storage_class_update_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The [soft delete policy](https://cloud.google.com/storage/docs/soft-delete) of a bucket. Objects
+/// that are deleted or overwritten remain restorable for the configured duration.
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoftDeletePolicy {
+    /// The period of time, in seconds, that soft-deleted objects in the bucket are retained and
+    /// cannot be permanently deleted. The value must be 0, which disables soft delete, or between
+    /// 604,800 (7 days) and 7,776,000 (90 days).
+    #[serde(deserialize_with = "crate::from_str")]
+    pub retention_duration_seconds: u64,
+    /// The time from which the policy was effective. This is set by Google Cloud Storage and is
+    /// ignored when sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The placement of the data of a
+/// [configurable dual-region](https://cloud.google.com/storage/docs/locations#location-dr) bucket.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomPlacementConfig {
+    /// The two regions in which the data of the bucket is stored, for example `US-EAST1` and
+    /// `US-WEST1`.
+    pub data_locations: Vec<String>,
+}
+
+/// The [recovery point objective](https://cloud.google.com/storage/docs/availability-durability#turbo-replication)
+/// of a bucket, which determines how quickly newly written objects are replicated to a second
+/// region.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Rpo {
+    /// Objects are replicated asynchronously, most of them within an hour.
+    Default,
+    /// Turbo replication: objects are replicated to the second region within 15 minutes. Only
+    /// available for dual-region buckets.
+    AsyncTurbo,
+}
+
 /// Contains information about the encryption used for data in this Bucket.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Encryption {
     /// A Cloud KMS key that will be used to encrypt objects inserted into this bucket, if no
@@ -193,17 +656,18 @@ pub struct Encryption {
 }
 
 /// Contains information about an entity that is able to own a `Bucket`.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Owner {
     /// The entity, in the form project-owner-projectId.
     pub entity: Entity,
     /// The ID for the entity.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub entity_id: Option<String>,
 }
 
 /// Contains configuration about how to visit the website linked to this Bucket.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Website {
     /// If the requested object path is missing, the service will ensure the path has a trailing
@@ -217,7 +681,7 @@ pub struct Website {
 }
 
 /// Contains information of where and how access logs to this bucket are maintained.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Logging {
     /// The destination bucket where the current bucket's logs should be placed.
@@ -227,7 +691,7 @@ pub struct Logging {
 }
 
 /// Contains information about whether a Bucket keeps track of its version.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Versioning {
     /// While set to true, versioning is fully enabled for this bucket.
@@ -235,26 +699,29 @@ pub struct Versioning {
 }
 
 /// Contains information about how OPTIONS requests for this Bucket are handled.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Cors {
     /// The list of Origins eligible to receive CORS response headers. Note: "*" is permitted in the
     /// list of origins, and means "any Origin".
+    #[serde(default)]
     pub origin: Vec<String>,
     /// The list of HTTP methods on which to include CORS response headers, (GET, OPTIONS, POST,
     /// etc) Note: "*" is permitted in the list of methods, and means "any method".
+    #[serde(default)]
     pub method: Vec<String>,
     /// The list of HTTP headers other than the simple response headers to give permission for the
     /// user-agent to share across domains.
+    #[serde(default)]
     pub response_header: Vec<String>,
     /// The value, in seconds, to return in the Access-Control-Max-Age header used in preflight
     /// responses.
-    #[serde(deserialize_with = "crate::from_str")]
-    pub max_age_seconds: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<i32>,
 }
 
 /// Contains a set of `Rule` Objects which together describe the way this lifecycle behaves
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Lifecycle {
     /// A lifecycle management rule, which is made of an action to take and the condition(s) under
@@ -263,7 +730,7 @@ pub struct Lifecycle {
 }
 
 /// An element of the lifecyle list.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Rule {
     /// The action to take.
@@ -273,17 +740,18 @@ pub struct Rule {
 }
 
 /// Represents an action that might be undertaken due to a `Condition`.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Action {
     /// Type of the action.
     pub r#type: ActionType,
     /// Target storage class. Required iff the type of the action is SetStorageClass.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub storage_class: Option<StorageClass>,
 }
 
 /// Type of the action.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum ActionType {
     /// Deletes a Bucket.
     Delete,
@@ -292,42 +760,43 @@ pub enum ActionType {
 }
 
 /// A rule that might induce an `Action` if met.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Condition {
     /// Age of an object (in days). This condition is satisfied when an object reaches the specified
     /// age.
-    #[serde(deserialize_with = "crate::from_str")]
-    pub age: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age: Option<i32>,
     /// A date in `RFC 3339` format with only the date part (for instance, "2013-01-15"). This
     /// condition is satisfied when an object is created before midnight of the specified date in
     /// UTC.
-    pub created_before: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<chrono::NaiveDate>,
     /// Relevant only for versioned objects. If the value is true, this condition matches the live
     /// version of objects; if the value is `false`, it matches noncurrent versions of objects.
-    pub is_live: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_live: Option<bool>,
     /// Objects having any of the storage classes specified by this condition will be matched.
-    /// Values include STANDARD, NEARLINE, COLDLINE, MULTI_REGIONAL, REGIONAL, and
-    /// DURABLE_REDUCED_AVAILABILITY.
-    pub matches_storage_class: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches_storage_class: Option<Vec<StorageClass>>,
     /// Relevant only for versioned objects. If the value is N, this condition is satisfied when
     /// there are at least N versions (including the live version) newer than this version of the
     /// object.
-    #[serde(deserialize_with = "crate::from_str")]
-    pub num_newer_versions: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_newer_versions: Option<i32>,
 }
 
 /// Contains information about the payment structure of this bucket
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Billing {
     /// When set to true, Requester Pays is enabled for this bucket.
     pub requester_pays: bool,
 }
 
-/// The type of storage that is used. Pertains to availability, performance and cost.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+/// The type of storage that is used. Pertains to availability, performance and cost. Used for
+/// buckets, objects and lifecycle rules alike.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum StorageClass {
     /// Standard Storage is best for data that is frequently accessed ("hot" data) and/or stored for
     /// only brief periods of time.
@@ -352,19 +821,100 @@ pub enum StorageClass {
     ///
     /// You can move your data from DRA to other storage classes by performing a storage transfer.
     DurableReducedAvailability,
+    /// Archive Storage is the lowest-cost, highly durable storage service for data archiving,
+    /// online backup, and disaster recovery. Data is available within milliseconds, but has a 365
+    /// day minimum storage duration.
+    Archive,
+    /// A storage class that this version of the crate does not know about, by the name Google
+    /// uses for it.
+    Other(String),
+}
+
+impl StorageClass {
+    /// The name Google uses for this storage class, for example `NEARLINE`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            StorageClass::Standard => "STANDARD",
+            StorageClass::Nearline => "NEARLINE",
+            StorageClass::Coldline => "COLDLINE",
+            StorageClass::MultiRegional => "MULTI_REGIONAL",
+            StorageClass::Regional => "REGIONAL",
+            StorageClass::DurableReducedAvailability => "DURABLE_REDUCED_AVAILABILITY",
+            StorageClass::Archive => "ARCHIVE",
+            StorageClass::Other(name) => name,
+        }
+    }
+}
+
+impl From<&str> for StorageClass {
+    fn from(name: &str) -> Self {
+        match name {
+            "STANDARD" => StorageClass::Standard,
+            "NEARLINE" => StorageClass::Nearline,
+            "COLDLINE" => StorageClass::Coldline,
+            "MULTI_REGIONAL" => StorageClass::MultiRegional,
+            "REGIONAL" => StorageClass::Regional,
+            "DURABLE_REDUCED_AVAILABILITY" => StorageClass::DurableReducedAvailability,
+            "ARCHIVE" => StorageClass::Archive,
+            other => StorageClass::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for StorageClass {
+    fn from(name: String) -> Self {
+        match StorageClass::from(name.as_str()) {
+            StorageClass::Other(_) => StorageClass::Other(name),
+            known => known,
+        }
+    }
+}
+
+impl std::str::FromStr for StorageClass {
+    type Err = std::convert::Infallible;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(StorageClass::from(name))
+    }
+}
+
+impl std::fmt::Display for StorageClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl serde::Serialize for StorageClass {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for StorageClass {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(StorageClass::from)
+    }
 }
 
 /// A representation of the IAM Policiy for a certain bucket.
-#[derive(Debug, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, Default, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IamPolicy {
     /// The [Cloud IAM policy](https://cloud.google.com/iam/docs/policies#versions) version.
     pub version: i32,
     /// The kind of item this is. For policies, this field is ignored in a request and is
     /// `storage#policy` in a response.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub kind: Option<String>,
     /// The ID of the resource to which this policy belongs. The response for this field is of the
     /// form `projects/_/buckets/bucket`. This field is ignored in a request.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub resource_id: Option<String>,
     /// A list of the bindings for this policy.
     pub bindings: Vec<Binding>,
@@ -374,7 +924,7 @@ pub struct IamPolicy {
 
 /// An association between a role, which comes with a set of permissions, and members who may assume
 /// that role.
-#[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Binding {
     /// The role to which members belong. Two types of roles are supported: standard IAM roles,
@@ -410,16 +960,18 @@ pub struct Binding {
     pub members: Vec<String>,
     /// A condition object associated with this binding. Each role binding can only contain one
     /// condition.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub condition: Option<IamCondition>,
 }
 
 /// A condition object associated with a binding.
-#[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IamCondition {
     /// Title of the condition. For example, "expires_end_of_2018".
     pub title: String,
     /// Optional description of the condition. For example, "Expires at midnight on 2018-12-31".
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// [Attribute-based](https://cloud.google.com/iam/docs/conditions-overview#attributes) logic
     /// expression using a subset of the Common Expression Language (CEL). For example,
@@ -429,7 +981,7 @@ pub struct IamCondition {
 
 /// All possible roles that can exist in the IAM system. For a more comprehensive version, check
 /// [Googles Documentation](https://cloud.google.com/storage/docs/access-control/iam-roles).
-#[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(untagged)]
 pub enum IamRole {
     /// Standard roles can be applied to either buckets or projects.
@@ -438,12 +990,86 @@ pub enum IamRole {
     Primitive(PrimitiveIamRole),
     /// Legacy roles are roles that can only be added to an individual bucket.
     Legacy(LegacyIamRole),
+    /// Any other role by its name, such as a custom role `projects/my-project/roles/myRole` or a
+    /// role that this version of the crate does not know about. Policies with such roles can still
+    /// be read and written back without losing them.
+    Other(String),
+}
+
+impl IamRole {
+    /// `roles/storage.objectCreator`: create objects.
+    pub const OBJECT_CREATOR: IamRole = IamRole::Standard(StandardIamRole::ObjectCreator);
+    /// `roles/storage.objectViewer`: view and list objects.
+    pub const OBJECT_VIEWER: IamRole = IamRole::Standard(StandardIamRole::ObjectViewer);
+    /// `roles/storage.objectAdmin`: full control over objects.
+    pub const OBJECT_ADMIN: IamRole = IamRole::Standard(StandardIamRole::ObjectAdmin);
+    /// `roles/storage.hmacKeyAdmin`: full control over HMAC keys.
+    pub const HMAC_KEY_ADMIN: IamRole = IamRole::Standard(StandardIamRole::HmacKeyAdmin);
+    /// `roles/storage.admin`: full control over buckets and objects.
+    pub const ADMIN: IamRole = IamRole::Standard(StandardIamRole::Admin);
+    /// `roles/storage.legacyObjectReader`: view objects.
+    pub const LEGACY_OBJECT_READER: IamRole = IamRole::Legacy(LegacyIamRole::LegacyObjectReader);
+    /// `roles/storage.legacyObjectOwner`: view and edit objects, including their ACLs.
+    pub const LEGACY_OBJECT_OWNER: IamRole = IamRole::Legacy(LegacyIamRole::LegacyObjectOwner);
+    /// `roles/storage.legacyBucketReader`: list objects and read bucket metadata.
+    pub const LEGACY_BUCKET_READER: IamRole = IamRole::Legacy(LegacyIamRole::LegacyBucketReader);
+    /// `roles/storage.legacyBucketWriter`: create, overwrite and delete objects.
+    pub const LEGACY_BUCKET_WRITER: IamRole = IamRole::Legacy(LegacyIamRole::LegacyBucketWriter);
+    /// `roles/storage.legacyBucketOwner`: the writer role, plus editing bucket metadata and
+    /// policies.
+    pub const LEGACY_BUCKET_OWNER: IamRole = IamRole::Legacy(LegacyIamRole::LegacyBucketOwner);
+}
+
+impl IamPolicy {
+    /// Grants `role` to `member`, such as `user:jane@example.com` or `allUsers`, through the
+    /// binding of that role without a condition, which is created if there is none yet. Returns
+    /// whether the policy changed, which it does not if the member already had the role.
+    ///
+    /// Save the policy with `Bucket::set_iam_policy`, or use `Bucket::modify_iam_policy`, which
+    /// retries when the policy was changed concurrently.
+    pub fn grant(&mut self, role: IamRole, member: &str) -> bool {
+        let binding = self
+            .bindings
+            .iter_mut()
+            .find(|b| b.role == role && b.condition.is_none());
+        match binding {
+            Some(binding) if binding.members.iter().any(|m| m == member) => false,
+            Some(binding) => {
+                binding.members.push(member.to_string());
+                true
+            }
+            None => {
+                self.bindings.push(Binding {
+                    role,
+                    members: vec![member.to_string()],
+                    condition: None,
+                });
+                true
+            }
+        }
+    }
+
+    /// Removes `member` from the bindings of `role` without a condition, and removes bindings
+    /// that are left without members. Bindings with a condition are left alone. Returns whether
+    /// the policy changed.
+    pub fn revoke(&mut self, role: &IamRole, member: &str) -> bool {
+        let mut changed = false;
+        for binding in &mut self.bindings {
+            if &binding.role == role && binding.condition.is_none() {
+                let before = binding.members.len();
+                binding.members.retain(|m| m != member);
+                changed |= binding.members.len() != before;
+            }
+        }
+        self.bindings.retain(|b| !b.members.is_empty());
+        changed
+    }
 }
 
 /// The following enum contains Cloud Identity and Access Management (Cloud IAM) roles that are
 /// associated with Cloud Storage and lists the permissions that are contained in each role. Unless
 /// otherwise noted, these roles can be applied either to entire projects or specific buckets.
-#[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum StandardIamRole {
     /// Allows users to create objects. Does not give permission to view, delete, or overwrite
     /// objects.
@@ -471,7 +1097,7 @@ pub enum StandardIamRole {
 
 /// The following enum contains primitive roles and the Cloud Storage permissions that these roles
 /// contain. Primitive roles cannot be added at the bucket-level.
-#[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum PrimitiveIamRole {
     /// Grants permission to list buckets as well as view bucket metadata, excluding ACLs, when
     /// listing. Also grants permission to list and get HMAC keys in the project.
@@ -489,7 +1115,7 @@ pub enum PrimitiveIamRole {
 
 /// The following enum contains Cloud IAM roles that are equivalent to Access Control List (ACL)
 /// permissions. These Cloud IAM roles can only be applied to a bucket, not a project.
-#[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum LegacyIamRole {
     /// Grants permission to view objects and their metadata, excluding ACLs.
     #[serde(rename = "roles/storage.legacyObjectReader")]
@@ -527,7 +1153,7 @@ pub enum LegacyIamRole {
 }
 
 /// The request needed to perform the Object::test_iam_permission function.
-#[derive(Debug, PartialEq, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TestIamPermission {
     /// The kind of item this is.
@@ -540,6 +1166,7 @@ pub struct TestIamPermission {
     permissions: Vec<String>,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Bucket {
     /// Creates a new `Bucket`. There are many options that you can provide for creating a new
     /// bucket, so the `NewBucket` resource contains all of them. Note that `NewBucket` implements
@@ -562,21 +1189,7 @@ impl Bucket {
     /// # }
     /// ```
     pub fn create(new_bucket: &NewBucket) -> Result<Self, Error> {
-        let url = format!("{}/b/", crate::BASE_URL);
-        let project = crate::SERVICE_ACCOUNT.project_id.clone();
-        let query = [("project", project)];
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .post(&url)
-            .headers(crate::get_headers()?)
-            .query(&query)
-            .json(new_bucket)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().bucket().create(new_bucket)
     }
 
     /// Returns all `Bucket`s within this project.
@@ -590,20 +1203,32 @@ impl Bucket {
     /// # }
     /// ```
     pub fn list() -> Result<Vec<Self>, Error> {
-        let url = format!("{}/b/", crate::BASE_URL);
-        let project = crate::SERVICE_ACCOUNT.project_id.clone();
-        let query = [("project", project)];
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<ListResponse<Self>> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .query(&query)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s.items),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().bucket().list()
+    }
+
+    /// Returns an iterator over the `Bucket`s within `project`, optionally limited to the buckets
+    /// whose name starts with `prefix`. Buckets are requested from Google in pages of at most
+    /// `page_size` buckets as the iterator is advanced, so this also works for projects with a
+    /// large amount of buckets.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    ///
+    /// for bucket in Bucket::list_with("my-project", Some("logs-"), Some(100)) {
+    ///     println!("{}", bucket?.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_with(
+        project: &str,
+        prefix: Option<&str>,
+        page_size: Option<u32>,
+    ) -> impl Iterator<Item = Result<Self, Error>> {
+        crate::Client::global()
+            .bucket()
+            .list_with(project, prefix, page_size)
     }
 
     /// Returns a single `Bucket` by its name. If the Bucket does not exist, an error is returned.
@@ -624,17 +1249,52 @@ impl Bucket {
     /// # }
     /// ```
     pub fn read(name: &str) -> Result<Self, Error> {
-        let url = format!("{}/b/{}", crate::BASE_URL, name);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().bucket().read(name)
+    }
+
+    /// Returns a single `Bucket` by its name, if its metageneration satisfies `preconditions`.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::bucket::{Bucket, BucketPreconditions};
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// // only fetch the configuration again once it has changed
+    /// let preconditions = BucketPreconditions {
+    ///     if_metageneration_not_match: Some(bucket.metageneration),
+    ///     ..Default::default()
+    /// };
+    /// match Bucket::read_with("my-bucket", &preconditions) {
+    ///     Ok(changed) => println!("now at metageneration {}", changed.metageneration),
+    ///     Err(e) if e.status() == Some(304) => println!("unchanged"),
+    ///     Err(e) => return Err(e.into()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_with(name: &str, preconditions: &BucketPreconditions) -> Result<Self, Error> {
+        crate::Client::global()
+            .bucket()
+            .read_with(name, preconditions)
+    }
+
+    /// Checks whether a bucket with the given name exists. Returns `Ok(false)` only if Google
+    /// responds with `404 Not Found`. If the bucket exists but you are not allowed to view it, an
+    /// `Error::PermissionDenied` is returned instead, so that you can tell a missing bucket
+    /// apart from one you lack permission for.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::bucket::{Bucket, NewBucket};
+    ///
+    /// if !Bucket::exists("my-bucket")? {
+    ///     Bucket::create(&NewBucket::new("my-bucket"))?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exists(name: &str) -> Result<bool, Error> {
+        crate::Client::global().bucket().exists(name)
     }
 
     /// Update an existing `Bucket`. If you declare you bucket as mutable, you can edit its fields.
@@ -662,18 +1322,79 @@ impl Bucket {
     /// # }
     /// ```
     pub fn update(&self) -> Result<Self, Error> {
-        let url = format!("{}/b/{}", crate::BASE_URL, self.name);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .put(&url)
-            .headers(crate::get_headers()?)
-            .json(self)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().bucket().update(self)
+    }
+
+    /// Updates this `Bucket` like `Bucket::update`, if its metageneration satisfies
+    /// `preconditions`.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::bucket::{Bucket, BucketPreconditions};
+    ///
+    /// let mut bucket = Bucket::read("my-bucket")?;
+    /// let preconditions = BucketPreconditions {
+    ///     if_metageneration_match: Some(bucket.metageneration),
+    ///     ..Default::default()
+    /// };
+    /// bucket.default_event_based_hold = Some(true);
+    /// bucket.update_with(&preconditions)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_with(&self, preconditions: &BucketPreconditions) -> Result<Self, Error> {
+        crate::Client::global()
+            .bucket()
+            .update_with(self, preconditions)
+    }
+
+    /// Applies the changes in `patch` to this `Bucket`, leaving all properties that are not set in
+    /// `patch` untouched. The request only succeeds if the bucket was not modified since `self`
+    /// was retrieved, that is, if its metageneration still matches. Otherwise, Google responds with
+    /// `412 Precondition Failed` and nothing is changed, so that concurrent updates are never
+    /// silently overwritten. In that case, read the bucket again and retry.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::bucket::{Bucket, BucketPatch, Versioning};
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let patch = BucketPatch {
+    ///     versioning: Some(Versioning { enabled: true }),
+    ///     ..Default::default()
+    /// };
+    /// let bucket = bucket.patch(&patch)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn patch(&self, patch: &BucketPatch) -> Result<Self, Error> {
+        crate::Client::global().bucket().patch(self, patch)
+    }
+
+    /// Applies the changes in `patch` like `Bucket::patch`, but under `preconditions` instead of
+    /// the metageneration of `self`. Without preconditions, the changes are always applied.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::bucket::{Bucket, BucketPatch, BucketPreconditions, Versioning};
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let patch = BucketPatch {
+    ///     versioning: Some(Versioning { enabled: true }),
+    ///     ..Default::default()
+    /// };
+    /// let bucket = bucket.patch_with(&patch, &BucketPreconditions::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn patch_with(
+        &self,
+        patch: &BucketPatch,
+        preconditions: &BucketPreconditions,
+    ) -> Result<Self, Error> {
+        crate::Client::global()
+            .bucket()
+            .patch_with(self, patch, preconditions)
     }
 
     /// Delete an existing `Bucket`. This permanently removes a bucket from Google Cloud Storage.
@@ -696,14 +1417,47 @@ impl Bucket {
     /// # }
     /// ```
     pub fn delete(self) -> Result<(), Error> {
-        let url = format!("{}/b/{}", crate::BASE_URL, self.name);
-        let client = reqwest::blocking::Client::new();
-        let response = client.delete(&url).headers(crate::get_headers()?).send()?;
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(Error::Google(response.json()?))
-        }
+        crate::Client::global().bucket().delete(self)
+    }
+
+    /// Deletes this `Bucket` like `Bucket::delete`, if its metageneration satisfies
+    /// `preconditions`.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::bucket::{Bucket, BucketPreconditions};
+    ///
+    /// let bucket = Bucket::read("unnecessary-bucket")?;
+    /// let preconditions = BucketPreconditions {
+    ///     if_metageneration_match: Some(bucket.metageneration),
+    ///     ..Default::default()
+    /// };
+    /// bucket.delete_with(&preconditions)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn delete_with(self, preconditions: &BucketPreconditions) -> Result<(), Error> {
+        crate::Client::global()
+            .bucket()
+            .delete_with(self, preconditions)
+    }
+
+    /// Restores a soft-deleted bucket. Since a new bucket with the same name may have been created
+    /// in the meantime, the `generation` of the deleted bucket must be provided as well.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let generation = bucket.generation.unwrap();
+    /// bucket.delete()?;
+    /// let bucket = Bucket::restore("my-bucket", generation)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn restore(name: &str, generation: i64) -> Result<Self, Error> {
+        crate::Client::global().bucket().restore(name, generation)
     }
 
     /// Returns the [IAM Policy](https://cloud.google.com/iam/docs/) for this bucket.
@@ -724,18 +1478,10 @@ impl Bucket {
     /// # Ok(())
     /// # }
     /// ```
+    ///
+    /// Version 3 of the policy is requested, so that bindings with a condition are kept intact.
     pub fn get_iam_policy(&self) -> Result<IamPolicy, Error> {
-        let url = format!("{}/b/{}/iam", crate::BASE_URL, self.name);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<IamPolicy> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().bucket().get_iam_policy(self)
     }
 
     /// Updates the [IAM Policy](https://cloud.google.com/iam/docs/) for this bucket.
@@ -769,18 +1515,34 @@ impl Bucket {
     /// # }
     /// ```
     pub fn set_iam_policy(&self, iam: &IamPolicy) -> Result<IamPolicy, Error> {
-        let url = format!("{}/b/{}/iam", crate::BASE_URL, self.name);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<IamPolicy> = client
-            .put(&url)
-            .headers(crate::get_headers()?)
-            .json(iam)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().bucket().set_iam_policy(self, iam)
+    }
+
+    /// Reads the [IAM Policy](https://cloud.google.com/iam/docs/) of this bucket, lets `modify`
+    /// change it and saves the result. The policy is only saved if its `etag` still matches, so
+    /// that concurrent changes are not overwritten; if it does not, the cycle is repeated with
+    /// the new policy. Nothing is saved if `modify` leaves the policy unchanged.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    /// use cloud_storage::bucket::IamRole;
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// bucket.modify_iam_policy(|policy| {
+    ///     policy.grant(IamRole::OBJECT_VIEWER, "group:readers@example.com");
+    ///     policy.revoke(&IamRole::OBJECT_ADMIN, "user:former-employee@example.com");
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn modify_iam_policy<F>(&self, modify: F) -> Result<IamPolicy, Error>
+    where
+        F: FnMut(&mut IamPolicy),
+    {
+        crate::Client::global()
+            .bucket()
+            .modify_iam_policy(self, modify)
     }
 
     /// Checks whether the user provided in the service account has this permission.
@@ -795,23 +1557,214 @@ impl Bucket {
     /// # }
     /// ```
     pub fn test_iam_permission(&self, permission: &str) -> Result<TestIamPermission, Error> {
-        if permission == "storage.buckets.list" || permission == "storage.buckets.create" {
-            return Err(Error::new(
-                "tested permission must not be `storage.buckets.list` or `storage.buckets.create`",
-            ));
-        }
-        let url = format!("{}/b/{}/iam/testPermissions", crate::BASE_URL, self.name);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<TestIamPermission> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .query(&[("permissions", permission)])
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .bucket()
+            .test_iam_permission(self, permission)
+    }
+
+    /// Returns the ACL entries of this bucket.
+    ///
+    /// ### Important
+    /// Important: This method fails with a `400 Bad Request` response for buckets with uniform
+    /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
+    /// control access instead.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let acls = bucket.acl_list()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn acl_list(&self) -> Result<Vec<BucketAccessControl>, Error> {
+        crate::Client::global().bucket().acl_list(self)
+    }
+
+    /// Adds an ACL entry to this bucket.
+    ///
+    /// ### Important
+    /// Important: This method fails with a `400 Bad Request` response for buckets with uniform
+    /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
+    /// control access instead.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    /// use cloud_storage::bucket_access_control::{NewBucketAccessControl, Role, Entity};
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let new_acl = NewBucketAccessControl {
+    ///     entity: Entity::group("readers@example.com")?,
+    ///     role: Role::Reader,
+    /// };
+    /// bucket.acl_create(&new_acl)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn acl_create(
+        &self,
+        new_acl: &NewBucketAccessControl,
+    ) -> Result<BucketAccessControl, Error> {
+        crate::Client::global().bucket().acl_create(self, new_acl)
+    }
+
+    /// Changes the role of the entity in `acl` in the ACL of this bucket. Only the role is sent to
+    /// Google, as a patch, so the other properties of the entry are left untouched.
+    ///
+    /// ### Important
+    /// Important: This method fails with a `400 Bad Request` response for buckets with uniform
+    /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
+    /// control access instead.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    /// use cloud_storage::bucket_access_control::{NewBucketAccessControl, Role, Entity};
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let acl = NewBucketAccessControl {
+    ///     entity: Entity::group("readers@example.com")?,
+    ///     role: Role::Writer,
+    /// };
+    /// bucket.acl_update(&acl)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn acl_update(&self, acl: &NewBucketAccessControl) -> Result<BucketAccessControl, Error> {
+        crate::Client::global().bucket().acl_update(self, acl)
+    }
+
+    /// Removes the entry for `entity` from the ACL of this bucket.
+    ///
+    /// ### Important
+    /// Important: This method fails with a `400 Bad Request` response for buckets with uniform
+    /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
+    /// control access instead.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    /// use cloud_storage::bucket_access_control::Entity;
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// bucket.acl_delete(&Entity::AllUsers)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn acl_delete(&self, entity: &Entity) -> Result<(), Error> {
+        crate::Client::global().bucket().acl_delete(self, entity)
+    }
+
+    /// Returns the default object ACL entries of this bucket, which are applied to new objects
+    /// that are created without an ACL of their own.
+    ///
+    /// ### Important
+    /// Important: This method fails with a `400 Bad Request` response for buckets with uniform
+    /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
+    /// control access instead.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let default_acls = bucket.default_object_acl_list()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn default_object_acl_list(&self) -> Result<Vec<DefaultObjectAccessControl>, Error> {
+        crate::Client::global()
+            .bucket()
+            .default_object_acl_list(self)
+    }
+
+    /// Adds a default object ACL entry to this bucket.
+    ///
+    /// ### Important
+    /// Important: This method fails with a `400 Bad Request` response for buckets with uniform
+    /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
+    /// control access instead.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    /// use cloud_storage::default_object_access_control::{
+    ///     NewDefaultObjectAccessControl, Role, Entity,
+    /// };
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let new_acl = NewDefaultObjectAccessControl {
+    ///     entity: Entity::AllUsers,
+    ///     role: Role::Reader,
+    /// };
+    /// bucket.default_object_acl_create(&new_acl)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn default_object_acl_create(
+        &self,
+        new_acl: &NewDefaultObjectAccessControl,
+    ) -> Result<DefaultObjectAccessControl, Error> {
+        crate::Client::global()
+            .bucket()
+            .default_object_acl_create(self, new_acl)
+    }
+
+    /// Changes the role of the entity in `acl` in the default object ACL of this bucket.
+    ///
+    /// ### Important
+    /// Important: This method fails with a `400 Bad Request` response for buckets with uniform
+    /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
+    /// control access instead.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    /// use cloud_storage::default_object_access_control::{
+    ///     NewDefaultObjectAccessControl, Role, Entity,
+    /// };
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// let acl = NewDefaultObjectAccessControl {
+    ///     entity: Entity::AllAuthenticatedUsers,
+    ///     role: Role::Owner,
+    /// };
+    /// bucket.default_object_acl_update(&acl)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn default_object_acl_update(
+        &self,
+        acl: &NewDefaultObjectAccessControl,
+    ) -> Result<DefaultObjectAccessControl, Error> {
+        crate::Client::global()
+            .bucket()
+            .default_object_acl_update(self, acl)
+    }
+
+    /// Removes the entry for `entity` from the default object ACL of this bucket.
+    ///
+    /// ### Important
+    /// Important: This method fails with a `400 Bad Request` response for buckets with uniform
+    /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
+    /// control access instead.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Bucket;
+    /// use cloud_storage::default_object_access_control::Entity;
+    ///
+    /// let bucket = Bucket::read("my-bucket")?;
+    /// bucket.default_object_acl_delete(&Entity::AllUsers)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn default_object_acl_delete(&self, entity: &Entity) -> Result<(), Error> {
+        crate::Client::global()
+            .bucket()
+            .default_object_acl_delete(self, entity)
     }
 
     fn _lock_retention_policy() {
@@ -845,6 +1798,7 @@ mod tests {
                     enabled: false,
                     locked_time: None,
                 },
+                public_access_prevention: Some(PublicAccessPrevention::Inherited),
             }),
             ..Default::default()
         };
@@ -853,42 +1807,344 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn create_with_builder() -> Result<(), Box<dyn std::error::Error>> {
+        dotenv::dotenv().ok();
+        let base_name = std::env::var("TEST_BUCKET")?;
+        let new_bucket = NewBucket::new(&format!("{}-test-create-with-builder", base_name))
+            .storage_class(StorageClass::Nearline)
+            .versioning(true)
+            .label("purpose", "testing")
+            .lifecycle_rule(Rule {
+                action: Action {
+                    r#type: ActionType::Delete,
+                    storage_class: None,
+                },
+                condition: Condition {
+                    age: Some(30),
+                    ..Default::default()
+                },
+            })
+            .cors(Cors {
+                origin: vec!["*".to_string()],
+                method: vec!["GET".to_string()],
+                response_header: vec![],
+                max_age_seconds: Some(3600),
+            })
+            .retention_period(60);
+        let bucket = Bucket::create(&new_bucket)?;
+        assert_eq!(bucket.storage_class, StorageClass::Nearline);
+        assert_eq!(
+            bucket.retention_policy.as_ref().unwrap().retention_period,
+            60
+        );
+        assert_eq!(bucket.lifecycle.as_ref().unwrap().rule.len(), 1);
+        bucket.delete()?;
+        Ok(())
+    }
+
+    #[test]
+    fn patch() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::create_test_bucket("test-patch");
+        let mut labels = std::collections::HashMap::new();
+        labels.insert("patched".to_string(), Some("yes".to_string()));
+        let patch = BucketPatch {
+            labels: Some(labels),
+            ..Default::default()
+        };
+        let patched = bucket.patch(&patch)?;
+        assert_eq!(patched.labels.as_ref().unwrap()["patched"], "yes");
+        assert_eq!(patched.storage_class, bucket.storage_class);
+        // `bucket` is now stale, so patching it again must fail
+        assert!(bucket.patch(&patch).is_err());
+        patched.delete()?;
+        Ok(())
+    }
+
+    #[test]
+    fn metageneration_preconditions() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::create_test_bucket("test-preconditions");
+        let unchanged = BucketPreconditions {
+            if_metageneration_not_match: Some(bucket.metageneration),
+            ..Default::default()
+        };
+        assert_eq!(
+            Bucket::read_with(&bucket.name, &unchanged)
+                .unwrap_err()
+                .status(),
+            Some(304)
+        );
+        let stale = BucketPreconditions {
+            if_metageneration_match: Some(bucket.metageneration + 1),
+            ..Default::default()
+        };
+        assert!(bucket
+            .update_with(&stale)
+            .unwrap_err()
+            .is_precondition_failed());
+        assert!(bucket
+            .clone()
+            .delete_with(&stale)
+            .unwrap_err()
+            .is_precondition_failed());
+        let current = BucketPreconditions {
+            if_metageneration_match: Some(bucket.metageneration),
+            ..Default::default()
+        };
+        bucket.delete_with(&current)?;
+        Ok(())
+    }
+
+    #[test]
+    fn preconditions_query() {
+        assert!(BucketPreconditions::default().query().is_empty());
+        let preconditions = BucketPreconditions {
+            if_metageneration_match: Some(3),
+            if_metageneration_not_match: Some(2),
+        };
+        assert_eq!(
+            preconditions.query(),
+            vec![
+                ("ifMetagenerationMatch", 3),
+                ("ifMetagenerationNotMatch", 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn autoclass() -> Result<(), Box<dyn std::error::Error>> {
+        dotenv::dotenv().ok();
+        let base_name = std::env::var("TEST_BUCKET")?;
+        let new_bucket = NewBucket::new(&format!("{}-test-autoclass", base_name))
+            .autoclass(Some(StorageClass::Archive));
+        let bucket = Bucket::create(&new_bucket)?;
+        let autoclass = bucket.autoclass.as_ref().unwrap();
+        assert!(autoclass.enabled);
+        assert_eq!(
+            autoclass.terminal_storage_class,
+            Some(StorageClass::Archive)
+        );
+        let patch = BucketPatch {
+            autoclass: Some(Autoclass::default()),
+            ..Default::default()
+        };
+        let bucket = bucket.patch(&patch)?;
+        assert!(!bucket.autoclass.as_ref().unwrap().enabled);
+        bucket.delete()?;
+        Ok(())
+    }
+
+    #[test]
+    fn soft_delete_and_restore() -> Result<(), Box<dyn std::error::Error>> {
+        dotenv::dotenv().ok();
+        let base_name = std::env::var("TEST_BUCKET")?;
+        let name = format!("{}-test-soft-delete", base_name);
+        let new_bucket = NewBucket::new(&name).soft_delete_retention(7 * 24 * 60 * 60);
+        let bucket = Bucket::create(&new_bucket)?;
+        let policy = bucket.soft_delete_policy.as_ref().unwrap();
+        assert_eq!(policy.retention_duration_seconds, 7 * 24 * 60 * 60);
+        let generation = bucket.generation.unwrap();
+        bucket.delete()?;
+        let bucket = Bucket::restore(&name, generation)?;
+        bucket.delete()?;
+        Ok(())
+    }
+
+    #[test]
+    fn dual_region_with_turbo_replication() -> Result<(), Box<dyn std::error::Error>> {
+        dotenv::dotenv().ok();
+        let base_name = std::env::var("TEST_BUCKET")?;
+        let new_bucket = NewBucket::new(&format!("{}-test-dual-region", base_name))
+            .dual_region(MultiRegion::Us, ["US-EAST1", "US-WEST1"])
+            .rpo(Rpo::AsyncTurbo);
+        let bucket = Bucket::create(&new_bucket)?;
+        assert_eq!(bucket.rpo, Some(Rpo::AsyncTurbo));
+        let patch = BucketPatch {
+            rpo: Some(Rpo::Default),
+            ..Default::default()
+        };
+        let bucket = bucket.patch(&patch)?;
+        assert_eq!(bucket.rpo, Some(Rpo::Default));
+        bucket.delete()?;
+        Ok(())
+    }
+
+    #[test]
+    fn exists() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        assert!(Bucket::exists(&bucket.name)?);
+        assert!(!Bucket::exists(&format!("{}-does-not-exist", bucket.name))?);
+        Ok(())
+    }
+
+    #[test]
+    fn bucket_patch_serializes_only_set_fields() {
+        let mut labels = std::collections::HashMap::new();
+        labels.insert("removed".to_string(), None);
+        let patch = BucketPatch {
+            labels: Some(labels),
+            storage_class: Some(StorageClass::Coldline),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            serde_json::json!({ "labels": { "removed": null }, "storageClass": "COLDLINE" })
+        );
+    }
+
+    #[test]
+    fn new_bucket_builder() {
+        let new_bucket = NewBucket::new("my-bucket")
+            .versioning(true)
+            .label("a", "b")
+            .uniform_bucket_level_access(true)
+            .public_access_prevention(PublicAccessPrevention::Enforced)
+            .default_kms_key("projects/p/locations/l/keyRings/r/cryptoKeys/k");
+        let json = serde_json::to_value(&new_bucket).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "name": "my-bucket",
+                "location": "US-EAST1",
+                "versioning": { "enabled": true },
+                "labels": { "a": "b" },
+                "iamConfiguration": {
+                    "uniformBucketLevelAccess": { "enabled": true },
+                    "publicAccessPrevention": "enforced"
+                },
+                "encryption": {
+                    "defaultKmsKeyName": "projects/p/locations/l/keyRings/r/cryptoKeys/k"
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn iam_configuration() {
+        let json = r#"{
+            "uniformBucketLevelAccess": {
+                "enabled": true,
+                "lockedTime": "2024-01-02T03:04:05.000Z"
+            },
+            "bucketPolicyOnly": { "enabled": true },
+            "publicAccessPrevention": "inherited"
+        }"#;
+        let config: IamConfiguration = serde_json::from_str(json).unwrap();
+        assert!(config.uniform_bucket_level_access.enabled);
+        assert_eq!(
+            config.uniform_bucket_level_access.locked_time,
+            Some("2024-01-02T03:04:05Z".parse().unwrap())
+        );
+        assert_eq!(
+            config.public_access_prevention,
+            Some(PublicAccessPrevention::Inherited)
+        );
+
+        let legacy: IamConfiguration =
+            serde_json::from_str(r#"{"publicAccessPrevention": "unspecified"}"#).unwrap();
+        assert!(!legacy.uniform_bucket_level_access.enabled);
+        assert_eq!(
+            serde_json::to_value(&legacy).unwrap(),
+            serde_json::json!({
+                "uniformBucketLevelAccess": { "enabled": false },
+                "publicAccessPrevention": "unspecified"
+            })
+        );
+    }
+
+    #[test]
+    fn storage_class_names() {
+        let classes = vec![
+            StorageClass::Standard,
+            StorageClass::DurableReducedAvailability,
+            StorageClass::Other("FROZEN".to_string()),
+        ];
+        let json = serde_json::to_string(&classes).unwrap();
+        assert_eq!(
+            json,
+            r#"["STANDARD","DURABLE_REDUCED_AVAILABILITY","FROZEN"]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<StorageClass>>(&json).unwrap(),
+            classes
+        );
+        assert_eq!("ARCHIVE".parse(), Ok(StorageClass::Archive));
+        assert_eq!(StorageClass::MultiRegional.to_string(), "MULTI_REGIONAL");
+
+        let condition: Condition =
+            serde_json::from_str(r#"{"matchesStorageClass":["NEARLINE","COLDLINE"]}"#).unwrap();
+        assert_eq!(
+            condition.matches_storage_class,
+            Some(vec![StorageClass::Nearline, StorageClass::Coldline])
+        );
+    }
+
     #[test]
     fn list() -> Result<(), Box<dyn std::error::Error>> {
         Bucket::list()?;
         Ok(())
     }
 
+    #[test]
+    fn list_with() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::create_test_bucket("test-list-with");
+        let project = &crate::Client::global()
+            .service_account()
+            .unwrap()
+            .project_id;
+        let buckets = Bucket::list_with(project, Some(&bucket.name), Some(1))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(buckets.iter().any(|b| b.name == bucket.name));
+        assert!(buckets.iter().all(|b| b.name.starts_with(&bucket.name)));
+        bucket.delete()?;
+        Ok(())
+    }
+
+    /// Creates the bucket `{FIXTURE_BUCKET}-{name}` through `client`.
+    #[cfg(feature = "testing")]
+    fn create_fixture_bucket(client: &crate::Client, name: &str) -> Result<Bucket, Error> {
+        client.bucket().create(&NewBucket {
+            name: format!("{}-{}", crate::FIXTURE_BUCKET, name),
+            ..Default::default()
+        })
+    }
+
+    #[cfg(feature = "testing")]
     #[test]
     fn read() -> Result<(), Box<dyn std::error::Error>> {
-        let bucket = crate::create_test_bucket("test-read");
-        let also_bucket = Bucket::read(&bucket.name)?;
+        let client = crate::replay_client("bucket_read");
+        let bucket = create_fixture_bucket(&client, "test-read")?;
+        let also_bucket = client.bucket().read(&bucket.name)?;
         assert_eq!(bucket, also_bucket);
-        bucket.delete()?;
-        assert!(also_bucket.delete().is_err());
+        client.bucket().delete(bucket)?;
+        assert!(client.bucket().delete(also_bucket).is_err());
         Ok(())
     }
 
+    #[cfg(feature = "testing")]
     #[test]
     fn update() -> Result<(), Box<dyn std::error::Error>> {
-        let mut bucket = crate::create_test_bucket("test-update");
+        let client = crate::replay_client("bucket_update");
+        let mut bucket = create_fixture_bucket(&client, "test-update")?;
         bucket.retention_policy = Some(RetentionPolicy {
             retention_period: 50,
-            effective_time: chrono::Utc::now() + chrono::Duration::seconds(50),
+            effective_time: "2026-10-18T12:00:50Z".parse()?,
             is_locked: Some(false),
         });
-        bucket.update()?;
-        let updated = Bucket::read(&bucket.name)?;
+        client.bucket().update(&bucket)?;
+        let updated = client.bucket().read(&bucket.name)?;
         assert_eq!(updated.retention_policy.unwrap().retention_period, 50);
-        bucket.delete()?;
+        client.bucket().delete(bucket)?;
         Ok(())
     }
 
     // used a lot throughout the other tests, but included for completeness
+    #[cfg(feature = "testing")]
     #[test]
     fn delete() -> Result<(), Box<dyn std::error::Error>> {
-        let bucket = crate::create_test_bucket("test-delete");
-        bucket.delete()?;
+        let client = crate::replay_client("bucket_delete");
+        let bucket = create_fixture_bucket(&client, "test-delete")?;
+        client.bucket().delete(bucket)?;
         Ok(())
     }
 
@@ -919,6 +2175,134 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn grant_and_revoke() {
+        let condition = IamCondition {
+            title: "temporary".to_string(),
+            description: None,
+            expression: "request.time < timestamp('2030-01-01T00:00:00Z')".to_string(),
+        };
+        let mut policy = IamPolicy {
+            bindings: vec![Binding {
+                role: IamRole::OBJECT_VIEWER,
+                members: vec!["user:a@example.com".to_string()],
+                condition: Some(condition),
+            }],
+            ..Default::default()
+        };
+        assert!(policy.grant(IamRole::OBJECT_VIEWER, "user:a@example.com"));
+        assert!(!policy.grant(IamRole::OBJECT_VIEWER, "user:a@example.com"));
+        assert!(policy.grant(IamRole::OBJECT_VIEWER, "allUsers"));
+        assert_eq!(policy.bindings.len(), 2);
+        assert_eq!(policy.bindings[1].members.len(), 2);
+
+        assert!(policy.revoke(&IamRole::OBJECT_VIEWER, "user:a@example.com"));
+        assert!(!policy.revoke(&IamRole::OBJECT_ADMIN, "allUsers"));
+        assert!(policy.revoke(&IamRole::OBJECT_VIEWER, "allUsers"));
+        // the conditional binding is untouched and the emptied one is removed
+        assert_eq!(policy.bindings.len(), 1);
+        assert!(policy.bindings[0].condition.is_some());
+    }
+
+    #[test]
+    fn iam_roles() {
+        let roles: Vec<IamRole> = serde_json::from_str(
+            r#"["roles/storage.admin", "roles/storage.legacyBucketOwner", "projects/p/roles/custom"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            roles,
+            vec![
+                IamRole::ADMIN,
+                IamRole::LEGACY_BUCKET_OWNER,
+                IamRole::Other("projects/p/roles/custom".to_string())
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&roles[2]).unwrap(),
+            r#""projects/p/roles/custom""#
+        );
+    }
+
+    #[test]
+    fn conditional_iam_binding() -> Result<(), Box<dyn std::error::Error>> {
+        dotenv::dotenv().ok();
+        let base_name = std::env::var("TEST_BUCKET")?;
+        // conditions can only be used in buckets with uniform bucket-level access
+        let new_bucket = NewBucket::new(&format!("{}-test-conditional-iam", base_name))
+            .uniform_bucket_level_access(true);
+        let bucket = Bucket::create(&new_bucket).or_else(|_| Bucket::read(&new_bucket.name))?;
+        let mut policy = bucket.get_iam_policy()?;
+        assert_eq!(policy.version, 3);
+        let binding = Binding {
+            role: IamRole::Standard(StandardIamRole::ObjectViewer),
+            members: vec!["allAuthenticatedUsers".to_string()],
+            condition: Some(IamCondition {
+                title: "public-prefix".to_string(),
+                description: Some("Only objects under public/".to_string()),
+                expression: format!(
+                    "resource.name.startsWith('projects/_/buckets/{}/objects/public/')",
+                    bucket.name
+                ),
+            }),
+        };
+        policy.version = 1;
+        policy.bindings.push(binding.clone());
+        let updated = bucket.set_iam_policy(&policy)?;
+        assert_eq!(updated.version, 3);
+        // a read-modify-write cycle keeps the condition
+        let read = bucket.get_iam_policy()?;
+        assert!(read.bindings.contains(&binding));
+        bucket.set_iam_policy(&read)?;
+        assert!(bucket.get_iam_policy()?.bindings.contains(&binding));
+        bucket.delete()?;
+        Ok(())
+    }
+
+    #[test]
+    fn acl() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::create_test_bucket("test-acl");
+        let new_acl = NewBucketAccessControl {
+            entity: Entity::AllUsers,
+            role: Role::Reader,
+        };
+        bucket.acl_create(&new_acl)?;
+        assert!(bucket
+            .acl_list()?
+            .iter()
+            .any(|acl| acl.entity == Entity::AllUsers));
+        let updated = bucket.acl_update(&NewBucketAccessControl {
+            entity: Entity::AllUsers,
+            role: Role::Writer,
+        })?;
+        assert_eq!(updated.role, Role::Writer);
+        bucket.acl_delete(&Entity::AllUsers)?;
+        bucket.delete()?;
+        Ok(())
+    }
+
+    #[test]
+    fn default_object_acl() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::create_test_bucket("test-default-object-acl");
+        let new_acl = NewDefaultObjectAccessControl {
+            entity: Entity::AllUsers,
+            role: Role::Reader,
+        };
+        bucket.default_object_acl_create(&new_acl)?;
+        assert!(bucket
+            .default_object_acl_list()?
+            .iter()
+            .any(|acl| acl.entity == Entity::AllUsers));
+        let updated = bucket.default_object_acl_update(&NewDefaultObjectAccessControl {
+            entity: Entity::AllUsers,
+            role: Role::Owner,
+        })?;
+        assert_eq!(updated.role, Role::Owner);
+        bucket.default_object_acl_delete(&Entity::AllUsers)?;
+        bucket.delete()?;
+        Ok(())
+    }
+
     #[test]
     fn test_iam_permission() -> Result<(), Box<dyn std::error::Error>> {
         let bucket = crate::create_test_bucket("test-test-ia-permission");
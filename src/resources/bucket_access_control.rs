@@ -1,5 +1,3 @@
-use crate::error::GoogleResponse;
-use crate::resources::common::ListResponse;
 pub use crate::resources::common::{Entity, ProjectTeam, Role};
 
 /// The BucketAccessControl resource represents the Access Control Lists (ACLs) for buckets within
@@ -19,7 +17,7 @@ pub use crate::resources::common::{Entity, ProjectTeam, Role};
 /// objects.
 /// * OWNERs are WRITERs, and they can get the acl property of a bucket, update a bucket, and call
 /// all BucketAccessControl methods on the bucket.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BucketAccessControl {
     /// The kind of item this is. For bucket access control entries, this is always storage#bucketAccessControl.
@@ -51,19 +49,23 @@ pub struct BucketAccessControl {
     /// The access permission for the entity.
     pub role: Role,
     /// The email address associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
     /// The ID for the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub entity_id: Option<String>,
     /// The domain associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub domain: Option<String>,
     /// The project team associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub project_team: Option<ProjectTeam>,
     /// HTTP 1.1 Entity tag for the access-control entry.
     pub etag: String,
 }
 
 /// Model that can be used to create a new BucketAccessControl object.
-#[derive(Debug, PartialEq, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NewBucketAccessControl {
     /// The entity holding the permission, in one of the following forms:
@@ -88,6 +90,7 @@ pub struct NewBucketAccessControl {
     pub role: Role,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl BucketAccessControl {
     /// Create a new `BucketAccessControl` using the provided `NewBucketAccessControl`, related to
     /// the `Bucket` provided by the `bucket_name` argument.
@@ -114,18 +117,9 @@ impl BucketAccessControl {
         bucket: &str,
         new_bucket_access_control: &NewBucketAccessControl,
     ) -> Result<Self, crate::Error> {
-        let url = format!("{}/b/{}/acl", crate::BASE_URL, bucket);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .post(&url)
-            .headers(crate::get_headers()?)
-            .json(new_bucket_access_control)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .bucket_access_control()
+            .create(bucket, new_bucket_access_control)
     }
 
     /// Returns all `BucketAccessControl`s related to this bucket.
@@ -144,17 +138,7 @@ impl BucketAccessControl {
     /// # }
     /// ```
     pub fn list(bucket: &str) -> Result<Vec<Self>, crate::Error> {
-        let url = format!("{}/b/{}/acl", crate::BASE_URL, bucket);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<ListResponse<Self>> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s.items),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().bucket_access_control().list(bucket)
     }
 
     /// Returns the ACL entry for the specified entity on the specified bucket.
@@ -173,17 +157,9 @@ impl BucketAccessControl {
     /// # }
     /// ```
     pub fn read(bucket: &str, entity: &Entity) -> Result<Self, crate::Error> {
-        let url = format!("{}/b/{}/acl/{}", crate::BASE_URL, bucket, entity);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .bucket_access_control()
+            .read(bucket, entity)
     }
 
     /// Update this `BucketAccessControl`.
@@ -203,18 +179,7 @@ impl BucketAccessControl {
     /// # }
     /// ```
     pub fn update(&self) -> Result<Self, crate::Error> {
-        let url = format!("{}/b/{}/acl/{}", crate::BASE_URL, self.bucket, self.entity);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .put(&url)
-            .headers(crate::get_headers()?)
-            .json(self)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().bucket_access_control().update(self)
     }
 
     /// Permanently deletes the ACL entry for the specified entity on the specified bucket.
@@ -234,14 +199,7 @@ impl BucketAccessControl {
     /// # }
     /// ```
     pub fn delete(self) -> Result<(), crate::Error> {
-        let url = format!("{}/b/{}/acl/{}", crate::BASE_URL, self.bucket, self.entity);
-        let client = reqwest::blocking::Client::new();
-        let response = client.delete(&url).headers(crate::get_headers()?).send()?;
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(crate::Error::Google(response.json()?))
-        }
+        crate::Client::global().bucket_access_control().delete(self)
     }
 }
 
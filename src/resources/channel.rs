@@ -1,23 +1,145 @@
-
-
+/// A notification channel that delivers
+/// [object change notifications](https://cloud.google.com/storage/docs/object-change-notification)
+/// for a bucket to a webhook.
+///
+/// Note that Google recommends using Pub/Sub notifications (see the `notification` module) for new
+/// applications.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Channel {
+    /// The kind of item this is. For channels, this is always `api#channel`.
+    pub kind: String,
+    /// The id of the channel, as chosen when it was created.
+    pub id: String,
+    /// An opaque id that identifies the resource that is being watched on this channel.
+    pub resource_id: String,
+    /// A version-specific identifier for the watched resource.
+    pub resource_uri: String,
+    /// The token that is sent along with every notification delivered on this channel.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// The time at which the channel stops delivering notifications, in milliseconds since the
+    /// Unix epoch.
+    #[serde(
+        default,
+        deserialize_with = "crate::from_str_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expiration: Option<i64>,
+}
+
+/// Use this struct to create new channels.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewChannel {
+    /// A unique id for the channel, for example a UUID.
     pub id: String,
-    pub resourceId: String,
+    /// The https address of the webhook that notifications are delivered to.
+    pub address: String,
+    /// An arbitrary string that is sent along with every notification in the
+    /// `X-Goog-Channel-Token` header, which can be used to verify that notifications are genuine.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// The time at which the channel should stop delivering notifications, in milliseconds since
+    /// the Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+pub(crate) struct WatchRequest<'a> {
+    #[serde(flatten)]
+    pub(crate) channel: &'a NewChannel,
+    pub(crate) r#type: &'static str,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Channel {
+    /// Starts watching all objects in `bucket` for changes. Every change is delivered to the
+    /// webhook at `new_channel.address` until the channel expires or is stopped.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::channel::{Channel, NewChannel};
+    ///
+    /// let new_channel = NewChannel {
+    ///     id: "my-channel".to_string(),
+    ///     address: "https://example.com/notifications".to_string(),
+    ///     token: Some("secret".to_string()),
+    ///     expiration: None,
+    /// };
+    /// let channel = Channel::watch_all("my_bucket", &new_channel)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch_all(bucket: &str, new_channel: &NewChannel) -> Result<Self, crate::Error> {
+        crate::Client::global()
+            .channel()
+            .watch_all(bucket, new_channel)
+    }
+
     /// Stop receiving object change notifications through this channel.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::channel::{Channel, NewChannel};
+    ///
+    /// # let new_channel = NewChannel {
+    /// #     id: "my-channel".to_string(),
+    /// #     address: "https://example.com/notifications".to_string(),
+    /// #     token: None,
+    /// #     expiration: None,
+    /// # };
+    /// let channel = Channel::watch_all("my_bucket", &new_channel)?;
+    /// channel.stop()?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn stop(&self) -> Result<(), crate::Error> {
-        let url = format!("{}/channels/stop", crate::BASE_URL);
-        let client = reqwest::blocking::Client::new();
-        let response = client
-            .post(&url)
-            .headers(crate::get_headers()?)
-            .send()?;
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(crate::Error::Google(response.json()?))
-        }
+        crate::Client::global().channel().stop(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_all_request() {
+        let new_channel = NewChannel {
+            id: "channel".to_string(),
+            address: "https://example.com/hook".to_string(),
+            token: None,
+            expiration: Some(1_600_000_000_000),
+        };
+        let request = WatchRequest {
+            channel: &new_channel,
+            r#type: "web_hook",
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "id": "channel",
+                "address": "https://example.com/hook",
+                "expiration": 1_600_000_000_000i64,
+                "type": "web_hook",
+            })
+        );
+    }
+
+    #[test]
+    fn deserialize() {
+        let channel: Channel = serde_json::from_str(
+            r#"{
+                "kind": "api#channel",
+                "id": "channel",
+                "resourceId": "abc",
+                "resourceUri": "https://www.googleapis.com/storage/v1/b/bucket/o",
+                "expiration": "1600000000000"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(channel.expiration, Some(1_600_000_000_000));
+        assert_eq!(channel.token, None);
     }
 }
@@ -1,8 +1,13 @@
+use crate::error::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::error::GoogleResponse;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::http::RequestExt;
 use serde::Serializer;
 use std::str::FromStr;
 
 /// Contains information about the team related to this `DefaultObjectAccessControls`
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProjectTeam {
     /// The project number.
@@ -47,7 +52,7 @@ impl FromStr for Team {
 }
 
 /// Any type of role we can encounter.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum Role {
     /// Full access.
@@ -58,6 +63,27 @@ pub enum Role {
     Reader,
 }
 
+/// The set of properties Google returns for a resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Projection {
+    /// Include all properties.
+    Full,
+    /// Omit the `acl` and `owner` properties. This makes responses smaller, and only requires
+    /// read permission on the metadata, not the permission to view the ACL.
+    NoAcl,
+}
+
+impl Projection {
+    /// The value of the `projection` query parameter.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Projection::Full => "full",
+            Projection::NoAcl => "noAcl",
+        }
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct ListResponse<T> {
@@ -66,6 +92,92 @@ pub(crate) struct ListResponse<T> {
     pub next_page_token: Option<String>,
 }
 
+/// Iterates over the items of a paginated list endpoint, requesting the next page from Google
+/// whenever the current one is exhausted. Iteration stops after the first error.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct ListIter<T> {
+    client: crate::Client,
+    url: String,
+    query: Vec<(&'static str, String)>,
+    items: std::vec::IntoIter<T>,
+    next_page_token: Option<String>,
+    finished: bool,
+    /// An error that occurred before the first page could be requested.
+    error: Option<Error>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: serde::de::DeserializeOwned> ListIter<T> {
+    pub fn new(client: &crate::Client, url: String, query: Vec<(&'static str, String)>) -> Self {
+        Self {
+            client: client.clone(),
+            url,
+            query,
+            items: Vec::new().into_iter(),
+            next_page_token: None,
+            finished: false,
+            error: None,
+        }
+    }
+
+    /// An iterator that only yields `error`.
+    pub fn failed(client: &crate::Client, error: Error) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::new(client, String::new(), Vec::new())
+        }
+    }
+
+    fn fetch(&mut self) -> Result<(), Error> {
+        let mut query = self.query.clone();
+        if let Some(page_token) = self.next_page_token.take() {
+            query.push(("pageToken", page_token));
+        }
+        let client = &self.client;
+        let result: GoogleResponse<ListResponse<T>> = client
+            .http()?
+            .get(&self.url)
+            .headers(client.headers()?)
+            .query(&client.user_project_query())
+            .query(&query)
+            .execute(client)?
+            .json()?;
+        match result {
+            GoogleResponse::Success(s) => {
+                self.items = s.items.into_iter();
+                self.finished = s.next_page_token.is_none();
+                self.next_page_token = s.next_page_token;
+                Ok(())
+            }
+            GoogleResponse::Error(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: serde::de::DeserializeOwned> Iterator for ListIter<T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            self.finished = true;
+            return Some(Err(e));
+        }
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(e) = self.fetch() {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
 /// An entity is used to represent a user or group of users that often have some kind of permission.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Entity {
@@ -90,6 +202,86 @@ pub enum Entity {
 
 use Entity::*;
 
+impl Entity {
+    /// A single user, identified by their email address, such as `jane@example.com`. Fails if
+    /// `email` is not an email address.
+    pub fn user(email: &str) -> Result<Self, Error> {
+        validate_email(email)?;
+        Ok(UserEmail(email.to_string()))
+    }
+
+    /// A group of users, identified by the email address of the group, such as
+    /// `readers@googlegroups.com`. Fails if `email` is not an email address.
+    pub fn group(email: &str) -> Result<Self, Error> {
+        validate_email(email)?;
+        Ok(GroupEmail(email.to_string()))
+    }
+
+    /// All users whose email address ends with `domain`, such as `example.com`. Fails if
+    /// `domain` is not a domain name.
+    pub fn domain(domain: &str) -> Result<Self, Error> {
+        let valid = domain.contains('.')
+            && domain
+                .split('.')
+                .all(|label| !label.is_empty() && is_name(label));
+        if !valid {
+            return Err(Error::Other(format!(
+                "invalid domain for an entity: {}",
+                domain
+            )));
+        }
+        Ok(Domain(domain.to_string()))
+    }
+
+    /// All members of `team` in the project with the given id or number, such as the owners of
+    /// `my-project`. Fails if `project` is not a project id or number.
+    pub fn project_team(team: Team, project: &str) -> Result<Self, Error> {
+        if project.is_empty() || !is_name(project) {
+            return Err(Error::Other(format!(
+                "invalid project for an entity: {}",
+                project
+            )));
+        }
+        Ok(Project(team, project.to_string()))
+    }
+
+    /// All users, including anonymous ones.
+    pub fn all_users() -> Self {
+        AllUsers
+    }
+
+    /// All users that are signed in to a Google account.
+    pub fn all_authenticated_users() -> Self {
+        AllAuthenticatedUsers
+    }
+}
+
+fn validate_email(email: &str) -> Result<(), Error> {
+    let valid = match email.split('@').collect::<Vec<_>>()[..] {
+        [local, domain] => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.chars().any(|c| c.is_whitespace() || c.is_control())
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Other(format!(
+            "invalid email address for an entity: {}",
+            email
+        )))
+    }
+}
+
+// letters, digits and hyphens, as used in domain labels and project ids
+fn is_name(name: &str) -> bool {
+    name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
 impl std::fmt::Display for Entity {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -134,8 +326,8 @@ impl<'de> serde::de::Visitor<'de> for EntityVisitor {
             ["group", rest @ ..] if is_email(rest) => GroupEmail(rest.join("-")),
             ["group", rest @ ..] => GroupId(rest.join("-")),
             ["domain", rest @ ..] => Domain(rest.join("-")),
-            ["project", team, project_id] => {
-                Project(Team::from_str(team).unwrap(), project_id.to_string())
+            ["project", team, rest @ ..] if !rest.is_empty() => {
+                Project(Team::from_str(team).map_err(E::custom)?, rest.join("-"))
             }
             ["allUsers"] => AllUsers,
             ["allAuthenticatedUsers"] => AllAuthenticatedUsers,
@@ -207,6 +399,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn constructors() {
+        assert_eq!(
+            Entity::user("jane@example.com").unwrap().to_string(),
+            "user-jane@example.com"
+        );
+        assert_eq!(
+            Entity::group("readers@googlegroups.com").unwrap(),
+            GroupEmail("readers@googlegroups.com".to_string())
+        );
+        assert_eq!(
+            Entity::domain("example.com").unwrap().to_string(),
+            "domain-example.com"
+        );
+        let owners = Entity::project_team(Team::Owners, "my-project").unwrap();
+        assert_eq!(owners.to_string(), "project-owners-my-project");
+        let json = serde_json::to_string(&owners).unwrap();
+        assert_eq!(serde_json::from_str::<Entity>(&json).unwrap(), owners);
+        assert_eq!(Entity::all_users().to_string(), "allUsers");
+        for invalid in &[
+            "",
+            "jane",
+            "@example.com",
+            "jane@",
+            "jane@example",
+            "a b@example.com",
+            "a@b@example.com",
+        ] {
+            assert!(Entity::user(invalid).is_err(), "{} is accepted", invalid);
+        }
+        for invalid in &[
+            "",
+            "example",
+            "me@example.com",
+            "example..com",
+            "example.com.",
+        ] {
+            assert!(Entity::domain(invalid).is_err(), "{} is accepted", invalid);
+        }
+        assert!(Entity::project_team(Team::Viewers, "").is_err());
+        assert!(Entity::project_team(Team::Viewers, "my project").is_err());
+        assert!(serde_json::from_str::<Entity>("\"project-admins-my-project\"").is_err());
+    }
+
     #[test]
     fn deserialize() {
         let str1 = "\"user-some id\"";
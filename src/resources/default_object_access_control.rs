@@ -1,11 +1,9 @@
-use crate::error::GoogleResponse;
-use crate::resources::common::ListResponse;
 pub use crate::resources::common::{Entity, ProjectTeam, Role};
 
 /// The DefaultObjectAccessControls resources represent the Access Control Lists (ACLs) applied to a
 /// new object within Google Cloud Storage when no ACL was provided for that object. ACLs let you
 /// specify who has access to your data and to what extent.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DefaultObjectAccessControl {
     /// The kind of item this is. For object access control entries, this is always
@@ -32,12 +30,16 @@ pub struct DefaultObjectAccessControl {
     /// The access permission for the entity.
     pub role: Role,
     /// The email address associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
     /// The ID for the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub entity_id: Option<String>,
     /// The domain associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub domain: Option<String>,
     /// The project team associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub project_team: Option<ProjectTeam>,
     /// HTTP 1.1 Entity tag for the access-control entry.
     pub etag: String,
@@ -48,7 +50,7 @@ pub struct DefaultObjectAccessControl {
 }
 
 /// Model that can be used to create a new DefaultObjectAccessControl object.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NewDefaultObjectAccessControl {
     /// The entity holding the permission, in one of the following forms:
@@ -73,6 +75,7 @@ pub struct NewDefaultObjectAccessControl {
     pub role: Role,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl DefaultObjectAccessControl {
     /// Create a new `DefaultObjectAccessControl` entry on the specified bucket.
     /// ### Important
@@ -99,21 +102,9 @@ impl DefaultObjectAccessControl {
         bucket: &str,
         new_acl: &NewDefaultObjectAccessControl,
     ) -> Result<Self, crate::Error> {
-        let url = format!("{}/b/{}/defaultObjectAcl", crate::BASE_URL, bucket);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .post(&url)
-            .headers(crate::get_headers()?)
-            .json(new_acl)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(mut s) => {
-                s.bucket = bucket.to_string();
-                Ok(s)
-            }
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .default_object_access_control()
+            .create(bucket, new_acl)
     }
 
     /// Retrieves default object ACL entries on the specified bucket.
@@ -131,24 +122,9 @@ impl DefaultObjectAccessControl {
     /// # }
     /// ```
     pub fn list(bucket: &str) -> Result<Vec<Self>, crate::Error> {
-        let url = format!("{}/b/{}/defaultObjectAcl", crate::BASE_URL, bucket);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<ListResponse<Self>> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s
-                .items
-                .into_iter()
-                .map(|item| DefaultObjectAccessControl {
-                    bucket: bucket.to_string(),
-                    ..item
-                })
-                .collect()),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .default_object_access_control()
+            .list(bucket)
     }
 
     /// Read a single `DefaultObjectAccessControl`.
@@ -170,25 +146,9 @@ impl DefaultObjectAccessControl {
     /// # }
     /// ```
     pub fn read(bucket: &str, entity: &Entity) -> Result<Self, crate::Error> {
-        let url = dbg!(format!(
-            "{}/b/{}/defaultObjectAcl/{}",
-            crate::BASE_URL,
-            bucket,
-            entity
-        ));
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(mut s) => {
-                s.bucket = bucket.to_string();
-                Ok(s)
-            }
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .default_object_access_control()
+            .read(bucket, entity)
     }
 
     /// Update the current `DefaultObjectAccessControl`.
@@ -208,26 +168,9 @@ impl DefaultObjectAccessControl {
     /// # }
     /// ```
     pub fn update(&self) -> Result<Self, crate::Error> {
-        let url = format!(
-            "{}/b/{}/defaultObjectAcl/{}",
-            crate::BASE_URL,
-            self.bucket,
-            self.entity
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .put(&url)
-            .headers(crate::get_headers()?)
-            .json(self)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(mut s) => {
-                s.bucket = self.bucket.to_string();
-                Ok(s)
-            }
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .default_object_access_control()
+            .update(self)
     }
 
     /// Delete this 'DefaultObjectAccessControl`.
@@ -246,19 +189,9 @@ impl DefaultObjectAccessControl {
     /// # }
     /// ```
     pub fn delete(self) -> Result<(), crate::Error> {
-        let url = format!(
-            "{}/b/{}/defaultObjectAcl/{}",
-            crate::BASE_URL,
-            self.bucket,
-            self.entity
-        );
-        let client = reqwest::blocking::Client::new();
-        let response = client.delete(&url).headers(crate::get_headers()?).send()?;
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(crate::Error::Google(response.json()?))
-        }
+        crate::Client::global()
+            .default_object_access_control()
+            .delete(self)
     }
 }
 
@@ -1,12 +1,10 @@
-use crate::error::GoogleResponse;
-
 /// The `HmacKey` resource represents an HMAC key within Cloud Storage. The resource consists of a
 /// secret and `HmacMeta`. HMAC keys can be used as credentials for service accounts. For more
 /// information, see HMAC Keys.
 ///
 /// Note that the `HmacKey` resource is only returned when you use `HmacKey::create`. Other
 /// methods, such as `HmacKey::read`, return the metadata portion of the HMAC key resource.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HmacKey {
     /// The kind of item this is. For HMAC keys, this is always `storage#hmacKey`.
@@ -17,8 +15,27 @@ pub struct HmacKey {
     pub secret: String,
 }
 
+/// The access id and secret of an HMAC key, which authenticate requests to the XML API in the
+/// same way AWS access keys authenticate requests to S3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HmacCredentials {
+    /// The access ID of the HMAC key, such as `GOOG1E...`.
+    pub access_id: String,
+    /// The secret of the HMAC key, which is only returned by Google when the key is created.
+    pub secret: String,
+}
+
+impl From<HmacKey> for HmacCredentials {
+    fn from(key: HmacKey) -> Self {
+        Self {
+            access_id: key.metadata.access_id,
+            secret: key.secret,
+        }
+    }
+}
+
 /// Contains information about an Hmac Key.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HmacMeta {
     /// The kind of item this is. For HMAC key metadata, this is always `storage#hmacKeyMetadata`.
@@ -55,9 +72,17 @@ pub enum HmacState {
     Deleted,
 }
 
-#[derive(serde::Deserialize)]
-struct ListResponse {
-    items: Vec<HmacMeta>,
+/// The outcome of `HmacKey::rotate`.
+#[derive(Debug)]
+pub struct HmacRotation {
+    /// The new key, including its secret, which Google does not return again.
+    pub key: HmacKey,
+    /// The keys that were active before the rotation and have been deactivated, or deleted if
+    /// that was requested.
+    pub retired: Vec<HmacMeta>,
+    /// The access ids of the keys that could not be retired, with the reason. These keys are
+    /// still usable, so they should be retired by hand or by another rotation.
+    pub failed: Vec<(String, crate::Error)>,
 }
 
 #[derive(serde::Serialize)]
@@ -67,10 +92,11 @@ struct UpdateRequest {
 }
 
 #[derive(serde::Serialize)]
-struct UpdateMeta {
-    state: HmacState,
+pub(crate) struct UpdateMeta {
+    pub(crate) state: HmacState,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl HmacKey {
     /// Creates a new HMAC key for the specified service account.
     ///
@@ -92,27 +118,59 @@ impl HmacKey {
     /// # }
     /// ```
     pub fn create() -> Result<Self, crate::Error> {
-        use reqwest::header::CONTENT_LENGTH;
+        crate::Client::global().hmac_key().create()
+    }
 
-        let url = format!(
-            "{}/projects/{}/hmacKeys",
-            crate::BASE_URL,
-            crate::SERVICE_ACCOUNT.project_id
-        );
-        let query = [("serviceAccountEmail", &crate::SERVICE_ACCOUNT.client_email)];
-        let mut headers = crate::get_headers()?;
-        headers.insert(CONTENT_LENGTH, 0.into());
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .post(&url)
-            .headers(headers)
-            .query(&query)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+    /// Creates a new HMAC key for the service account identified by `service_account_email`, in
+    /// the project of the service account that is used to authenticate. This allows you to hand
+    /// out S3-interoperable credentials for a dedicated service account with limited permissions.
+    ///
+    /// The secret of the key is only returned by this call, so make sure to store it right away.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::hmac_key::HmacKey;
+    ///
+    /// let hmac_key = HmacKey::create_for("uploader@my-project.iam.gserviceaccount.com")?;
+    /// println!("{}: {}", hmac_key.metadata.access_id, hmac_key.secret);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_for(service_account_email: &str) -> Result<Self, crate::Error> {
+        crate::Client::global()
+            .hmac_key()
+            .create_for(service_account_email)
+    }
+
+    /// Replaces the active HMAC keys of the service account identified by
+    /// `service_account_email` with a new key. The new key is created first; then every key of
+    /// the service account that was active before is deactivated, and deleted as well if
+    /// `delete_old` is set. Deactivated keys can be activated again with `HmacKey::update` if a
+    /// client turns out to still need them, deleted keys cannot.
+    ///
+    /// Only a failure to create the new key is returned as an error. Keys that could not be
+    /// retired are listed in `HmacRotation::failed`, so that the secret of the new key is never
+    /// lost.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::hmac_key::HmacKey;
+    ///
+    /// let rotation = HmacKey::rotate("uploader@my-project.iam.gserviceaccount.com", false)?;
+    /// println!("{}: {}", rotation.key.metadata.access_id, rotation.key.secret);
+    /// for (access_id, error) in &rotation.failed {
+    ///     eprintln!("could not deactivate {}: {}", access_id, error);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rotate(
+        service_account_email: &str,
+        delete_old: bool,
+    ) -> Result<HmacRotation, crate::Error> {
+        crate::Client::global()
+            .hmac_key()
+            .rotate(service_account_email, delete_old)
     }
 
     /// Retrieves a list of HMAC keys matching the criteria. Since the HmacKey is secret, this does
@@ -134,21 +192,31 @@ impl HmacKey {
     /// # }
     /// ```
     pub fn list() -> Result<Vec<HmacMeta>, crate::Error> {
-        let url = format!(
-            "{}/projects/{}/hmacKeys",
-            crate::BASE_URL,
-            crate::SERVICE_ACCOUNT.project_id
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<ListResponse> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s.items),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().hmac_key().list()
+    }
+
+    /// Lazily lists the HMAC keys of the project, fetching additional pages as needed. If
+    /// `service_account_email` is provided, only the keys of that service account are returned.
+    /// Keys in the `Deleted` state are only included if `show_deleted_keys` is set.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::hmac_key::HmacKey;
+    ///
+    /// let email = "uploader@my-project.iam.gserviceaccount.com";
+    /// for key in HmacKey::list_with(Some(email), false) {
+    ///     println!("{}", key?.access_id);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_with(
+        service_account_email: Option<&str>,
+        show_deleted_keys: bool,
+    ) -> impl Iterator<Item = Result<HmacMeta, crate::Error>> {
+        crate::Client::global()
+            .hmac_key()
+            .list_with(service_account_email, show_deleted_keys)
     }
 
     /// Retrieves an HMAC key's metadata. Since the HmacKey is secret, this does not return a
@@ -169,22 +237,7 @@ impl HmacKey {
     /// # Ok(())
     /// # }
     pub fn read(access_id: &str) -> Result<HmacMeta, crate::Error> {
-        let url = format!(
-            "{}/projects/{}/hmacKeys/{}",
-            crate::BASE_URL,
-            crate::SERVICE_ACCOUNT.project_id,
-            access_id
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<HmacMeta> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().hmac_key().read(access_id)
     }
 
     /// Updates the state of an HMAC key. See the HMAC Key resource descriptor for valid states.
@@ -205,24 +258,7 @@ impl HmacKey {
     /// # Ok(())
     /// # }
     pub fn update(access_id: &str, state: HmacState) -> Result<HmacMeta, crate::Error> {
-        let url = format!(
-            "{}/projects/{}/hmacKeys/{}",
-            crate::BASE_URL,
-            crate::SERVICE_ACCOUNT.project_id,
-            access_id
-        );
-        let client = reqwest::blocking::Client::new();
-        serde_json::to_string(&UpdateMeta { state })?;
-        let result: GoogleResponse<HmacMeta> = client
-            .put(&url)
-            .headers(crate::get_headers()?)
-            .json(&UpdateMeta { state })
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().hmac_key().update(access_id, state)
     }
 
     /// Deletes an HMAC key. Note that a key must be set to `Inactive` first.
@@ -242,19 +278,7 @@ impl HmacKey {
     /// # Ok(())
     /// # }
     pub fn delete(access_id: &str) -> Result<(), crate::Error> {
-        let url = format!(
-            "{}/projects/{}/hmacKeys/{}",
-            crate::BASE_URL,
-            crate::SERVICE_ACCOUNT.project_id,
-            access_id
-        );
-        let client = reqwest::blocking::Client::new();
-        let response = client.delete(&url).headers(crate::get_headers()?).send()?;
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(crate::Error::Google(response.json()?))
-        }
+        crate::Client::global().hmac_key().delete(access_id)
     }
 }
 
@@ -287,6 +311,53 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn list_with() -> Result<(), Box<dyn std::error::Error>> {
+        let key = HmacKey::create_for(
+            &crate::Client::global()
+                .service_account()
+                .unwrap()
+                .client_email,
+        )?;
+        let email = &key.metadata.service_account_email;
+        let keys = HmacKey::list_with(Some(email), false).collect::<Result<Vec<_>, _>>()?;
+        assert!(keys.iter().any(|k| k.access_id == key.metadata.access_id));
+        assert!(keys.iter().all(|k| &k.service_account_email == email));
+        remove_test_hmac(&key.metadata.access_id);
+        Ok(())
+    }
+
+    #[test]
+    fn rotate() -> Result<(), Box<dyn std::error::Error>> {
+        let email = crate::Client::global()
+            .service_account()
+            .unwrap()
+            .client_email
+            .clone();
+        let old = HmacKey::create_for(&email)?;
+        let rotation = HmacKey::rotate(&email, false)?;
+        assert!(rotation.failed.is_empty());
+        assert!(rotation
+            .retired
+            .iter()
+            .any(|k| k.access_id == old.metadata.access_id));
+        assert!(rotation
+            .retired
+            .iter()
+            .all(|k| k.state == HmacState::Inactive));
+        assert_eq!(
+            HmacKey::read(&old.metadata.access_id)?.state,
+            HmacState::Inactive
+        );
+        assert_eq!(
+            HmacKey::read(&rotation.key.metadata.access_id)?.state,
+            HmacState::Active
+        );
+        HmacKey::delete(&old.metadata.access_id)?;
+        remove_test_hmac(&rotation.key.metadata.access_id);
+        Ok(())
+    }
+
     #[test]
     fn read() -> Result<(), Box<dyn std::error::Error>> {
         let key = get_test_hmac();
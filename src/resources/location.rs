@@ -1,5 +1,5 @@
 /// Deeply nested enum that represents a location where a bucket might store its files.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
 pub enum Location {
     /// Objects are stored in a single location.
@@ -8,6 +8,10 @@ pub enum Location {
     Multi(MultiRegion),
     /// Objects are stored redundantly accross two locations.
     Dual(DualRegion),
+    /// Any other location by its name, such as `ME-CENTRAL2`, for locations that are not listed
+    /// here yet. Buckets with such a location can still be read, and `Location::validate` checks
+    /// that the name is well-formed before a bucket is created in it.
+    Other(String),
 }
 
 impl Default for Location {
@@ -16,8 +20,67 @@ impl Default for Location {
     }
 }
 
+impl Location {
+    /// The name of the location as Google knows it, such as `US-EAST1`, `NAM4` or `EU`.
+    pub fn name(&self) -> String {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => name,
+            _ => unreachable!("locations serialize to strings"),
+        }
+    }
+
+    /// Checks that the name of an `Other` location has the shape of a region, such as
+    /// `us-west4`, or of a dual-region or multi-region, such as `NAM4` or `EU`. This catches
+    /// typos before a request is made, but does not guarantee that Google knows the location.
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        let name = match self {
+            Location::Other(name) => name,
+            _ => return Ok(()),
+        };
+        // letters followed by up to `digits` digits
+        let is_code = |part: &str, digits: usize| {
+            let letters = part.trim_end_matches(|c: char| c.is_ascii_digit());
+            !letters.is_empty()
+                && letters.chars().all(|c| c.is_ascii_alphabetic())
+                && part.len() - letters.len() <= digits
+        };
+        let valid = match name.split('-').collect::<Vec<_>>()[..] {
+            // multi-regions and dual-regions, such as EU and NAM4
+            [code] => (2..=6).contains(&code.len()) && is_code(code, 1),
+            // regions, such as us-east1
+            [area, region] => {
+                is_code(area, 0)
+                    && is_code(region, 2)
+                    && region.ends_with(|c: char| c.is_ascii_digit())
+            }
+            _ => false,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(crate::Error::Other(format!(
+                "invalid location {}: expected a region such as us-east1, a dual-region such as NAM4 or a multi-region such as EU",
+                name
+            )))
+        }
+    }
+}
+
+impl std::str::FromStr for Location {
+    type Err = crate::Error;
+
+    /// Parses the name of a location, regardless of its case. Names that are not listed in this
+    /// module become `Location::Other` if they are well-formed.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = serde_json::Value::String(name.to_ascii_uppercase());
+        let location: Location = serde_json::from_value(name)?;
+        location.validate()?;
+        Ok(location)
+    }
+}
+
 /// The possible options for single regions.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
 pub enum SingleRegion {
     /// All options in North America.
@@ -33,7 +96,7 @@ pub enum SingleRegion {
 }
 
 /// All options in North America.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum NALocation {
     /// Store the files in Montréal.
     #[serde(rename = "NORTHAMERICA-NORTHEAST1")]
@@ -56,7 +119,7 @@ pub enum NALocation {
 }
 
 /// All options in South America.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum SALocation {
     /// Store the files in Soa Paulo.
     #[serde(rename = "SOUTHAMERICA-EAST1")]
@@ -64,7 +127,7 @@ pub enum SALocation {
 }
 
 /// All options in Europe.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum EuropeLocation {
     /// Store the files in Finland.
     #[serde(rename = "EUROPE-NORTH1")]
@@ -87,7 +150,7 @@ pub enum EuropeLocation {
 }
 
 /// ALl options in Asia.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum AsiaLocation {
     /// Store the files in Taiwan.
     #[serde(rename = "ASIA-EAST1")]
@@ -110,7 +173,7 @@ pub enum AsiaLocation {
 }
 
 /// All options in Australia.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum AusLocation {
     /// Store the files in Sydney.
     #[serde(rename = "AUSTRALIA-SOUTHEAST1")]
@@ -118,7 +181,7 @@ pub enum AusLocation {
 }
 
 /// The possible options for multi-region storage.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum MultiRegion {
     /// Data centers in Asia
@@ -133,11 +196,43 @@ pub enum MultiRegion {
 }
 
 /// The possible options for dual-region storage
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum DualRegion {
     /// EUROPE-NORTH1 and EUROPE-WEST4. Additionally, object metadata may be stored in EUROPE-WEST1.
     Eur4,
     /// US-CENTRAL1 and US-EAST1. Additionally, object metadata may be stored in Tulsa, Oklahoma.
     Nam4,
+    /// ASIA-NORTHEAST1 and ASIA-NORTHEAST2.
+    Asia1,
+    /// EUROPE-WEST1 and EUROPE-WEST2.
+    Eur5,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_validate() {
+        let parse = |name: &str| name.parse::<Location>();
+        assert_eq!(parse("eu").unwrap(), Location::Multi(MultiRegion::Eu));
+        assert_eq!(parse("NAM4").unwrap(), Location::Dual(DualRegion::Nam4));
+        assert_eq!(
+            parse("us-west1").unwrap(),
+            Location::Single(SingleRegion::NorthAmerica(NALocation::Oregon))
+        );
+        assert_eq!(
+            parse("me-central2").unwrap(),
+            Location::Other("ME-CENTRAL2".to_string())
+        );
+        assert_eq!(parse("EUR7").unwrap().name(), "EUR7");
+        for invalid in &["", "us east1", "us-east", "europe-west-1", "eu44", "u"] {
+            assert!(parse(invalid).is_err(), "{} is accepted", invalid);
+        }
+        assert_eq!(
+            Location::Single(SingleRegion::Europe(EuropeLocation::Zurich)).name(),
+            "EUROPE-WEST6"
+        );
+    }
 }
@@ -3,22 +3,26 @@
 pub mod bucket;
 /// A Bucket Access Control object can be used to configure access on a bucket-wide level.
 pub mod bucket_access_control;
+/// A channel that delivers object change notifications to a webhook.
+pub mod channel;
 /// Commonly used types.
-mod common;
+pub(crate) mod common;
 /// Default Object Access Control objects can be used the configure access that is used as a
 /// fallback in the abscence of more specific data.
 pub mod default_object_access_control;
 /// An Hmac key is a secret key stored in Cloud Storage.
 pub mod hmac_key;
 /// A location where a bucket can exists physically.
-mod location;
-// /// A subscription to receive
-// /// [Pub/Sub notifications](https://cloud.google.com/storage/docs/pubsub-notifications).
-// pub mod notification;
+pub(crate) mod location;
+/// A subscription to receive
+/// [Pub/Sub notifications](https://cloud.google.com/storage/docs/pubsub-notifications).
+pub mod notification;
 /// A file
 pub mod object;
 /// Contains data about to access specific files.
 pub mod object_access_control;
+/// The Cloud Storage service agent of a project.
+pub mod project;
 /// A deserialized version of the `service-account-********.json` file. Used to authenticate
 /// requests.
 pub mod service_account;
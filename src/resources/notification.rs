@@ -1,61 +1,165 @@
-use crate::error::GoogleResponse;
-use crate::resources::common::ListResponse;
 pub use crate::resources::topic::Topic;
 
 /// A subscription to receive
 /// [Pub/Sub notifications](https://cloud.google.com/storage/docs/pubsub-notifications).
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Notification {
     /// The ID of the notification.
-    id: String,
+    pub id: String,
     /// The Pub/Sub topic to which this subscription publishes. Formatted as:
     /// `'//pubsub.googleapis.com/projects/{project-identifier}/topics/{my-topic}'`.
-    topic: Topic,
+    pub topic: Topic,
     /// If present, only send notifications about listed event types. If empty, send notifications
     /// for all event types.
-    event_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_types: Option<Vec<EventType>>,
     /// An optional list of additional attributes to attach to each Pub/Sub message published
     /// for this notification subscription.
-    custom_attributes: Option<std::collections::HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_attributes: Option<std::collections::HashMap<String, String>>,
     /// The desired content of the Payload.
-    ///
-    /// Acceptable values are:
-    /// * "JSON_API_V1"
-    /// * "NONE"
-    payload_format: String,
+    pub payload_format: PayloadFormat,
     /// If present, only apply this notification configuration to object names that begin with this
     /// prefix.
-    object_name_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_name_prefix: Option<String>,
     /// HTTP 1.1 Entity tag for this subscription notification.
-    etag: String,
+    pub etag: String,
     /// The canonical URL of this notification.
-    #[serde(rename = "selfLink")]
-    self_link: String,
-    /// The kind of item this is. For notifications, this is always `storage#notification`.   
-    kind: String,
+    pub self_link: String,
+    /// The kind of item this is. For notifications, this is always `storage#notification`.
+    pub kind: String,
 }
 
 /// Use this struct to create new notifications.
-#[derive(Debug, PartialEq, Default, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct NewNotification {
     /// The Pub/Sub topic to which this subscription publishes. Formatted as:
     /// `'//pubsub.googleapis.com/projects/{project-identifier}/topics/{my-topic}'`.
-    topic: String,
+    pub topic: Topic,
     /// If present, only send notifications about listed event types. If empty, send notifications
     /// for all event types.
-    event_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_types: Option<Vec<EventType>>,
     /// An optional list of additional attributes to attach to each Pub/Sub message published
     /// for this notification subscription.
-    custom_attributes: Option<std::collections::HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_attributes: Option<std::collections::HashMap<String, String>>,
     /// The desired content of the Payload.
-    payload_format: Option<PayloadFormat>,
+    pub payload_format: PayloadFormat,
     /// If present, only apply this notification configuration to object names that begin with this
     /// prefix.
-    object_name_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_name_prefix: Option<String>,
+}
+
+impl NewNotification {
+    /// Prepares a notification that publishes the metadata of objects that change in any way to
+    /// `topic`. Use the other methods to narrow it down before creating it.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::notification::{EventType, NewNotification, Notification, Topic};
+    ///
+    /// let new_notification = NewNotification::new(Topic {
+    ///     project_id: "my-project".to_string(),
+    ///     topic: "my-topic".to_string(),
+    /// })
+    /// .event_type(EventType::ObjectFinalize)
+    /// .object_name_prefix("uploads/")
+    /// .custom_attribute("team", "storage");
+    /// let notification = Notification::create("my_bucket", &new_notification)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(topic: Topic) -> Self {
+        Self {
+            topic,
+            event_types: None,
+            custom_attributes: None,
+            payload_format: PayloadFormat::JsonApiV1,
+            object_name_prefix: None,
+        }
+    }
+
+    /// Adds an event type to send notifications about. Without any, notifications are sent for
+    /// all event types.
+    pub fn event_type(mut self, event_type: EventType) -> Self {
+        let event_types = self.event_types.get_or_insert_with(Vec::new);
+        if !event_types.contains(&event_type) {
+            event_types.push(event_type);
+        }
+        self
+    }
+
+    /// Sets the content of the messages.
+    pub fn payload_format(mut self, payload_format: PayloadFormat) -> Self {
+        self.payload_format = payload_format;
+        self
+    }
+
+    /// Only sends notifications about objects whose name starts with `prefix`.
+    pub fn object_name_prefix(mut self, prefix: &str) -> Self {
+        self.object_name_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Adds an attribute to every message. `validate` checks that it can be attached.
+    pub fn custom_attribute(mut self, key: &str, value: &str) -> Self {
+        self.custom_attributes
+            .get_or_insert_with(Default::default)
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Checks that the notification can be created as intended: the custom attributes must be
+    /// accepted by Pub/Sub and must not be hidden by the attributes that Google sets itself, and
+    /// an empty list of event types, which Google treats as all event types, is rejected. This is
+    /// done by `Notification::create` as well.
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        if self.event_types.as_ref().is_some_and(Vec::is_empty) {
+            return Err(crate::Error::Other(
+                "the list of event types is empty; use None to receive all event types".to_string(),
+            ));
+        }
+        for (key, value) in self.custom_attributes.iter().flatten() {
+            let problem = if key.is_empty() || key.len() > 256 {
+                "keys must be 1 to 256 bytes long"
+            } else if key.starts_with("goog") {
+                "keys must not start with goog"
+            } else if RESERVED_ATTRIBUTES.contains(&key.as_str()) {
+                "this attribute is set by Google"
+            } else if value.len() > 1024 {
+                "values must be at most 1024 bytes long"
+            } else {
+                continue;
+            };
+            return Err(crate::Error::Other(format!(
+                "invalid custom attribute {}: {}",
+                key, problem
+            )));
+        }
+        Ok(())
+    }
 }
 
+/// The attributes that Google attaches to every message.
+const RESERVED_ATTRIBUTES: &[&str] = &[
+    "notificationConfig",
+    "eventType",
+    "payloadFormat",
+    "bucketId",
+    "objectId",
+    "objectGeneration",
+    "eventTime",
+    "overwroteGeneration",
+    "overwrittenByGeneration",
+];
+
 /// Various ways of having the response formatted.
-#[derive(Debug, PartialEq, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum PayloadFormat {
     /// Respond with a format as specified in the Json API V1 documentation.
@@ -64,73 +168,248 @@ pub enum PayloadFormat {
     None,
 }
 
+/// The kinds of changes to objects that a `Notification` can be restricted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EventType {
+    /// Sent when a new object (or a new generation of an existing object) is successfully created
+    /// in the bucket.
+    ObjectFinalize,
+    /// Sent when the metadata of an existing object changes.
+    ObjectMetadataUpdate,
+    /// Sent when an object has been permanently deleted.
+    ObjectDelete,
+    /// Only sent when a bucket has enabled object versioning. This event indicates that the live
+    /// version of an object has become a noncurrent version.
+    ObjectArchive,
+}
+
+impl std::str::FromStr for EventType {
+    type Err = crate::Error;
+
+    /// Parses the name Google uses for an event type, such as `OBJECT_FINALIZE`. Unknown names
+    /// are an error.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(name.to_string()))
+            .map_err(|_| crate::Error::Other(format!("unknown event type {}", name)))
+    }
+}
+
+impl std::str::FromStr for PayloadFormat {
+    type Err = crate::Error;
+
+    /// Parses the name Google uses for a payload format, such as `JSON_API_V1`. Unknown names are
+    /// an error.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(name.to_string()))
+            .map_err(|_| crate::Error::Other(format!("unknown payload format {}", name)))
+    }
+}
+
+/// A change to an object, as published to Pub/Sub by a `Notification`. Parse the messages that a
+/// subscription to its topic receives with `Event::from_message`, or the requests of a push
+/// subscription with `Event::from_push`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// The kind of change.
+    pub event_type: EventType,
+    /// The canonical url of the `Notification` that published this event.
+    pub notification_config: String,
+    /// The name of the bucket that contains the object.
+    pub bucket: String,
+    /// The name of the object.
+    pub object_name: String,
+    /// The generation of the object that the event is about.
+    pub generation: i64,
+    /// The moment the change happened.
+    pub event_time: chrono::DateTime<chrono::Utc>,
+    /// For `ObjectFinalize` events, the generation of the object that was replaced, if any.
+    pub overwrote_generation: Option<i64>,
+    /// For `ObjectArchive` and `ObjectDelete` events, the generation of the object that replaced
+    /// this one, if any.
+    pub overwritten_by_generation: Option<i64>,
+    /// The metadata of the object, if the notification uses `PayloadFormat::JsonApiV1`. For
+    /// `ObjectDelete` events, this is the metadata just before the object was deleted.
+    pub object: Option<crate::Object>,
+    /// The attributes of the message that are not parsed into the fields above, such as the
+    /// `custom_attributes` of the notification.
+    pub custom_attributes: std::collections::HashMap<String, String>,
+}
+
+impl Event {
+    /// Parses a Pub/Sub message, given its attributes and its data, which must already be
+    /// decoded from base64.
+    /// ### Example
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::notification::{Event, EventType};
+    /// use std::collections::HashMap;
+    ///
+    /// let attributes: HashMap<String, String> = vec![
+    ///     ("notificationConfig", "projects/_/buckets/my_bucket/notificationConfigs/1"),
+    ///     ("eventType", "OBJECT_DELETE"),
+    ///     ("payloadFormat", "NONE"),
+    ///     ("bucketId", "my_bucket"),
+    ///     ("objectId", "uploads/cat.png"),
+    ///     ("objectGeneration", "1600000000000000"),
+    ///     ("eventTime", "2020-09-13T12:26:40.000000Z"),
+    /// ]
+    /// .into_iter()
+    /// .map(|(key, value)| (key.to_string(), value.to_string()))
+    /// .collect();
+    /// let event = Event::from_message(&attributes, &[])?;
+    /// assert_eq!(event.event_type, EventType::ObjectDelete);
+    /// assert_eq!(event.object_name, "uploads/cat.png");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_message(
+        attributes: &std::collections::HashMap<String, String>,
+        data: &[u8],
+    ) -> Result<Self, crate::Error> {
+        let mut attributes = attributes.clone();
+        let mut take = |name: &str| {
+            attributes
+                .remove(name)
+                .ok_or_else(|| crate::Error::Other(format!("missing attribute {}", name)))
+        };
+        let parse_generation = |value: String| {
+            value
+                .parse()
+                .map_err(|_| crate::Error::Other(format!("invalid generation {}", value)))
+        };
+        let event_type = serde_json::from_value(serde_json::Value::String(take("eventType")?))?;
+        let event_time = take("eventTime")?;
+        let event_time = chrono::DateTime::parse_from_rfc3339(&event_time)
+            .map_err(|e| crate::Error::Other(format!("invalid event time {}: {}", event_time, e)))?
+            .into();
+        let notification_config = take("notificationConfig")?;
+        let bucket = take("bucketId")?;
+        let object_name = take("objectId")?;
+        let generation = parse_generation(take("objectGeneration")?)?;
+        let overwrote_generation = take("overwroteGeneration")
+            .ok()
+            .map(parse_generation)
+            .transpose()?;
+        let overwritten_by_generation = take("overwrittenByGeneration")
+            .ok()
+            .map(parse_generation)
+            .transpose()?;
+        let payload_format = take("payloadFormat").ok();
+        let object = match payload_format.as_deref() {
+            Some("JSON_API_V1") if !data.is_empty() => Some(serde_json::from_slice(data)?),
+            _ => None,
+        };
+        Ok(Self {
+            event_type,
+            notification_config,
+            bucket,
+            object_name,
+            generation,
+            event_time,
+            overwrote_generation,
+            overwritten_by_generation,
+            object,
+            custom_attributes: attributes,
+        })
+    }
+
+    /// Parses the body of a request that a Pub/Sub
+    /// [push subscription](https://cloud.google.com/pubsub/docs/push) sends to an endpoint.
+    pub fn from_push(body: &[u8]) -> Result<Self, crate::Error> {
+        #[derive(serde::Deserialize)]
+        struct PushRequest {
+            message: PushMessage,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct PushMessage {
+            #[serde(default)]
+            attributes: std::collections::HashMap<String, String>,
+            #[serde(default)]
+            data: String,
+        }
+
+        let request: PushRequest = serde_json::from_slice(body)?;
+        let data = base64::decode(&request.message.data)
+            .map_err(|e| crate::Error::Other(format!("invalid message data: {}", e)))?;
+        Self::from_message(&request.message.attributes, &data)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 impl Notification {
     /// Creates a notification subscription for a given bucket.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::notification::{EventType, NewNotification, Notification};
+    /// use cloud_storage::notification::{PayloadFormat, Topic};
+    ///
+    /// let new_notification = NewNotification {
+    ///     topic: Topic {
+    ///         project_id: "my-project".to_string(),
+    ///         topic: "my-topic".to_string(),
+    ///     },
+    ///     event_types: Some(vec![EventType::ObjectFinalize]),
+    ///     custom_attributes: None,
+    ///     payload_format: PayloadFormat::JsonApiV1,
+    ///     object_name_prefix: Some("uploads/".to_string()),
+    /// };
+    /// let notification = Notification::create("my_bucket", &new_notification)?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn create(bucket: &str, new_notification: &NewNotification) -> Result<Self, crate::Error> {
-        let url = format!("{}/b/{}/notificationConfigs", crate::BASE_URL, bucket);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .post(&url)
-            .headers(crate::get_headers()?)
-            .json(new_notification)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .notification()
+            .create(bucket, new_notification)
     }
 
     /// View a notification configuration.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::notification::Notification;
+    ///
+    /// let notification = Notification::read("my_bucket", "1")?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn read(bucket: &str, notification: &str) -> Result<Self, crate::Error> {
-        let url = format!(
-            "{}/b/{}/notificationConfigs/{}",
-            crate::BASE_URL,
-            bucket,
-            notification
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .notification()
+            .read(bucket, notification)
     }
 
-    /// Retrieves a list of notification subscriptions for a given bucket.}
+    /// Retrieves a list of notification subscriptions for a given bucket.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::notification::Notification;
+    ///
+    /// let notifications = Notification::list("my_bucket")?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn list(bucket: &str) -> Result<Vec<Self>, crate::Error> {
-        let url = format!("{}/v1/b/{}/notificationConfigs", crate::BASE_URL, bucket);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<ListResponse<Self>> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s.items),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().notification().list(bucket)
     }
 
     /// Permanently deletes a notification subscription.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::notification::Notification;
+    ///
+    /// Notification::delete("my_bucket", "1")?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn delete(bucket: &str, notification: &str) -> Result<(), crate::Error> {
-        let url = format!(
-            "{}/b/{}/notificationConfigs/{}",
-            crate::BASE_URL,
-            bucket,
-            notification
-        );
-        let client = reqwest::blocking::Client::new();
-        let response = client.get(&url).headers(crate::get_headers()?).send()?;
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(crate::Error::Google(response.json()?))
-        }
+        crate::Client::global()
+            .notification()
+            .delete(bucket, notification)
     }
 }
 
@@ -138,26 +417,124 @@ impl Notification {
 mod tests {
     use super::*;
 
+    fn new_notification() -> NewNotification {
+        NewNotification {
+            topic: Topic {
+                project_id: crate::Client::global()
+                    .service_account()
+                    .unwrap()
+                    .project_id
+                    .clone(),
+                topic: "testing-is-important".to_string(),
+            },
+            event_types: Some(vec![EventType::ObjectFinalize, EventType::ObjectDelete]),
+            custom_attributes: None,
+            payload_format: PayloadFormat::JsonApiV1,
+            object_name_prefix: Some("test-notification/".to_string()),
+        }
+    }
+
+    #[test]
+    fn parse_push_request() -> Result<(), Box<dyn std::error::Error>> {
+        let object = serde_json::json!({
+            "kind": "storage#object",
+            "id": "my_bucket/cat.png/1600000000000000",
+            "selfLink": "https://www.googleapis.com/storage/v1/b/my_bucket/o/cat.png",
+            "name": "cat.png",
+            "bucket": "my_bucket",
+            "generation": "1600000000000000",
+            "metageneration": "1",
+            "contentType": "image/png",
+            "timeCreated": "2020-09-13T12:26:40.000Z",
+            "updated": "2020-09-13T12:26:40.000Z",
+            "storageClass": "STANDARD",
+            "timeStorageClassUpdated": "2020-09-13T12:26:40.000Z",
+            "size": "3",
+            "mediaLink": "https://www.googleapis.com/download/storage/v1/b/my_bucket/o/cat.png",
+            "crc32c": "AAAAAA==",
+            "etag": "CAE="
+        });
+        let body = serde_json::json!({
+            "message": {
+                "attributes": {
+                    "notificationConfig": "projects/_/buckets/my_bucket/notificationConfigs/1",
+                    "eventType": "OBJECT_FINALIZE",
+                    "payloadFormat": "JSON_API_V1",
+                    "bucketId": "my_bucket",
+                    "objectId": "cat.png",
+                    "objectGeneration": "1600000000000000",
+                    "eventTime": "2020-09-13T12:26:40.000000Z",
+                    "overwroteGeneration": "1500000000000000",
+                    "team": "cats"
+                },
+                "data": base64::encode(&object.to_string()),
+                "messageId": "1"
+            },
+            "subscription": "projects/my-project/subscriptions/my-subscription"
+        });
+        let event = Event::from_push(body.to_string().as_bytes())?;
+        assert_eq!(event.event_type, EventType::ObjectFinalize);
+        assert_eq!(event.generation, 1_600_000_000_000_000);
+        assert_eq!(event.overwrote_generation, Some(1_500_000_000_000_000));
+        assert_eq!(event.overwritten_by_generation, None);
+        assert_eq!(event.event_time.timestamp(), 1_600_000_000);
+        assert_eq!(event.object.map(|o| o.size), Some(3));
+        assert_eq!(event.custom_attributes["team"], "cats");
+        assert!(Event::from_message(&Default::default(), &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn new_notification_builder() {
+        let topic = Topic {
+            project_id: "my-project".to_string(),
+            topic: "my-topic".to_string(),
+        };
+        let new_notification = NewNotification::new(topic)
+            .event_type(EventType::ObjectFinalize)
+            .event_type(EventType::ObjectFinalize)
+            .event_type("OBJECT_METADATA_UPDATE".parse().unwrap())
+            .payload_format(PayloadFormat::None)
+            .custom_attribute("team", "storage");
+        assert!(new_notification.validate().is_ok());
+        assert_eq!(
+            serde_json::to_value(&new_notification).unwrap(),
+            serde_json::json!({
+                "topic": "//pubsub.googleapis.com/projects/my-project/topics/my-topic",
+                "eventTypes": ["OBJECT_FINALIZE", "OBJECT_METADATA_UPDATE"],
+                "customAttributes": { "team": "storage" },
+                "payloadFormat": "NONE",
+            })
+        );
+
+        assert!("OBJECT_FINALISE".parse::<EventType>().is_err());
+        assert!("json_api_v1".parse::<PayloadFormat>().is_err());
+        for (key, value) in &[("", "a"), ("googTeam", "a"), ("eventType", "a")] {
+            let invalid = new_notification.clone().custom_attribute(key, value);
+            assert!(invalid.validate().is_err(), "{} is accepted", key);
+        }
+        let long = "a".repeat(1025);
+        let invalid = new_notification.clone().custom_attribute("team", &long);
+        assert!(invalid.validate().is_err());
+        let mut empty = new_notification;
+        empty.event_types = Some(Vec::new());
+        assert!(empty.validate().is_err());
+    }
+
     #[test]
     fn create() {
         let bucket = crate::read_test_bucket();
-        let topic = format!(
-            "//pubsub.googleapis.com/projects/{}/topics/{}",
-            crate::SERVICE_ACCOUNT.project_id,
-            "testing-is-important",
-        );
-        let new_notification = NewNotification {
-            topic,
-            payload_format: Some(PayloadFormat::JsonApiV1),
-            ..Default::default()
-        };
-        Notification::create(&bucket.name, &new_notification).unwrap();
+        let notification = Notification::create(&bucket.name, &new_notification()).unwrap();
+        Notification::delete(&bucket.name, &notification.id).unwrap();
     }
 
     #[test]
     fn read() {
         let bucket = crate::read_test_bucket();
-        Notification::read(&bucket.name, "testing-is-important").unwrap();
+        let notification = Notification::create(&bucket.name, &new_notification()).unwrap();
+        let read = Notification::read(&bucket.name, &notification.id).unwrap();
+        assert_eq!(read.event_types, new_notification().event_types);
+        Notification::delete(&bucket.name, &notification.id).unwrap();
     }
 
     #[test]
@@ -169,17 +546,8 @@ mod tests {
     #[test]
     fn delete() {
         let bucket = crate::read_test_bucket();
-        let topic = format!(
-            "//pubsub.googleapis.com/projects/{}/topics/{}",
-            crate::SERVICE_ACCOUNT.project_id,
-            "testing-is-important",
-        );
-        let new_notification = NewNotification {
-            topic,
-            payload_format: Some(PayloadFormat::JsonApiV1),
-            ..Default::default()
-        };
-        Notification::create(&bucket.name, &new_notification).unwrap();
-        Notification::delete(&bucket.name, "testing-is-important").unwrap();
+        let notification = Notification::create(&bucket.name, &new_notification()).unwrap();
+        Notification::delete(&bucket.name, &notification.id).unwrap();
+        assert!(Notification::read(&bucket.name, &notification.id).is_err());
     }
 }
@@ -1,11 +1,11 @@
-use crate::error::{Error, GoogleResponse};
-pub use crate::resources::bucket::Owner;
-use crate::resources::common::ListResponse;
-use crate::resources::object_access_control::ObjectAccessControl;
+use crate::error::Error;
+pub use crate::resources::bucket::{Owner, StorageClass};
+pub use crate::resources::common::Projection;
+use crate::resources::object_access_control::{NewObjectAccessControl, ObjectAccessControl};
 use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 
 /// A resource representing a file in Google Cloud Storage.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Object {
     /// The kind of item this is. For objects, this is always `storage#object`.
@@ -28,6 +28,7 @@ pub struct Object {
     pub metageneration: i64,
     /// Content-Type of the object data. If an object is stored without a Content-Type, it is served
     /// as application/octet-stream.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content_type: Option<String>,
     /// The creation time of the object in RFC 3339 format.
     pub time_created: chrono::DateTime<chrono::Utc>,
@@ -35,16 +36,20 @@ pub struct Object {
     pub updated: chrono::DateTime<chrono::Utc>,
     /// The deletion time of the object in RFC 3339 format. Returned if and only if this version of
     /// the object is no longer a live version, but remains in the bucket as a noncurrent version.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub time_deleted: Option<chrono::DateTime<chrono::Utc>>,
     /// Whether or not the object is subject to a temporary hold.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub temporary_hold: Option<bool>,
     /// Whether or not the object is subject to an event-based hold.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub event_based_hold: Option<bool>,
     /// The earliest time that the object can be deleted, based on a bucket's retention policy, in
     /// RFC 3339 format.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub retention_expiration_time: Option<chrono::DateTime<chrono::Utc>>,
     /// Storage class of the object.
-    pub storage_class: String,
+    pub storage_class: StorageClass,
     /// The time at which the object's storage class was last changed. When the object is initially
     /// created, it will be set to timeCreated.
     pub time_storage_class_updated: chrono::DateTime<chrono::Utc>,
@@ -53,27 +58,35 @@ pub struct Object {
     pub size: u64,
     /// MD5 hash of the data; encoded using base64. For more information about using the MD5 hash,
     /// see Hashes and ETags: Best Practices.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub md5_hash: Option<String>,
     /// Media download link.
     pub media_link: String,
     /// Content-Encoding of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content_encoding: Option<String>,
     /// Content-Disposition of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content_disposition: Option<String>,
     /// Content-Language of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content_language: Option<String>,
     /// Cache-Control directive for the object data. If omitted, and the object is accessible to all
     /// anonymous users, the default will be public, max-age=3600.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_control: Option<String>,
     /// User-provided metadata, in key/value pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<std::collections::HashMap<String, String>>,
     /// Access controls on the object, containing one or more objectAccessControls Resources. If
     /// iamConfiguration.uniformBucketLevelAccess.enabled is set to true, this field is omitted in
     /// responses, and requests that specify this field fail.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub acl: Option<Vec<ObjectAccessControl>>,
     /// The owner of the object. This will always be the uploader of the object. If
     /// `iamConfiguration.uniformBucketLevelAccess.enabled` is set to true, this field does not
     /// apply, and is omitted in responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub owner: Option<Owner>,
     /// CRC32c checksum, as described in RFC 4960, Appendix B; encoded using base64 in big-endian
     /// byte order. For more information about using the CRC32c checksum, see Hashes and ETags: Best
@@ -83,18 +96,24 @@ pub struct Object {
     /// by compose operations, counting 1 for each non-composite source object and componentCount
     /// for each composite source object. Note: componentCount is included in the metadata for
     /// composite objects only.
-    #[serde(default, deserialize_with = "crate::from_str_opt")]
+    #[serde(
+        default,
+        deserialize_with = "crate::from_str_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub component_count: Option<i32>,
     /// HTTP 1.1 Entity tag for the object.
     pub etag: String,
     /// Metadata of customer-supplied encryption key, if the object is encrypted by such a key.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub customer_encryption: Option<CustomerEncrypton>,
     /// Cloud KMS Key used to encrypt this object, if the object is encrypted by such a key.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub kms_key_name: Option<String>,
 }
 
 /// Contains data about how a user might encrypt their files in Google Cloud Storage.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CustomerEncrypton {
     /// The encryption algorithm.
@@ -103,8 +122,213 @@ pub struct CustomerEncrypton {
     pub key_sha256: String,
 }
 
+/// An object of which only some properties were requested, through the `fields` parameter of
+/// `Object::read_partial` or `Object::list_partial`. Every property that was not requested, or
+/// that the object does not have, is `None`. See `Object` for the meaning of each property.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PartialObject {
+    /// The kind of item this is. For objects, this is always `storage#object`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// The ID of the object, including the bucket name, object name, and generation number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The link to this object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub self_link: Option<String>,
+    /// The name of the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The name of the bucket containing this object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<String>,
+    /// The content generation of this object.
+    #[serde(
+        deserialize_with = "crate::from_str_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub generation: Option<i64>,
+    /// The version of the metadata for this object at this generation.
+    #[serde(
+        deserialize_with = "crate::from_str_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub metageneration: Option<i64>,
+    /// Content-Type of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// The creation time of the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_created: Option<chrono::DateTime<chrono::Utc>>,
+    /// The modification time of the object metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<chrono::DateTime<chrono::Utc>>,
+    /// The deletion time of the object, for noncurrent versions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_deleted: Option<chrono::DateTime<chrono::Utc>>,
+    /// Whether or not the object is subject to a temporary hold.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temporary_hold: Option<bool>,
+    /// Whether or not the object is subject to an event-based hold.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_based_hold: Option<bool>,
+    /// The earliest time that the object can be deleted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_expiration_time: Option<chrono::DateTime<chrono::Utc>>,
+    /// Storage class of the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_class: Option<StorageClass>,
+    /// The time at which the object's storage class was last changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_storage_class_updated: Option<chrono::DateTime<chrono::Utc>>,
+    /// Content-Length of the data in bytes.
+    #[serde(
+        deserialize_with = "crate::from_str_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub size: Option<u64>,
+    /// MD5 hash of the data, encoded using base64.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub md5_hash: Option<String>,
+    /// Media download link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_link: Option<String>,
+    /// Content-Encoding of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+    /// Content-Disposition of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_disposition: Option<String>,
+    /// Content-Language of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_language: Option<String>,
+    /// Cache-Control directive for the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<String>,
+    /// User-provided metadata, in key/value pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<std::collections::HashMap<String, String>>,
+    /// Access controls on the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acl: Option<Vec<ObjectAccessControl>>,
+    /// The owner of the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+    /// CRC32c checksum, encoded using base64 in big-endian byte order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crc32c: Option<String>,
+    /// Number of underlying components that make up a composite object.
+    #[serde(
+        deserialize_with = "crate::from_str_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub component_count: Option<i32>,
+    /// HTTP 1.1 Entity tag for the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Metadata of customer-supplied encryption key, if the object is encrypted by such a key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_encryption: Option<CustomerEncrypton>,
+    /// Cloud KMS Key used to encrypt this object, if the object is encrypted by such a key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kms_key_name: Option<String>,
+}
+
+/// What the `x-goog-*` headers of a media download say about the content, as returned by
+/// `Object::download_with_info`. Every property that Google did not send, or sent in a form that
+/// could not be parsed, is `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+    /// The CRC32C checksum of the stored content, from `x-goog-hash`.
+    pub crc32c: Option<u32>,
+    /// The MD5 hash of the stored content, from `x-goog-hash`. Composite objects do not have one.
+    pub md5: Option<[u8; 16]>,
+    /// The generation of the downloaded content, from `x-goog-generation`.
+    pub generation: Option<i64>,
+    /// The metageneration of the object, from `x-goog-metageneration`.
+    pub metageneration: Option<i64>,
+    /// The size of the content as it is stored, from `x-goog-stored-content-length`. This differs
+    /// from the length of the downloaded content when Google decompresses it on the fly.
+    pub stored_content_length: Option<u64>,
+    /// The Content-Encoding with which the content is stored, from
+    /// `x-goog-stored-content-encoding`.
+    pub stored_content_encoding: Option<String>,
+}
+
+impl MediaInfo {
+    /// Parses the `x-goog-*` headers of a response to a media request.
+    pub(crate) fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+        };
+        let mut info = Self {
+            generation: header("x-goog-generation").and_then(|value| value.parse().ok()),
+            metageneration: header("x-goog-metageneration").and_then(|value| value.parse().ok()),
+            stored_content_length: header("x-goog-stored-content-length")
+                .and_then(|value| value.parse().ok()),
+            stored_content_encoding: header("x-goog-stored-content-encoding").map(str::to_string),
+            ..Self::default()
+        };
+        // the hashes come as separate headers or as one comma separated list
+        let hashes = headers
+            .get_all("x-goog-hash")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for hash in hashes {
+            match hash.trim().split_once('=') {
+                Some(("crc32c", value)) => info.crc32c = crate::crc32c::decode(value).ok(),
+                Some(("md5", value)) => info.md5 = crate::md5::decode(value).ok(),
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+/// The properties of an object that `Object::head` reads from the headers of a `HEAD` request
+/// for its content. This is cheaper than reading the metadata of the object, but only covers the
+/// properties that Google sends along with the content.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectHead {
+    /// The size of the content as it is stored, in bytes.
+    pub size: Option<u64>,
+    /// Content-Type of the object data.
+    pub content_type: Option<String>,
+    /// HTTP 1.1 Entity tag for the object data.
+    pub etag: Option<String>,
+    /// The generation, metageneration and hashes of the object.
+    pub media: MediaInfo,
+}
+
+impl ObjectHead {
+    /// Reads the properties from the headers of a response to a media request.
+    pub(crate) fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, ETAG};
+
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+        };
+        let media = MediaInfo::from_headers(headers);
+        Self {
+            size: media
+                .stored_content_length
+                .or_else(|| header(CONTENT_LENGTH)?.trim().parse().ok()),
+            content_type: header(CONTENT_TYPE).map(str::to_string),
+            etag: header(ETAG).map(|etag| etag.trim_matches('"').to_string()),
+            media,
+        }
+    }
+}
+
 /// The request that is supplied to perform `Object::compose`.
-#[derive(Debug, PartialEq, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ComposeRequest {
     /// The kind of item this is. Will always be `storage#composeRequest`.
@@ -112,24 +336,55 @@ pub struct ComposeRequest {
     /// The list of source objects that will be concatenated into a single object.
     pub source_objects: Vec<SourceObject>,
     /// Properties of the resulting object.
-    pub destination: Option<Object>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination: Option<DestinationObject>,
+}
+
+/// The properties that can be set on the object that `Object::compose` creates. Properties that
+/// are not set are left to Google's defaults.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DestinationObject {
+    /// Content-Type of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Content-Encoding of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+    /// Content-Disposition of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_disposition: Option<String>,
+    /// Content-Language of the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_language: Option<String>,
+    /// Cache-Control directive for the object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<String>,
+    /// User-provided metadata, in key/value pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<std::collections::HashMap<String, String>>,
+    /// Access controls on the object. Not allowed in buckets with uniform bucket-level access.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acl: Option<Vec<NewObjectAccessControl>>,
 }
 
 /// A SourceObject represents one of the objects that is to be composed.
-#[derive(Debug, PartialEq, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SourceObject {
     /// The source object's name. All source objects must have the same storage class and reside in
     /// the same bucket.
     pub name: String,
     /// The generation of this object to use as the source.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub generation: Option<i64>,
     /// Conditions that must be met for this operation to execute.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub object_preconditions: Option<ObjectPrecondition>,
 }
 
 /// Allows conditional copying of this file.
-#[derive(Debug, PartialEq, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ObjectPrecondition {
     /// Only perform the composition if the generation of the source object that would be used
@@ -138,6 +393,180 @@ pub struct ObjectPrecondition {
     pub if_generation_match: i64,
 }
 
+/// The key in `Object::metadata` under which `Object::create_gzipped` records the size of the
+/// content before it was compressed.
+pub const UNCOMPRESSED_SIZE: &str = "uncompressed-size";
+
+/// When a [Signed Url](https://cloud.google.com/storage/docs/access-control/signed-urls) expires.
+/// Signed urls are valid for at most 7 days. Can be created from a `std::time::Duration`, a
+/// `chrono::DateTime<chrono::Utc>`, or a number of seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Expiry {
+    /// The url expires this long after it is created.
+    Duration(std::time::Duration),
+    /// The url expires at this moment.
+    At(chrono::DateTime<chrono::Utc>),
+}
+
+impl Expiry {
+    /// The longest time a signed url can be valid for: 7 days.
+    pub const MAX: std::time::Duration = std::time::Duration::from_secs(604800);
+
+    /// The number of seconds a url created at `issued` is valid for, or an error if it would
+    /// expire before it was created, or more than 7 days after.
+    pub(crate) fn seconds(self, issued: chrono::DateTime<chrono::Utc>) -> Result<u64, Error> {
+        let seconds = match self {
+            // a fraction of a second is rounded up
+            Expiry::Duration(duration) => {
+                duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+            }
+            Expiry::At(moment) => {
+                let remaining = (moment - issued).num_seconds();
+                if remaining <= 0 {
+                    let msg = format!("signed url would already be expired at {}", moment);
+                    return Err(Error::Other(msg));
+                }
+                remaining as u64
+            }
+        };
+        if seconds > Self::MAX.as_secs() {
+            let msg = format!(
+                "signed urls can be valid for at most 7 days ({} seconds), but {} seconds were requested",
+                Self::MAX.as_secs(),
+                seconds
+            );
+            return Err(Error::Other(msg));
+        }
+        Ok(seconds)
+    }
+}
+
+impl From<std::time::Duration> for Expiry {
+    fn from(duration: std::time::Duration) -> Self {
+        Expiry::Duration(duration)
+    }
+}
+
+impl From<chrono::DateTime<chrono::Utc>> for Expiry {
+    fn from(moment: chrono::DateTime<chrono::Utc>) -> Self {
+        Expiry::At(moment)
+    }
+}
+
+impl From<u32> for Expiry {
+    /// An expiry `seconds` after the url is created.
+    fn from(seconds: u32) -> Self {
+        Expiry::Duration(std::time::Duration::from_secs(seconds.into()))
+    }
+}
+
+/// The kind of request that a signed url created by `Object::signed_url` allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignedUrlMethod {
+    /// Download the content of the object.
+    Get,
+    /// Read the metadata of the object, as headers, without downloading its content.
+    Head,
+    /// Upload the content of the object in a single request.
+    Put,
+    /// Start a resumable upload, together with the `x-goog-resumable: start` header.
+    Post,
+    /// Delete the object.
+    Delete,
+}
+
+impl SignedUrlMethod {
+    /// The name of the HTTP method, such as `GET`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignedUrlMethod::Get => "GET",
+            SignedUrlMethod::Head => "HEAD",
+            SignedUrlMethod::Put => "PUT",
+            SignedUrlMethod::Post => "POST",
+            SignedUrlMethod::Delete => "DELETE",
+        }
+    }
+}
+
+impl std::fmt::Display for SignedUrlMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Additional settings for a signed url created by `Object::signed_url`, `Object::download_url_with`
+/// or `Object::resumable_upload_url`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SignedUrlOptions {
+    /// Query parameters that become part of the signed url, such as `generation` or
+    /// `response-content-disposition`. They are signed too, so the posessor of the url cannot
+    /// change them. The `X-Goog-` parameters that make up the signature itself cannot be set.
+    pub query_parameters: Vec<(String, String)>,
+    /// Headers that the request made with the url must carry, with exactly these values, such as
+    /// `content-type`. The `host` header is always signed.
+    pub headers: Vec<(String, String)>,
+}
+
+impl SignedUrlOptions {
+    /// Adds the query parameter `name` with `value`.
+    pub fn query_parameter(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query_parameters.push((name.into(), value.into()));
+        self
+    }
+
+    /// Requires the header `name` with `value`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// Which objects `Object::list_with_options` returns, and how they are requested. Fields that are
+/// not set are left to Google's defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListOptions {
+    /// Only list objects whose name starts with this prefix.
+    pub prefix: Option<String>,
+    /// Only list objects whose name matches this glob, such as `**/*.parquet`.
+    pub match_glob: Option<String>,
+    /// The maximum amount of objects requested from Google at once.
+    pub page_size: Option<u32>,
+    /// The properties of the objects to return. `Projection::NoAcl` leaves out `acl` and `owner`.
+    pub projection: Option<Projection>,
+    /// Only list objects whose name, after `prefix`, does not contain this delimiter, such as
+    /// `/` to list the objects in a single folder. The names of the subfolders are not returned.
+    pub delimiter: Option<String>,
+    /// Also list the objects whose name ends with `delimiter` right after `prefix`, such as the
+    /// placeholder objects that represent empty folders.
+    pub include_trailing_delimiter: bool,
+    /// Only list objects whose name is lexicographically equal to or after this one. Together
+    /// with `end_offset`, this splits a huge listing into ranges that can be listed in parallel.
+    pub start_offset: Option<String>,
+    /// Only list objects whose name is lexicographically before this one.
+    pub end_offset: Option<String>,
+}
+
+/// A set of access controls that Google applies to an object when it is written, instead of the
+/// default object ACL of the bucket. Saves a separate request to change the ACL afterwards. Not
+/// allowed in buckets with uniform bucket-level access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PredefinedAcl {
+    /// The object owner gets `OWNER` access, and `allAuthenticatedUsers` get `READER` access.
+    AuthenticatedRead,
+    /// The object owner gets `OWNER` access, and project team owners get `OWNER` access.
+    BucketOwnerFullControl,
+    /// The object owner gets `OWNER` access, and project team owners get `READER` access.
+    BucketOwnerRead,
+    /// The object owner gets `OWNER` access.
+    Private,
+    /// The object owner gets `OWNER` access, and project team members get access according to
+    /// their roles.
+    ProjectPrivate,
+    /// The object owner gets `OWNER` access, and `allUsers` get `READER` access.
+    PublicRead,
+}
+
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ObjectList {
@@ -147,18 +576,20 @@ struct ObjectList {
 
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct RewriteResponse {
+pub(crate) struct RewriteResponse {
     kind: String,
     total_bytes_rewritten: String,
     object_size: String,
     done: bool,
-    resource: Object,
+    pub(crate) resource: Object,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Object {
     /// Create a new object.
     /// Upload a file as that is loaded in memory to google cloud storage, where it will be
-    /// interpreted according to the mime type you specified.
+    /// interpreted according to the mime type you specified. Pass `mime::AUTO` to have the mime type
+    /// guessed from the extension of `filename` and the content of `file`, see `mime::guess`.
     /// ## Example
     /// ```rust,no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -177,34 +608,38 @@ impl Object {
         filename: &str,
         mime_type: &str,
     ) -> Result<Self, Error> {
-        use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
-
-        // has its own url for some reason
-        const BASE_URL: &str = "https://www.googleapis.com/upload/storage/v1/b";
-        let client = reqwest::blocking::Client::new();
-        let url = &format!(
-            "{}/{}/o?uploadType=media&name={}",
-            BASE_URL,
-            percent_encode(&bucket),
-            percent_encode(&filename),
-        );
-        let mut headers = crate::get_headers()?;
-        headers.insert(CONTENT_TYPE, mime_type.to_string().parse()?);
-        headers.insert(CONTENT_LENGTH, file.len().to_string().parse()?);
-        let response = client
-            .post(url)
-            .headers(headers)
-            .body(file.to_owned())
-            .send()?;
-        if response.status() == 200 {
-            Ok(serde_json::from_str(&response.text()?)?)
-        } else {
-            Err(Error::new(&response.text()?))
-        }
+        crate::Client::global()
+            .object()
+            .create(bucket, file, filename, mime_type)
+    }
+
+    /// Create a new object like `Object::create`, and apply the predefined ACL `acl` to it in the
+    /// same request.
+    /// ## Example
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{Object, PredefinedAcl};
+    ///
+    /// let page = b"<html>hello</html>";
+    /// Object::create_with_acl("my_site", page, "index.html", "text/html", PredefinedAcl::PublicRead)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_with_acl(
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+        acl: PredefinedAcl,
+    ) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .create_with_acl(bucket, file, filename, mime_type, acl)
     }
 
     /// Create a new object. This works in the same way as `Object::create`, except it does not need
-    /// to load the entire file in ram.
+    /// to load the entire file in ram. With `mime::AUTO`, only the first 512 bytes of `file` are
+    /// looked at.
     /// ## Example
     /// ```rust,no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -224,467 +659,885 @@ impl Object {
         filename: &str,
         mime_type: &str,
     ) -> Result<Self, Error> {
-        use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
-
-        // has its own url for some reason
-        const BASE_URL: &str = "https://www.googleapis.com/upload/storage/v1/b";
-        let client = reqwest::blocking::Client::new();
-        let url = &format!(
-            "{}/{}/o?uploadType=media&name={}",
-            BASE_URL,
-            percent_encode(&bucket),
-            percent_encode(&filename),
-        );
-        let mut headers = crate::get_headers()?;
-        headers.insert(CONTENT_TYPE, mime_type.to_string().parse()?);
-        headers.insert(CONTENT_LENGTH, length.to_string().parse()?);
-        let body = reqwest::blocking::Body::sized(file, length);
-        let response = client.post(url).headers(headers).body(body).send()?;
-        if response.status() == 200 {
-            Ok(serde_json::from_str(&response.text()?)?)
-        } else {
-            Err(Error::new(&response.text()?))
-        }
+        crate::Client::global()
+            .object()
+            .create_streamed(bucket, file, length, filename, mime_type)
+    }
+
+    /// Create a new object from `file`, whose length is not known up front, such as the output of
+    /// another program. The content is uploaded in chunks of 8 MiB.
+    /// ## Example
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    /// use std::process::{Command, Stdio};
+    ///
+    /// let dump = Command::new("pg_dump").stdout(Stdio::piped()).spawn()?;
+    /// let stdout = dump.stdout.expect("no stdout");
+    /// Object::create_streamed_unsized("backups", stdout, "db.sql", "application/sql")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_streamed_unsized<R: std::io::Read>(
+        bucket: &str,
+        file: R,
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .create_streamed_unsized(bucket, file, filename, mime_type)
+    }
+
+    /// Compresses `file` with gzip and uploads it as a new object, with `Content-Encoding: gzip`
+    /// and `mime_type` as its content type. The uncompressed size of the content is stored in the
+    /// metadata of the object under `UNCOMPRESSED_SIZE`. Downloads through this crate return the
+    /// uncompressed content again.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let log = std::fs::read("app.log")?;
+    /// let object = Object::create_gzipped("my_bucket", &log, "logs/app.log", "text/plain")?;
+    /// let content = Object::download("my_bucket", "logs/app.log")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "gzip")]
+    pub fn create_gzipped(
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .create_gzipped(bucket, file, filename, mime_type)
+    }
+
+    /// Create a new object from a stream of chunks, such as the body of an incoming request, without
+    /// buffering it. `length` is the total size of the chunks, if it is known up front.
+    /// ## Example
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let chunks = vec![Ok::<_, std::io::Error>(b"hello ".to_vec()), Ok(b"world".to_vec())];
+    /// let stream = futures::stream::iter(chunks);
+    /// Object::create_from_stream("my_bucket", stream, None, "hello.txt", "text/plain")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "stream")]
+    pub fn create_from_stream<S, B, E>(
+        bucket: &str,
+        stream: S,
+        length: Option<u64>,
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Self, Error>
+    where
+        S: futures::Stream<Item = Result<B, E>> + Send + Unpin + 'static,
+        B: AsRef<[u8]> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        crate::Client::global()
+            .object()
+            .create_from_stream(bucket, stream, length, filename, mime_type)
+    }
+
+    /// Obtain a list of objects within this Bucket.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let all_objects = Object::list("my_bucket")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list(bucket: &str) -> Result<Vec<Self>, Error> {
+        crate::Client::global().object().list(bucket)
+    }
+
+    /// Obtain a list of objects by prefix within this Bucket .
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let all_objects = Object::list_prefix("my_bucket", "prefix/")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_prefix(bucket: &str, prefix: &str) -> Result<Vec<Self>, Error> {
+        crate::Client::global().object().list_prefix(bucket, prefix)
+    }
+
+    /// Returns an iterator over the objects in `bucket`, optionally limited to the objects whose
+    /// name starts with `prefix`. Objects are requested from Google in pages of at most
+    /// `page_size` objects as the iterator is advanced, so this also works for buckets that
+    /// contain millions of objects.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// for object in Object::list_with("my_bucket", Some("logs/"), Some(1000)) {
+    ///     println!("{}", object?.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_with(
+        bucket: &str,
+        prefix: Option<&str>,
+        page_size: Option<u32>,
+    ) -> impl Iterator<Item = Result<Self, Error>> {
+        crate::Client::global()
+            .object()
+            .list_with(bucket, prefix, page_size)
+    }
+
+    /// Returns an iterator over the objects in `bucket` whose name matches the glob `pattern`.
+    /// The pattern is evaluated by Google, so this is much cheaper than listing all objects and
+    /// filtering them afterwards. `*` matches any characters except `/`, `**` also matches `/`,
+    /// `?` matches a single character and `[abc]` and `{a,b}` match one of the alternatives. See
+    /// [the documentation](https://cloud.google.com/storage/docs/json_api/v1/objects/list#list-objects-and-prefixes-using-glob)
+    /// for details.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// for object in Object::list_glob("my_bucket", "tables/**/*.parquet") {
+    ///     println!("{}", object?.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_glob(bucket: &str, pattern: &str) -> impl Iterator<Item = Result<Self, Error>> {
+        crate::Client::global().object().list_glob(bucket, pattern)
+    }
+
+    /// Returns an iterator over the objects in `bucket` that match `options`. Objects are
+    /// requested from Google page by page as the iterator is advanced.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{ListOptions, Object, Projection};
+    ///
+    /// let options = ListOptions {
+    ///     prefix: Some("logs/".to_string()),
+    ///     projection: Some(Projection::NoAcl),
+    ///     ..Default::default()
+    /// };
+    /// for object in Object::list_with_options("my_bucket", &options) {
+    ///     println!("{}", object?.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_with_options(
+        bucket: &str,
+        options: &ListOptions,
+    ) -> impl Iterator<Item = Result<Self, Error>> {
+        crate::Client::global()
+            .object()
+            .list_with_options(bucket, options)
+    }
+
+    /// Returns an iterator over the objects in `bucket` that match `options`, with only the
+    /// properties in `fields`. Listing only names is an order of magnitude smaller than listing
+    /// complete objects. The syntax of `fields` is described in
+    /// [the documentation](https://cloud.google.com/storage/docs/json_api#partial-response).
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{ListOptions, Object};
+    ///
+    /// for object in Object::list_partial("my_bucket", &ListOptions::default(), "name,size") {
+    ///     let object = object?;
+    ///     println!("{:?} is {:?} bytes", object.name, object.size);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_partial(
+        bucket: &str,
+        options: &ListOptions,
+        fields: &str,
+    ) -> impl Iterator<Item = Result<PartialObject, Error>> {
+        crate::Client::global()
+            .object()
+            .list_partial(bucket, options, fields)
+    }
+
+    /// Obtains a single object with the specified name in the specified bucket.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let object = Object::read("my_bucket", "path/to/my/file.png")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read(bucket: &str, file_name: &str) -> Result<Self, Error> {
+        crate::Client::global().object().read(bucket, file_name)
+    }
+
+    /// Obtains a single object like `Object::read`, with the properties selected by `projection`.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{Object, Projection};
+    ///
+    /// let object = Object::read_with_projection("my_bucket", "file.png", Projection::NoAcl)?;
+    /// assert!(object.acl.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_with_projection(
+        bucket: &str,
+        file_name: &str,
+        projection: Projection,
+    ) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .read_with_projection(bucket, file_name, projection)
+    }
+
+    /// Obtains only the properties in `fields` of a single object.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let object = Object::read_partial("my_bucket", "file.png", "size,updated")?;
+    /// println!("{:?} bytes, updated at {:?}", object.size, object.updated);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_partial(
+        bucket: &str,
+        file_name: &str,
+        fields: &str,
+    ) -> Result<PartialObject, Error> {
+        crate::Client::global()
+            .object()
+            .read_partial(bucket, file_name, fields)
+    }
+
+    /// Download the content of the object with the specified name in the specified bucket.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let bytes = Object::download("my_bucket", "path/to/my/file.png")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn download(bucket: &str, file_name: &str) -> Result<bytes::Bytes, Error> {
+        crate::Client::global().object().download(bucket, file_name)
+    }
+
+    /// Reads the size, content type, generation and hashes of the object with the specified name
+    /// in the specified bucket from the headers of a `HEAD` request for its content. This is a
+    /// cheaper way to check whether an object exists, or how large it is, than `Object::read`.
+    /// Fails with `Error::NotFound` if the object does not exist.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let head = Object::head("my_bucket", "path/to/my/file.png")?;
+    /// println!("{:?} bytes of {:?}", head.size, head.content_type);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn head(bucket: &str, file_name: &str) -> Result<ObjectHead, Error> {
+        crate::Client::global().object().head(bucket, file_name)
+    }
+
+    /// Download the content of the object with the specified name in the specified bucket,
+    /// together with the hashes, generation and stored size that Google reports for it.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let (bytes, info) = Object::download_with_info("my_bucket", "path/to/my/file.png")?;
+    /// if let Some(crc32c) = info.crc32c {
+    ///     assert_eq!(cloud_storage::crc32c::checksum(&bytes), crc32c);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn download_with_info(
+        bucket: &str,
+        file_name: &str,
+    ) -> Result<(bytes::Bytes, MediaInfo), Error> {
+        crate::Client::global()
+            .object()
+            .download_with_info(bucket, file_name)
+    }
+
+    /// Replaces the metadata of this object with the values in `self`. Changes that were made
+    /// since `self` was retrieved are overwritten; use `Object::update_if_unchanged` to prevent
+    /// that.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let mut object = Object::read("my_bucket", "path/to/my/file.png")?;
+    /// object.content_type = Some("application/xml".to_string());
+    /// object.update();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update(&self) -> Result<Self, Error> {
+        crate::Client::global().object().update(self)
+    }
+
+    /// Replaces the metadata of this object like `Object::update`, but only if it was not changed
+    /// since `self` was retrieved, that is, if its metageneration still matches. Otherwise Google
+    /// responds with `412 Precondition Failed`, which surfaces as `Error::PreconditionFailed`.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::{Error, Object};
+    ///
+    /// let mut object = Object::read("my_bucket", "path/to/my/file.png")?;
+    /// object.content_type = Some("application/xml".to_string());
+    /// match object.update_if_unchanged() {
+    ///     Err(Error::PreconditionFailed(_)) => println!("someone else changed the object"),
+    ///     result => println!("{:?}", result?),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_if_unchanged(&self) -> Result<Self, Error> {
+        crate::Client::global().object().update_if_unchanged(self)
+    }
+
+    /// Updates an existing object like `Object::update`, and replaces its ACL with the predefined
+    /// ACL `acl`.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{Object, PredefinedAcl};
+    ///
+    /// let object = Object::read("my_bucket", "path/to/my/file.png")?;
+    /// object.update_with_acl(PredefinedAcl::Private)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_with_acl(&self, acl: PredefinedAcl) -> Result<Self, Error> {
+        crate::Client::global().object().update_with_acl(self, acl)
+    }
+
+    /// Deletes a single object with the specified name in the specified bucket.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::Object;
+    ///
+    /// let mut object = Object::delete("my_bucket", "path/to/my/file.png")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn delete(bucket: &str, file_name: &str) -> Result<(), Error> {
+        crate::Client::global().object().delete(bucket, file_name)
+    }
+
+    /// Obtains a single object with the specified name in the specified bucket.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{Object, ComposeRequest, SourceObject};
+    ///
+    /// let obj1 = Object::read("my_bucket", "file1")?;
+    /// let obj2 = Object::read("my_bucket", "file2")?;
+    /// let compose_request = ComposeRequest {
+    ///     kind: "storage#composeRequest".to_string(),
+    ///     source_objects: vec![
+    ///         SourceObject {
+    ///             name: obj1.name.clone(),
+    ///             generation: None,
+    ///             object_preconditions: None,
+    ///         },
+    ///         SourceObject {
+    ///             name: obj2.name.clone(),
+    ///             generation: None,
+    ///             object_preconditions: None,
+    ///         },
+    ///     ],
+    ///     destination: None,
+    /// };
+    /// let obj3 = Object::compose("my_bucket", &compose_request, "test-concatted-file")?;
+    /// // obj3 is now a file with the content of obj1 and obj2 concatted together.
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compose(
+        bucket: &str,
+        req: &ComposeRequest,
+        destination_object: &str,
+    ) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .compose(bucket, req, destination_object)
+    }
+
+    /// Concatenates objects like `Object::compose`, and applies the predefined ACL `acl` to the
+    /// resulting object.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{ComposeRequest, Object, PredefinedAcl, SourceObject};
+    ///
+    /// let compose_request = ComposeRequest {
+    ///     kind: "storage#composeRequest".to_string(),
+    ///     source_objects: vec![
+    ///         SourceObject { name: "part1".to_string(), generation: None, object_preconditions: None },
+    ///         SourceObject { name: "part2".to_string(), generation: None, object_preconditions: None },
+    ///     ],
+    ///     destination: None,
+    /// };
+    /// Object::compose_with_acl("my_bucket", &compose_request, "whole", PredefinedAcl::BucketOwnerRead)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compose_with_acl(
+        bucket: &str,
+        req: &ComposeRequest,
+        destination_object: &str,
+        acl: PredefinedAcl,
+    ) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .compose_with_acl(bucket, req, destination_object, acl)
+    }
+
+    /// Copy this object to the target bucket and path
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{Object, ComposeRequest};
+    ///
+    /// let obj1 = Object::read("my_bucket", "file1")?;
+    /// let obj2 = obj1.copy("my_other_bucket", "file2")?;
+    /// // obj2 is now a copy of obj1.
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn copy(&self, destination_bucket: &str, path: &str) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .copy(self, destination_bucket, path)
+    }
+
+    /// Copy this object to the target bucket and path, and apply the predefined ACL `acl` to the
+    /// copy.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{Object, PredefinedAcl};
+    ///
+    /// let obj1 = Object::read("my_bucket", "file1")?;
+    /// let obj2 = obj1.copy_with_acl("my_public_bucket", "file2", PredefinedAcl::PublicRead)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn copy_with_acl(
+        &self,
+        destination_bucket: &str,
+        path: &str,
+        acl: PredefinedAcl,
+    ) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .copy_with_acl(self, destination_bucket, path, acl)
+    }
+
+    /// Restores a noncurrent `generation` of the object `name` in a versioned bucket, by copying it
+    /// over the live object. This fails with `Error::is_precondition_failed` when the live object
+    /// is changed at the same time, rather than overwriting that change.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::Object;
+    ///
+    /// let previous = Object::read("my_bucket", "config.json")?.generation;
+    /// Object::create("my_bucket", b"{}", "config.json", "application/json")?;
+    /// // undo the upload
+    /// let restored = Object::promote_generation("my_bucket", "config.json", previous)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn promote_generation(bucket: &str, name: &str, generation: i64) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .promote_generation(bucket, name, generation)
+    }
+
+    /// Renames this object to `new_name` within its bucket, and returns the renamed object. This
+    /// fails instead of overwriting an existing object called `new_name`, and when this object was
+    /// changed since it was read. Buckets with a hierarchical namespace rename the object
+    /// atomically; in other buckets it is copied and then deleted.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::Object;
+    ///
+    /// let draft = Object::read("my_bucket", "drafts/post.md")?;
+    /// let published = draft.rename("posts/post.md")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rename(&self, new_name: &str) -> Result<Self, Error> {
+        crate::Client::global().object().rename(self, new_name)
     }
 
-    /// Obtain a list of objects within this Bucket.
+    /// Moves a file from the current location to the target bucket and path.
+    ///
+    /// ## Limitations
+    /// This function does not yet support rewriting objects to another
+    /// * Geographical Location,
+    /// * Encryption,
+    /// * Storage class.
+    /// These limitations mean that for now, the rewrite and the copy methods do the same thing.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::Object;
+    /// use cloud_storage::object::Object;
     ///
-    /// let all_objects = Object::list("my_bucket")?;
+    /// let obj1 = Object::read("my_bucket", "file1")?;
+    /// let obj2 = obj1.rewrite("my_other_bucket", "file2")?;
+    /// // obj2 is now a copy of obj1.
     /// # Ok(())
     /// # }
     /// ```
-    pub fn list(bucket: &str) -> Result<Vec<Self>, Error> {
-        Self::list_from(bucket, None, None)
+    pub fn rewrite(&self, destination_bucket: &str, path: &str) -> Result<Self, Error> {
+        crate::Client::global()
+            .object()
+            .rewrite(self, destination_bucket, path)
     }
 
-    /// Obtain a list of objects by prefix within this Bucket .
+    /// Uploads all files in `local_dir` and its subdirectories to `bucket`. Each file is stored
+    /// under `prefix`, followed by its path relative to `local_dir`, with a content type based on
+    /// its extension and content. Up to `UploadOptions::concurrency` files are uploaded at once, and uploads
+    /// that fail temporarily are retried. A failing file does not stop the others: the returned
+    /// report holds the outcome of every file. Only failures to read the directory itself are
+    /// returned as an error.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::Object;
+    /// use cloud_storage::{Object, UploadOptions};
     ///
-    /// let all_objects = Object::list_prefix("my_bucket", "prefix/")?;
+    /// let options = UploadOptions {
+    ///     concurrency: Some(16),
+    ///     ..Default::default()
+    /// };
+    /// for upload in Object::upload_dir("./build", "my_bucket", "site", &options)? {
+    ///     if let Err(e) = upload.result {
+    ///         eprintln!("{} failed after {} attempts: {}", upload.path, upload.attempts, e);
+    ///     }
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn list_prefix(bucket: &str, prefix: &str) -> Result<Vec<Self>, Error> {
-        Self::list_from(bucket, Some(prefix), None)
-    }
-
-    fn list_from(
+    pub fn upload_dir(
+        local_dir: impl AsRef<std::path::Path>,
         bucket: &str,
-        prefix: Option<&str>,
-        page_token: Option<&str>,
-    ) -> Result<Vec<Self>, Error> {
-        let url = format!("{}/b/{}/o", crate::BASE_URL, percent_encode(bucket));
-        let client = reqwest::blocking::Client::new();
-        let mut query = if let Some(page_token) = page_token {
-            vec![("pageToken", page_token)]
-        } else {
-            vec![]
-        };
-        if let Some(prefix) = prefix {
-            query.push(("prefix", prefix));
-        };
-
-        let result: GoogleResponse<ListResponse<Self>> = client
-            .get(&url)
-            .query(&query)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(mut s) => {
-                if let Some(page_token) = s.next_page_token {
-                    s.items
-                        .extend(Self::list_from(bucket, prefix, Some(&page_token))?.into_iter());
-                }
-                Ok(s.items)
-            }
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        prefix: &str,
+        options: &crate::UploadOptions,
+    ) -> Result<Vec<crate::FileUpload>, Error> {
+        crate::Client::global()
+            .object()
+            .upload_dir(local_dir, bucket, prefix, options)
     }
 
-    /// Obtains a single object with the specified name in the specified bucket.
+    /// Downloads all objects under `prefix` in `bucket` into `local_dir`. Each object is stored at
+    /// its name without `prefix`, relative to `local_dir`, and missing directories are created.
+    /// Up to `DownloadOptions::concurrency` objects are downloaded at once. A download that fails
+    /// halfway is resumed where it stopped, and the content is only moved into place once its
+    /// CRC32C checksum matches. A failing object does not stop the others: the returned report
+    /// holds the outcome of every object. Only failures to list the objects are returned as an
+    /// error.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::Object;
+    /// use cloud_storage::{DownloadOptions, Object};
     ///
-    /// let object = Object::read("my_bucket", "path/to/my/file.png")?;
+    /// let report = Object::download_prefix("my_bucket", "datasets/2020", "./data", &DownloadOptions::default())?;
+    /// let failed = report.iter().filter(|download| download.result.is_err()).count();
+    /// println!("{} of {} downloads failed", failed, report.len());
     /// # Ok(())
     /// # }
     /// ```
-    pub fn read(bucket: &str, file_name: &str) -> Result<Self, Error> {
-        let url = format!(
-            "{}/b/{}/o/{}",
-            crate::BASE_URL,
-            percent_encode(bucket),
-            percent_encode(file_name),
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+    pub fn download_prefix(
+        bucket: &str,
+        prefix: &str,
+        local_dir: impl AsRef<std::path::Path>,
+        options: &crate::DownloadOptions,
+    ) -> Result<Vec<crate::FileDownload>, Error> {
+        crate::Client::global()
+            .object()
+            .download_prefix(bucket, prefix, local_dir, options)
     }
 
-    /// Download the content of the object with the specified name in the specified bucket.
+    /// Copies all objects under `source_prefix` in `source_bucket` to `destination_prefix` in
+    /// `destination_bucket`, where each keeps its name relative to the prefix. Up to
+    /// `CopyOptions::concurrency` objects are copied at once, each exactly in the generation that
+    /// was listed, and every copy is checked against the CRC32C checksum of its source. A failing
+    /// object does not stop the others: the returned report holds the outcome of every object.
+    /// Only failures to list the objects are returned as an error.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::Object;
+    /// use cloud_storage::{CopyOptions, Object};
     ///
-    /// let bytes = Object::download("my_bucket", "path/to/my/file.png")?;
+    /// let report = Object::copy_prefix("my_bucket", "2020", "archive", "2020", &CopyOptions::default())?;
+    /// for copy in report.iter().filter(|copy| copy.result.is_err()) {
+    ///     eprintln!("{} was not copied", copy.path);
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn download(bucket: &str, file_name: &str) -> Result<bytes::Bytes, Error> {
-        let url = format!(
-            "{}/b/{}/o/{}?alt=media",
-            crate::BASE_URL,
-            percent_encode(bucket),
-            percent_encode(file_name),
-        );
-        let client = reqwest::blocking::Client::new();
-        Ok(client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .bytes()?)
+    pub fn copy_prefix(
+        source_bucket: &str,
+        source_prefix: &str,
+        destination_bucket: &str,
+        destination_prefix: &str,
+        options: &crate::CopyOptions,
+    ) -> Result<Vec<crate::ObjectCopy>, Error> {
+        crate::Client::global().object().copy_prefix(
+            source_bucket,
+            source_prefix,
+            destination_bucket,
+            destination_prefix,
+            options,
+        )
     }
 
-    /// Obtains a single object with the specified name in the specified bucket.
+    /// Moves all objects under `source_prefix` in `source_bucket` to `destination_prefix` in
+    /// `destination_bucket`, like `Object::copy_prefix`. Each source object is deleted once its
+    /// copy is verified, unless a newer generation of it was written in the meantime. Objects
+    /// that failed to copy remain at the source.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::Object;
+    /// use cloud_storage::{CopyOptions, Object};
     ///
-    /// let mut object = Object::read("my_bucket", "path/to/my/file.png")?;
-    /// object.content_type = Some("application/xml".to_string());
-    /// object.update();
+    /// let report = Object::move_prefix("my_bucket", "incoming", "my_bucket", "processed", &CopyOptions::default())?;
+    /// let moved = report.iter().filter(|copy| copy.result.is_ok()).count();
+    /// println!("moved {} objects", moved);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn update(&self) -> Result<Self, Error> {
-        let url = format!(
-            "{}/b/{}/o/{}",
-            crate::BASE_URL,
-            percent_encode(&self.bucket),
-            percent_encode(&self.name),
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .put(&url)
-            .headers(crate::get_headers()?)
-            .json(&self)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+    pub fn move_prefix(
+        source_bucket: &str,
+        source_prefix: &str,
+        destination_bucket: &str,
+        destination_prefix: &str,
+        options: &crate::CopyOptions,
+    ) -> Result<Vec<crate::ObjectCopy>, Error> {
+        crate::Client::global().object().move_prefix(
+            source_bucket,
+            source_prefix,
+            destination_bucket,
+            destination_prefix,
+            options,
+        )
     }
 
-    /// Deletes a single object with the specified name in the specified bucket.
+    /// Uploads the files in `local_dir` and its subdirectories to `bucket`, like `gsutil rsync`.
+    /// Each file is stored under `prefix`, followed by its path relative to `local_dir`. Files
+    /// that already exist with the same size and CRC32C checksum are skipped. When
+    /// `SyncOptions::delete` is set, objects under `prefix` that have no local counterpart are
+    /// deleted. Symbolic links to files are followed, but linked directories are skipped.
+    ///
+    /// No new transfers are started after one fails. The error is then returned as
+    /// `Error::SyncIncomplete`, together with the files that were transferred until then.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::Object;
+    /// use cloud_storage::{Object, SyncOptions};
     ///
-    /// let mut object = Object::delete("my_bucket", "path/to/my/file.png")?;
+    /// let options = SyncOptions {
+    ///     delete: true,
+    ///     ..Default::default()
+    /// };
+    /// let summary = Object::sync_up("./site", "my_bucket", "www", &options)?;
+    /// println!("uploaded {} files", summary.transferred.len());
     /// # Ok(())
     /// # }
     /// ```
-    pub fn delete(bucket: &str, file_name: &str) -> Result<(), Error> {
-        let url = format!(
-            "{}/b/{}/o/{}",
-            crate::BASE_URL,
-            percent_encode(bucket),
-            percent_encode(file_name),
-        );
-        let client = reqwest::blocking::Client::new();
-        let response = client.delete(&url).headers(crate::get_headers()?).send()?;
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(Error::Google(response.json()?))
-        }
+    pub fn sync_up(
+        local_dir: impl AsRef<std::path::Path>,
+        bucket: &str,
+        prefix: &str,
+        options: &crate::SyncOptions,
+    ) -> Result<crate::SyncSummary, Error> {
+        crate::Client::global()
+            .object()
+            .sync_up(local_dir, bucket, prefix, options)
     }
 
-    /// Obtains a single object with the specified name in the specified bucket.
+    /// Downloads the objects under `prefix` in `bucket` to `local_dir`, like `gsutil rsync`. Each
+    /// object is stored at its name without `prefix`, relative to `local_dir`. Files that already
+    /// exist with the same size and either the same modification time or the same CRC32C checksum
+    /// are skipped. The modification time of downloaded files is set to that of their object, so
+    /// that later runs do not need to compute checksums. When `SyncOptions::delete` is set, local
+    /// files that have no remote counterpart are deleted. Like `Object::sync_up`, it stops with
+    /// `Error::SyncIncomplete` after a failure.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::object::{Object, ComposeRequest, SourceObject};
+    /// use cloud_storage::{Object, SyncOptions};
     ///
-    /// let obj1 = Object::read("my_bucket", "file1")?;
-    /// let obj2 = Object::read("my_bucket", "file2")?;
-    /// let compose_request = ComposeRequest {
-    ///     kind: "storage#composeRequest".to_string(),
-    ///     source_objects: vec![
-    ///         SourceObject {
-    ///             name: obj1.name.clone(),
-    ///             generation: None,
-    ///             object_preconditions: None,
-    ///         },
-    ///         SourceObject {
-    ///             name: obj2.name.clone(),
-    ///             generation: None,
-    ///             object_preconditions: None,
-    ///         },
-    ///     ],
-    ///     destination: None,
-    /// };
-    /// let obj3 = Object::compose("my_bucket", &compose_request, "test-concatted-file")?;
-    /// // obj3 is now a file with the content of obj1 and obj2 concatted together.
+    /// let summary = Object::sync_down("my_bucket", "www", "./site", &SyncOptions::default())?;
+    /// println!("{} files were already up to date", summary.unchanged);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn compose(
+    pub fn sync_down(
         bucket: &str,
-        req: &ComposeRequest,
-        destination_object: &str,
-    ) -> Result<Self, Error> {
-        let url = format!(
-            "{}/b/{}/o/{}/compose",
-            crate::BASE_URL,
-            percent_encode(&bucket),
-            percent_encode(&destination_object)
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .post(&url)
-            .headers(crate::get_headers()?)
-            .json(req)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        prefix: &str,
+        local_dir: impl AsRef<std::path::Path>,
+        options: &crate::SyncOptions,
+    ) -> Result<crate::SyncSummary, Error> {
+        crate::Client::global()
+            .object()
+            .sync_down(bucket, prefix, local_dir, options)
     }
 
-    /// Copy this object to the target bucket and path
+    /// The `gs://bucket/name` uri of this object.
+    pub fn uri(&self) -> crate::GsUri {
+        crate::GsUri::from(self)
+    }
+
+    /// Creates a [Signed Url](https://cloud.google.com/storage/docs/access-control/signed-urls)
+    /// which is valid until `expiry`, and lets the posessor download the file contents without any
+    /// authentication. `expiry` is either a `std::time::Duration` or a `chrono::DateTime<Utc>`, at
+    /// most 7 days away. The url is signed with `Config::hmac_credentials` when those are set, and
+    /// with the private key of the service account otherwise.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// use cloud_storage::object::{Object, ComposeRequest};
+    /// use std::time::Duration;
     ///
     /// let obj1 = Object::read("my_bucket", "file1")?;
-    /// let obj2 = obj1.copy("my_other_bucket", "file2")?;
-    /// // obj2 is now a copy of obj1.
+    /// let url = obj1.download_url(Duration::from_secs(50))?;
+    /// // url is now a url to which an unauthenticated user can make a request to download a file
+    /// // for 50 seconds.
+    /// let tonight = chrono::Utc::now() + chrono::Duration::hours(12);
+    /// let url = obj1.download_url(tonight)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn copy(&self, destination_bucket: &str, path: &str) -> Result<Self, Error> {
-        use reqwest::header::CONTENT_LENGTH;
-
-        let url = format!(
-            "{base}/b/{sBucket}/o/{sObject}/copyTo/b/{dBucket}/o/{dObject}",
-            base = crate::BASE_URL,
-            sBucket = percent_encode(&self.bucket),
-            sObject = percent_encode(&self.name),
-            dBucket = percent_encode(&destination_bucket),
-            dObject = percent_encode(&path),
-        );
-        let client = reqwest::blocking::Client::new();
-        let mut headers = crate::get_headers()?;
-        headers.insert(CONTENT_LENGTH, "0".parse()?);
-        let result: GoogleResponse<Self> = client.post(&url).headers(headers).send()?.json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+    pub fn download_url(&self, expiry: impl Into<Expiry>) -> Result<String, Error> {
+        crate::Client::global().object().download_url(self, expiry)
     }
 
-    /// Moves a file from the current location to the target bucket and path.
-    ///
-    /// ## Limitations
-    /// This function does not yet support rewriting objects to another
-    /// * Geographical Location,
-    /// * Encryption,
-    /// * Storage class.
-    /// These limitations mean that for now, the rewrite and the copy methods do the same thing.
+    /// Creates a signed download url like `Object::download_url`, with the query parameters in
+    /// `options`.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::object::Object;
+    /// use cloud_storage::object::{Object, SignedUrlOptions};
+    /// use std::time::Duration;
     ///
-    /// let obj1 = Object::read("my_bucket", "file1")?;
-    /// let obj2 = obj1.rewrite("my_other_bucket", "file2")?;
-    /// // obj2 is now a copy of obj1.
+    /// let object = Object::read("my_bucket", "report.pdf")?;
+    /// let options = SignedUrlOptions::default()
+    ///     .query_parameter("generation", object.generation.to_string())
+    ///     .query_parameter("response-content-disposition", "attachment; filename=\"report.pdf\"");
+    /// let url = object.download_url_with(Duration::from_secs(600), &options)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn rewrite(&self, destination_bucket: &str, path: &str) -> Result<Self, Error> {
-        use reqwest::header::CONTENT_LENGTH;
-
-        let url = format!(
-            "{base}/b/{sBucket}/o/{sObject}/rewriteTo/b/{dBucket}/o/{dObject}",
-            base = crate::BASE_URL,
-            sBucket = percent_encode(&self.bucket),
-            sObject = percent_encode(&self.name),
-            dBucket = percent_encode(destination_bucket),
-            dObject = percent_encode(path),
-        );
-        let client = reqwest::blocking::Client::new();
-        let mut headers = crate::get_headers()?;
-        headers.insert(CONTENT_LENGTH, "0".parse()?);
-        let result: GoogleResponse<RewriteResponse> =
-            client.post(&url).headers(headers).send()?.json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s.resource),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+    pub fn download_url_with(
+        &self,
+        expiry: impl Into<Expiry>,
+        options: &SignedUrlOptions,
+    ) -> Result<String, Error> {
+        crate::Client::global()
+            .object()
+            .download_url_with(self, expiry, options)
     }
 
     /// Creates a [Signed Url](https://cloud.google.com/storage/docs/access-control/signed-urls)
-    /// which is valid for `duration` seconds, and lets the posessor download the file contents
-    /// without any authentication.
+    /// which is valid until `expiry`, and lets the posessor make a `method` request to this object
+    /// without any authentication: download it, read its metadata, overwrite it or delete it.
+    /// Requests made with the url must carry the headers in `options`.
     /// ### Example
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use cloud_storage::object::{Object, ComposeRequest};
+    /// use cloud_storage::object::{Object, SignedUrlMethod, SignedUrlOptions};
+    /// use std::time::Duration;
     ///
-    /// let obj1 = Object::read("my_bucket", "file1")?;
-    /// let url = obj1.download_url(50)?;
-    /// // url is now a url to which an unauthenticated user can make a request to download a file
-    /// // for 50 seconds.
+    /// let object = Object::read("my_bucket", "uploads/tmp.csv")?;
+    /// let options = SignedUrlOptions::default()
+    ///     .query_parameter("generation", object.generation.to_string());
+    /// // lets a cleanup job delete exactly this generation within the next hour
+    /// let url = object.signed_url(SignedUrlMethod::Delete, Duration::from_secs(3600), &options)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn download_url(&self, duration: u32) -> Result<String, Error> {
-        self.sign(&self.name, duration, "GET")
-    }
-
-    // /// Creates a [Signed Url](https://cloud.google.com/storage/docs/access-control/signed-urls)
-    // /// which is valid for `duration` seconds, and lets the posessor upload new file contents.
-    // /// without any authentication.
-    // pub fn upload_url(&self, duration: u32) -> Result<String, Error> {
-    //     self.sign(&self.name, duration, "POST")
-    // }
-
-    #[inline(always)]
-    fn sign(&self, file_path: &str, duration: u32, http_verb: &str) -> Result<String, Error> {
-        use openssl::sha;
-
-        if duration > 604800 {
-            let msg = format!(
-                "duration may not be greater than 604800, but was {}",
-                duration
-            );
-            return Err(Error::Other(msg));
-        }
-
-        // 1 construct the canonical reques
-        let issue_date = chrono::Utc::now();
-        let file_path = self.path_to_resource(file_path);
-        let query_string = Self::get_canonical_query_string(&issue_date, duration);
-        let canonical_request = self.get_canonical_request(&file_path, &query_string, http_verb);
-
-        // 2 get hex encoded SHA256 hash the canonical request
-        let hash = sha::sha256(canonical_request.as_bytes());
-        let hex_hash = hex::encode(hash);
-
-        // 3 construct the string to sign
-        let string_to_sign = format!(
-            "{signing_algorithm}\n\
-            {current_datetime}\n\
-            {credential_scope}\n\
-            {hashed_canonical_request}",
-            signing_algorithm = "GOOG4-RSA-SHA256",
-            current_datetime = issue_date.format("%Y%m%dT%H%M%SZ"),
-            credential_scope = Self::get_credential_scope(&issue_date),
-            hashed_canonical_request = hex_hash,
-        );
-
-        // 4 sign the string to sign with RSA - SHA256
-        let buffer = Self::sign_str(&string_to_sign);
-        let signature = hex::encode(&buffer?);
-
-        // 5 construct the signed url
-        Ok(format!(
-            "https://storage.googleapis.com{path_to_resource}?\
-            {query_string}&\
-            X-Goog-Signature={request_signature}",
-            path_to_resource = file_path,
-            query_string = query_string,
-            request_signature = signature,
-        ))
-    }
-
-    #[inline(always)]
-    fn get_canonical_request(&self, path: &str, query_string: &str, http_verb: &str) -> String {
-        format!(
-            "{http_verb}\n\
-            {path_to_resource}\n\
-            {canonical_query_string}\n\
-            {canonical_headers}\n\
-            \n\
-            {signed_headers}\n\
-            {payload}",
-            http_verb = http_verb,
-            path_to_resource = path,
-            canonical_query_string = query_string,
-            canonical_headers = "host:storage.googleapis.com",
-            signed_headers = "host",
-            payload = "UNSIGNED-PAYLOAD",
-        )
-    }
-
-    #[inline(always)]
-    fn get_canonical_query_string(date: &chrono::DateTime<chrono::Utc>, exp: u32) -> String {
-        let credential = format!(
-            "{authorizer}/{scope}",
-            authorizer = crate::SERVICE_ACCOUNT.client_email,
-            scope = Self::get_credential_scope(date),
-        );
-        format!(
-            "X-Goog-Algorithm={algo}&\
-            X-Goog-Credential={cred}&\
-            X-Goog-Date={date}&\
-            X-Goog-Expires={exp}&\
-            X-Goog-SignedHeaders={signed}",
-            algo = "GOOG4-RSA-SHA256",
-            cred = percent_encode(&credential),
-            date = date.format("%Y%m%dT%H%M%SZ"),
-            exp = exp,
-            signed = "host",
-        )
-    }
-
-    #[inline(always)]
-    fn path_to_resource(&self, path: &str) -> String {
-        format!(
-            "/{bucket}/{file_path}",
-            bucket = self.bucket,
-            file_path = percent_encode_noslash(path),
+    pub fn signed_url(
+        &self,
+        method: SignedUrlMethod,
+        expiry: impl Into<Expiry>,
+        options: &SignedUrlOptions,
+    ) -> Result<String, Error> {
+        crate::Client::global().object().signed_url(
+            &self.bucket,
+            &self.name,
+            method,
+            expiry,
+            options,
         )
     }
 
-    #[inline(always)]
-    fn get_credential_scope(date: &chrono::DateTime<chrono::Utc>) -> String {
-        format!("{}/henk/storage/goog4_request", date.format("%Y%m%d"))
-    }
-
-    #[inline(always)]
-    fn sign_str(message: &str) -> Result<Vec<u8>, Error> {
-        use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
-
-        let key = PKey::private_key_from_pem(crate::SERVICE_ACCOUNT.private_key.as_bytes())?;
-        let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
-        signer.update(message.as_bytes())?;
-        Ok(signer.sign_to_vec()?)
+    /// Creates a [Signed Url](https://cloud.google.com/storage/docs/access-control/signed-urls)
+    /// which is valid until `expiry`, and lets the posessor start a
+    /// [resumable upload](https://cloud.google.com/storage/docs/performing-resumable-uploads) of
+    /// `filename` in `bucket` without any authentication, for example from a browser.
+    ///
+    /// The upload is started by a `POST` request to the url with the header
+    /// `x-goog-resumable: start` and the headers in `options`, and the content is then uploaded
+    /// with `PUT` requests to the session url in the `Location` header of the response.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::object::{Object, SignedUrlOptions};
+    /// use std::time::Duration;
+    ///
+    /// let options = SignedUrlOptions::default().header("content-type", "video/mp4");
+    /// let url = Object::resumable_upload_url(
+    ///     "my_bucket",
+    ///     "holiday.mp4",
+    ///     Duration::from_secs(600),
+    ///     &options,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resumable_upload_url(
+        bucket: &str,
+        filename: &str,
+        expiry: impl Into<Expiry>,
+        options: &SignedUrlOptions,
+    ) -> Result<String, Error> {
+        crate::Client::global()
+            .object()
+            .resumable_upload_url(bucket, filename, expiry, options)
     }
 }
 
@@ -698,22 +1551,131 @@ const NOSLASH_ENCODE_SET: &AsciiSet = &ENCODE_SET.remove(b'/').remove(b'~');
 
 // We need to be able to percent encode stuff, but without touching the slashes in filenames. To
 // this end we create an implementation that does this, without touching the slashes.
-fn percent_encode_noslash(input: &str) -> String {
+pub(crate) fn percent_encode_noslash(input: &str) -> String {
     utf8_percent_encode(input, NOSLASH_ENCODE_SET).to_string()
 }
 
-fn percent_encode(input: &str) -> String {
+pub(crate) fn percent_encode(input: &str) -> String {
     utf8_percent_encode(input, ENCODE_SET).to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::RequestExt;
+    use std::time::Duration;
 
     #[test]
-    fn create() -> Result<(), Box<dyn std::error::Error>> {
+    fn media_info() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        let hashes = "crc32c=n03x6A==, md5=Ojk9c3dhfxgoKVVHYwFbHQ==";
+        headers.append("x-goog-hash", HeaderValue::from_static(hashes));
+        headers.append(
+            "x-goog-generation",
+            HeaderValue::from_static("1560000000000000"),
+        );
+        headers.append("x-goog-metageneration", HeaderValue::from_static("2"));
+        headers.append(
+            "x-goog-stored-content-length",
+            HeaderValue::from_static("11"),
+        );
+        headers.append(
+            "x-goog-stored-content-encoding",
+            HeaderValue::from_static("identity"),
+        );
+        let info = MediaInfo::from_headers(&headers);
+        assert_eq!(
+            info.crc32c,
+            Some(crate::crc32c::decode("n03x6A==").unwrap())
+        );
+        assert_eq!(
+            info.md5,
+            Some(crate::md5::decode("Ojk9c3dhfxgoKVVHYwFbHQ==").unwrap())
+        );
+        assert_eq!(info.generation, Some(1560000000000000));
+        assert_eq!(info.metageneration, Some(2));
+        assert_eq!(info.stored_content_length, Some(11));
+        assert_eq!(info.stored_content_encoding.as_deref(), Some("identity"));
+
+        // separate headers, and values that cannot be parsed
+        let mut headers = HeaderMap::new();
+        headers.append("x-goog-hash", HeaderValue::from_static("crc32c=n03x6A=="));
+        headers.append("x-goog-hash", HeaderValue::from_static("md5=not-base64"));
+        headers.append("x-goog-generation", HeaderValue::from_static("latest"));
+        let info = MediaInfo::from_headers(&headers);
+        assert!(info.crc32c.is_some());
+        assert_eq!(info.md5, None);
+        assert_eq!(info.generation, None);
+        assert_eq!(
+            MediaInfo::from_headers(&HeaderMap::new()),
+            MediaInfo::default()
+        );
+    }
+
+    #[test]
+    fn object_head_headers() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        headers.append("content-type", HeaderValue::from_static("text/plain"));
+        headers.append("content-length", HeaderValue::from_static("31"));
+        headers.append("etag", HeaderValue::from_static("\"CPjf1dHo+OkCEAE=\""));
+        headers.append("x-goog-generation", HeaderValue::from_static("3"));
+        let head = ObjectHead::from_headers(&headers);
+        assert_eq!(head.size, Some(31));
+        assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(head.etag.as_deref(), Some("CPjf1dHo+OkCEAE="));
+        assert_eq!(head.media.generation, Some(3));
+
+        // the stored size wins over the length of a decompressed response
+        headers.append(
+            "x-goog-stored-content-length",
+            HeaderValue::from_static("20"),
+        );
+        assert_eq!(ObjectHead::from_headers(&headers).size, Some(20));
+    }
+
+    #[test]
+    fn head() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let object = Object::create(&bucket.name, b"head", "test-head", "text/plain")?;
+        let head = Object::head(&bucket.name, "test-head")?;
+        assert_eq!(head.size, Some(4));
+        assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(head.media.generation, Some(object.generation));
+        assert_eq!(head.media.crc32c, Some(crate::crc32c::checksum(b"head")));
+        Object::delete(&bucket.name, "test-head")?;
+        assert!(Object::head(&bucket.name, "test-head")
+            .unwrap_err()
+            .is_not_found());
+        Ok(())
+    }
+
+    #[test]
+    fn download_with_info() -> Result<(), Box<dyn std::error::Error>> {
         let bucket = crate::read_test_bucket();
-        Object::create(&bucket.name, &[0, 1], "test-create", "text/plain")?;
+        let object = Object::create(&bucket.name, b"hashed", "test-media-info", "text/plain")?;
+        let (bytes, info) = Object::download_with_info(&bucket.name, "test-media-info")?;
+        assert_eq!(&bytes[..], b"hashed");
+        assert_eq!(info.crc32c, Some(crate::crc32c::checksum(b"hashed")));
+        assert_eq!(info.md5, Some(crate::md5::compute(b"hashed")));
+        assert_eq!(info.generation, Some(object.generation));
+        assert_eq!(info.stored_content_length, Some(6));
+        Object::delete(&bucket.name, "test-media-info")?;
+        Ok(())
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn create() -> Result<(), Box<dyn std::error::Error>> {
+        let client = crate::replay_client("object_create");
+        let object =
+            client
+                .object()
+                .create(crate::FIXTURE_BUCKET, &[0, 1], "test-create", "text/plain")?;
+        assert_eq!(object.size, 2);
         Ok(())
     }
 
@@ -760,48 +1722,104 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn list_with_pages() -> Result<(), Box<dyn std::error::Error>> {
+        let test_bucket = crate::read_test_bucket();
+        for i in 0..5 {
+            let name = format!("test-list-with/{}", i);
+            Object::create(&test_bucket.name, &[0, 1], &name, "text/plain")?;
+        }
+        // a page size of 2 takes three pages to list all five objects
+        let names = Object::list_with(&test_bucket.name, Some("test-list-with/"), Some(2))
+            .map(|object| object.map(|o| o.name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let expected: Vec<_> = (0..5).map(|i| format!("test-list-with/{}", i)).collect();
+        assert_eq!(names, expected);
+        assert_eq!(
+            Object::list_prefix(&test_bucket.name, "test-list-with/")?.len(),
+            5
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn list_glob() -> Result<(), Box<dyn std::error::Error>> {
+        let test_bucket = crate::read_test_bucket();
+        for name in &[
+            "test-glob/a.parquet",
+            "test-glob/b/c.parquet",
+            "test-glob/d.csv",
+        ] {
+            Object::create(&test_bucket.name, &[0, 1], name, "text/plain")?;
+        }
+        let names = Object::list_glob(&test_bucket.name, "test-glob/**.parquet")
+            .map(|object| object.map(|o| o.name))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(names, ["test-glob/a.parquet", "test-glob/b/c.parquet"]);
+        Ok(())
+    }
+
+    #[cfg(feature = "testing")]
     #[test]
     fn read() -> Result<(), Box<dyn std::error::Error>> {
-        let bucket = crate::read_test_bucket();
-        Object::create(&bucket.name, &[0, 1], "test-read", "text/plain")?;
-        Object::read(&bucket.name, "test-read")?;
+        let client = crate::replay_client("object_read");
+        let bucket = crate::FIXTURE_BUCKET;
+        let created = client
+            .object()
+            .create(bucket, &[0, 1], "test-read", "text/plain")?;
+        let object = client.object().read(bucket, "test-read")?;
+        assert_eq!(object.generation, created.generation);
         Ok(())
     }
 
+    #[cfg(feature = "testing")]
     #[test]
     fn download() -> Result<(), Box<dyn std::error::Error>> {
-        let bucket = crate::read_test_bucket();
+        let client = crate::replay_client("object_download");
+        let bucket = crate::FIXTURE_BUCKET;
         let content = b"hello world";
-        Object::create(
-            &bucket.name,
-            content,
-            "test-download",
-            "application/octet-stream",
-        )?;
+        client
+            .object()
+            .create(bucket, content, "test-download", "application/octet-stream")?;
 
-        let data = Object::download(&bucket.name, "test-download")?;
+        let data = client.object().download(bucket, "test-download")?;
         assert_eq!(data.as_ref(), content);
 
         Ok(())
     }
 
+    #[cfg(feature = "testing")]
     #[test]
     fn update() -> Result<(), Box<dyn std::error::Error>> {
-        let bucket = crate::read_test_bucket();
-        let mut obj = Object::create(&bucket.name, &[0, 1], "test-update", "text/plain")?;
+        let client = crate::replay_client("object_update");
+        let objects = client.object();
+        let mut obj =
+            objects.create(crate::FIXTURE_BUCKET, &[0, 1], "test-update", "text/plain")?;
         obj.content_type = Some("application/xml".to_string());
-        obj.update()?;
+        let updated = objects.update(&obj)?;
+        assert_eq!(updated.content_type.as_deref(), Some("application/xml"));
+        // the metageneration of `obj` is stale now
+        obj.content_type = Some("text/xml".to_string());
+        assert!(matches!(
+            objects.update_if_unchanged(&obj),
+            Err(Error::PreconditionFailed(_))
+        ));
+        objects.update_if_unchanged(&updated)?;
         Ok(())
     }
 
+    #[cfg(feature = "testing")]
     #[test]
     fn delete() -> Result<(), Box<dyn std::error::Error>> {
-        let bucket = crate::read_test_bucket();
-        Object::create(&bucket.name, &[0, 1], "test-delete", "text/plain")?;
+        let client = crate::replay_client("object_delete");
+        let bucket = crate::FIXTURE_BUCKET;
+        client
+            .object()
+            .create(bucket, &[0, 1], "test-delete", "text/plain")?;
 
-        Object::delete(&bucket.name, "test-delete")?;
+        client.object().delete(bucket, "test-delete")?;
 
-        let list = Object::list_prefix(&bucket.name, "test-delete")?;
+        let list = client.object().list_prefix(bucket, "test-delete")?;
         assert!(list.is_empty());
 
         Ok(())
@@ -829,12 +1847,86 @@ mod tests {
             destination: None,
         };
         let obj3 = Object::compose(&bucket.name, &compose_request, "test-concatted-file")?;
-        let url = obj3.download_url(100)?;
+        let url = obj3.download_url(Duration::from_secs(100))?;
         let content = reqwest::blocking::get(&url)?.text()?;
         assert_eq!(content.as_bytes(), &[0, 1, 2, 3]);
         Ok(())
     }
 
+    #[test]
+    fn serializes_without_nulls() {
+        let json = serde_json::json!({
+            "kind": "storage#object",
+            "id": "bucket/file.txt/1",
+            "selfLink": "https://www.googleapis.com/storage/v1/b/bucket/o/file.txt",
+            "name": "file.txt",
+            "bucket": "bucket",
+            "generation": "1",
+            "metageneration": "1",
+            "contentType": "text/plain",
+            "timeCreated": "2020-09-13T12:26:40.000Z",
+            "updated": "2020-09-13T12:26:40.000Z",
+            "storageClass": "STANDARD",
+            "timeStorageClassUpdated": "2020-09-13T12:26:40.000Z",
+            "size": "5",
+            "mediaLink": "https://storage.googleapis.com/download/storage/v1/b/bucket/o/file.txt",
+            "crc32c": "AAAAAA==",
+            "etag": "CAE=",
+        });
+        let object: Object = serde_json::from_value(json).unwrap();
+        let mut changed = object.clone();
+        changed.cache_control = Some("no-cache".to_string());
+        assert_ne!(changed, object);
+
+        let serialized = serde_json::to_value(&changed).unwrap();
+        let fields = serialized.as_object().unwrap();
+        assert!(fields.values().all(|value| !value.is_null()));
+        assert!(!fields.contains_key("metadata"));
+        assert_eq!(fields["cacheControl"], "no-cache");
+    }
+
+    #[test]
+    fn expiry_is_at_most_seven_days() {
+        let now = chrono::Utc::now();
+        assert_eq!(Expiry::from(50).seconds(now).unwrap(), 50);
+        assert_eq!(
+            Expiry::from(Duration::from_millis(1500))
+                .seconds(now)
+                .unwrap(),
+            2
+        );
+        let in_an_hour = now + chrono::Duration::hours(1);
+        assert_eq!(Expiry::from(in_an_hour).seconds(now).unwrap(), 3600);
+        assert!(Expiry::from(Expiry::MAX).seconds(now).is_ok());
+        assert!(Expiry::from(Expiry::MAX + Duration::from_secs(1))
+            .seconds(now)
+            .is_err());
+        assert!(Expiry::from(now - chrono::Duration::seconds(1))
+            .seconds(now)
+            .is_err());
+    }
+
+    #[test]
+    fn compose_destination() {
+        let request = ComposeRequest {
+            kind: "storage#composeRequest".to_string(),
+            source_objects: Vec::new(),
+            destination: Some(DestinationObject {
+                content_type: Some("text/csv".to_string()),
+                cache_control: Some("no-cache".to_string()),
+                ..Default::default()
+            }),
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "kind": "storage#composeRequest",
+                "sourceObjects": [],
+                "destination": { "contentType": "text/csv", "cacheControl": "no-cache" },
+            })
+        );
+    }
+
     #[test]
     fn copy() -> Result<(), Box<dyn std::error::Error>> {
         let bucket = crate::read_test_bucket();
@@ -843,14 +1935,150 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn create_and_copy_with_acl() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let original = Object::create_with_acl(
+            &bucket.name,
+            &[4, 5],
+            "test-create-acl",
+            "text/plain",
+            PredefinedAcl::Private,
+        )?;
+        let copy = original.copy_with_acl(
+            &bucket.name,
+            "test-create-acl - copy",
+            PredefinedAcl::BucketOwnerRead,
+        )?;
+        assert!(copy
+            .acl
+            .unwrap_or_default()
+            .iter()
+            .any(|acl| acl.entity.to_string().starts_with("project-owners-")));
+        Ok(())
+    }
+
+    #[test]
+    fn partial_objects() -> Result<(), Box<dyn std::error::Error>> {
+        let partial: PartialObject =
+            serde_json::from_str(r#"{"name": "a.txt", "size": "12", "generation": "3"}"#)?;
+        assert_eq!(partial.name.as_deref(), Some("a.txt"));
+        assert_eq!((partial.size, partial.generation), (Some(12), Some(3)));
+        assert_eq!(partial.updated, None);
+
+        let bucket = crate::read_test_bucket();
+        Object::create(&bucket.name, &[7, 8], "test-partial", "text/plain")?;
+        let object = Object::read_partial(&bucket.name, "test-partial", "name,size")?;
+        assert_eq!(object.size, Some(2));
+        assert_eq!(object.etag, None);
+        let options = ListOptions {
+            prefix: Some("test-partial".to_string()),
+            page_size: Some(1),
+            ..Default::default()
+        };
+        let listed =
+            Object::list_partial(&bucket.name, &options, "name").collect::<Result<Vec<_>, _>>()?;
+        assert!(listed
+            .iter()
+            .any(|object| object.name.as_deref() == Some("test-partial")));
+        Ok(())
+    }
+
+    #[test]
+    fn read_and_list_without_acl() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        Object::create(&bucket.name, &[6], "test-projection", "text/plain")?;
+        let object =
+            Object::read_with_projection(&bucket.name, "test-projection", Projection::NoAcl)?;
+        assert!(object.acl.is_none() && object.owner.is_none());
+        let options = ListOptions {
+            prefix: Some("test-projection".to_string()),
+            projection: Some(Projection::NoAcl),
+            ..Default::default()
+        };
+        let listed =
+            Object::list_with_options(&bucket.name, &options).collect::<Result<Vec<_>, _>>()?;
+        assert!(listed.iter().all(|object| object.acl.is_none()));
+        Ok(())
+    }
+
+    #[test]
+    fn list_ranges_and_folders() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        for name in &[
+            "test-range/a",
+            "test-range/b",
+            "test-range/c",
+            "test-range/d/",
+        ] {
+            Object::create(&bucket.name, &[1], name, "text/plain")?;
+        }
+        let names = |options: &ListOptions| -> Result<Vec<String>, Error> {
+            Object::list_with_options(&bucket.name, options)
+                .map(|object| object.map(|object| object.name))
+                .collect()
+        };
+        let range = ListOptions {
+            prefix: Some("test-range/".to_string()),
+            start_offset: Some("test-range/b".to_string()),
+            end_offset: Some("test-range/d".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(names(&range)?, vec!["test-range/b", "test-range/c"]);
+        let folder = ListOptions {
+            prefix: Some("test-range/".to_string()),
+            delimiter: Some("/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(names(&folder)?.len(), 3);
+        let folder = ListOptions {
+            include_trailing_delimiter: true,
+            ..folder
+        };
+        assert!(names(&folder)?.contains(&"test-range/d/".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn promote_generation() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let live = Object::create(&bucket.name, &[1], "test-promote", "text/plain")?;
+        let promoted = Object::promote_generation(&bucket.name, "test-promote", live.generation)?;
+        assert_eq!(promoted.generation, live.generation);
+        let missing = Object::promote_generation(&bucket.name, "test-promote", 1);
+        assert!(missing.unwrap_err().is_not_found());
+        Object::delete(&bucket.name, "test-promote")?;
+        Ok(())
+    }
+
+    #[test]
+    fn rename() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let object = Object::create(&bucket.name, &[1], "test-rename", "text/plain")?;
+        let taken = Object::create(&bucket.name, &[2], "test-renamed-taken", "text/plain")?;
+        assert!(object
+            .rename("test-renamed-taken")
+            .unwrap_err()
+            .is_precondition_failed());
+        let renamed = object.rename("test-renamed")?;
+        assert_eq!(renamed.name, "test-renamed");
+        assert!(Object::read(&bucket.name, "test-rename")
+            .unwrap_err()
+            .is_not_found());
+        assert!(object.rename("test-renamed-again").is_err());
+        Object::delete(&bucket.name, &renamed.name)?;
+        Object::delete(&bucket.name, &taken.name)?;
+        Ok(())
+    }
+
     #[test]
     fn rewrite() -> Result<(), Box<dyn std::error::Error>> {
         let bucket = crate::read_test_bucket();
         let obj = Object::create(&bucket.name, &[0, 1], "test-rewrite", "text/plain")?;
         let obj = obj.rewrite(&bucket.name, "test-rewritten")?;
-        let url = obj.download_url(100)?;
-        let client = reqwest::blocking::Client::new();
-        let download = client.head(&url).send()?;
+        let url = obj.download_url(Duration::from_secs(100))?;
+        let client = crate::Client::global();
+        let download = client.http()?.head(&url).execute(&client)?;
         assert_eq!(download.status().as_u16(), 200);
         Ok(())
     }
@@ -868,10 +2096,10 @@ mod tests {
         ];
         for name in &complicated_names {
             let _obj = Object::create(&bucket.name, &[0, 1], name, "text/plain")?;
-            let obj = Object::read(&bucket.name, &name).unwrap();
-            let url = obj.download_url(100)?;
-            let client = reqwest::blocking::Client::new();
-            let download = client.head(&url).send()?;
+            let obj = Object::read(&bucket.name, name).unwrap();
+            let url = obj.download_url(Duration::from_secs(100))?;
+            let client = crate::Client::global();
+            let download = client.http()?.head(&url).execute(&client)?;
             assert_eq!(download.status().as_u16(), 200);
         }
         Ok(())
@@ -1,5 +1,3 @@
-use crate::error::GoogleResponse;
-use crate::resources::common::ListResponse;
 pub use crate::resources::common::{Entity, ProjectTeam, Role};
 
 /// The ObjectAccessControls resources represent the Access Control Lists (ACLs) for objects within
@@ -19,7 +17,7 @@ pub use crate::resources::common::{Entity, ProjectTeam, Role};
 ///
 /// For more information, see Access Control, with the caveat that this API uses READER and OWNER
 /// instead of READ and FULL_CONTROL.
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ObjectAccessControl {
     /// The kind of item this is. For object access control entries, this is always
@@ -34,6 +32,7 @@ pub struct ObjectAccessControl {
     /// The name of the object, if applied to an object.
     pub object: String,
     /// The content generation of the object, if applied to an object.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub generation: Option<String>,
     /// The entity holding the permission, in one of the following forms:
     ///
@@ -56,19 +55,23 @@ pub struct ObjectAccessControl {
     /// The access permission for the entity.
     pub role: Role,
     /// The email address associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
     /// The ID for the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub entity_id: Option<String>,
     /// The domain associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub domain: Option<String>,
     /// The project team associated with the entity, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub project_team: Option<ProjectTeam>,
     /// HTTP 1.1 Entity tag for the access-control entry.
     pub etag: String,
 }
 
 /// Used to create a new `ObjectAccessControl` object.
-#[derive(Debug, PartialEq, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NewObjectAccessControl {
     /// The entity holding the permission, in one of the following forms:
@@ -101,6 +104,7 @@ struct ObjectAccessControlList {
     items: Vec<ObjectAccessControl>,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl ObjectAccessControl {
     /// Creates a new ACL entry on the specified `object`.
     ///
@@ -113,18 +117,11 @@ impl ObjectAccessControl {
         object: &str,
         new_object_access_control: &NewObjectAccessControl,
     ) -> Result<Self, crate::Error> {
-        let url = format!("{}/b/{}/o/{}/acl", crate::BASE_URL, bucket, object);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .post(&url)
-            .headers(crate::get_headers()?)
-            .json(new_object_access_control)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().object_access_control().create(
+            bucket,
+            object,
+            new_object_access_control,
+        )
     }
 
     /// Retrieves `ACL` entries on the specified object.
@@ -134,17 +131,9 @@ impl ObjectAccessControl {
     /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
     /// control access instead.
     pub fn list(bucket: &str, object: &str) -> Result<Vec<Self>, crate::Error> {
-        let url = format!("{}/b/{}/o/{}/acl", crate::BASE_URL, bucket, object);
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<ListResponse<Self>> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s.items),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .object_access_control()
+            .list(bucket, object)
     }
 
     /// Returns the `ACL` entry for the specified entity on the specified bucket.
@@ -154,23 +143,9 @@ impl ObjectAccessControl {
     /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
     /// control access instead.
     pub fn read(bucket: &str, object: &str, entity: &Entity) -> Result<Self, crate::Error> {
-        let url = format!(
-            "{}/b/{}/o/{}/acl/{}",
-            crate::BASE_URL,
-            bucket,
-            object,
-            entity
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .get(&url)
-            .headers(crate::get_headers()?)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global()
+            .object_access_control()
+            .read(bucket, object, entity)
     }
 
     /// Updates an ACL entry on the specified object.
@@ -180,24 +155,7 @@ impl ObjectAccessControl {
     /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
     /// control access instead.
     pub fn update(&self) -> Result<Self, crate::Error> {
-        let url = format!(
-            "{}/b/{}/o/{}/acl/{}",
-            crate::BASE_URL,
-            self.bucket,
-            self.object,
-            self.entity,
-        );
-        let client = reqwest::blocking::Client::new();
-        let result: GoogleResponse<Self> = client
-            .put(&url)
-            .headers(crate::get_headers()?)
-            .json(self)
-            .send()?
-            .json()?;
-        match result {
-            GoogleResponse::Success(s) => Ok(s),
-            GoogleResponse::Error(e) => Err(e.into()),
-        }
+        crate::Client::global().object_access_control().update(self)
     }
 
     /// Permanently deletes the ACL entry for the specified entity on the specified object.
@@ -207,20 +165,7 @@ impl ObjectAccessControl {
     /// bucket-level access enabled. Use `Bucket::get_iam_policy` and `Bucket::set_iam_policy` to
     /// control access instead.
     pub fn delete(self) -> Result<(), crate::Error> {
-        let url = format!(
-            "{}/b/{}/o/{}/acl/{}",
-            crate::BASE_URL,
-            self.bucket,
-            self.object,
-            self.entity,
-        );
-        let client = reqwest::blocking::Client::new();
-        let response = client.delete(&url).headers(crate::get_headers()?).send()?;
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(crate::Error::Google(response.json()?))
-        }
+        crate::Client::global().object_access_control().delete(self)
     }
 }
 
@@ -0,0 +1,51 @@
+/// The service agent that Cloud Storage uses to act on behalf of a project, for example to publish
+/// Pub/Sub notifications or to encrypt objects with a customer-managed Cloud KMS key. Such
+/// features only work after this account has been granted the appropriate permissions.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectServiceAccount {
+    /// The kind of item this is. For this resource, this is always `storage#serviceAccount`.
+    pub kind: String,
+    /// The email address of the service agent, which has the form
+    /// `service-<project-number>@gs-project-accounts.iam.gserviceaccount.com`.
+    pub email_address: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ProjectServiceAccount {
+    /// Retrieves the email address of the Cloud Storage service agent of the given project.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::project::ProjectServiceAccount;
+    ///
+    /// let service_agent = ProjectServiceAccount::get("my-project")?;
+    /// println!("grant access to {}", service_agent.email_address);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get(project_id: &str) -> Result<Self, crate::Error> {
+        crate::Client::global()
+            .project_service_account()
+            .get(project_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get() -> Result<(), Box<dyn std::error::Error>> {
+        let service_agent = ProjectServiceAccount::get(
+            &crate::Client::global()
+                .service_account()
+                .unwrap()
+                .project_id,
+        )?;
+        assert!(service_agent
+            .email_address
+            .ends_with("@gs-project-accounts.iam.gserviceaccount.com"));
+        Ok(())
+    }
+}
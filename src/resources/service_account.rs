@@ -1,5 +1,5 @@
 /// A deserialized `service-account-********.json`-file.
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Deserialize, Debug, Clone)]
 pub struct ServiceAccount {
     /// The type of authentication, this should always be `service_account`.
     #[serde(rename = "type")]
@@ -25,15 +25,52 @@ pub struct ServiceAccount {
 }
 
 impl ServiceAccount {
-    pub(crate) fn get() -> Self {
-        dotenv::dotenv().ok();
-        let path = std::env::var("SERVICE_ACCOUNT")
-            .expect("SERVICE_ACCOUNT environment parameter required");
-        let file = std::fs::read_to_string(path).expect("SERVICE_ACCOUNT file not found");
-        let account: Self = serde_json::from_str(&file).expect("serivce account file not valid");
+    /// Reads a `service-account-********.json` file, as downloaded from the cloud console.
+    /// ### Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use cloud_storage::{Client, ServiceAccount};
+    ///
+    /// let client = Client::new(ServiceAccount::from_file("service-account.json")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, crate::Error> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            crate::Error::Other(format!(
+                "could not read service account file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_json(&contents)
+    }
+
+    /// Parses the contents of a `service-account-********.json` file.
+    pub fn from_json(json: &str) -> Result<Self, crate::Error> {
+        let account: Self = serde_json::from_str(json)?;
         if account.r#type != "service_account" {
-            panic!("`type` paramter of `SERVICE_ACCOUNT` variable is not 'service_account'");
+            return Err(crate::Error::Other(format!(
+                "expected credentials of type `service_account`, but found `{}`",
+                account.r#type
+            )));
+        }
+        Ok(account)
+    }
+
+    /// Reads the service account file that the `SERVICE_ACCOUNT` or
+    /// `GOOGLE_APPLICATION_CREDENTIALS` environment parameter points to.
+    pub(crate) fn from_env() -> Result<Self, crate::Error> {
+        dotenv::dotenv().ok();
+        for var in &["SERVICE_ACCOUNT", "GOOGLE_APPLICATION_CREDENTIALS"] {
+            if let Some(path) = std::env::var_os(var).filter(|p| !p.is_empty()) {
+                return Self::from_file(path);
+            }
         }
-        account
+        Err(crate::Error::new(
+            "this operation requires a service account: pass one to `Client::new`, or point the \
+             `SERVICE_ACCOUNT` environment parameter to a service account file",
+        ))
     }
 }
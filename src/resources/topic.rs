@@ -1,5 +1,5 @@
 /// The topic of a notification
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Topic {
     /// The project within which you want to receive notifications
     pub project_id: String,
@@ -42,7 +42,7 @@ impl<'de> serde::de::Visitor<'de> for TopicVisitor {
         let mut parts_iter = value.split('/');
         let error = || E::custom(format!("Invalid topic: `{}`", value));
         const START: [&str; 4] = ["", "", "pubsub.googleapis.com", "projects"];
-        if parts_iter.clone().take(4).collect::<Vec<_>>() != START {
+        if parts_iter.by_ref().take(4).collect::<Vec<_>>() != START {
             return Err(error());
         }
         let project_id = parts_iter.next().ok_or_else(error)?;
@@ -66,3 +66,23 @@ impl<'de> serde::Deserialize<'de> for Topic {
         deserializer.deserialize_str(TopicVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let topic = Topic {
+            project_id: "my-project".to_string(),
+            topic: "my-topic".to_string(),
+        };
+        let json = serde_json::to_string(&topic).unwrap();
+        assert_eq!(
+            json,
+            "\"//pubsub.googleapis.com/projects/my-project/topics/my-topic\""
+        );
+        assert_eq!(serde_json::from_str::<Topic>(&json).unwrap(), topic);
+        assert!(serde_json::from_str::<Topic>("\"projects/my-project\"").is_err());
+    }
+}
@@ -0,0 +1,43 @@
+//! Adapters that let the async parts of this crate run under any async runtime. Every request of
+//! this crate blocks the current thread, so async callers hand them off to a thread pool, which is
+//! the only part that depends on the runtime. Retries and their backoff happen within that
+//! blocking call, so no runtime-specific timers are needed.
+//!
+//! By default the thread pool of the [blocking](https://docs.rs/blocking) crate is used, which is
+//! also what `smol` uses and works from within any runtime. The `runtime-tokio` and
+//! `runtime-async-std` features switch to the thread pool of those runtimes instead, which then
+//! has to be running.
+
+/// Runs `f` on a thread on which it may block, and waits for its result.
+#[cfg(feature = "runtime-tokio")]
+pub(crate) async fn unblock<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => panic!("blocking task did not finish: {}", e),
+    }
+}
+
+/// Runs `f` on a thread on which it may block, and waits for its result.
+#[cfg(all(feature = "runtime-async-std", not(feature = "runtime-tokio")))]
+pub(crate) async fn unblock<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    async_std::task::spawn_blocking(f).await
+}
+
+/// Runs `f` on a thread on which it may block, and waits for its result.
+#[cfg(not(any(feature = "runtime-tokio", feature = "runtime-async-std")))]
+pub(crate) async fn unblock<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    blocking::unblock(f).await
+}
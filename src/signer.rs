@@ -0,0 +1,421 @@
+use crate::error::Error;
+use crate::resources::hmac_key::HmacCredentials;
+use crate::resources::object::{
+    percent_encode_noslash, Expiry, Object, SignedUrlMethod, SignedUrlOptions,
+};
+use crate::resources::service_account::ServiceAccount;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// Creates [V4 signed urls](https://cloud.google.com/storage/docs/access-control/signed-urls) with
+/// the key of a service account or an HMAC key, without a `Client`. Signing happens locally and
+/// never sends a request, so it is also available on targets without the blocking client, such as
+/// `wasm32-unknown-unknown`.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::object::{SignedUrlMethod, SignedUrlOptions};
+/// use cloud_storage::{ServiceAccount, UrlSigner};
+/// use std::time::Duration;
+///
+/// # let json = String::new();
+/// // the contents of a `service-account-********.json` file, for example from a secret
+/// let account = ServiceAccount::from_json(&json)?;
+/// let signer = UrlSigner::new(&account);
+/// let url = signer.signed_url(
+///     "my_bucket",
+///     "path/to/file.png",
+///     SignedUrlMethod::Put,
+///     Duration::from_secs(600),
+///     &SignedUrlOptions::default(),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UrlSigner {
+    key: SigningKey,
+    region: String,
+}
+
+#[derive(Debug, Clone)]
+enum SigningKey {
+    ServiceAccount {
+        client_email: String,
+        private_key: String,
+    },
+    Hmac(HmacCredentials),
+}
+
+impl UrlSigner {
+    /// Creates a signer that signs with the private key of `service_account`.
+    pub fn new(service_account: &ServiceAccount) -> Self {
+        Self {
+            key: SigningKey::ServiceAccount {
+                client_email: service_account.client_email.clone(),
+                private_key: service_account.private_key.clone(),
+            },
+            region: "auto".to_string(),
+        }
+    }
+
+    /// Creates a signer that signs with the HMAC key `credentials`.
+    pub fn hmac(credentials: HmacCredentials) -> Self {
+        Self {
+            key: SigningKey::Hmac(credentials),
+            region: "auto".to_string(),
+        }
+    }
+
+    /// Sets the region in the credential scope of the signatures. Google accepts `auto` for every
+    /// bucket, which is the default.
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = region.into();
+        self
+    }
+
+    /// Creates a signed url which is valid until `expiry`, at most 7 days from now, and lets the
+    /// posessor download the file contents of `object` without any authentication.
+    pub fn download_url(
+        &self,
+        object: &Object,
+        expiry: impl Into<Expiry>,
+    ) -> Result<String, Error> {
+        let options = SignedUrlOptions::default();
+        self.signed_url(
+            &object.bucket,
+            &object.name,
+            SignedUrlMethod::Get,
+            expiry,
+            &options,
+        )
+    }
+
+    /// Creates a signed url that lets the posessor make a `method` request to the object `name` in
+    /// `bucket` without any authentication. Requests made with the url must carry the headers in
+    /// `options`. See `ObjectClient::signed_url`.
+    pub fn signed_url(
+        &self,
+        bucket: &str,
+        name: &str,
+        method: SignedUrlMethod,
+        expiry: impl Into<Expiry>,
+        options: &SignedUrlOptions,
+    ) -> Result<String, Error> {
+        let verb = method.as_str();
+        let (url, _) = self.sign(bucket, name, expiry.into(), verb, options, &options.headers)?;
+        Ok(url)
+    }
+
+    /// Creates a signed url that lets the posessor start a resumable upload of the object `name`
+    /// in `bucket` without any authentication. See `ObjectClient::resumable_upload_url`.
+    pub fn resumable_upload_url(
+        &self,
+        bucket: &str,
+        name: &str,
+        expiry: impl Into<Expiry>,
+        options: &SignedUrlOptions,
+    ) -> Result<String, Error> {
+        let mut headers = options.headers.clone();
+        headers.push(("x-goog-resumable".to_string(), "start".to_string()));
+        let (url, _) = self.sign(bucket, name, expiry.into(), "POST", options, &headers)?;
+        Ok(url)
+    }
+
+    /// Creates a V4 signed url for a `http_verb` request to the object `name` in `bucket`, which
+    /// must carry `headers`, and returns it with the number of seconds it is valid for.
+    pub(crate) fn sign(
+        &self,
+        bucket: &str,
+        name: &str,
+        expiry: Expiry,
+        http_verb: &str,
+        options: &SignedUrlOptions,
+        headers: &[(String, String)],
+    ) -> Result<(String, u64), Error> {
+        let issue_date = chrono::Utc::now();
+        let duration = expiry.seconds(issue_date)?;
+        let (authorizer, algorithm) = match &self.key {
+            SigningKey::Hmac(credentials) => (&credentials.access_id, "GOOG4-HMAC-SHA256"),
+            SigningKey::ServiceAccount { client_email, .. } => (client_email, "GOOG4-RSA-SHA256"),
+        };
+
+        // 1 construct the canonical reques
+        let region = self.region.as_str();
+        let credential_scope = get_credential_scope(&issue_date, region);
+        let file_path = path_to_resource(bucket, name);
+        let (canonical_headers, signed_headers) = get_canonical_headers(headers)?;
+        let query_string = get_canonical_query_string(
+            algorithm,
+            authorizer,
+            &credential_scope,
+            &signed_headers,
+            &issue_date,
+            duration,
+            &options.query_parameters,
+        )?;
+        let canonical_request = get_canonical_request(
+            &file_path,
+            &query_string,
+            http_verb,
+            &canonical_headers,
+            &signed_headers,
+        );
+
+        // 2 get hex encoded SHA256 hash the canonical request
+        let hash = crate::crypto::sha256(canonical_request.as_bytes());
+        let hex_hash = hex::encode(hash);
+
+        // 3 construct the string to sign
+        let string_to_sign = format!(
+            "{signing_algorithm}\n\
+            {current_datetime}\n\
+            {credential_scope}\n\
+            {hashed_canonical_request}",
+            signing_algorithm = algorithm,
+            current_datetime = issue_date.format("%Y%m%dT%H%M%SZ"),
+            credential_scope = credential_scope,
+            hashed_canonical_request = hex_hash,
+        );
+
+        // 4 sign the string to sign with HMAC - SHA256 or RSA - SHA256
+        let signature = match &self.key {
+            SigningKey::Hmac(credentials) => {
+                let date = issue_date.format("%Y%m%d").to_string();
+                let scope = [date.as_str(), region, "storage", "goog4_request"];
+                crate::crypto::v4_signature("GOOG4", &credentials.secret, &scope, &string_to_sign)
+            }
+            SigningKey::ServiceAccount { private_key, .. } => hex::encode(
+                crate::crypto::rsa_sha256(private_key, string_to_sign.as_bytes())?,
+            ),
+        };
+
+        // 5 construct the signed url
+        let url = format!(
+            "https://storage.googleapis.com{path_to_resource}?\
+            {query_string}&\
+            X-Goog-Signature={request_signature}",
+            path_to_resource = file_path,
+            query_string = query_string,
+            request_signature = signature,
+        );
+        Ok((url, duration))
+    }
+}
+
+fn get_canonical_request(
+    path: &str,
+    query_string: &str,
+    http_verb: &str,
+    canonical_headers: &str,
+    signed_headers: &str,
+) -> String {
+    format!(
+        "{http_verb}\n\
+        {path_to_resource}\n\
+        {canonical_query_string}\n\
+        {canonical_headers}\n\
+        \n\
+        {signed_headers}\n\
+        {payload}",
+        http_verb = http_verb,
+        path_to_resource = path,
+        canonical_query_string = query_string,
+        canonical_headers = canonical_headers,
+        signed_headers = signed_headers,
+        payload = "UNSIGNED-PAYLOAD",
+    )
+}
+
+/// Returns the canonical headers and the signed headers of a signed url for a request that must
+/// carry `headers`, besides the `host` header.
+fn get_canonical_headers(headers: &[(String, String)]) -> Result<(String, String), Error> {
+    let mut canonical = vec![("host".to_string(), "storage.googleapis.com".to_string())];
+    for (name, value) in headers {
+        let name = name.to_ascii_lowercase();
+        if canonical.iter().any(|(n, _)| *n == name) {
+            let msg = format!("the header {} can only be signed once", name);
+            return Err(Error::Other(msg));
+        }
+        // sequential spaces are folded into one
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        canonical.push((name, value));
+    }
+    canonical.sort();
+    let lines: Vec<String> = canonical
+        .iter()
+        .map(|(name, value)| format!("{}:{}", name, value))
+        .collect();
+    let names: Vec<&str> = canonical.iter().map(|(name, _)| name.as_str()).collect();
+    Ok((lines.join("\n"), names.join(";")))
+}
+
+/// The characters that are percent encoded in the canonical query string of a signed url: all
+/// except the unreserved characters of RFC 3986.
+const QUERY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Builds the canonical query string of a signed url: the signing parameters together with the
+/// `extra` parameters, each percent encoded, sorted by name and then by value.
+fn get_canonical_query_string(
+    algorithm: &str,
+    authorizer: &str,
+    credential_scope: &str,
+    signed_headers: &str,
+    date: &chrono::DateTime<chrono::Utc>,
+    exp: u64,
+    extra: &[(String, String)],
+) -> Result<String, Error> {
+    let credential = format!(
+        "{authorizer}/{scope}",
+        authorizer = authorizer,
+        scope = credential_scope,
+    );
+    let parameters = vec![
+        ("X-Goog-Algorithm", algorithm.to_string()),
+        ("X-Goog-Credential", credential),
+        ("X-Goog-Date", date.format("%Y%m%dT%H%M%SZ").to_string()),
+        ("X-Goog-Expires", exp.to_string()),
+        ("X-Goog-SignedHeaders", signed_headers.to_string()),
+    ];
+    for (name, _) in extra {
+        let reserved = name.eq_ignore_ascii_case("X-Goog-Signature")
+            || parameters.iter().any(|(p, _)| name.eq_ignore_ascii_case(p));
+        if reserved {
+            let msg = format!("the query parameter {} is set by the signer", name);
+            return Err(Error::Other(msg));
+        }
+    }
+    let encode = |s: &str| utf8_percent_encode(s, QUERY_ENCODE_SET).to_string();
+    let mut encoded: Vec<(String, String)> = parameters
+        .into_iter()
+        .map(|(name, value)| (encode(name), encode(&value)))
+        .chain(
+            extra
+                .iter()
+                .map(|(name, value)| (encode(name), encode(value))),
+        )
+        .collect();
+    encoded.sort();
+    let pairs: Vec<String> = encoded
+        .into_iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    Ok(pairs.join("&"))
+}
+
+#[inline(always)]
+fn path_to_resource(bucket: &str, path: &str) -> String {
+    format!(
+        "/{bucket}/{file_path}",
+        bucket = bucket,
+        file_path = percent_encode_noslash(path),
+    )
+}
+
+#[inline(always)]
+fn get_credential_scope(date: &chrono::DateTime<chrono::Utc>, region: &str) -> String {
+    format!("{}/{}/storage/goog4_request", date.format("%Y%m%d"), region)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_query_string() {
+        let date = chrono::DateTime::parse_from_rfc3339("2020-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let extra = vec![
+            ("generation".to_string(), "123".to_string()),
+            (
+                "response-content-disposition".to_string(),
+                "attachment; a=\"b c\"".to_string(),
+            ),
+            ("Apple".to_string(), "~*".to_string()),
+        ];
+        let scope = get_credential_scope(&date, "auto");
+        let query = get_canonical_query_string(
+            "GOOG4-RSA-SHA256",
+            "me@example.com",
+            &scope,
+            "host",
+            &date,
+            60,
+            &extra,
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "Apple=~%2A&\
+             X-Goog-Algorithm=GOOG4-RSA-SHA256&\
+             X-Goog-Credential=me%40example.com%2F20200102%2Fauto%2Fstorage%2Fgoog4_request&\
+             X-Goog-Date=20200102T030405Z&\
+             X-Goog-Expires=60&\
+             X-Goog-SignedHeaders=host&\
+             generation=123&\
+             response-content-disposition=attachment%3B%20a%3D%22b%20c%22"
+        );
+
+        let reserved = vec![("x-goog-expires".to_string(), "1".to_string())];
+        assert!(get_canonical_query_string(
+            "GOOG4-RSA-SHA256",
+            "me@example.com",
+            &scope,
+            "host",
+            &date,
+            60,
+            &reserved
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn canonical_headers() {
+        let headers = vec![
+            ("X-Goog-Resumable".to_string(), "start".to_string()),
+            (
+                "content-type".to_string(),
+                " text/plain;  charset=utf-8 ".to_string(),
+            ),
+        ];
+        let (canonical, signed) = get_canonical_headers(&headers).unwrap();
+        assert_eq!(
+            canonical,
+            "content-type:text/plain; charset=utf-8\n\
+             host:storage.googleapis.com\n\
+             x-goog-resumable:start"
+        );
+        assert_eq!(signed, "content-type;host;x-goog-resumable");
+
+        let host = vec![("Host".to_string(), "example.com".to_string())];
+        assert!(get_canonical_headers(&host).is_err());
+    }
+
+    #[test]
+    fn hmac_signed_url() {
+        let signer = UrlSigner::hmac(HmacCredentials {
+            access_id: "GOOG1EXAMPLE".to_string(),
+            secret: "secret".to_string(),
+        })
+        .region("europe-west4");
+        let url = signer
+            .signed_url(
+                "bucket",
+                "a b.txt",
+                SignedUrlMethod::Delete,
+                std::time::Duration::from_secs(60),
+                &SignedUrlOptions::default(),
+            )
+            .unwrap();
+        assert!(url.starts_with("https://storage.googleapis.com/bucket/a%20b.txt?"));
+        assert!(url.contains("X-Goog-Algorithm=GOOG4-HMAC-SHA256&"));
+        assert!(url.contains("%2Feurope-west4%2Fstorage%2Fgoog4_request&"));
+        assert!(url.contains("X-Goog-Expires=60&"));
+        let signature = url.rsplit("X-Goog-Signature=").next().unwrap();
+        assert_eq!(signature.len(), 64);
+    }
+}
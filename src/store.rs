@@ -0,0 +1,767 @@
+use crate::error::Error;
+use crate::http::RequestExt;
+use crate::resources::object::{percent_encode, Object};
+use crate::Client;
+use futures::channel::oneshot;
+use futures::stream::{BoxStream, StreamExt};
+use object_store::path::Path;
+use object_store::{
+    GetOptions, GetRange, GetResult, GetResultPayload, ListResult, MultipartUpload, ObjectMeta,
+    ObjectStore, PutMode, PutMultipartOpts, PutOptions, PutPayload, PutResult, UploadPart,
+};
+use std::io::Read;
+use std::ops::Range;
+
+/// The name under which errors of this store are reported.
+const STORE: &str = "GCS";
+
+/// The size that every chunk of a resumable upload but the last one must be a multiple of.
+const CHUNK_ALIGNMENT: usize = 256 * 1024;
+
+/// The number of bytes of a download that are read at a time.
+const DOWNLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Exposes a bucket as an [`ObjectStore`](https://docs.rs/object_store), so that it can be used as
+/// storage by DataFusion, Arrow, Parquet and other libraries built on that trait. Requests are
+/// sent by the `Client` that the store was created with.
+///
+/// This crate only offers a blocking api, so every request is made on a separate thread pool. The
+/// store works under any async runtime, such as tokio, async-std or smol. Enable the
+/// `runtime-tokio` or `runtime-async-std` feature to use the thread pool of that runtime instead.
+/// ### Example
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::{Client, GcsStore};
+/// use object_store::{path::Path, ObjectStore};
+///
+/// let store = GcsStore::new(Client::default(), "my_bucket");
+/// let path = Path::from("data/numbers.parquet");
+/// store.put(&path, vec![0, 1, 2].into()).await?;
+/// let bytes = store.get(&path).await?.bytes().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GcsStore {
+    client: Client,
+    bucket: String,
+}
+
+impl GcsStore {
+    /// Creates a store for the objects in `bucket`, which are accessed through `client`.
+    pub fn new(client: Client, bucket: impl Into<String>) -> Self {
+        Self {
+            client,
+            bucket: bucket.into(),
+        }
+    }
+
+    /// The name of the bucket that contains the objects of this store.
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// Runs `f` on a thread on which it may block.
+    async fn run<T, F>(&self, f: F) -> object_store::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self) -> object_store::Result<T> + Send + 'static,
+    {
+        let store = self.clone();
+        crate::runtime::unblock(move || f(&store)).await
+    }
+
+    fn object_url(&self, path: &str) -> String {
+        format!(
+            "{}/b/{}/o/{}",
+            self.client.api_url(),
+            percent_encode(&self.bucket),
+            percent_encode(path),
+        )
+    }
+
+    /// Uploads `payload` in a single request, only if the current generation of the object
+    /// matches `if_generation_match`, where `0` means that the object may not exist yet.
+    fn upload(
+        &self,
+        path: &str,
+        payload: &PutPayload,
+        if_generation_match: Option<&str>,
+    ) -> Result<Object, Error> {
+        use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+
+        let url = format!(
+            "{}/b/{}/o",
+            self.client.upload_url(),
+            percent_encode(&self.bucket)
+        );
+        let mut query = vec![("uploadType", "media"), ("name", path)];
+        if let Some(generation) = if_generation_match {
+            query.push(("ifGenerationMatch", generation));
+        }
+        let mut body = Vec::with_capacity(payload.content_length());
+        for chunk in payload.iter() {
+            body.extend_from_slice(chunk);
+        }
+        let mut headers = self.client.headers()?;
+        headers.insert(CONTENT_TYPE, "application/octet-stream".parse()?);
+        headers.insert(CONTENT_LENGTH, body.len().to_string().parse()?);
+        let response = self
+            .client
+            .http()?
+            .post(&url)
+            .headers(headers)
+            .query(&self.client.user_project_query())
+            .query(&query)
+            .body(body)
+            .execute(&self.client)?;
+        if response.status().is_success() {
+            Ok(response.json()?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    fn metadata(&self, path: &str, generation: Option<&str>) -> Result<Object, Error> {
+        let mut request = self
+            .client
+            .http()?
+            .get(&self.object_url(path))
+            .headers(self.client.headers()?)
+            .query(&self.client.user_project_query());
+        if let Some(generation) = generation {
+            request = request.query(&[("generation", generation)]);
+        }
+        let response = request.execute(&self.client)?;
+        if response.status().is_success() {
+            Ok(response.json()?)
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Starts the download of the non-empty `range` of the given generation of the object, so
+    /// that the content matches the metadata that was read before.
+    fn download(
+        &self,
+        object: &Object,
+        range: &Range<usize>,
+    ) -> Result<reqwest::blocking::Response, Error> {
+        let mut headers = self.client.headers()?;
+        let value = format!("bytes={}-{}", range.start, range.end - 1);
+        headers.insert(reqwest::header::RANGE, value.parse()?);
+        let response = self
+            .client
+            .http()?
+            .get(&self.object_url(&object.name))
+            .headers(headers)
+            .query(&self.client.user_project_query())
+            .query(&[
+                ("alt", "media"),
+                ("generation", &object.generation.to_string()),
+            ])
+            .execute(&self.client)?;
+        if response.status().is_success() {
+            Ok(response.into_inner())
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+
+    /// Starts a [resumable upload](https://cloud.google.com/storage/docs/performing-resumable-uploads)
+    /// of the object at `path`, and returns the url of the session.
+    fn start_session(&self, path: &str) -> Result<String, Error> {
+        use reqwest::header::{CONTENT_LENGTH, LOCATION};
+
+        let url = format!(
+            "{}/b/{}/o",
+            self.client.upload_url(),
+            percent_encode(&self.bucket)
+        );
+        let mut headers = self.client.headers()?;
+        headers.insert("X-Upload-Content-Type", "application/octet-stream".parse()?);
+        headers.insert(CONTENT_LENGTH, 0.into());
+        let response = self
+            .client
+            .http()?
+            .post(&url)
+            .headers(headers)
+            .query(&self.client.user_project_query())
+            .query(&[("uploadType", "resumable"), ("name", path)])
+            .execute(&self.client)?;
+        if !response.status().is_success() {
+            return Err(Error::from_response(response));
+        }
+        response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| Error::Other("resumable upload has no session url".to_string()))
+    }
+
+    /// Uploads `chunk` as the next part of the resumable upload of the object at `path`, starting
+    /// the session first if needed. When `last` is set the object is created, and its metadata is
+    /// returned.
+    fn upload_chunk(
+        &self,
+        path: &str,
+        session: &mut Session,
+        chunk: Vec<u8>,
+        last: bool,
+    ) -> Result<Option<Object>, Error> {
+        use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE};
+
+        let url = match &session.url {
+            Some(url) => url.clone(),
+            None => self.start_session(path)?,
+        };
+        session.url = Some(url.clone());
+        let (start, end) = (session.offset, session.offset + chunk.len());
+        let range = match (chunk.is_empty(), last) {
+            (true, _) => format!("bytes */{}", end),
+            (false, true) => format!("bytes {}-{}/{}", start, end - 1, end),
+            (false, false) => format!("bytes {}-{}/*", start, end - 1),
+        };
+        let mut headers = self.client.headers()?;
+        headers.insert(CONTENT_RANGE, range.parse()?);
+        headers.insert(CONTENT_LENGTH, chunk.len().into());
+        let response = self
+            .client
+            .http()?
+            .put(&url)
+            .headers(headers)
+            .body(chunk)
+            .execute(&self.client)?;
+        match response.status().as_u16() {
+            200 | 201 if last => Ok(Some(response.json()?)),
+            // Google has received the chunk, and waits for the next one
+            308 if !last => {
+                let expected = format!("bytes=0-{}", end - 1);
+                if response.headers().get("range").map(|r| r == &*expected) != Some(true) {
+                    let msg = format!("resumable upload of {} lost bytes before {}", path, end);
+                    return Err(Error::Other(msg));
+                }
+                session.offset = end;
+                Ok(None)
+            }
+            _ => Err(Error::from_response(response)),
+        }
+    }
+
+    fn list_delimited(&self, prefix: &str) -> Result<ListResult, Error> {
+        let (objects, prefixes) = self.client.object().list_delimited(&self.bucket, prefix)?;
+        Ok(ListResult {
+            objects: objects.iter().map(object_meta).collect(),
+            common_prefixes: prefixes
+                .iter()
+                .map(|prefix| Path::from(prefix.trim_end_matches('/')))
+                .collect(),
+        })
+    }
+
+    fn copy_object(&self, from: &str, to: &str, if_not_exists: bool) -> Result<(), Error> {
+        let url = format!(
+            "{}/copyTo/b/{}/o/{}",
+            self.object_url(from),
+            percent_encode(&self.bucket),
+            percent_encode(to),
+        );
+        let mut headers = self.client.headers()?;
+        headers.insert(reqwest::header::CONTENT_LENGTH, "0".parse()?);
+        let mut request = self
+            .client
+            .http()?
+            .post(&url)
+            .headers(headers)
+            .query(&self.client.user_project_query());
+        if if_not_exists {
+            request = request.query(&[("ifGenerationMatch", "0")]);
+        }
+        let response = request.execute(&self.client)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::from_response(response))
+        }
+    }
+}
+
+impl std::fmt::Display for GcsStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GcsStore({})", self.bucket)
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectStore for GcsStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        let path = location.to_string();
+        self.run(move |store| {
+            let generation = match opts.mode {
+                PutMode::Overwrite => None,
+                PutMode::Create => Some("0".to_string()),
+                PutMode::Update(version) => match version.version {
+                    Some(generation) => Some(generation),
+                    None => {
+                        return Err(object_store::Error::NotSupported {
+                            source: "updating an object requires its version".into(),
+                        })
+                    }
+                },
+            };
+            let object = store
+                .upload(&path, &payload, generation.as_deref())
+                .map_err(|e| match e {
+                    Error::PreconditionFailed(_) if generation.as_deref() == Some("0") => {
+                        object_store::Error::AlreadyExists {
+                            path: path.clone(),
+                            source: Box::new(e),
+                        }
+                    }
+                    e => store_error(e, &path),
+                })?;
+            Ok(PutResult {
+                e_tag: Some(object.etag),
+                version: Some(object.generation.to_string()),
+            })
+        })
+        .await
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &Path,
+        _opts: PutMultipartOpts,
+    ) -> object_store::Result<Box<dyn MultipartUpload>> {
+        let (sender, previous) = oneshot::channel();
+        let _ = sender.send(Session::default());
+        Ok(Box::new(Upload {
+            store: self.clone(),
+            location: location.clone(),
+            pending: Vec::new(),
+            previous,
+        }))
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let path = location.to_string();
+        self.run(move |store| {
+            let object = store
+                .metadata(&path, options.version.as_deref())
+                .map_err(|e| store_error(e, &path))?;
+            let meta = object_meta(&object);
+            check_preconditions(&options, &meta)?;
+            let range = match &options.range {
+                Some(range) => resolve_range(range, meta.size).map_err(|message| {
+                    object_store::Error::Generic {
+                        store: STORE,
+                        source: message.into(),
+                    }
+                })?,
+                None => 0..meta.size,
+            };
+            if options.head || range.is_empty() {
+                return Ok(GetResult {
+                    payload: GetResultPayload::Stream(futures::stream::empty().boxed()),
+                    meta,
+                    range,
+                    attributes: Default::default(),
+                });
+            }
+            let response = store
+                .download(&object, &range)
+                .map_err(|e| store_error(e, &path))?;
+            // the body is read in chunks as the stream is polled, each on a thread that may block
+            let body = futures::stream::unfold(Some(response), |response| async move {
+                let response = response?;
+                let (chunk, response) = crate::runtime::unblock(move || {
+                    let mut response = response;
+                    let mut chunk = Vec::new();
+                    let read = (&mut response)
+                        .take(DOWNLOAD_CHUNK_SIZE as u64)
+                        .read_to_end(&mut chunk);
+                    (read.map(|_| chunk), response)
+                })
+                .await;
+                match chunk {
+                    Ok(chunk) if chunk.is_empty() => None,
+                    Ok(chunk) => Some((Ok(chunk.into()), Some(response))),
+                    Err(e) => {
+                        let error = object_store::Error::Generic {
+                            store: STORE,
+                            source: Box::new(e),
+                        };
+                        Some((Err(error), None))
+                    }
+                }
+            });
+            Ok(GetResult {
+                payload: GetResultPayload::Stream(body.boxed()),
+                meta,
+                range,
+                attributes: Default::default(),
+            })
+        })
+        .await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        let path = location.to_string();
+        self.run(move |store| {
+            store
+                .client
+                .object()
+                .delete(&store.bucket, &path)
+                .map_err(|e| store_error(e, &path))
+        })
+        .await
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        let prefix = list_prefix(prefix);
+        let objects = self
+            .client
+            .object()
+            .list_with(&self.bucket, Some(&prefix), None);
+        futures::stream::unfold(Some(objects), move |objects| {
+            let prefix = prefix.clone();
+            async move {
+                let mut objects = objects?;
+                let (object, objects) =
+                    crate::runtime::unblock(move || (objects.next(), objects)).await;
+                let meta = object?
+                    .map(|object| object_meta(&object))
+                    .map_err(|e| store_error(e, &prefix));
+                Some((meta, Some(objects)))
+            }
+        })
+        .boxed()
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let prefix = list_prefix(prefix);
+        self.run(move |store| {
+            store
+                .list_delimited(&prefix)
+                .map_err(|e| store_error(e, &prefix))
+        })
+        .await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        let (from, to) = (from.to_string(), to.to_string());
+        self.run(move |store| {
+            store
+                .copy_object(&from, &to, false)
+                .map_err(|e| store_error(e, &from))
+        })
+        .await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        let (from, to) = (from.to_string(), to.to_string());
+        self.run(move |store| {
+            store.copy_object(&from, &to, true).map_err(|e| match e {
+                Error::PreconditionFailed(_) => object_store::Error::AlreadyExists {
+                    path: to.clone(),
+                    source: Box::new(e),
+                },
+                e => store_error(e, &from),
+            })
+        })
+        .await
+    }
+}
+
+/// A multipart upload, backed by a resumable upload session. The parts are uploaded as they
+/// arrive, in chunks of a multiple of 256 KiB, so only the remainder that does not fill a chunk
+/// is kept in memory. The object is created when the upload is completed, so a partially written
+/// object is never visible. Objects smaller than a chunk are uploaded in a single request.
+#[derive(Debug)]
+struct Upload {
+    store: GcsStore,
+    location: Path,
+    /// The content of the last parts that does not fill a chunk yet.
+    pending: Vec<u8>,
+    /// Resolves to the session once the previous chunk has been uploaded, since the chunks have
+    /// to be uploaded in order. Fails when uploading an earlier chunk failed.
+    previous: oneshot::Receiver<Session>,
+}
+
+/// The state of the resumable upload session of an `Upload`.
+#[derive(Debug, Default)]
+struct Session {
+    /// The url that the chunks are uploaded to, once the session has been started.
+    url: Option<String>,
+    /// The number of bytes that have been uploaded.
+    offset: usize,
+}
+
+impl Upload {
+    /// Takes the session once the previous chunk has been uploaded, and makes the next chunk
+    /// wait for `next`.
+    fn take_previous(&mut self, next: oneshot::Receiver<Session>) -> oneshot::Receiver<Session> {
+        std::mem::replace(&mut self.previous, next)
+    }
+}
+
+#[async_trait::async_trait]
+impl MultipartUpload for Upload {
+    fn put_part(&mut self, data: PutPayload) -> UploadPart {
+        for bytes in data.iter() {
+            self.pending.extend_from_slice(bytes);
+        }
+        let len = self.pending.len() / CHUNK_ALIGNMENT * CHUNK_ALIGNMENT;
+        if len == 0 {
+            return Box::pin(futures::future::ready(Ok(())));
+        }
+        let rest = self.pending.split_off(len);
+        let chunk = std::mem::replace(&mut self.pending, rest);
+        let (sender, next) = oneshot::channel();
+        let previous = self.take_previous(next);
+        let (store, path) = (self.store.clone(), self.location.to_string());
+        Box::pin(async move {
+            let mut session = previous.await.map_err(|_| earlier_part_failed(&path))?;
+            let session = store
+                .run(move |store| {
+                    store
+                        .upload_chunk(&path, &mut session, chunk, false)
+                        .map_err(|e| store_error(e, &path))?;
+                    Ok(session)
+                })
+                .await?;
+            let _ = sender.send(session);
+            Ok(())
+        })
+    }
+
+    async fn complete(&mut self) -> object_store::Result<PutResult> {
+        let path = self.location.to_string();
+        let previous = self.take_previous(oneshot::channel().1);
+        let mut session = previous.await.map_err(|_| earlier_part_failed(&path))?;
+        let chunk = std::mem::take(&mut self.pending);
+        let object = self
+            .store
+            .run(move |store| {
+                let object = match session.url {
+                    Some(_) => store
+                        .upload_chunk(&path, &mut session, chunk, true)
+                        .map(|object| object.expect("the last chunk creates the object")),
+                    None => store.upload(&path, &chunk.into(), None),
+                };
+                object.map_err(|e| store_error(e, &path))
+            })
+            .await?;
+        Ok(PutResult {
+            e_tag: Some(object.etag),
+            version: Some(object.generation.to_string()),
+        })
+    }
+
+    async fn abort(&mut self) -> object_store::Result<()> {
+        let path = self.location.to_string();
+        let previous = self.take_previous(oneshot::channel().1);
+        self.pending.clear();
+        // a session whose chunk failed is discarded by Google after a week
+        let url = match previous.await {
+            Ok(Session { url: Some(url), .. }) => url,
+            _ => return Ok(()),
+        };
+        self.store
+            .run(move |store| {
+                store
+                    .client
+                    .object()
+                    .cancel_session(&url)
+                    .map_err(|e| store_error(e, &path))
+            })
+            .await
+    }
+}
+
+fn earlier_part_failed(path: &str) -> object_store::Error {
+    object_store::Error::Generic {
+        store: STORE,
+        source: format!("an earlier part of the upload of {} failed", path).into(),
+    }
+}
+
+fn object_meta(object: &Object) -> ObjectMeta {
+    ObjectMeta {
+        location: Path::from(object.name.as_str()),
+        last_modified: object.updated,
+        size: object.size as usize,
+        e_tag: Some(object.etag.clone()),
+        version: Some(object.generation.to_string()),
+    }
+}
+
+/// The prefix that the names of the objects below `prefix` start with.
+fn list_prefix(prefix: Option<&Path>) -> String {
+    match prefix {
+        Some(prefix) if !prefix.as_ref().is_empty() => format!("{}/", prefix),
+        _ => String::new(),
+    }
+}
+
+fn store_error(error: Error, path: &str) -> object_store::Error {
+    let path = path.to_string();
+    match error {
+        Error::NotFound(_) => object_store::Error::NotFound {
+            path,
+            source: Box::new(error),
+        },
+        Error::PreconditionFailed(_) => object_store::Error::Precondition {
+            path,
+            source: Box::new(error),
+        },
+        Error::PermissionDenied(_) => object_store::Error::PermissionDenied {
+            path,
+            source: Box::new(error),
+        },
+        error => object_store::Error::Generic {
+            store: STORE,
+            source: Box::new(error),
+        },
+    }
+}
+
+/// Evaluates the conditional headers of `options` against the current metadata of the object, the
+/// way an HTTP server would.
+fn check_preconditions(options: &GetOptions, meta: &ObjectMeta) -> object_store::Result<()> {
+    let path = meta.location.to_string();
+    let etag = meta.e_tag.as_deref().unwrap_or_default();
+    let failed = |message: String| object_store::Error::Precondition {
+        path: path.clone(),
+        source: message.into(),
+    };
+    let not_modified = |message: String| object_store::Error::NotModified {
+        path: path.clone(),
+        source: message.into(),
+    };
+    if let Some(expected) = &options.if_match {
+        if expected != "*" && expected != etag {
+            return Err(failed(format!("{} does not match {}", etag, expected)));
+        }
+    }
+    if let Some(since) = options.if_unmodified_since {
+        if meta.last_modified > since {
+            return Err(failed(format!("modified at {}", meta.last_modified)));
+        }
+    }
+    if let Some(unexpected) = &options.if_none_match {
+        if unexpected == "*" || unexpected == etag {
+            return Err(not_modified(format!("{} matches {}", etag, unexpected)));
+        }
+    }
+    if let Some(since) = options.if_modified_since {
+        if meta.last_modified <= since {
+            return Err(not_modified(format!("modified at {}", meta.last_modified)));
+        }
+    }
+    Ok(())
+}
+
+/// The bytes of an object of `size` bytes that are selected by `range`.
+fn resolve_range(range: &GetRange, size: usize) -> Result<Range<usize>, String> {
+    let range = match *range {
+        GetRange::Bounded(ref range) => range.start..range.end.min(size),
+        GetRange::Offset(offset) => offset..size,
+        GetRange::Suffix(length) => size.saturating_sub(length)..size,
+    };
+    if range.start >= size && size > 0 || range.start > range.end {
+        Err(format!("range {:?} is not within {} bytes", range, size))
+    } else {
+        Ok(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(resolve_range(&GetRange::Bounded(2..5), 10), Ok(2..5));
+        assert_eq!(resolve_range(&GetRange::Bounded(2..50), 10), Ok(2..10));
+        assert_eq!(resolve_range(&GetRange::Offset(4), 10), Ok(4..10));
+        assert_eq!(resolve_range(&GetRange::Suffix(3), 10), Ok(7..10));
+        assert_eq!(resolve_range(&GetRange::Suffix(30), 10), Ok(0..10));
+        assert!(resolve_range(&GetRange::Offset(10), 10).is_err());
+    }
+
+    #[test]
+    fn preconditions() {
+        let meta = ObjectMeta {
+            location: Path::from("a/b"),
+            last_modified: chrono::Utc::now(),
+            size: 3,
+            e_tag: Some("etag".to_string()),
+            version: Some("1".to_string()),
+        };
+        let options = |f: fn(&mut GetOptions)| {
+            let mut options = GetOptions::default();
+            f(&mut options);
+            options
+        };
+        assert!(check_preconditions(&GetOptions::default(), &meta).is_ok());
+        let matching = options(|o| o.if_match = Some("etag".to_string()));
+        assert!(check_preconditions(&matching, &meta).is_ok());
+        let other = options(|o| o.if_match = Some("other".to_string()));
+        assert!(matches!(
+            check_preconditions(&other, &meta),
+            Err(object_store::Error::Precondition { .. })
+        ));
+        let unchanged = options(|o| o.if_none_match = Some("etag".to_string()));
+        assert!(matches!(
+            check_preconditions(&unchanged, &meta),
+            Err(object_store::Error::NotModified { .. })
+        ));
+    }
+
+    #[test]
+    fn put_get_list_delete() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let store = GcsStore::new(crate::Client::global(), bucket.name);
+        futures::executor::block_on(async {
+            let path = Path::from("test-object-store/file");
+            store.put(&path, vec![0, 1, 2, 3].into()).await?;
+            let bytes = store.get_range(&path, 1..3).await?;
+            assert_eq!(bytes.as_ref(), [1, 2]);
+            let mut upload = store.put_multipart(&path).await?;
+            upload.put_part(vec![1; CHUNK_ALIGNMENT + 1].into()).await?;
+            upload.put_part(vec![2; 10].into()).await?;
+            upload.complete().await?;
+            let bytes = store.get(&path).await?.bytes().await?;
+            assert_eq!(bytes.len(), CHUNK_ALIGNMENT + 11);
+            assert_eq!(bytes[CHUNK_ALIGNMENT..], [1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2]);
+            let created = store
+                .put_opts(&path, vec![4].into(), PutMode::Create.into())
+                .await;
+            assert!(matches!(
+                created,
+                Err(object_store::Error::AlreadyExists { .. })
+            ));
+            let listed = store
+                .list_with_delimiter(Some(&Path::from("test-object-store")))
+                .await?;
+            assert!(listed.objects.iter().any(|meta| meta.location == path));
+            store.delete(&path).await?;
+            assert!(matches!(
+                store.head(&path).await,
+                Err(object_store::Error::NotFound { .. })
+            ));
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,1005 @@
+//! Transfers between a local directory and the objects under a prefix of a bucket: uploads of
+//! whole directory trees, and synchronization comparable to `gsutil rsync`. Also copies and moves
+//! all objects under a prefix to another prefix.
+
+use crate::error::Error;
+use crate::http::RequestExt;
+use crate::resources::object::{percent_encode, Object};
+use crate::Client;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// The number of files that are transferred at once when `SyncOptions::concurrency` is not set.
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// The settings of `Object::sync_up` and `Object::sync_down`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncOptions {
+    /// Whether files that only exist at the destination are deleted, so that the destination ends
+    /// up with exactly the files of the source. Disabled by default.
+    pub delete: bool,
+    /// The maximum number of files that are transferred at the same time. Defaults to 8.
+    pub concurrency: Option<usize>,
+}
+
+/// What a synchronization changed. Files are identified by their path relative to the local
+/// directory, with `/` as separator.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncSummary {
+    /// The files that were uploaded or downloaded, because they were missing or had changed.
+    pub transferred: Vec<String>,
+    /// The files that were deleted from the destination.
+    pub deleted: Vec<String>,
+    /// The number of files that were already up to date.
+    pub unchanged: usize,
+}
+
+/// The settings of `Object::upload_dir`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UploadOptions {
+    /// The maximum number of files that are uploaded at the same time. Defaults to 8.
+    pub concurrency: Option<usize>,
+    /// How often the upload of a file is repeated after a failure that may be temporary, such as
+    /// a connection error or `503 Service Unavailable`. The delay between attempts follows
+    /// `Config::retry`. Defaults to 3.
+    pub retries: Option<u32>,
+}
+
+/// The outcome of uploading a single file with `Object::upload_dir`.
+#[derive(Debug)]
+pub struct FileUpload {
+    /// The path of the file relative to the uploaded directory, with `/` as separator.
+    pub path: String,
+    /// The number of times the upload was attempted.
+    pub attempts: u32,
+    /// The uploaded object, or the error of the last attempt.
+    pub result: Result<Object, Error>,
+}
+
+/// The settings of `Object::download_prefix`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadOptions {
+    /// The maximum number of files that are downloaded at the same time. Defaults to 8.
+    pub concurrency: Option<usize>,
+    /// How often the download of a file is resumed after a failure that may be temporary, such as
+    /// a dropped connection or `503 Service Unavailable`. The delay between attempts follows
+    /// `Config::retry`. Defaults to 3.
+    pub retries: Option<u32>,
+}
+
+/// The outcome of downloading a single object with `Object::download_prefix`.
+#[derive(Debug)]
+pub struct FileDownload {
+    /// The path of the file relative to the local directory, with `/` as separator.
+    pub path: String,
+    /// The number of times the download was attempted.
+    pub attempts: u32,
+    /// The downloaded object, or the error of the last attempt.
+    pub result: Result<Object, Error>,
+}
+
+/// The settings of `Object::copy_prefix` and `Object::move_prefix`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CopyOptions {
+    /// The maximum number of objects that are copied at the same time. Defaults to 8.
+    pub concurrency: Option<usize>,
+    /// How often the copy of an object is repeated after a failure that may be temporary, such as
+    /// `503 Service Unavailable`. The delay between attempts follows `Config::retry`. Defaults
+    /// to 3.
+    pub retries: Option<u32>,
+}
+
+/// The outcome of copying or moving a single object with `Object::copy_prefix` or
+/// `Object::move_prefix`.
+#[derive(Debug)]
+pub struct ObjectCopy {
+    /// The name of the object relative to the source prefix, which is also its name relative to
+    /// the destination prefix.
+    pub path: String,
+    /// The number of times the copy was attempted.
+    pub attempts: u32,
+    /// The copy at the destination, or the error of the last attempt.
+    pub result: Result<Object, Error>,
+}
+
+/// A file in the local directory.
+#[derive(Debug)]
+struct LocalFile {
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Whether the local file has the same content as `object`. The sizes are compared first, then
+/// the modification times, which match if the file was downloaded by `sync_down`, and finally the
+/// CRC32C checksums.
+fn is_unchanged(file: &LocalFile, object: &Object) -> Result<bool, Error> {
+    if file.size != object.size {
+        return Ok(false);
+    }
+    if file.modified == Some(object.updated.into()) {
+        return Ok(true);
+    }
+    Ok(crc32c(&file.path)? == object.crc32c)
+}
+
+/// The CRC32C checksum of the file at `path`, base64 encoded like `Object::crc32c`.
+fn crc32c(path: &Path) -> Result<String, Error> {
+    let mut file = std::fs::File::open(path).map_err(|e| io_error(e, path))?;
+    let mut crc = crate::crc32c::Crc32c::new();
+    std::io::copy(&mut file, &mut crc).map_err(|e| io_error(e, path))?;
+    Ok(crate::crc32c::encode(crc.value()))
+}
+
+/// Uploads the files in `local_dir` and its subdirectories that are missing or different under
+/// `prefix` in `bucket`.
+pub(crate) fn sync_up(
+    client: &Client,
+    local_dir: &Path,
+    bucket: &str,
+    prefix: &str,
+    options: &SyncOptions,
+) -> Result<SyncSummary, Error> {
+    let prefix = dir_prefix(prefix);
+    let local = local_files(local_dir)?;
+    let mut remote = remote_objects(client, bucket, &prefix)?;
+    let mut summary = SyncSummary::default();
+    let mut uploads = Vec::new();
+    for (path, file) in local {
+        match remote.remove(&path) {
+            Some(object) if is_unchanged(&file, &object)? => summary.unchanged += 1,
+            _ => uploads.push((path, file)),
+        }
+    }
+    let (transferred, failure) = run(options, uploads, |(path, file)| {
+        let reader = std::fs::File::open(&file.path).map_err(|e| io_error(e, &file.path))?;
+        let name = format!("{}{}", prefix, path);
+        client
+            .object()
+            .create_streamed(bucket, reader, file.size, &name, crate::mime::AUTO)?;
+        Ok(path)
+    });
+    summary.transferred = transferred;
+    check(&mut summary, failure)?;
+    if options.delete {
+        let (deleted, failure) = run(options, remote.into_iter().collect(), |(path, object)| {
+            client.object().delete(bucket, &object.name)?;
+            Ok(path)
+        });
+        summary.deleted = deleted;
+        check(&mut summary, failure)?;
+    }
+    Ok(summary)
+}
+
+/// Uploads all files in `local_dir` and its subdirectories under `prefix` in `bucket`. Failing
+/// uploads do not stop the others; their errors are part of the returned report, which is sorted
+/// by path.
+pub(crate) fn upload_dir(
+    client: &Client,
+    local_dir: &Path,
+    bucket: &str,
+    prefix: &str,
+    options: &UploadOptions,
+) -> Result<Vec<FileUpload>, Error> {
+    let prefix = dir_prefix(prefix);
+    let files = local_files(local_dir)?.into_iter().collect();
+    let mut report: Vec<_> = parallel(options.concurrency, files, false, |(path, file)| {
+        Ok(upload_file(client, bucket, &prefix, path, &file, options))
+    })
+    .into_iter()
+    .filter_map(Result::ok)
+    .collect();
+    report.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
+
+fn upload_file(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+    path: String,
+    file: &LocalFile,
+    options: &UploadOptions,
+) -> FileUpload {
+    let name = format!("{}{}", prefix, path);
+    let retries = options.retries.unwrap_or(3);
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let result = std::fs::File::open(&file.path)
+            .map_err(|e| io_error(e, &file.path))
+            .and_then(|reader| {
+                client
+                    .object()
+                    .create_streamed(bucket, reader, file.size, &name, crate::mime::AUTO)
+            });
+        match result {
+            Err(e) if e.is_retryable() && attempts <= retries => {
+                let delay = client.config().retry.backoff(attempts - 1);
+                std::thread::sleep(crate::http::jitter(delay));
+            }
+            result => {
+                return FileUpload {
+                    path,
+                    attempts,
+                    result,
+                }
+            }
+        }
+    }
+}
+
+/// Downloads the objects under `prefix` in `bucket` that are missing or different in `local_dir`.
+pub(crate) fn sync_down(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+    local_dir: &Path,
+    options: &SyncOptions,
+) -> Result<SyncSummary, Error> {
+    let prefix = dir_prefix(prefix);
+    let remote = remote_objects(client, bucket, &prefix)?;
+    let mut local = if local_dir.exists() {
+        local_files(local_dir)?
+    } else {
+        HashMap::new()
+    };
+    let mut summary = SyncSummary::default();
+    let mut downloads = Vec::new();
+    for (path, object) in remote {
+        match local.remove(&path) {
+            Some(file) if is_unchanged(&file, &object)? => summary.unchanged += 1,
+            _ => downloads.push((path, object)),
+        }
+    }
+    let (transferred, failure) = run(options, downloads, |(path, object)| {
+        download(client, &object, &local_path(local_dir, &path)?, false)?;
+        Ok(path)
+    });
+    summary.transferred = transferred;
+    check(&mut summary, failure)?;
+    if options.delete {
+        let (deleted, failure) = run(options, local.into_iter().collect(), |(path, file)| {
+            std::fs::remove_file(&file.path).map_err(|e| io_error(e, &file.path))?;
+            Ok(path)
+        });
+        summary.deleted = deleted;
+        check(&mut summary, failure)?;
+    }
+    Ok(summary)
+}
+
+/// Downloads all objects under `prefix` in `bucket` into `local_dir`. Failing downloads do not
+/// stop the others; their errors are part of the returned report, which is sorted by path.
+pub(crate) fn download_prefix(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+    local_dir: &Path,
+    options: &DownloadOptions,
+) -> Result<Vec<FileDownload>, Error> {
+    let prefix = dir_prefix(prefix);
+    let objects = remote_objects(client, bucket, &prefix)?
+        .into_iter()
+        .collect();
+    let mut report: Vec<_> = parallel(options.concurrency, objects, false, |(path, object)| {
+        Ok(download_file(client, local_dir, path, object, options))
+    })
+    .into_iter()
+    .filter_map(Result::ok)
+    .collect();
+    report.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
+
+fn download_file(
+    client: &Client,
+    local_dir: &Path,
+    path: String,
+    object: Object,
+    options: &DownloadOptions,
+) -> FileDownload {
+    let retries = options.retries.unwrap_or(3);
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let result = local_path(local_dir, &path)
+            .and_then(|target| download(client, &object, &target, true));
+        match result {
+            // dropped connections while reading the content are not reported by Google, but worth
+            // retrying, unlike local failures such as an unsafe object name or a read-only file
+            Err(e)
+                if attempts <= retries && (e.is_retryable() || matches!(e, Error::Reqwest(_))) =>
+            {
+                if let Error::Cancelled = e {
+                    return FileDownload {
+                        path,
+                        attempts,
+                        result: Err(e),
+                    };
+                }
+                let delay = client.config().retry.backoff(attempts - 1);
+                std::thread::sleep(crate::http::jitter(delay));
+            }
+            result => {
+                return FileDownload {
+                    path,
+                    attempts,
+                    result: result.map(|_| object),
+                }
+            }
+        }
+    }
+}
+
+/// Copies all objects under `source_prefix` in `source_bucket` to `destination_prefix` in
+/// `destination_bucket`. With `delete_source`, every source object is deleted once its copy is
+/// complete and verified, which moves the objects instead. Failing objects do not stop the others;
+/// their errors are part of the returned report, which is sorted by path.
+pub(crate) fn copy_prefix(
+    client: &Client,
+    source_bucket: &str,
+    source_prefix: &str,
+    destination_bucket: &str,
+    destination_prefix: &str,
+    options: &CopyOptions,
+    delete_source: bool,
+) -> Result<Vec<ObjectCopy>, Error> {
+    let source_prefix = dir_prefix(source_prefix);
+    let destination_prefix = dir_prefix(destination_prefix);
+    let objects = remote_objects(client, source_bucket, &source_prefix)?
+        .into_iter()
+        .collect();
+    let mut report: Vec<_> = parallel(options.concurrency, objects, false, |(path, object)| {
+        let name = format!("{}{}", destination_prefix, path);
+        let retries = options.retries.unwrap_or(3);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let result = copy_object(client, &object, destination_bucket, &name, delete_source);
+            match result {
+                Err(e) if e.is_retryable() && attempts <= retries => {
+                    let delay = client.config().retry.backoff(attempts - 1);
+                    std::thread::sleep(crate::http::jitter(delay));
+                }
+                result => {
+                    return Ok(ObjectCopy {
+                        path,
+                        attempts,
+                        result,
+                    })
+                }
+            }
+        }
+    })
+    .into_iter()
+    .filter_map(Result::ok)
+    .collect();
+    report.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
+
+/// Copies exactly the generation of `object` to `name` in `bucket`, checks that the copy has the
+/// same content, and optionally deletes `object` afterwards.
+fn copy_object(
+    client: &Client,
+    object: &Object,
+    bucket: &str,
+    name: &str,
+    delete_source: bool,
+) -> Result<Object, Error> {
+    let copy = rewrite(client, object, bucket, name)?;
+    if copy.size != object.size || copy.crc32c != object.crc32c {
+        return Err(Error::Other(format!("{}: checksum mismatch", name)));
+    }
+    if delete_source {
+        let url = format!(
+            "{}/b/{}/o/{}",
+            client.api_url(),
+            percent_encode(&object.bucket),
+            percent_encode(&object.name),
+        );
+        // a newer generation that was written in the meantime is kept
+        let response = client
+            .http()?
+            .delete(&url)
+            .headers(client.headers()?)
+            .query(&client.user_project_query())
+            .query(&[("ifGenerationMatch", object.generation)])
+            .execute(client)?;
+        // an earlier attempt may have deleted it already
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            return Err(Error::from_response(response));
+        }
+    }
+    Ok(copy)
+}
+
+/// Rewrites `object` to `name` in `bucket`, which takes several requests for large objects that
+/// are copied between locations or storage classes.
+fn rewrite(client: &Client, object: &Object, bucket: &str, name: &str) -> Result<Object, Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct RewriteProgress {
+        done: bool,
+        rewrite_token: Option<String>,
+        resource: Option<Object>,
+    }
+
+    let url = format!(
+        "{}/b/{}/o/{}/rewriteTo/b/{}/o/{}",
+        client.api_url(),
+        percent_encode(&object.bucket),
+        percent_encode(&object.name),
+        percent_encode(bucket),
+        percent_encode(name),
+    );
+    let mut rewrite_token: Option<String> = None;
+    loop {
+        let mut headers = client.headers()?;
+        headers.insert(reqwest::header::CONTENT_LENGTH, 0.into());
+        let mut request = client
+            .http()?
+            .post(&url)
+            .headers(headers)
+            .query(&client.user_project_query())
+            .query(&[("sourceGeneration", object.generation)]);
+        if let Some(rewrite_token) = &rewrite_token {
+            request = request.query(&[("rewriteToken", rewrite_token)]);
+        }
+        let response = request.execute(client)?;
+        if !response.status().is_success() {
+            return Err(Error::from_response(response));
+        }
+        let progress: RewriteProgress = response.json()?;
+        match progress.resource {
+            Some(copy) if progress.done => return Ok(copy),
+            _ => rewrite_token = progress.rewrite_token,
+        }
+        if rewrite_token.is_none() {
+            let msg = format!("rewrite of {} did not return a token", object.name);
+            return Err(Error::Other(msg));
+        }
+    }
+}
+
+/// The local file that the object at `path`, relative to the synchronized prefix, is stored in.
+/// Fails for paths that would end up outside of `local_dir`.
+fn local_path(local_dir: &Path, path: &str) -> Result<PathBuf, Error> {
+    let mut target = local_dir.to_path_buf();
+    for part in path.split('/') {
+        if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+            return Err(Error::Other(format!("unsafe object name {}", path)));
+        }
+        target.push(part);
+    }
+    Ok(target)
+}
+
+/// Downloads the content of `object` to a temporary file next to `target`, which replaces
+/// `target` once the content is complete and its CRC32C checksum matches, so that a failed
+/// download never leaves a partial file behind. With `resume`, the temporary file is kept when the
+/// download fails, and a later call only downloads the rest of the content. The modification time
+/// of the file is set to that of the object.
+fn download(client: &Client, object: &Object, target: &Path, resume: bool) -> Result<(), Error> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| io_error(e, parent))?;
+    }
+    let mut partial = target.as_os_str().to_owned();
+    partial.push(format!(".{}.partial", object.generation));
+    let partial = PathBuf::from(partial);
+    let offset = match std::fs::metadata(&partial) {
+        Ok(metadata) if resume && metadata.len() <= object.size => metadata.len(),
+        _ => 0,
+    };
+    let result = fetch(client, object, &partial, offset).and_then(|_| {
+        if !object.crc32c.is_empty() && crc32c(&partial)? != object.crc32c {
+            std::fs::remove_file(&partial).ok();
+            return Err(Error::Other(format!("{}: checksum mismatch", object.name)));
+        }
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&partial)
+            .and_then(|file| file.set_modified(object.updated.into()))
+            .and_then(|_| std::fs::rename(&partial, target))
+            .map_err(|e| io_error(e, target))
+    });
+    if result.is_err() && !resume {
+        std::fs::remove_file(&partial).ok();
+    }
+    result
+}
+
+/// Appends the content of `object` from `offset` onwards to the file at `partial`.
+fn fetch(client: &Client, object: &Object, partial: &Path, offset: u64) -> Result<(), Error> {
+    let url = format!(
+        "{}/b/{}/o/{}",
+        client.api_url(),
+        percent_encode(&object.bucket),
+        percent_encode(&object.name),
+    );
+    let mut headers = client.headers()?;
+    if offset > 0 {
+        if offset == object.size {
+            return Ok(());
+        }
+        let range = format!("bytes={}-", offset);
+        headers.insert(reqwest::header::RANGE, range.parse()?);
+    }
+    let mut response = client
+        .http()?
+        .get(&url)
+        .headers(headers)
+        .query(&client.user_project_query())
+        .query(&[
+            ("alt", "media"),
+            ("generation", &object.generation.to_string()),
+        ])
+        .execute(client)?;
+    if !response.status().is_success() {
+        return Err(Error::from_response(response));
+    }
+    let partial_content = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(partial_content)
+        .truncate(!partial_content)
+        .open(partial)
+        .map_err(|e| io_error(e, partial))?;
+    let mut body = crate::cancel::reader(&mut response);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let len = match body.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(body_error(e)),
+        };
+        file.write_all(&buf[..len])
+            .map_err(|e| io_error(e, partial))?;
+    }
+}
+
+/// Turns a failure to read the body of a response into `Error::Reqwest`, which `download_file`
+/// retries, unless the download was cancelled.
+fn body_error(error: std::io::Error) -> Error {
+    if let Err(cancelled) = crate::cancel::check() {
+        return cancelled;
+    }
+    let msg = error.to_string();
+    match error
+        .into_inner()
+        .map(|inner| inner.downcast::<reqwest::Error>())
+    {
+        Some(Ok(e)) => Error::Reqwest(*e),
+        _ => Error::Other(format!("reading the response failed: {}", msg)),
+    }
+}
+
+/// Runs `task` for every item, on at most `SyncOptions::concurrency` threads at once, and returns
+/// the results of the tasks that succeeded in no particular order. Stops starting new tasks after
+/// the first failure, which is returned as well.
+fn run<T, F>(options: &SyncOptions, items: Vec<T>, task: F) -> (Vec<String>, Option<Error>)
+where
+    T: Send,
+    F: Fn(T) -> Result<String, Error> + Sync,
+{
+    let mut done = Vec::new();
+    let mut failure = None;
+    for result in parallel(options.concurrency, items, true, task) {
+        match result {
+            Ok(path) => done.push(path),
+            Err(e) => {
+                failure.get_or_insert(e);
+            }
+        }
+    }
+    (done, failure)
+}
+
+/// Fails with `Error::SyncIncomplete` if a transfer failed, keeping what `summary` says was
+/// changed until then.
+fn check(summary: &mut SyncSummary, failure: Option<Error>) -> Result<(), Error> {
+    match failure {
+        Some(error) => Err(Error::SyncIncomplete {
+            summary: Box::new(std::mem::take(summary)),
+            error: Box::new(error),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Runs `task` for every item, on at most `concurrency` threads at once, and returns the results
+/// in no particular order. With `fail_fast`, no new tasks are started after the first failure.
+fn parallel<T, R, F>(
+    concurrency: Option<usize>,
+    items: Vec<T>,
+    fail_fast: bool,
+    task: F,
+) -> Vec<Result<R, Error>>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> Result<R, Error> + Sync,
+{
+    let threads = concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+    let items = Mutex::new(items.into_iter());
+    let results = Mutex::new(Vec::new());
+    let failed = AtomicBool::new(false);
+    // the workers do not inherit the cancellation token of this thread
+    let token = crate::cancel::current().unwrap_or_default();
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                crate::with_cancellation(&token, || loop {
+                    if fail_fast && failed.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let item = match items.lock().unwrap().next() {
+                        Some(item) => item,
+                        None => return,
+                    };
+                    let result = task(item);
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    results.lock().unwrap().push(result);
+                })
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+/// Returns `prefix` with a trailing `/`, unless it is empty.
+fn dir_prefix(prefix: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", prefix)
+    }
+}
+
+/// The objects under `prefix`, by their name without the prefix.
+fn remote_objects(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+) -> Result<HashMap<String, Object>, Error> {
+    let mut objects = HashMap::new();
+    for object in client.object().list_with(bucket, Some(prefix), None) {
+        let object = object?;
+        let path = object.name[prefix.len()..].to_string();
+        // skip the placeholders that some tools create for directories
+        if !path.is_empty() && !path.ends_with('/') {
+            objects.insert(path, object);
+        }
+    }
+    Ok(objects)
+}
+
+/// The files in `dir` and its subdirectories, by their path relative to `dir`. Symbolic links to
+/// files are followed, but linked directories are skipped, since they may lead outside of `dir` or
+/// into a cycle. The temporary files of unfinished downloads are skipped as well.
+fn local_files(dir: &Path) -> Result<HashMap<String, LocalFile>, Error> {
+    let mut files = HashMap::new();
+    let mut dirs = vec![(dir.to_path_buf(), String::new())];
+    while let Some((dir, relative)) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir).map_err(|e| io_error(e, &dir))? {
+            let entry = entry.map_err(|e| io_error(e, &dir))?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = format!("{}{}", relative, name);
+            let mut metadata = std::fs::symlink_metadata(&path).map_err(|e| io_error(e, &path))?;
+            if metadata.file_type().is_symlink() {
+                metadata = std::fs::metadata(&path).map_err(|e| io_error(e, &path))?;
+                if metadata.is_dir() {
+                    continue;
+                }
+            }
+            if metadata.is_dir() {
+                dirs.push((path, format!("{}/", relative)));
+            } else if !is_partial_download(&name) {
+                let file = LocalFile {
+                    path,
+                    size: metadata.len(),
+                    modified: metadata.modified().ok(),
+                };
+                files.insert(relative, file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Whether `name` is that of a temporary file of `download`, which is named after the target file
+/// and the generation of the object, as in `file.txt.1712345678901234.partial`.
+fn is_partial_download(name: &str) -> bool {
+    name.strip_suffix(".partial")
+        .and_then(|rest| rest.rsplit_once('.'))
+        .is_some_and(|(file, generation)| {
+            !file.is_empty()
+                && !generation.is_empty()
+                && generation.bytes().all(|b| b.is_ascii_digit())
+        })
+}
+
+fn io_error(error: std::io::Error, path: &Path) -> Error {
+    Error::Other(format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_files_are_found_recursively() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = std::env::temp_dir().join(format!("sync-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a/b"))?;
+        std::fs::write(dir.join("top.txt"), b"123456789")?;
+        std::fs::write(dir.join("a/b/deep.txt"), b"deep!")?;
+        // only the temporary files of downloads are skipped
+        std::fs::write(dir.join("top.txt.1712345678901234.partial"), b"")?;
+        std::fs::write(dir.join("notes.partial"), b"")?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("a/loop"))?;
+        let files = local_files(&dir)?;
+        let mut paths: Vec<_> = files.keys().cloned().collect();
+        paths.sort();
+        assert_eq!(paths, ["a/b/deep.txt", "notes.partial", "top.txt"]);
+        assert_eq!(files["a/b/deep.txt"].size, 5);
+        // the CRC32C check value 0xE3069283, as Google would report it
+        assert_eq!(crc32c(&dir.join("top.txt"))?, "4waSgw==");
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn run_reports_failures() {
+        let options = SyncOptions {
+            concurrency: Some(3),
+            ..Default::default()
+        };
+        let (mut done, failure) = run(&options, (0..10).collect(), |i| Ok(i.to_string()));
+        assert!(failure.is_none());
+        done.sort_by_key(|i| i.parse::<u32>().unwrap());
+        assert_eq!(done.len(), 10);
+        let (done, failure) = run(&options, (0..10).collect(), |i: u32| match i {
+            4 => Err(Error::new("failed")),
+            i => Ok(i.to_string()),
+        });
+        assert!(failure.is_some());
+        // the files that were transferred before the failure are still reported
+        assert!(!done.contains(&"4".to_string()));
+        let mut summary = SyncSummary {
+            transferred: done,
+            ..Default::default()
+        };
+        let transferred = summary.transferred.clone();
+        match check(&mut summary, failure) {
+            Err(Error::SyncIncomplete { summary, .. }) => {
+                assert_eq!(summary.transferred, transferred)
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+        assert_eq!(dir_prefix("/a/b/"), "a/b/");
+        assert_eq!(dir_prefix(""), "");
+    }
+
+    #[test]
+    fn upload_dir_reports_every_file() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let dir = std::env::temp_dir().join(format!("upload-dir-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("css"))?;
+        std::fs::write(dir.join("index.html"), b"<html></html>")?;
+        std::fs::write(dir.join("css/site.css"), b"body {}")?;
+        let report = upload_dir(
+            &client,
+            &dir,
+            &bucket.name,
+            "test-upload-dir",
+            &Default::default(),
+        )?;
+        let paths: Vec<_> = report.iter().map(|upload| upload.path.as_str()).collect();
+        assert_eq!(paths, ["css/site.css", "index.html"]);
+        let css = report[0].result.as_ref().map_err(|e| e.to_string())?;
+        assert_eq!(css.name, "test-upload-dir/css/site.css");
+        assert_eq!(css.content_type.as_deref(), Some("text/css"));
+        for upload in &report {
+            client
+                .object()
+                .delete(&bucket.name, &format!("test-upload-dir/{}", upload.path))?;
+        }
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn local_paths_stay_inside_the_directory() {
+        let dir = Path::new("/data");
+        assert_eq!(
+            local_path(dir, "a/b.txt").unwrap(),
+            dir.join("a").join("b.txt")
+        );
+        assert!(local_path(dir, "../etc/passwd").is_err());
+        assert!(local_path(dir, "a//b").is_err());
+        assert!(local_path(dir, "a\\..\\b").is_err());
+    }
+
+    #[test]
+    fn local_failures_are_not_retried() -> Result<(), Box<dyn std::error::Error>> {
+        let object: Object = serde_json::from_value(serde_json::json!({
+            "kind": "storage#object",
+            "id": "bucket/../a.txt/1",
+            "selfLink": "https://www.googleapis.com/storage/v1/b/bucket/o/..%2Fa.txt",
+            "mediaLink": "https://storage.googleapis.com/download/storage/v1/b/bucket/o/..%2Fa.txt",
+            "name": "../a.txt",
+            "bucket": "bucket",
+            "generation": "1",
+            "metageneration": "1",
+            "storageClass": "STANDARD",
+            "size": "1",
+            "md5Hash": "DMF1ucDxtqgxw5niaXcmYQ==",
+            "crc32c": "kxa7Tw==",
+            "etag": "CAE=",
+            "timeCreated": "2026-10-18T12:00:00.000Z",
+            "updated": "2026-10-18T12:00:00.000Z",
+            "timeStorageClassUpdated": "2026-10-18T12:00:00.000Z",
+        }))?;
+        let options = DownloadOptions {
+            retries: Some(3),
+            ..Default::default()
+        };
+        let client = Client::default();
+        let download = download_file(
+            &client,
+            Path::new("/data"),
+            object.name.clone(),
+            object,
+            &options,
+        );
+        assert_eq!(download.attempts, 1);
+        assert!(download.result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn download_prefix_resumes() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let object = client.object().create(
+            &bucket.name,
+            b"0123456789",
+            "test-download-prefix/dir/digits.txt",
+            "text/plain",
+        )?;
+        let dir = std::env::temp_dir().join(format!("download-prefix-{}", std::process::id()));
+        // the first half was downloaded by an earlier, interrupted attempt
+        std::fs::create_dir_all(dir.join("dir"))?;
+        let partial = format!("dir/digits.txt.{}.partial", object.generation);
+        std::fs::write(dir.join(partial), b"01234")?;
+        let options = DownloadOptions::default();
+        let report = download_prefix(
+            &client,
+            &bucket.name,
+            "test-download-prefix",
+            &dir,
+            &options,
+        )?;
+        assert_eq!(report.len(), 1);
+        assert!(report[0].result.is_ok());
+        assert_eq!(std::fs::read(dir.join("dir/digits.txt"))?, b"0123456789");
+        client.object().delete(&bucket.name, &object.name)?;
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn sync_up_and_down() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let dir = std::env::temp_dir().join(format!("sync-live-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("up/sub"))?;
+        std::fs::write(dir.join("up/a.txt"), b"a")?;
+        std::fs::write(dir.join("up/sub/b.txt"), b"b")?;
+        let options = SyncOptions::default();
+        let up = sync_up(
+            &client,
+            &dir.join("up"),
+            &bucket.name,
+            "test-sync",
+            &options,
+        )?;
+        assert_eq!(up.transferred.len(), 2);
+        let again = sync_up(
+            &client,
+            &dir.join("up"),
+            &bucket.name,
+            "test-sync",
+            &options,
+        )?;
+        assert_eq!((again.transferred.len(), again.unchanged), (0, 2));
+        let down = sync_down(
+            &client,
+            &bucket.name,
+            "test-sync",
+            &dir.join("down"),
+            &options,
+        )?;
+        assert_eq!(down.transferred.len(), 2);
+        assert_eq!(std::fs::read(dir.join("down/sub/b.txt"))?, b"b");
+
+        std::fs::remove_file(dir.join("up/a.txt"))?;
+        let delete = SyncOptions {
+            delete: true,
+            ..Default::default()
+        };
+        let up = sync_up(&client, &dir.join("up"), &bucket.name, "test-sync", &delete)?;
+        assert_eq!(up.deleted, ["a.txt"]);
+        let down = sync_down(
+            &client,
+            &bucket.name,
+            "test-sync",
+            &dir.join("down"),
+            &delete,
+        )?;
+        assert_eq!((down.deleted.len(), down.unchanged), (1, 1));
+        client
+            .object()
+            .delete(&bucket.name, "test-sync/sub/b.txt")?;
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn copy_and_move_prefix() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = Client::global();
+        let objects = client.object();
+        objects.create(&bucket.name, b"one", "test-copy/a.txt", "text/plain")?;
+        objects.create(&bucket.name, b"two", "test-copy/sub/b.txt", "text/plain")?;
+        let options = CopyOptions::default();
+        let copied = objects.copy_prefix(
+            &bucket.name,
+            "test-copy",
+            &bucket.name,
+            "test-copied/",
+            &options,
+        )?;
+        let paths: Vec<_> = copied.iter().map(|copy| copy.path.as_str()).collect();
+        assert_eq!(paths, ["a.txt", "sub/b.txt"]);
+        assert!(copied.iter().all(|copy| copy.result.is_ok()));
+        objects.read(&bucket.name, "test-copy/a.txt")?;
+        let moved = objects.move_prefix(
+            &bucket.name,
+            "test-copied",
+            &bucket.name,
+            "test-moved",
+            &options,
+        )?;
+        assert!(moved.iter().all(|copy| copy.result.is_ok()));
+        assert!(objects
+            .read(&bucket.name, "test-copied/a.txt")
+            .unwrap_err()
+            .is_not_found());
+        assert_eq!(
+            &objects.download(&bucket.name, "test-moved/sub/b.txt")?[..],
+            b"two"
+        );
+        for name in &["test-copy/a.txt", "test-copy/sub/b.txt"] {
+            objects.delete(&bucket.name, name)?;
+        }
+        for name in &["test-moved/a.txt", "test-moved/sub/b.txt"] {
+            objects.delete(&bucket.name, name)?;
+        }
+        Ok(())
+    }
+}
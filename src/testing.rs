@@ -0,0 +1,574 @@
+//! Tools for tests that should not depend on Google or on an emulator.
+//!
+//! `RecordingTransport` and `ReplayTransport` record the requests of a test against Google in a
+//! fixture file once, and answer them from that file from then on, see `record_or_replay`.
+//!
+//! `FakeClient` is an in-memory implementation of `StorageClient`. Code under test takes a
+//! `&dyn StorageClient`, which is a `Client` in production and a `FakeClient` in tests:
+//! ```rust
+//! use cloud_storage::testing::FakeClient;
+//! use cloud_storage::{Error, NewBucket, StorageClient};
+//!
+//! fn archive(storage: &dyn StorageClient, bucket: &str, name: &str) -> Result<(), Error> {
+//!     let object = storage.read_object(bucket, name)?;
+//!     storage.copy_object(&object, bucket, &format!("archive/{}", name))?;
+//!     storage.delete_object(bucket, name)
+//! }
+//!
+//! # fn main() -> Result<(), Error> {
+//! let fake = FakeClient::new();
+//! fake.create_bucket(&NewBucket {
+//!     name: "my_bucket".to_string(),
+//!     ..Default::default()
+//! })?;
+//! fake.create_object("my_bucket", b"report", "report.txt", "text/plain")?;
+//! archive(&fake, "my_bucket", "report.txt")?;
+//! let archived = fake.list_objects("my_bucket", Some("archive/"))?;
+//! assert_eq!(archived[0].name, "archive/report.txt");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Error;
+use crate::resources::bucket::{Bucket, NewBucket, StorageClass};
+use crate::resources::object::{ComposeRequest, Object};
+use crate::StorageClient;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+mod replay;
+
+pub use replay::{is_recording, record_or_replay, RecordingTransport, ReplayTransport};
+
+/// The object fields that `StorageClient::update_object` and `copy_object` take from the object
+/// that is passed in. All other fields are managed by the store.
+const OBJECT_METADATA: &[&str] = &[
+    "contentType",
+    "contentEncoding",
+    "contentDisposition",
+    "contentLanguage",
+    "cacheControl",
+    "metadata",
+    "temporaryHold",
+    "eventBasedHold",
+];
+
+/// The bucket fields that `StorageClient::update_bucket` leaves alone.
+const BUCKET_IMMUTABLE: &[&str] = &[
+    "kind",
+    "id",
+    "selfLink",
+    "projectNumber",
+    "name",
+    "timeCreated",
+    "location",
+    "locationType",
+    "generation",
+];
+
+/// A `StorageClient` that keeps buckets and objects in memory. It behaves like Google Cloud Storage
+/// where tests are likely to notice:
+/// - every write of an object gives it a new, increasing generation, and every change of its
+///   metadata increments its metageneration;
+/// - `update_object` fails with `Error::PreconditionFailed` when the metageneration of the passed
+///   object is outdated, and `compose_object` checks the generations and preconditions of its
+///   sources;
+/// - objects are listed in lexicographical order, optionally filtered by a prefix;
+/// - missing buckets and objects result in `Error::NotFound`, and creating a bucket that exists or
+///   deleting one that is not empty in `Error::Conflict`.
+///
+/// Checksums, hashes and sizes are computed like Google does. Access control, retention and
+/// billing are not simulated. Clones share the same store, so a clone can be handed to the code
+/// under test while the test inspects the original.
+#[derive(Debug, Clone, Default)]
+pub struct FakeClient {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    buckets: BTreeMap<String, FakeBucket>,
+    /// The last generation that was handed out.
+    generation: i64,
+}
+
+#[derive(Debug)]
+struct FakeBucket {
+    /// The bucket as Google would send it.
+    resource: Value,
+    objects: BTreeMap<String, FakeObject>,
+}
+
+#[derive(Debug)]
+struct FakeObject {
+    /// The object as Google would send it.
+    resource: Value,
+    content: bytes::Bytes,
+}
+
+impl FakeClient {
+    /// Creates an empty store, without any buckets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty store with buckets called `names`, so tests do not have to create them.
+    pub fn with_buckets(names: &[&str]) -> Self {
+        let fake = Self::new();
+        for name in names {
+            let new_bucket = NewBucket {
+                name: name.to_string(),
+                ..Default::default()
+            };
+            fake.create_bucket(&new_bucket)
+                .expect("bucket names are unique");
+        }
+        fake
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl State {
+    /// Returns a new generation, which like those of Google is the current time in microseconds,
+    /// but always larger than the previous one.
+    fn next_generation(&mut self) -> i64 {
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() / 1000;
+        self.generation = now.max(self.generation + 1);
+        self.generation
+    }
+
+    fn bucket(&self, name: &str) -> Result<&FakeBucket, Error> {
+        self.buckets
+            .get(name)
+            .ok_or_else(|| not_found(format!("bucket {} does not exist", name)))
+    }
+
+    fn bucket_mut(&mut self, name: &str) -> Result<&mut FakeBucket, Error> {
+        self.buckets
+            .get_mut(name)
+            .ok_or_else(|| not_found(format!("bucket {} does not exist", name)))
+    }
+
+    fn object(&self, bucket: &str, name: &str) -> Result<&FakeObject, Error> {
+        self.bucket(bucket)?
+            .objects
+            .get(name)
+            .ok_or_else(|| not_found(format!("object {}/{} does not exist", bucket, name)))
+    }
+
+    /// Stores `content` as a new generation of the object `name`, with metadata taken from
+    /// `metadata` if given.
+    fn write(
+        &mut self,
+        bucket: &str,
+        name: &str,
+        content: bytes::Bytes,
+        metadata: Option<&Value>,
+        component_count: Option<i32>,
+    ) -> Result<Object, Error> {
+        self.bucket(bucket)?;
+        let generation = self.next_generation();
+        let now = chrono::Utc::now();
+        let object = Object {
+            kind: "storage#object".to_string(),
+            id: format!("{}/{}/{}", bucket, name, generation),
+            self_link: format!("https://www.googleapis.com/storage/v1/b/{}/o/{}", bucket, name),
+            name: name.to_string(),
+            bucket: bucket.to_string(),
+            generation,
+            metageneration: 1,
+            content_type: None,
+            time_created: now,
+            updated: now,
+            time_deleted: None,
+            temporary_hold: None,
+            event_based_hold: None,
+            retention_expiration_time: None,
+            storage_class: StorageClass::Standard,
+            time_storage_class_updated: now,
+            size: content.len() as u64,
+            // composite objects do not have an MD5 hash
+            md5_hash: match component_count {
+                Some(_) => None,
+                None => Some(crate::md5::encode(crate::md5::compute(&content))),
+            },
+            media_link: format!(
+                "https://storage.googleapis.com/download/storage/v1/b/{}/o/{}?generation={}&alt=media",
+                bucket, name, generation
+            ),
+            content_encoding: None,
+            content_disposition: None,
+            content_language: None,
+            cache_control: None,
+            metadata: None,
+            acl: None,
+            owner: None,
+            crc32c: crate::crc32c::encode(crate::crc32c::checksum(&content)),
+            component_count,
+            etag: base64::encode(&generation.to_be_bytes()),
+            customer_encryption: None,
+            kms_key_name: None,
+        };
+        let mut resource = to_wire(&object, &["generation", "metageneration", "size"]);
+        if let Some(metadata) = metadata {
+            copy_fields(metadata, &mut resource, OBJECT_METADATA);
+        }
+        let object = from_wire(&resource);
+        let stored = FakeObject { resource, content };
+        self.bucket_mut(bucket)?
+            .objects
+            .insert(name.to_string(), stored);
+        Ok(object)
+    }
+}
+
+impl StorageClient for FakeClient {
+    fn create_object(
+        &self,
+        bucket: &str,
+        file: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error> {
+        let mime_type = crate::mime::resolve(mime_type, filename, file);
+        let metadata = serde_json::json!({ "contentType": mime_type });
+        let content = bytes::Bytes::copy_from_slice(file);
+        self.state()
+            .write(bucket, filename, content, Some(&metadata), None)
+    }
+
+    fn read_object(&self, bucket: &str, file_name: &str) -> Result<Object, Error> {
+        Ok(from_wire(&self.state().object(bucket, file_name)?.resource))
+    }
+
+    fn download_object(&self, bucket: &str, file_name: &str) -> Result<bytes::Bytes, Error> {
+        Ok(self.state().object(bucket, file_name)?.content.clone())
+    }
+
+    fn list_objects(&self, bucket: &str, prefix: Option<&str>) -> Result<Vec<Object>, Error> {
+        let state = self.state();
+        let prefix = prefix.unwrap_or_default();
+        Ok(state
+            .bucket(bucket)?
+            .objects
+            .range(prefix.to_string()..)
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .map(|(_, object)| from_wire(&object.resource))
+            .collect())
+    }
+
+    fn update_object(&self, object: &Object) -> Result<Object, Error> {
+        let mut state = self.state();
+        let stored = state
+            .bucket_mut(&object.bucket)?
+            .objects
+            .get_mut(&object.name)
+            .ok_or_else(|| not_found(format!("object {} does not exist", object.name)))?;
+        let metageneration = integer(&stored.resource, "metageneration");
+        if object.metageneration != metageneration {
+            return Err(precondition_failed(format!(
+                "metageneration of {} is {}, not {}",
+                object.name, metageneration, object.metageneration
+            )));
+        }
+        let update = to_wire(object, &[]);
+        copy_fields(&update, &mut stored.resource, OBJECT_METADATA);
+        stored.resource["metageneration"] = (metageneration + 1).to_string().into();
+        stored.resource["updated"] = serde_json::to_value(chrono::Utc::now())?;
+        Ok(from_wire(&stored.resource))
+    }
+
+    fn delete_object(&self, bucket: &str, file_name: &str) -> Result<(), Error> {
+        self.state()
+            .bucket_mut(bucket)?
+            .objects
+            .remove(file_name)
+            .map(|_| ())
+            .ok_or_else(|| not_found(format!("object {}/{} does not exist", bucket, file_name)))
+    }
+
+    fn copy_object(
+        &self,
+        object: &Object,
+        destination_bucket: &str,
+        path: &str,
+    ) -> Result<Object, Error> {
+        let mut state = self.state();
+        let source = state.object(&object.bucket, &object.name)?;
+        let (content, metadata) = (source.content.clone(), source.resource.clone());
+        let component_count = from_wire::<Object>(&metadata).component_count;
+        state.write(
+            destination_bucket,
+            path,
+            content,
+            Some(&metadata),
+            component_count,
+        )
+    }
+
+    fn compose_object(
+        &self,
+        bucket: &str,
+        req: &ComposeRequest,
+        destination_object: &str,
+    ) -> Result<Object, Error> {
+        let mut state = self.state();
+        let mut content = Vec::new();
+        let mut component_count = 0;
+        for source in &req.source_objects {
+            let stored = state.object(bucket, &source.name)?;
+            let generation = integer(&stored.resource, "generation");
+            if source.generation.is_some_and(|g| g != generation) {
+                return Err(not_found(format!(
+                    "generation {:?} of {} does not exist",
+                    source.generation, source.name
+                )));
+            }
+            if let Some(precondition) = &source.object_preconditions {
+                if precondition.if_generation_match != generation {
+                    return Err(precondition_failed(format!(
+                        "generation of {} is {}, not {}",
+                        source.name, generation, precondition.if_generation_match
+                    )));
+                }
+            }
+            content.extend_from_slice(&stored.content);
+            component_count += from_wire::<Object>(&stored.resource)
+                .component_count
+                .unwrap_or(1);
+        }
+        let metadata = req.destination.as_ref().map(|object| to_wire(object, &[]));
+        state.write(
+            bucket,
+            destination_object,
+            content.into(),
+            metadata.as_ref(),
+            Some(component_count),
+        )
+    }
+
+    fn create_bucket(&self, new_bucket: &NewBucket) -> Result<Bucket, Error> {
+        let mut state = self.state();
+        if state.buckets.contains_key(&new_bucket.name) {
+            return Err(conflict(format!(
+                "bucket {} already exists",
+                new_bucket.name
+            )));
+        }
+        let now = serde_json::to_value(chrono::Utc::now())?;
+        let mut resource = serde_json::to_value(new_bucket)?;
+        let defaults = serde_json::json!({
+            "kind": "storage#bucket",
+            "id": new_bucket.name,
+            "selfLink": format!("https://www.googleapis.com/storage/v1/b/{}", new_bucket.name),
+            "projectNumber": "0",
+            "timeCreated": now,
+            "updated": now,
+            "metageneration": "1",
+            "iamConfiguration": { "uniformBucketLevelAccess": { "enabled": false } },
+            "locationType": "region",
+            "storageClass": "STANDARD",
+            "etag": "CAE=",
+        });
+        for (key, value) in defaults.as_object().unwrap() {
+            if matches!(resource.get(key), None | Some(Value::Null)) {
+                resource[key] = value.clone();
+            }
+        }
+        let resource = without_nulls(resource);
+        let bucket = from_wire(&resource);
+        let objects = BTreeMap::new();
+        let fake_bucket = FakeBucket { resource, objects };
+        state.buckets.insert(new_bucket.name.clone(), fake_bucket);
+        Ok(bucket)
+    }
+
+    fn read_bucket(&self, name: &str) -> Result<Bucket, Error> {
+        Ok(from_wire(&self.state().bucket(name)?.resource))
+    }
+
+    fn list_buckets(&self) -> Result<Vec<Bucket>, Error> {
+        let state = self.state();
+        Ok(state
+            .buckets
+            .values()
+            .map(|bucket| from_wire(&bucket.resource))
+            .collect())
+    }
+
+    fn update_bucket(&self, bucket: &Bucket) -> Result<Bucket, Error> {
+        let mut state = self.state();
+        let stored = state.bucket_mut(&bucket.name)?;
+        let metageneration = integer(&stored.resource, "metageneration");
+        let mut resource = to_wire(bucket, &[]);
+        copy_fields(&stored.resource, &mut resource, BUCKET_IMMUTABLE);
+        resource["metageneration"] = (metageneration + 1).to_string().into();
+        resource["updated"] = serde_json::to_value(chrono::Utc::now())?;
+        stored.resource = resource;
+        Ok(from_wire(&stored.resource))
+    }
+
+    fn delete_bucket(&self, bucket: Bucket) -> Result<(), Error> {
+        let mut state = self.state();
+        if !state.bucket(&bucket.name)?.objects.is_empty() {
+            return Err(conflict(format!("bucket {} is not empty", bucket.name)));
+        }
+        state.buckets.remove(&bucket.name);
+        Ok(())
+    }
+}
+
+/// Converts a resource to the JSON that Google sends, in which the `integers` are strings and
+/// fields without a value are left out.
+fn to_wire(resource: &impl serde::Serialize, integers: &[&str]) -> Value {
+    let mut value = serde_json::to_value(resource).expect("resources can be serialized");
+    for key in integers {
+        if let Some(number @ Value::Number(_)) = value.get_mut(key) {
+            *number = Value::String(number.to_string());
+        }
+    }
+    without_nulls(value)
+}
+
+fn without_nulls(mut value: Value) -> Value {
+    if let Some(fields) = value.as_object_mut() {
+        fields.retain(|_, value| !value.is_null());
+    }
+    value
+}
+
+fn from_wire<T: serde::de::DeserializeOwned>(resource: &Value) -> T {
+    serde_json::from_value(resource.clone()).expect("stored resources are valid")
+}
+
+/// Copies the `fields` of `from` to `to`, removing those that `from` does not have.
+fn copy_fields(from: &Value, to: &mut Value, fields: &[&str]) {
+    for field in fields {
+        match from.get(field) {
+            Some(value) => to[*field] = value.clone(),
+            None => {
+                if let Some(to) = to.as_object_mut() {
+                    to.remove(*field);
+                }
+            }
+        }
+    }
+}
+
+/// Reads an integer that Google sends as a string.
+fn integer(resource: &Value, field: &str) -> i64 {
+    resource[field]
+        .as_str()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default()
+}
+
+fn not_found(message: String) -> Error {
+    Error::from_status(404, message)
+}
+
+fn conflict(message: String) -> Error {
+    Error::from_status(409, message)
+}
+
+fn precondition_failed(message: String) -> Error {
+    Error::from_status(412, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::object::{ObjectPrecondition, SourceObject};
+
+    #[test]
+    fn generations_and_preconditions() {
+        let fake = FakeClient::with_buckets(&["bucket"]);
+        let first = fake
+            .create_object("bucket", b"one", "a.txt", "text/plain")
+            .unwrap();
+        let second = fake
+            .create_object("bucket", b"two", "a.txt", "text/plain")
+            .unwrap();
+        assert!(second.generation > first.generation);
+        assert_eq!(
+            second.crc32c,
+            crate::crc32c::encode(crate::crc32c::checksum(b"two"))
+        );
+
+        let mut object = fake.read_object("bucket", "a.txt").unwrap();
+        object.content_type = Some("text/markdown".to_string());
+        let updated = fake.update_object(&object).unwrap();
+        assert_eq!(updated.metageneration, 2);
+        assert_eq!(updated.generation, second.generation);
+        // `object` still has the old metageneration
+        assert!(fake
+            .update_object(&object)
+            .unwrap_err()
+            .is_precondition_failed());
+
+        let compose = |if_generation_match| ComposeRequest {
+            kind: "storage#composeRequest".to_string(),
+            source_objects: vec![SourceObject {
+                name: "a.txt".to_string(),
+                generation: None,
+                object_preconditions: Some(ObjectPrecondition {
+                    if_generation_match,
+                }),
+            }],
+            destination: None,
+        };
+        let composed = fake
+            .compose_object("bucket", &compose(second.generation), "b.txt")
+            .unwrap();
+        assert_eq!(composed.component_count, Some(1));
+        assert_eq!(composed.md5_hash, None);
+        assert!(fake
+            .compose_object("bucket", &compose(first.generation), "b.txt")
+            .unwrap_err()
+            .is_precondition_failed());
+    }
+
+    #[test]
+    fn listing_and_errors() {
+        let fake = FakeClient::with_buckets(&["bucket"]);
+        for name in &["b/2", "a", "b/1", "c"] {
+            fake.create_object("bucket", b"", name, "text/plain")
+                .unwrap();
+        }
+        let names = |prefix| {
+            fake.list_objects("bucket", prefix)
+                .unwrap()
+                .into_iter()
+                .map(|object| object.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(None), vec!["a", "b/1", "b/2", "c"]);
+        assert_eq!(names(Some("b/")), vec!["b/1", "b/2"]);
+
+        assert!(matches!(
+            fake.read_object("bucket", "missing"),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            fake.create_object("missing", b"", "a", "text/plain"),
+            Err(Error::NotFound(_))
+        ));
+        let bucket = fake.read_bucket("bucket").unwrap();
+        assert!(matches!(
+            fake.delete_bucket(bucket),
+            Err(Error::Conflict(_))
+        ));
+        let new_bucket = NewBucket {
+            name: "bucket".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            fake.create_bucket(&new_bucket),
+            Err(Error::Conflict(_))
+        ));
+    }
+}
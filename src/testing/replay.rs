@@ -0,0 +1,259 @@
+use crate::error::Error;
+use crate::http::Transport;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The environment parameter that makes `record_or_replay` record instead of replay.
+const RECORD_ENV: &str = "CLOUD_STORAGE_RECORD";
+
+/// Response headers that are never written to a fixture file, since they can carry session
+/// cookies or identify the account and uploads of whoever recorded it.
+const REDACTED_HEADERS: &[&str] = &["set-cookie", "www-authenticate", "x-guploader-uploadid"];
+
+/// A request and the response it received, as stored in a fixture file.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    /// The response body, if it is valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    /// The base64 encoded response body, if it is not valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+impl Interaction {
+    fn body(&self) -> Result<Vec<u8>, Error> {
+        match (&self.body, &self.body_base64) {
+            (Some(body), _) => Ok(body.clone().into_bytes()),
+            (None, Some(body)) => base64::decode(body)
+                .map_err(|e| Error::Other(format!("invalid recorded body: {}", e))),
+            (None, None) => Ok(Vec::new()),
+        }
+    }
+
+    fn response(&self) -> Result<reqwest::blocking::Response, Error> {
+        let mut response = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            response = response.header(name.as_str(), value.as_str());
+        }
+        let response = response
+            .body(self.body()?)
+            .map_err(|e| Error::Other(format!("invalid recorded response: {}", e)))?;
+        Ok(response.into())
+    }
+}
+
+/// A `Transport` that sends requests to Google, and records every request together with its
+/// response in a fixture file, which a `ReplayTransport` can replay later on. The file is rewritten
+/// after every request, so it is complete even if the test fails halfway.
+///
+/// Only the method and url of requests are recorded, never their headers, so access tokens do not
+/// end up in the file. Response bodies are recorded as they are received, and so are response
+/// headers, except for those that can hold credentials, such as `set-cookie`.
+#[derive(Debug)]
+pub struct RecordingTransport {
+    path: PathBuf,
+    inner: Arc<dyn Transport>,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl RecordingTransport {
+    /// Creates a transport that sends requests with a default HTTP client, and records them in the
+    /// file at `path`, replacing what it contained.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::with_transport(path, Arc::new(reqwest::blocking::Client::new()))
+    }
+
+    /// Creates a transport that sends requests through `inner`, and records them in the file at
+    /// `path`, replacing what it contained.
+    pub fn with_transport(path: impl Into<PathBuf>, inner: Arc<dyn Transport>) -> Self {
+        Self {
+            path: path.into(),
+            inner,
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send(
+        &self,
+        request: reqwest::blocking::Request,
+    ) -> Result<reqwest::blocking::Response, Error> {
+        let method = request.method().to_string();
+        let url = request.url().to_string();
+        let response = self.inner.send(request)?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| !REDACTED_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes()?.to_vec();
+        let (body, body_base64) = match String::from_utf8(body) {
+            Ok(body) => (Some(body), None),
+            Err(e) => (None, Some(base64::encode(e.as_bytes()))),
+        };
+        let interaction = Interaction {
+            method,
+            url,
+            status,
+            headers,
+            body,
+            body_base64,
+        };
+        let response = interaction.response()?;
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push(interaction);
+        let json = serde_json::to_string_pretty(&*interactions)?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        std::fs::write(&self.path, json)
+            .map_err(|e| Error::Other(format!("failed to write {}: {}", self.path.display(), e)))?;
+        Ok(response)
+    }
+}
+
+/// A `Transport` that answers requests with the responses in a fixture file written by a
+/// `RecordingTransport`, without any network access.
+///
+/// Each request is answered with the first recorded response to a request with the same method and
+/// url that was not used yet, so a test that makes the same requests in the same order always gets
+/// the same responses. A request that was not recorded fails with an error. Since no request
+/// reaches Google, clients that replay should not need credentials: set `Config::anonymous` or a
+/// `Config::token_provider` that returns a fixed token.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    interactions: Mutex<Vec<Option<Interaction>>>,
+}
+
+impl ReplayTransport {
+    /// Reads the fixture file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| Error::Other(format!("failed to read {}: {}", path.display(), e)))?;
+        let interactions: Vec<Interaction> = serde_json::from_str(&json)?;
+        Ok(Self {
+            interactions: Mutex::new(interactions.into_iter().map(Some).collect()),
+        })
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(
+        &self,
+        request: reqwest::blocking::Request,
+    ) -> Result<reqwest::blocking::Response, Error> {
+        let method = request.method().as_str();
+        let url = request.url().as_str();
+        let mut interactions = self.interactions.lock().unwrap();
+        let interaction = interactions
+            .iter_mut()
+            .find(|interaction| match interaction {
+                Some(interaction) => interaction.method == method && interaction.url == url,
+                None => false,
+            })
+            .and_then(Option::take)
+            .ok_or_else(|| Error::Other(format!("no recorded response for {} {}", method, url)))?;
+        interaction.response()
+    }
+}
+
+/// Returns a `RecordingTransport` that writes to `path` when the `CLOUD_STORAGE_RECORD` environment
+/// parameter is set, and a `ReplayTransport` that reads from `path` otherwise. This lets the same
+/// test run against Google once to record its fixture, and without network access from then on.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::{testing, Client, Config};
+///
+/// let transport = testing::record_or_replay("tests/fixtures/read_object.json")?;
+/// let client = Client::with_config(Config {
+///     transport: Some(transport),
+///     // replayed requests do not need a token
+///     anonymous: !testing::is_recording(),
+///     ..Default::default()
+/// });
+/// let object = client.object().read("my_bucket", "my_file.txt")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn record_or_replay(path: impl AsRef<Path>) -> Result<Arc<dyn Transport>, Error> {
+    if is_recording() {
+        Ok(Arc::new(RecordingTransport::new(path.as_ref())))
+    } else {
+        Ok(Arc::new(ReplayTransport::from_file(path)?))
+    }
+}
+
+/// Whether `record_or_replay` records, because the `CLOUD_STORAGE_RECORD` environment parameter is
+/// set.
+pub fn is_recording() -> bool {
+    std::env::var_os(RECORD_ENV).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Fixed;
+
+    impl Transport for Fixed {
+        fn send(
+            &self,
+            request: reqwest::blocking::Request,
+        ) -> Result<reqwest::blocking::Response, Error> {
+            let body: Vec<u8> = match request.url().path() {
+                "/binary" => vec![0xff, 0, 1],
+                path => path.as_bytes().to_vec(),
+            };
+            let response = http::Response::builder()
+                .status(200)
+                .header("content-type", "application/octet-stream")
+                .header("set-cookie", "session=secret")
+                .body(body)
+                .unwrap();
+            Ok(response.into())
+        }
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let path = std::env::temp_dir().join(format!("replay-{}.json", std::process::id()));
+        let get = |transport: &dyn Transport, url: &str| -> Result<(u16, Vec<u8>), Error> {
+            let request =
+                reqwest::blocking::Request::new(reqwest::Method::GET, url.parse().unwrap());
+            let response = transport.send(request)?;
+            Ok((response.status().as_u16(), response.bytes()?.to_vec()))
+        };
+
+        let recording = RecordingTransport::with_transport(&path, Arc::new(Fixed));
+        assert_eq!(get(&recording, "http://gcs/text").unwrap().1, b"/text");
+        assert_eq!(
+            get(&recording, "http://gcs/binary").unwrap().1,
+            [0xff, 0, 1]
+        );
+
+        let fixture = std::fs::read_to_string(&path).unwrap();
+        assert!(fixture.contains("content-type") && !fixture.contains("secret"));
+
+        let replay = ReplayTransport::from_file(&path).unwrap();
+        assert_eq!(
+            get(&replay, "http://gcs/binary").unwrap(),
+            (200, vec![0xff, 0, 1])
+        );
+        assert_eq!(get(&replay, "http://gcs/text").unwrap().1, b"/text");
+        // every recorded response is used once
+        assert!(get(&replay, "http://gcs/text").is_err());
+        std::fs::remove_file(path).ok();
+    }
+}
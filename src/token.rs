@@ -1,13 +1,133 @@
 use crate::error::Error;
+use crate::http::RequestExt;
+use crate::resources::service_account::ServiceAccount;
+use crate::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
 
-/// This struct contains contains a token, an expiry, and an access scope.
+/// An access token, as handed out by a `TokenProvider`.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
-    // this field contains the JWT and the expiry thereof. They are in the same Option because if
-    // one of them is `Some`, we require that the other be `Some` as well.
-    token: Option<(String, u64)>,
-    // store the access scope for later use if we need to refresh the token
-    access_scope: String,
+    /// The OAuth2 bearer token that is sent in the `Authorization` header.
+    pub access_token: String,
+    /// The moment the token stops being valid. Tokens without an expiry are cached indefinitely.
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The access token a `Client` currently uses, as returned by `AuthClient::current_token`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo {
+    /// The OAuth2 bearer token that is sent in the `Authorization` header.
+    pub access_token: String,
+    /// The moment the token stops being valid, if it expires at all.
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The OAuth2 scopes the token was requested for.
+    pub scopes: Vec<String>,
+}
+
+/// An OAuth2 scope that the access tokens of a `Client` are requested for, configured through
+/// `Config::scopes`. Tokens with a narrower scope limit the damage that a leaked token can do,
+/// even when the account itself has more permissions.
+/// ### Example
+/// ```rust
+/// use cloud_storage::{AccessScope, Client, Config};
+///
+/// let reader = Client::with_config(Config {
+///     scopes: vec![AccessScope::ReadOnly],
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AccessScope {
+    /// Only allows reading data and metadata, and listing buckets and objects.
+    ReadOnly,
+    /// Allows reading and changing data and metadata, but not access control lists and IAM
+    /// policies.
+    ReadWrite,
+    /// Allows everything, including changing access control lists and IAM policies. This is the
+    /// default.
+    FullControl,
+    /// Allows access to all Google Cloud services that the account has permissions for.
+    CloudPlatform,
+    /// Any other scope, given as its full url.
+    Other(String),
+}
+
+impl AccessScope {
+    /// The url that identifies this scope.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::ReadOnly => "https://www.googleapis.com/auth/devstorage.read_only",
+            Self::ReadWrite => "https://www.googleapis.com/auth/devstorage.read_write",
+            Self::FullControl => "https://www.googleapis.com/auth/devstorage.full_control",
+            Self::CloudPlatform => "https://www.googleapis.com/auth/cloud-platform",
+            Self::Other(scope) => scope,
+        }
+    }
+}
+
+impl From<&str> for AccessScope {
+    fn from(scope: &str) -> Self {
+        [
+            Self::ReadOnly,
+            Self::ReadWrite,
+            Self::FullControl,
+            Self::CloudPlatform,
+        ]
+        .iter()
+        .find(|known| known.as_str() == scope)
+        .cloned()
+        .unwrap_or_else(|| Self::Other(scope.to_string()))
+    }
+}
+
+/// A source of access tokens. By default, tokens are obtained using the service account that
+/// `SERVICE_ACCOUNT` points to, but any implementation of this trait can be configured through
+/// `Config::token_provider`, for example to use a custom token exchange or a stub in tests.
+///
+/// Tokens are cached by this crate until they expire, so implementations do not need to do their
+/// own caching. Each token is requested for a particular `Client`, and the built-in providers send
+/// their token exchanges through that client, so that they use its proxy, timeouts and transport.
+/// ### Example
+/// ```no_run
+/// use cloud_storage::{Client, Config, Error, Token, TokenProvider};
+/// use std::sync::Arc;
+///
+/// #[derive(Debug)]
+/// struct FixedToken(String);
+///
+/// impl TokenProvider for FixedToken {
+///     fn token(&self, _client: &Client, _scopes: &[&str]) -> Result<Token, Error> {
+///         Ok(Token {
+///             access_token: self.0.clone(),
+///             expires_at: None,
+///         })
+///     }
+/// }
+///
+/// cloud_storage::set_config(Config {
+///     token_provider: Some(Arc::new(FixedToken("ya29.token".to_string()))),
+///     ..Default::default()
+/// });
+/// ```
+pub trait TokenProvider: std::fmt::Debug + Send + Sync {
+    /// Returns a token that is valid for the requested OAuth2 `scopes`, to authenticate the
+    /// requests of `client`.
+    fn token(&self, client: &Client, scopes: &[&str]) -> Result<Token, Error>;
+}
+
+/// Obtains tokens by signing a JWT with the private key of a service account, and exchanging it
+/// with Google's OAuth2 endpoint.
+#[derive(Debug)]
+pub struct ServiceAccountTokenProvider {
+    service_account: ServiceAccount,
+}
+
+impl ServiceAccountTokenProvider {
+    /// Creates a provider that authenticates as `service_account`.
+    pub fn new(service_account: ServiceAccount) -> Self {
+        Self { service_account }
+    }
 }
 
 #[derive(Serialize)]
@@ -22,66 +142,266 @@ struct Claims {
 #[derive(Deserialize, Debug)]
 struct TokenResponse {
     access_token: String,
-    expires_in: usize,
+    expires_in: u64,
     token_type: String,
 }
 
-impl Token {
-    pub fn new(scope: &str) -> Self {
-        Self {
-            token: None,
-            access_scope: scope.to_string(),
-        }
-    }
-
-    pub fn get<'a>(&'a mut self) -> Result<String, Error> {
-        match self.token {
-            Some((ref token, exp)) if exp > now() => Ok(token.clone()),
-            _ => self.retrieve(),
-        }
-    }
-
-    fn retrieve(&mut self) -> Result<String, Error> {
-        self.token = Some(Self::get_token(&self.access_scope)?);
-        match self.token {
-            Some(ref token) => Ok(token.0.clone()),
-            None => unreachable!(),
-        }
-    }
-
-    fn get_token(scope: &str) -> Result<(String, u64), Error> {
+impl TokenProvider for ServiceAccountTokenProvider {
+    fn token(&self, client: &Client, scopes: &[&str]) -> Result<Token, Error> {
         let now = now();
         let exp = now + 3600;
 
         let claims = Claims {
-            iss: crate::SERVICE_ACCOUNT.client_email.clone(),
-            scope: scope.into(),
+            iss: self.service_account.client_email.clone(),
+            scope: scopes.join(" "),
             aud: "https://www.googleapis.com/oauth2/v4/token".to_string(),
             exp,
             iat: now,
         };
-        let mut header = jsonwebtoken::Header::default();
-        header.alg = jsonwebtoken::Algorithm::RS256;
-        let private_key_bytes = crate::SERVICE_ACCOUNT.private_key.as_bytes();
+        let header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+        let private_key_bytes = self.service_account.private_key.as_bytes();
         let private_key = jsonwebtoken::EncodingKey::from_rsa_pem(private_key_bytes)?;
         let jwt = jsonwebtoken::encode(&header, &claims, &private_key)?;
         let body = [
             ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
             ("assertion", &jwt),
         ];
-        let client = reqwest::blocking::Client::new();
         let response: TokenResponse = client
+            .http()?
             .post("https://www.googleapis.com/oauth2/v4/token")
             .form(&body)
-            .send()?
+            .execute(client)?
             .json()?;
-        Ok((response.access_token, exp))
+        Ok(Token {
+            access_token: response.access_token,
+            expires_at: Some(
+                chrono::Utc::now() + chrono::Duration::seconds(response.expires_in as i64),
+            ),
+        })
     }
 }
 
+/// Tokens that expire within this many seconds are refreshed in the background, while the current
+/// token keeps being used.
+const REFRESH_MARGIN: i64 = 300;
+/// Tokens that expire within this many seconds are no longer used, since they could expire while
+/// the request is in flight.
+const EXPIRY_MARGIN: i64 = 10;
+
+/// Caches the token for a set of access scopes. Shortly before the token expires, a new one is
+/// requested from the `TokenProvider` on a background thread, so that requests never have to wait
+/// for a token to be refreshed.
+#[derive(Debug)]
+pub(crate) struct TokenCache {
+    state: Arc<Mutex<CacheState>>,
+    // store the access scopes for later use if we need to refresh the token
+    access_scopes: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    token: Option<Token>,
+    refreshing: bool,
+    // incremented whenever the cache is cleared, so that refreshes started before then are ignored
+    generation: u64,
+}
+
+impl TokenCache {
+    pub fn new(scopes: Vec<String>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState::default())),
+            access_scopes: scopes,
+        }
+    }
+
+    /// Returns a token from `provider` for the requests of `client`, which owns this cache.
+    pub fn get(&self, client: &Client, provider: &Arc<dyn TokenProvider>) -> Result<String, Error> {
+        self.get_token(client, provider)
+            .map(|token| token.access_token)
+    }
+
+    /// Like `get`, but returns the whole token.
+    pub fn get_token(
+        &self,
+        client: &Client,
+        provider: &Arc<dyn TokenProvider>,
+    ) -> Result<Token, Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(token) = &state.token {
+            let remaining = token.expires_at.map(|exp| exp - chrono::Utc::now());
+            match remaining {
+                None => return Ok(token.clone()),
+                Some(r) if r > chrono::Duration::seconds(REFRESH_MARGIN) => {
+                    return Ok(token.clone())
+                }
+                Some(r) if r > chrono::Duration::seconds(EXPIRY_MARGIN) => {
+                    let token = token.clone();
+                    if !state.refreshing {
+                        state.refreshing = true;
+                        self.refresh_in_background(client, provider.clone(), state.generation);
+                    }
+                    return Ok(token);
+                }
+                Some(_) => {}
+            }
+        }
+        let token = request(client, provider.as_ref(), &self.access_scopes)?;
+        state.token = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Replaces the cached token with a new one from `provider`, even if it is still valid. A
+    /// refresh that is running in the background is discarded. On failure the old token is kept.
+    pub fn refresh(
+        &self,
+        client: &Client,
+        provider: &Arc<dyn TokenProvider>,
+    ) -> Result<Token, Error> {
+        let mut state = self.state.lock().unwrap();
+        let token = request(client, provider.as_ref(), &self.access_scopes)?;
+        state.token = Some(token.clone());
+        state.refreshing = false;
+        state.generation += 1;
+        Ok(token)
+    }
+
+    /// The scopes that tokens are requested for.
+    pub fn scopes(&self) -> &[String] {
+        &self.access_scopes
+    }
+
+    fn refresh_in_background(
+        &self,
+        client: &Client,
+        provider: Arc<dyn TokenProvider>,
+        generation: u64,
+    ) {
+        let state = self.state.clone();
+        let scopes = self.access_scopes.clone();
+        let client = client.clone();
+        std::thread::spawn(move || {
+            let result = request(&client, provider.as_ref(), &scopes);
+            let mut state = state.lock().unwrap();
+            if state.generation != generation {
+                return;
+            }
+            state.refreshing = false;
+            // on failure the old token is kept, and the next request after it expires retries
+            if let Ok(token) = result {
+                state.token = Some(token);
+            }
+        });
+    }
+
+    /// The moment the cached token expires, if there is one.
+    pub fn expires_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.state.lock().unwrap().token.as_ref()?.expires_at
+    }
+
+    #[cfg(test)]
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.token = None;
+        state.refreshing = false;
+        state.generation += 1;
+    }
+}
+
+fn request(
+    client: &Client,
+    provider: &dyn TokenProvider,
+    scopes: &[String],
+) -> Result<Token, Error> {
+    let scopes: Vec<&str> = scopes.iter().map(String::as_str).collect();
+    provider.token(client, &scopes)
+}
+
 fn now() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_secs()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Default)]
+    struct Counting {
+        calls: AtomicUsize,
+        lifetime: i64,
+    }
+
+    impl TokenProvider for Counting {
+        fn token(&self, _client: &Client, scopes: &[&str]) -> Result<Token, Error> {
+            assert_eq!(scopes, ["scope"]);
+            let count = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Token {
+                access_token: format!("token-{}", count),
+                expires_at: Some(chrono::Utc::now() + chrono::Duration::seconds(self.lifetime)),
+            })
+        }
+    }
+
+    fn counting(lifetime: i64) -> Arc<dyn TokenProvider> {
+        Arc::new(Counting {
+            calls: AtomicUsize::new(0),
+            lifetime,
+        })
+    }
+
+    #[test]
+    fn cache_uses_provider_once() {
+        let provider = counting(3600);
+        let cache = TokenCache::new(vec!["scope".to_string()]);
+        let client = Client::default();
+        assert_eq!(cache.get(&client, &provider).unwrap(), "token-0");
+        assert_eq!(cache.get(&client, &provider).unwrap(), "token-0");
+        cache.clear();
+        assert_eq!(cache.get(&client, &provider).unwrap(), "token-1");
+    }
+
+    #[test]
+    fn refreshes_before_expiry() {
+        let provider = counting(60);
+        let cache = TokenCache::new(vec!["scope".to_string()]);
+        let client = Client::default();
+        assert_eq!(cache.get(&client, &provider).unwrap(), "token-0");
+        // the token expires soon, so it is still handed out while a new one is fetched
+        assert_eq!(cache.get(&client, &provider).unwrap(), "token-0");
+        for _ in 0..100 {
+            if cache.get(&client, &provider).unwrap() == "token-1" {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("token was not refreshed in the background");
+    }
+
+    #[test]
+    fn forced_refresh_replaces_valid_token() {
+        let provider = counting(3600);
+        let cache = TokenCache::new(vec!["scope".to_string()]);
+        let client = Client::default();
+        assert_eq!(cache.get(&client, &provider).unwrap(), "token-0");
+        assert_eq!(
+            cache.refresh(&client, &provider).unwrap().access_token,
+            "token-1"
+        );
+        assert_eq!(
+            cache.get_token(&client, &provider).unwrap().access_token,
+            "token-1"
+        );
+    }
+
+    #[test]
+    fn expired_tokens_are_not_used() {
+        let provider = counting(5);
+        let cache = TokenCache::new(vec!["scope".to_string()]);
+        let client = Client::default();
+        assert_eq!(cache.get(&client, &provider).unwrap(), "token-0");
+        assert_eq!(cache.get(&client, &provider).unwrap(), "token-1");
+    }
+}
@@ -0,0 +1,198 @@
+use crate::error::Error;
+use crate::resources::object::Object;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// The location of an object, or of all objects under a prefix, in the `gs://bucket/path` form that
+/// `gsutil`, `gcloud` and most configuration files use.
+///
+/// Parsing checks that the uri starts with `gs://` and names a bucket. Everything after the first
+/// `/` that follows the bucket is the object name, which may be empty. The common operations are
+/// available directly on a `GsUri`, and use the client configured through `set_config`.
+/// ### Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloud_storage::GsUri;
+///
+/// let uri: GsUri = "gs://my_bucket/path/to/file.png".parse()?;
+/// assert_eq!(uri.bucket(), "my_bucket");
+/// assert_eq!(uri.object(), "path/to/file.png");
+/// let bytes = uri.download()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GsUri {
+    bucket: String,
+    object: String,
+}
+
+impl GsUri {
+    /// Creates the uri of the object `object` in `bucket`. An empty `object` refers to the bucket
+    /// itself.
+    pub fn new(bucket: impl Into<String>, object: impl Into<String>) -> Self {
+        Self {
+            bucket: bucket.into(),
+            object: object.into(),
+        }
+    }
+
+    /// The name of the bucket.
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// The name of the object, or the prefix of the objects, that this uri refers to. It is empty
+    /// for the uri of a bucket.
+    pub fn object(&self) -> &str {
+        &self.object
+    }
+
+    /// Returns the uri of `name` relative to this uri, as if it were a directory.
+    pub fn join(&self, name: &str) -> Self {
+        let object = match self.object.as_str() {
+            "" => name.to_string(),
+            dir if dir.ends_with('/') => format!("{}{}", dir, name),
+            dir => format!("{}/{}", dir, name),
+        };
+        Self::new(self.bucket.clone(), object)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl GsUri {
+    /// Reads the metadata of the object. See `Object::read`.
+    pub fn read(&self) -> Result<Object, Error> {
+        Object::read(&self.bucket, &self.object)
+    }
+
+    /// Downloads the content of the object. See `Object::download`.
+    pub fn download(&self) -> Result<bytes::Bytes, Error> {
+        Object::download(&self.bucket, &self.object)
+    }
+
+    /// Uploads `file` as the object. See `Object::create`.
+    pub fn create(&self, file: &[u8], mime_type: &str) -> Result<Object, Error> {
+        Object::create(&self.bucket, file, &self.object, mime_type)
+    }
+
+    /// Deletes the object. See `Object::delete`.
+    pub fn delete(&self) -> Result<(), Error> {
+        Object::delete(&self.bucket, &self.object)
+    }
+
+    /// Lists the objects whose name starts with the object name of this uri. See
+    /// `Object::list_prefix`.
+    pub fn list(&self) -> Result<Vec<Object>, Error> {
+        Object::list_prefix(&self.bucket, &self.object)
+    }
+
+    /// Uploads all files in `local_dir` and its subdirectories under this uri. See
+    /// `Object::upload_dir`.
+    pub fn upload_dir(
+        &self,
+        local_dir: impl AsRef<Path>,
+        options: &crate::UploadOptions,
+    ) -> Result<Vec<crate::FileUpload>, Error> {
+        Object::upload_dir(local_dir, &self.bucket, &self.object, options)
+    }
+
+    /// Downloads all objects under this uri into `local_dir`. See `Object::download_prefix`.
+    pub fn download_prefix(
+        &self,
+        local_dir: impl AsRef<Path>,
+        options: &crate::DownloadOptions,
+    ) -> Result<Vec<crate::FileDownload>, Error> {
+        Object::download_prefix(&self.bucket, &self.object, local_dir, options)
+    }
+
+    /// Makes the objects under this uri match the files in `local_dir`. See `Object::sync_up`.
+    pub fn sync_up(
+        &self,
+        local_dir: impl AsRef<Path>,
+        options: &crate::SyncOptions,
+    ) -> Result<crate::SyncSummary, Error> {
+        Object::sync_up(local_dir, &self.bucket, &self.object, options)
+    }
+
+    /// Makes the files in `local_dir` match the objects under this uri. See `Object::sync_down`.
+    pub fn sync_down(
+        &self,
+        local_dir: impl AsRef<Path>,
+        options: &crate::SyncOptions,
+    ) -> Result<crate::SyncSummary, Error> {
+        Object::sync_down(&self.bucket, &self.object, local_dir, options)
+    }
+}
+
+impl std::str::FromStr for GsUri {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self, Error> {
+        let invalid = |reason| Error::Other(format!("invalid gs:// uri {:?}: {}", uri, reason));
+        let rest = uri
+            .strip_prefix("gs://")
+            .ok_or_else(|| invalid("it does not start with gs://"))?;
+        let (bucket, object) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(invalid("the bucket is missing"));
+        }
+        Ok(Self::new(bucket, object))
+    }
+}
+
+impl std::fmt::Display for GsUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.object.as_str() {
+            "" => write!(f, "gs://{}", self.bucket),
+            object => write!(f, "gs://{}/{}", self.bucket, object),
+        }
+    }
+}
+
+impl std::convert::TryFrom<&str> for GsUri {
+    type Error = Error;
+
+    fn try_from(uri: &str) -> Result<Self, Error> {
+        uri.parse()
+    }
+}
+
+impl From<&Object> for GsUri {
+    fn from(object: &Object) -> Self {
+        Self::new(object.bucket.clone(), object.name.clone())
+    }
+}
+
+impl From<GsUri> for (String, String) {
+    /// Splits the uri into the name of its bucket and the name of its object.
+    fn from(uri: GsUri) -> Self {
+        (uri.bucket, uri.object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        let uri: GsUri = "gs://bucket/dir/file.txt".parse().unwrap();
+        assert_eq!((uri.bucket(), uri.object()), ("bucket", "dir/file.txt"));
+        assert_eq!(uri.to_string(), "gs://bucket/dir/file.txt");
+
+        let bucket: GsUri = "gs://bucket".parse().unwrap();
+        assert_eq!(bucket.object(), "");
+        assert_eq!(bucket.to_string(), "gs://bucket");
+        assert_eq!(bucket.join("dir").join("file.txt"), uri);
+        assert_eq!(GsUri::new("bucket", "dir/").join("file.txt"), uri);
+
+        assert!("s3://bucket/key".parse::<GsUri>().is_err());
+        assert!("gs:///key".parse::<GsUri>().is_err());
+        let (bucket, object) = uri.into();
+        assert_eq!(
+            (bucket, object),
+            ("bucket".to_string(), "dir/file.txt".to_string())
+        );
+    }
+}
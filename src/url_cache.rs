@@ -0,0 +1,161 @@
+use crate::resources::object::Expiry;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Reuses signed urls, so that a service that hands out urls for the same objects over and over
+/// does not spend its CPU on RSA signatures. A url is reused for the same object, method,
+/// expiry and `SignedUrlOptions` while it is younger than `max_age` and still has at least half of
+/// the lifetime it was signed with left. A url requested to be valid for an hour may therefore
+/// expire up to `max_age` earlier than a freshly signed one would.
+/// ### Example
+/// ```rust
+/// use cloud_storage::{Client, Config, SignedUrlCache};
+/// use std::time::Duration;
+///
+/// let client = Client::with_config(Config {
+///     signed_url_cache: Some(SignedUrlCache {
+///         max_age: Duration::from_secs(30),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedUrlCache {
+    /// How long a url is reused after it was signed. Defaults to one minute.
+    pub max_age: Duration,
+    /// The maximum number of urls that are kept. Defaults to 10000.
+    pub capacity: usize,
+}
+
+impl Default for SignedUrlCache {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(60),
+            capacity: 10_000,
+        }
+    }
+}
+
+/// What a signed url was created for: the bucket, object name, HTTP method, expiry, signed query
+/// parameters and signed headers.
+pub(crate) type UrlKey = (
+    String,
+    String,
+    &'static str,
+    Expiry,
+    Vec<(String, String)>,
+    Vec<(String, String)>,
+);
+
+/// Keeps the urls signed by a `Client` according to its `SignedUrlCache`.
+#[derive(Debug)]
+pub(crate) struct SignedUrls {
+    config: SignedUrlCache,
+    urls: Mutex<HashMap<UrlKey, Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    url: String,
+    signed: Instant,
+    lifetime: Duration,
+}
+
+impl Entry {
+    fn is_usable(&self, now: Instant, max_age: Duration) -> bool {
+        let age = now.duration_since(self.signed);
+        age <= max_age && age <= self.lifetime / 2
+    }
+}
+
+impl SignedUrls {
+    pub fn new(config: &SignedUrlCache) -> Self {
+        Self {
+            config: config.clone(),
+            urls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the url stored for `key` if it can still be used, or signs a new one with `sign`,
+    /// which returns the url and how many seconds it is valid for.
+    pub fn get_or_sign<F>(&self, key: UrlKey, sign: F) -> Result<String, crate::Error>
+    where
+        F: FnOnce() -> Result<(String, u64), crate::Error>,
+    {
+        let now = Instant::now();
+        if let Some(entry) = self.urls.lock().unwrap().get(&key) {
+            if entry.is_usable(now, self.config.max_age) {
+                return Ok(entry.url.clone());
+            }
+        }
+        // signing happens without holding the lock, so that urls for other objects can be
+        // handed out meanwhile
+        let (url, seconds) = sign()?;
+        let mut urls = self.urls.lock().unwrap();
+        if urls.len() >= self.config.capacity && !urls.contains_key(&key) {
+            let max_age = self.config.max_age;
+            urls.retain(|_, entry| entry.is_usable(now, max_age));
+            if urls.len() >= self.config.capacity {
+                urls.clear();
+            }
+        }
+        if self.config.capacity > 0 {
+            let entry = Entry {
+                url: url.clone(),
+                signed: now,
+                lifetime: Duration::from_secs(seconds),
+            };
+            urls.insert(key, entry);
+        }
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn key(name: &str) -> UrlKey {
+        let expiry = Expiry::Duration(Duration::from_secs(60));
+        let (bucket, name) = ("bucket".to_string(), name.to_string());
+        (bucket, name, "GET", expiry, Vec::new(), Vec::new())
+    }
+
+    #[test]
+    fn reuses_young_urls() {
+        let urls = SignedUrls::new(&SignedUrlCache {
+            capacity: 2,
+            ..Default::default()
+        });
+        let count = &Cell::new(0);
+        let sign = |seconds| {
+            move || {
+                count.set(count.get() + 1);
+                Ok((format!("url-{}", count.get()), seconds))
+            }
+        };
+        assert_eq!(urls.get_or_sign(key("a"), sign(60)).unwrap(), "url-1");
+        assert_eq!(urls.get_or_sign(key("a"), sign(60)).unwrap(), "url-1");
+        assert_eq!(urls.get_or_sign(key("b"), sign(60)).unwrap(), "url-2");
+        // the cache is full, so the oldest urls make way
+        assert_eq!(urls.get_or_sign(key("c"), sign(60)).unwrap(), "url-3");
+        assert_eq!(urls.get_or_sign(key("c"), sign(60)).unwrap(), "url-3");
+        assert_eq!(urls.get_or_sign(key("a"), sign(60)).unwrap(), "url-4");
+        // a url that is only valid for a second is past half its lifetime before long
+        assert_eq!(urls.get_or_sign(key("d"), sign(0)).unwrap(), "url-5");
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(urls.get_or_sign(key("d"), sign(0)).unwrap(), "url-6");
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let urls = SignedUrls::new(&SignedUrlCache::default());
+        let failed = urls.get_or_sign(key("a"), || Err(crate::Error::Other("no".to_string())));
+        assert!(failed.is_err());
+        let url = urls.get_or_sign(key("a"), || Ok(("url".to_string(), 60)));
+        assert_eq!(url.unwrap(), "url");
+    }
+}
@@ -0,0 +1,149 @@
+//! An asynchronous client for `wasm32-unknown-unknown` targets, such as browsers and edge runtimes
+//! like Cloudflare Workers, where the blocking `Client` of this crate is not available. Requests
+//! are sent with `fetch`, through the wasm backend of reqwest.
+//!
+//! There is no way to obtain tokens on these targets: the access token is passed in by the
+//! application, for example after a server created it, and is sent with every request. To hand
+//! out access to single objects instead, create signed urls with `UrlSigner` and use them with
+//! `fetch` directly.
+
+use crate::error::Error;
+use crate::resources::common::ListResponse;
+use crate::resources::object::{percent_encode, Object};
+use crate::RequestContext;
+
+/// The url of the upload endpoint of the JSON API.
+const UPLOAD_URL: &str = "https://www.googleapis.com/upload/storage/v1";
+
+/// A client that reads and writes objects with an access token provided by the application.
+/// Unlike the blocking `Client`, it does not retry failed requests and never refreshes the token;
+/// replace it with `set_access_token` before it expires.
+/// ### Example
+/// ```no_run
+/// # async fn example() -> Result<(), cloud_storage::Error> {
+/// use cloud_storage::wasm::WasmClient;
+///
+/// let client = WasmClient::new("ya29.token-from-your-server");
+/// let object = client.create("my_bucket", b"hello".to_vec(), "hello.txt", "text/plain").await?;
+/// let content = client.download("my_bucket", &object.name).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WasmClient {
+    http: reqwest::Client,
+    access_token: String,
+}
+
+impl WasmClient {
+    /// Creates a client that authenticates its requests with `access_token`.
+    pub fn new(access_token: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            access_token: access_token.into(),
+        }
+    }
+
+    /// Replaces the access token that is sent with the next requests.
+    pub fn set_access_token(&mut self, access_token: impl Into<String>) {
+        self.access_token = access_token.into();
+    }
+
+    /// Obtains the metadata of the object `name` in `bucket`. See `ObjectClient::read`.
+    pub async fn read(&self, bucket: &str, name: &str) -> Result<Object, Error> {
+        let url = object_url(bucket, name);
+        let response = self.send(reqwest::Method::GET, &url, |r| r).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Downloads the content of the object `name` in `bucket`. See `ObjectClient::download`.
+    pub async fn download(&self, bucket: &str, name: &str) -> Result<bytes::Bytes, Error> {
+        let url = object_url(bucket, name);
+        let response = self
+            .send(reqwest::Method::GET, &url, |r| r.query(&[("alt", "media")]))
+            .await?;
+        Ok(response.bytes().await?)
+    }
+
+    /// Uploads `content` as the object `name` in `bucket`, which is served with `mime_type`. See
+    /// `ObjectClient::create`.
+    pub async fn create(
+        &self,
+        bucket: &str,
+        content: Vec<u8>,
+        name: &str,
+        mime_type: &str,
+    ) -> Result<Object, Error> {
+        let url = format!("{}/b/{}/o", UPLOAD_URL, percent_encode(bucket));
+        let response = self
+            .send(reqwest::Method::POST, &url, |r| {
+                r.query(&[("uploadType", "media"), ("name", name)])
+                    .header(reqwest::header::CONTENT_TYPE, mime_type)
+                    .body(content)
+            })
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Deletes the object `name` in `bucket`. See `ObjectClient::delete`.
+    pub async fn delete(&self, bucket: &str, name: &str) -> Result<(), Error> {
+        let url = object_url(bucket, name);
+        self.send(reqwest::Method::DELETE, &url, |r| r).await?;
+        Ok(())
+    }
+
+    /// Lists the objects in `bucket` whose name starts with `prefix`, requesting one page after
+    /// the other. See `ObjectClient::list_prefix`.
+    pub async fn list_prefix(&self, bucket: &str, prefix: &str) -> Result<Vec<Object>, Error> {
+        let url = format!("{}/b/{}/o", crate::BASE_URL, percent_encode(bucket));
+        let mut objects = Vec::new();
+        let mut page_token = None;
+        loop {
+            let mut query = vec![("prefix", prefix.to_string())];
+            if let Some(page_token) = page_token.take() {
+                query.push(("pageToken", page_token));
+            }
+            let response = self
+                .send(reqwest::Method::GET, &url, |r| r.query(&query))
+                .await?;
+            let page: ListResponse<Object> = response.json().await?;
+            objects.extend(page.items);
+            match page.next_page_token {
+                Some(next) => page_token = Some(next),
+                None => return Ok(objects),
+            }
+        }
+    }
+
+    /// Sends a `method` request to `url`, which `build` adds the parameters, headers and body to.
+    /// Unsuccessful responses are turned into the matching error.
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        build: impl FnOnce(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        let request = self
+            .http
+            .request(method.clone(), url)
+            .bearer_auth(&self.access_token);
+        let response = build(request).send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let context = RequestContext::new(method, response.url(), 1);
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        Err(Error::from_body(status.as_u16(), &headers, body, context))
+    }
+}
+
+fn object_url(bucket: &str, name: &str) -> String {
+    format!(
+        "{}/b/{}/o/{}",
+        crate::BASE_URL,
+        percent_encode(bucket),
+        percent_encode(name)
+    )
+}
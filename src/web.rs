@@ -0,0 +1,279 @@
+//! Adapters between the body streams of web frameworks such as axum and actix-web and the
+//! transfers of this crate, for services that proxy uploads to and downloads from Cloud Storage.
+//! The content passes through in chunks, so it is never held in memory as a whole.
+//!
+//! The adapters only use the `Stream` trait of the futures crate, so they do not depend on a
+//! particular framework. Like `GcsFs`, they run their blocking requests on a separate thread pool
+//! and work under any async runtime.
+
+use crate::error::Error;
+use crate::http::RequestExt;
+use crate::resources::object::{percent_encode, MediaInfo, Object};
+use crate::Client;
+use futures::channel::mpsc;
+use futures::io::AsyncReadExt;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use futures::SinkExt;
+
+/// The number of chunks of a request body that are buffered while the upload catches up.
+const UPLOAD_BUFFER: usize = 4;
+
+/// The size of the chunks of a `Download` body.
+const DOWNLOAD_CHUNK: usize = 64 * 1024;
+
+/// Uploads the chunks of `body`, for example the body of an incoming request, to the object
+/// `name` in `bucket` as they arrive. Pass the Content-Length of the request as `content_length`
+/// when it is known, so that the content is sent in a single request; otherwise it is sent in
+/// chunks. Use `mime::AUTO` as `content_type` to derive it from the name and the first bytes.
+///
+/// The body does not have to be `Send`, so the payload of actix-web can be passed as well.
+/// ### Example
+/// ```no_run
+/// # async fn example() -> Result<(), cloud_storage::Error> {
+/// use cloud_storage::{mime, web, Client};
+///
+/// // for example `request.into_body().into_data_stream()` in axum
+/// let chunks = vec![Ok::<_, std::io::Error>(&b"hello "[..]), Ok(&b"world"[..])];
+/// let body = futures::stream::iter(chunks);
+/// let object = web::upload(&Client::default(), "my_bucket", "upload", mime::AUTO, Some(11), body)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn upload<S, B, E>(
+    client: &Client,
+    bucket: &str,
+    name: &str,
+    content_type: &str,
+    content_length: Option<u64>,
+    body: S,
+) -> Result<Object, Error>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]> + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let (mut sender, receiver) = mpsc::channel(UPLOAD_BUFFER);
+    let forward = async move {
+        let mut body = body;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(Into::into);
+            let failed = chunk.is_err();
+            // the upload has stopped when the receiver is gone
+            if sender.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    };
+    let client = client.clone();
+    let (bucket, name, content_type) = (
+        bucket.to_string(),
+        name.to_string(),
+        content_type.to_string(),
+    );
+    let upload = crate::runtime::unblock(move || {
+        client
+            .object()
+            .create_from_stream(&bucket, receiver, content_length, &name, &content_type)
+    });
+    let ((), result) = futures::future::join(forward, upload).await;
+    result
+}
+
+/// Starts downloading the object `name` in `bucket`, and returns its metadata together with a
+/// stream of its content, which can be used as the body of a response. The metadata is read
+/// first, and the content of exactly that generation is downloaded, so that the headers from
+/// `Download::headers` always match the body, even if the object is replaced in the meantime.
+/// ### Example
+/// ```no_run
+/// # async fn example() -> Result<(), cloud_storage::Error> {
+/// use cloud_storage::{web, Client};
+///
+/// let download = web::download(&Client::default(), "my_bucket", "report.pdf").await?;
+/// for (header, value) in download.headers() {
+///     println!("{}: {}", header, value);
+/// }
+/// // for example `Body::from_stream(download.body)` in axum
+/// let body = download.body;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn download(client: &Client, bucket: &str, name: &str) -> Result<Download, Error> {
+    let client = client.clone();
+    let (bucket, name) = (bucket.to_string(), name.to_string());
+    let (object, response) = crate::runtime::unblock(move || {
+        let object = client.object().read(&bucket, &name)?;
+        let url = format!(
+            "{}/b/{}/o/{}",
+            client.api_url(),
+            percent_encode(&bucket),
+            percent_encode(&name),
+        );
+        let response = client
+            .http()?
+            .get(&url)
+            .headers(client.headers()?)
+            .query(&client.user_project_query())
+            .query(&[("alt", "media")])
+            .query(&[("generation", object.generation.to_string())])
+            .execute(&client)?;
+        if response.status().is_success() {
+            Ok((object, response))
+        } else {
+            Err(Error::from_response(response))
+        }
+    })
+    .await?;
+    let media = MediaInfo::from_headers(response.headers());
+    let reader = blocking::Unblock::new(response);
+    let body = futures::stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut chunk = vec![0; DOWNLOAD_CHUNK];
+        match reader.read(&mut chunk).await {
+            Ok(0) => None,
+            Ok(n) => {
+                chunk.truncate(n);
+                Some((Ok(chunk), Some(reader)))
+            }
+            // end the stream after an error, rather than reading from a broken connection
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    Ok(Download {
+        object,
+        media,
+        body: body.boxed(),
+    })
+}
+
+/// An object that is being downloaded, as returned by `download`.
+///
+/// With axum, the body can be passed to `Body::from_stream`. With actix-web, pass
+/// `download.body.map_ok(Bytes::from)` to `HttpResponseBuilder::streaming`.
+pub struct Download {
+    /// The metadata of the object.
+    pub object: Object,
+    /// The hashes and stored size that Google reports for the content.
+    pub media: MediaInfo,
+    /// The content of the object, in chunks of at most 64 KiB.
+    pub body: BoxStream<'static, std::io::Result<Vec<u8>>>,
+}
+
+impl Download {
+    /// The headers that a response with this download as its body should have: Content-Type,
+    /// Content-Length, ETag, and Content-Disposition, Content-Language and Cache-Control when the
+    /// object has them. Content-Length is omitted for objects with a Content-Encoding, since Google
+    /// may decompress those while they are downloaded.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let object = &self.object;
+        let content_type = object.content_type.as_deref();
+        let mut headers = vec![(
+            "content-type",
+            content_type.unwrap_or(crate::mime::DEFAULT).to_string(),
+        )];
+        if object.content_encoding.is_none() {
+            headers.push(("content-length", object.size.to_string()));
+        }
+        headers.push(("etag", format!("\"{}\"", object.etag)));
+        let optional = [
+            ("content-disposition", &object.content_disposition),
+            ("content-language", &object.content_language),
+            ("cache-control", &object.cache_control),
+        ];
+        for (header, value) in optional.iter() {
+            if let Some(value) = value {
+                headers.push((header, value.clone()));
+            }
+        }
+        headers
+    }
+}
+
+impl std::fmt::Debug for Download {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Download")
+            .field("object", &self.object)
+            .field("media", &self.media)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers() -> Result<(), Box<dyn std::error::Error>> {
+        let mut object: Object = serde_json::from_value(serde_json::json!({
+            "kind": "storage#object",
+            "id": "bucket/report.pdf/1",
+            "selfLink": "",
+            "name": "report.pdf",
+            "bucket": "bucket",
+            "generation": "1",
+            "metageneration": "1",
+            "contentType": "application/pdf",
+            "timeCreated": "2020-01-01T00:00:00Z",
+            "updated": "2020-01-01T00:00:00Z",
+            "storageClass": "STANDARD",
+            "timeStorageClassUpdated": "2020-01-01T00:00:00Z",
+            "size": "42",
+            "mediaLink": "",
+            "crc32c": "AAAAAA==",
+            "etag": "CAE=",
+        }))?;
+        let download = Download {
+            object: object.clone(),
+            media: MediaInfo::default(),
+            body: futures::stream::empty().boxed(),
+        };
+        let headers = download.headers();
+        assert_eq!(headers[0], ("content-type", "application/pdf".to_string()));
+        assert_eq!(headers[1], ("content-length", "42".to_string()));
+        assert_eq!(headers[2], ("etag", "\"CAE=\"".to_string()));
+
+        object.content_encoding = Some("gzip".to_string());
+        object.cache_control = Some("no-store".to_string());
+        let download = Download {
+            object,
+            media: MediaInfo::default(),
+            body: futures::stream::empty().boxed(),
+        };
+        let headers = download.headers();
+        assert!(headers
+            .iter()
+            .all(|(header, _)| *header != "content-length"));
+        assert!(headers.contains(&("cache-control", "no-store".to_string())));
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let bucket = crate::read_test_bucket();
+        let client = &Client::global();
+        let chunks = vec![
+            Ok::<_, std::io::Error>(&b"proxied "[..]),
+            Ok(&b"content"[..]),
+        ];
+        let object = futures::executor::block_on(upload(
+            client,
+            &bucket.name,
+            "test-web",
+            "text/plain",
+            None,
+            futures::stream::iter(chunks),
+        ))?;
+        assert_eq!(object.size, 15);
+
+        let download = futures::executor::block_on(download(client, &bucket.name, "test-web"))?;
+        assert!(download
+            .headers()
+            .contains(&("content-length", "15".to_string())));
+        assert_eq!(download.media.generation, Some(object.generation));
+        let chunks: Vec<_> = futures::executor::block_on(download.body.collect());
+        let content = chunks.into_iter().collect::<Result<Vec<_>, _>>()?.concat();
+        assert_eq!(content, b"proxied content");
+        client.object().delete(&bucket.name, "test-web")?;
+        Ok(())
+    }
+}